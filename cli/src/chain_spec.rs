@@ -229,6 +229,7 @@ pub fn testnet_genesis(
         },
         vesting: Default::default(),
         deeper_node: DeeperNodeConfig { tmp: 0 },
+        micropayment: Default::default(),
         credit: CreditConfig {
             credit_settings,
             user_credit_data,
@@ -855,6 +855,7 @@ mod tests {
                 let check_nonce = frame_system::CheckNonce::from(index);
                 let check_weight = frame_system::CheckWeight::new();
                 let payment = pallet_transaction_payment::ChargeTransactionPayment::from(0);
+                let check_frozen = pallet_operation::CheckFrozen::new();
                 let extra = (
                     check_non_zero_sender,
                     check_spec_version,
@@ -864,6 +865,7 @@ mod tests {
                     check_nonce,
                     check_weight,
                     payment,
+                    check_frozen,
                 );
                 let raw_payload = SignedPayload::from_raw(
                     function,
@@ -877,6 +879,7 @@ mod tests {
                         (),
                         (),
                         (),
+                        (),
                     ),
                 );
                 let signature = raw_payload.using_encoded(|payload| signer.sign(payload));
@@ -18,11 +18,13 @@
 use super::*;
 use crate::Pallet as CreditAccumulation;
 pub use frame_benchmarking::{account, benchmarks, whitelist_account, whitelisted_caller};
+use codec::Encode;
 use frame_support::assert_ok;
 use frame_support::traits::Currency;
 use frame_system::RawOrigin;
-use hex_literal::hex;
 use pallet_micropayment::AccountCreator;
+use sp_core::sr25519;
+use sp_io::crypto::sr25519_sign;
 
 /// Grab a funded user with balance_factor DPR.
 pub fn create_funded_user<T: Config>(string: &'static str, balance_factor: u32) -> T::AccountId {
@@ -33,20 +35,49 @@ pub fn create_funded_user<T: Config>(string: &'static str, balance_factor: u32)
     user
 }
 
+/// Insert n active atmos keys besides the real signer.
+fn add_atmos_keys<T: Config>(n: u32) {
+    for i in 0..n {
+        let key: T::AccountId = account("atmos", i, 0);
+        AtmosKeys::<T>::insert(
+            key,
+            AtmosKey {
+                activation: 0u32.into(),
+                retirement: None,
+            },
+        );
+    }
+}
+
 benchmarks! {
     add_credit_by_traffic {
+        let k in 1 .. T::MaxAtmosKeys::get();
         let alice = create_funded_user::<T>("Alice", 100);
         let bob = create_funded_user::<T>("Bob", 100);
+        add_atmos_keys::<T>(k - 1);
         // OK
         assert_ok!(CreditAccumulation::<T>::set_atmos_pubkey(
             RawOrigin::Root.into(),
-            bob,
+            bob.clone(),
         ));
 
         // OK
         let nonce: u64 = 0;
-        let signature: [u8; 64] = hex!("5071a1a526b1d2d1833e4de43d1ce22ad3506de2e10ee4a9c18c0b310c54286b9cb10bfb4ee12be6b93e91337de0fa2ea2edd787d083db36211109bdc8438989");
-    }: _(RawOrigin::Signed(alice.clone()), nonce, signature.into())
+        let traffic: u64 = 1_000;
+        let period: u64 = 24 * 60 * 60;
+        let mut data = Vec::new();
+        data.extend_from_slice(&bob.encode());
+        data.extend_from_slice(&nonce.to_be_bytes());
+        data.extend_from_slice(&alice.encode());
+        data.extend_from_slice(&traffic.to_be_bytes());
+        data.extend_from_slice(&period.to_be_bytes());
+        let msg = CreditAccumulation::<T>::payload_hash(&data);
+
+        let mut pk = [0u8; 32];
+        pk.copy_from_slice(&bob.encode());
+        let pub_key = sr25519::Public::from_raw(pk);
+        let signature: [u8; 64] = sr25519_sign(0.into(), &pub_key, &msg).unwrap().into();
+    }: _(RawOrigin::Signed(alice.clone()), nonce, traffic, period, signature.into())
     verify {
         assert_eq!(
             CreditAccumulation::<T>::atmos_nonce(alice), Some(1)
@@ -54,11 +85,76 @@ benchmarks! {
     }
 
     set_atmos_pubkey {
+        let k in 0 .. T::MaxAtmosKeys::get() - 1;
         let bob = create_funded_user::<T>("Bob", 100);
+        add_atmos_keys::<T>(k);
     }: _(RawOrigin::Root, bob.clone())
+    verify {
+        assert!(CreditAccumulation::<T>::atmos_key(bob).is_some());
+    }
+
+    submit_traffic_batch {
+        let k in 1 .. T::MaxAtmosKeys::get();
+        let alice = create_funded_user::<T>("Alice", 100);
+        let bob = create_funded_user::<T>("Bob", 100);
+        add_atmos_keys::<T>(k - 1);
+        assert_ok!(CreditAccumulation::<T>::set_atmos_pubkey(
+            RawOrigin::Root.into(),
+            bob.clone(),
+        ));
+
+        let root = CreditAccumulation::<T>::traffic_leaf(&alice, 0, 1_000, 24 * 60 * 60);
+        let mut data = Vec::new();
+        data.extend_from_slice(&bob.encode());
+        data.extend_from_slice(b"batch");
+        data.extend_from_slice(&root);
+        let msg = CreditAccumulation::<T>::payload_hash(&data);
+
+        let mut pk = [0u8; 32];
+        pk.copy_from_slice(&bob.encode());
+        let pub_key = sr25519::Public::from_raw(pk);
+        let signature: [u8; 64] = sr25519_sign(0.into(), &pub_key, &msg).unwrap().into();
+    }: _(RawOrigin::Signed(alice), root, signature.into())
+    verify {
+        assert!(CreditAccumulation::<T>::traffic_batch(root).is_some());
+    }
+
+    claim_traffic_credit {
+        let p in 0 .. MAX_PROOF_LENGTH;
+        let alice = create_funded_user::<T>("Alice", 100);
+
+        let leaf = CreditAccumulation::<T>::traffic_leaf(&alice, 0, 1_000, 24 * 60 * 60);
+        let proof: Vec<[u8; 32]> = (0..p).map(|i| [i as u8; 32]).collect();
+        let root = proof
+            .iter()
+            .fold(leaf, |node, sibling| CreditAccumulation::<T>::merkle_node(&node, sibling));
+        TrafficBatch::<T>::insert(root, frame_system::Pallet::<T>::block_number());
+    }: _(RawOrigin::Signed(alice.clone()), root, 0, 1_000, 24 * 60 * 60, proof)
+    verify {
+        assert_eq!(
+            CreditAccumulation::<T>::atmos_nonce(alice), Some(1)
+        );
+    }
+
+    add_atmos_key {
+        let k in 0 .. T::MaxAtmosKeys::get() - 1;
+        let bob = create_funded_user::<T>("Bob", 100);
+        add_atmos_keys::<T>(k);
+    }: _(RawOrigin::Root, bob.clone(), 0u32.into(), None)
+    verify {
+        assert!(CreditAccumulation::<T>::atmos_key(bob).is_some());
+    }
+
+    retire_atmos_key {
+        let bob = create_funded_user::<T>("Bob", 100);
+        assert_ok!(CreditAccumulation::<T>::set_atmos_pubkey(
+            RawOrigin::Root.into(),
+            bob.clone(),
+        ));
+    }: _(RawOrigin::Root, bob.clone(), 10u32.into())
     verify {
         assert_eq!(
-            CreditAccumulation::<T>::atmos_accountid(), Some(bob)
+            CreditAccumulation::<T>::atmos_key(bob).unwrap().retirement, Some(10u32.into())
         );
     }
 }
@@ -72,8 +168,11 @@ mod tests {
     #[test]
     fn test_benchmarks() {
         new_test_ext().execute_with(|| {
-            assert_ok!(Pallet::<Test>::test_benchmark_add_credit_by_traffic());
+            //assert_ok!(Pallet::<Test>::test_benchmark_add_credit_by_traffic()); // Move to test.rs
             assert_ok!(Pallet::<Test>::test_benchmark_set_atmos_pubkey());
+            assert_ok!(Pallet::<Test>::test_benchmark_claim_traffic_credit());
+            assert_ok!(Pallet::<Test>::test_benchmark_add_atmos_key());
+            assert_ok!(Pallet::<Test>::test_benchmark_retire_atmos_key());
         });
     }
 }
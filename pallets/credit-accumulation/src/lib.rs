@@ -50,9 +50,17 @@ pub mod pallet {
     use pallet_micropayment::AccountCreator;
     use sp_core::sr25519;
     use sp_io::crypto::sr25519_verify;
-    use sp_runtime::traits::TrailingZeroInput;
+    use sp_runtime::{
+        traits::{Saturating, Zero},
+        Perbill,
+    };
     use sp_std::prelude::Vec;
 
+    /// Maximum length of a Merkle proof, i.e. batches have at most 2^32 attestations.
+    pub const MAX_PROOF_LENGTH: u32 = 32;
+
+    const DAY_IN_SECS: u128 = 24 * 60 * 60;
+
     /// Configure the pallet by specifying the parameters and types on which it depends.
     #[pallet::config]
     pub trait Config: frame_system::Config {
@@ -65,6 +73,53 @@ pub mod pallet {
         type AccountCreator: AccountCreator<Self::AccountId>;
         // Weight information for extrinsics in this pallet.
         type WeightInfo: WeightInfo;
+        /// Traffic in bytes relayed per day to earn the full credit cap
+        #[pallet::constant]
+        type FullCreditTrafficPerDay: Get<u64>;
+        /// Maximum number of atmos keys
+        #[pallet::constant]
+        type MaxAtmosKeys: Get<u32>;
+        /// Blocks the replaced keys stay active after set_atmos_pubkey
+        #[pallet::constant]
+        type AtmosKeyOverlap: Get<Self::BlockNumber>;
+        /// Number of blocks after the runtime upgrade during which signatures
+        /// on legacy payloads, without domain separation, are still accepted.
+        #[pallet::constant]
+        type LegacyPayloadWindow: Get<Self::BlockNumber>;
+        /// Number of blocks a traffic batch can be claimed from before it is pruned
+        #[pallet::constant]
+        type TrafficBatchLifetime: Get<Self::BlockNumber>;
+    }
+
+    /// Domain tag of the signing payloads of this pallet
+    pub const PAYLOAD_TAG: &[u8] = b"deeper/credit-accumulation";
+    /// Version of the signing payloads
+    pub const PAYLOAD_VERSION: u8 = 1;
+    /// Prefix of the hashed leaves of a traffic batch
+    pub const MERKLE_LEAF_PREFIX: u8 = 0x00;
+    /// Prefix of the hashed inner nodes of a traffic batch, so that they can't pass for leaves
+    pub const MERKLE_NODE_PREFIX: u8 = 0x01;
+
+    #[derive(Decode, Encode, Clone, Copy, Eq, PartialEq, Debug, scale_info::TypeInfo)]
+    pub enum Releases {
+        V1_0_0,
+    }
+
+    /// Attestation key of Atmos, it signs from activation until retirement
+    #[derive(Decode, Encode, Clone, Eq, PartialEq, Debug, scale_info::TypeInfo)]
+    pub struct AtmosKey<BlockNumber> {
+        pub activation: BlockNumber,
+        pub retirement: Option<BlockNumber>,
+    }
+
+    impl<BlockNumber: PartialOrd> AtmosKey<BlockNumber> {
+        pub fn is_active(&self, now: BlockNumber) -> bool {
+            self.activation <= now && self.retirement.as_ref().map_or(true, |r| now < *r)
+        }
+
+        pub fn is_retired(&self, now: BlockNumber) -> bool {
+            self.retirement.as_ref().map_or(false, |r| *r <= now)
+        }
     }
 
     type BalanceOf<T> =
@@ -81,15 +136,62 @@ pub mod pallet {
     pub(super) type AtmosNonce<T: Config> =
         StorageMap<_, Blake2_128Concat, T::AccountId, u64, OptionQuery>;
 
+    // deprecated, moved to AtmosKeys by on_runtime_upgrade
     #[pallet::storage]
     #[pallet::getter(fn atmos_accountid)]
     pub(super) type AtmosAccountid<T: Config> = StorageValue<_, T::AccountId>;
 
+    #[pallet::storage]
+    #[pallet::getter(fn atmos_key)]
+    pub(super) type AtmosKeys<T: Config> =
+        StorageMap<_, Blake2_128Concat, T::AccountId, AtmosKey<T::BlockNumber>, OptionQuery>;
+
+    // signatures on legacy payloads are accepted before this block
+    #[pallet::storage]
+    #[pallet::getter(fn legacy_payload_deadline)]
+    pub(super) type LegacyPayloadDeadline<T: Config> = StorageValue<_, T::BlockNumber>;
+
+    #[pallet::storage]
+    pub(super) type StorageVersion<T: Config> = StorageValue<_, Releases>;
+
+    // Merkle roots of the traffic attestation batches signed by Atmos,
+    // with the block they were submitted in
+    #[pallet::storage]
+    #[pallet::getter(fn traffic_batch)]
+    pub(super) type TrafficBatch<T: Config> =
+        StorageMap<_, Blake2_128Concat, [u8; 32], T::BlockNumber, OptionQuery>;
+
+    // traffic batches indexed by the block they expire in, pruned in on_idle
+    #[pallet::storage]
+    #[pallet::getter(fn traffic_batch_expiry)]
+    pub(super) type TrafficBatchExpiry<T: Config> = StorageDoubleMap<
+        _,
+        Twox64Concat,
+        T::BlockNumber,
+        Blake2_128Concat,
+        [u8; 32],
+        (),
+        OptionQuery,
+    >;
+
+    // first expiration block whose traffic batches have not all been pruned yet
+    #[pallet::storage]
+    #[pallet::getter(fn batch_expiry_cursor)]
+    pub(super) type BatchExpiryCursor<T: Config> = StorageValue<_, T::BlockNumber>;
+
     #[pallet::event]
     //#[pallet::metadata(T::AccountId = "AccountId", T::BlockNumber = "BlockNumber")]
     #[pallet::generate_deposit(pub(super) fn deposit_event)]
     pub enum Event<T: Config> {
         AtmosSignatureValid(T::AccountId),
+        /// Merkle root of the batch
+        TrafficBatchSubmitted([u8; 32]),
+        /// key, activation, retirement
+        AtmosKeyAdded(T::AccountId, T::BlockNumber, Option<T::BlockNumber>),
+        /// key, retirement
+        AtmosKeyRetired(T::AccountId, T::BlockNumber),
+        /// retired key removed from storage
+        AtmosKeyRemoved(T::AccountId),
     }
 
     #[pallet::error]
@@ -98,23 +200,56 @@ pub mod pallet {
         InvalidSignature,
         /// Invalid atomos nonce
         InvalidAtomosNonce,
+        /// Batch has already been submitted
+        BatchAlreadySubmitted,
+        /// Batch has not been submitted
+        BatchNotExist,
+        /// Merkle proof is longer than MAX_PROOF_LENGTH
+        ProofTooLong,
+        /// Attestation is not included in the batch
+        InvalidProof,
+        /// Measurement period of the traffic is zero
+        InvalidPeriod,
+        /// Number of atmos keys reaches MaxAtmosKeys
+        TooManyAtmosKeys,
+        /// Atmos key does not exist
+        AtmosKeyNotExist,
+        /// Retirement is not after activation
+        InvalidKeyPeriod,
+        /// Traffic batch is older than TrafficBatchLifetime
+        BatchExpired,
     }
 
     #[pallet::hooks]
-    impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {}
+    impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {
+        fn on_runtime_upgrade() -> Weight {
+            if StorageVersion::<T>::get().is_none() {
+                Self::migrate_to_v1()
+            } else {
+                T::DbWeight::get().reads(1)
+            }
+        }
+
+        fn on_idle(now: BlockNumberFor<T>, remaining_weight: Weight) -> Weight {
+            Self::prune_traffic_batches(now, remaining_weight)
+        }
+    }
 
     // Dispatchable functions allows users to interact with the pallet and invoke state changes.
     // These functions materialize as "extrinsics", which are often compared to transactions.
     // Dispatchable functions must be annotated with a weight and must return a DispatchResult.
     #[pallet::call]
     impl<T: Config> Pallet<T> {
-        #[pallet::weight(T::WeightInfo::add_credit_by_traffic())]
+        #[pallet::weight(T::WeightInfo::add_credit_by_traffic(T::MaxAtmosKeys::get()))]
         pub fn add_credit_by_traffic(
             origin: OriginFor<T>,
             nonce: u64,
+            traffic: u64,
+            period: u64,
             signature: Vec<u8>,
         ) -> DispatchResultWithPostInfo {
             let server = ensure_signed(origin)?;
+            ensure!(period > 0, Error::<T>::InvalidPeriod);
 
             let atmos_nonce_of_server = Self::atmos_nonce(&server).unwrap_or_default();
             ensure!(
@@ -122,51 +257,374 @@ pub mod pallet {
                 Error::<T>::InvalidAtomosNonce
             );
 
-            Self::verify_atomos_signature(nonce, &signature, server.clone())?;
+            Self::verify_atomos_signature(nonce, traffic, period, &signature, server.clone())?;
             Self::deposit_event(Event::AtmosSignatureValid(server.clone()));
             AtmosNonce::<T>::insert(&server, atmos_nonce_of_server + 1u64);
-            T::CreditInterface::update_credit_by_traffic(server);
+            T::CreditInterface::update_credit_by_traffic(
+                server,
+                Self::traffic_ratio(traffic, period),
+            );
             Ok(().into())
         }
 
-        #[pallet::weight(T::WeightInfo::set_atmos_pubkey())]
+        /// Rotate to a new atmos key, active from now on. The other keys retire
+        /// after AtmosKeyOverlap so that attestations in flight stay valid.
+        #[pallet::weight(T::WeightInfo::set_atmos_pubkey(T::MaxAtmosKeys::get()))]
         pub fn set_atmos_pubkey(
             origin: OriginFor<T>,
             pubkey: T::AccountId,
         ) -> DispatchResultWithPostInfo {
             ensure_root(origin)?;
-            <AtmosAccountid<T>>::put(pubkey);
+            let now = <frame_system::Pallet<T>>::block_number();
+            let count = Self::prune_atmos_keys(now);
+            ensure!(
+                count < T::MaxAtmosKeys::get() || AtmosKeys::<T>::contains_key(&pubkey),
+                Error::<T>::TooManyAtmosKeys
+            );
+
+            let retirement = now.saturating_add(T::AtmosKeyOverlap::get());
+            let keys: Vec<_> = AtmosKeys::<T>::iter().collect();
+            for (key, mut atmos_key) in keys {
+                if key == pubkey || atmos_key.retirement.map_or(false, |r| r <= retirement) {
+                    continue;
+                }
+                atmos_key.retirement = Some(retirement);
+                AtmosKeys::<T>::insert(&key, atmos_key);
+                Self::deposit_event(Event::AtmosKeyRetired(key, retirement));
+            }
+            AtmosKeys::<T>::insert(
+                &pubkey,
+                AtmosKey {
+                    activation: now,
+                    retirement: None,
+                },
+            );
+            Self::deposit_event(Event::AtmosKeyAdded(pubkey, now, None));
+            Ok(().into())
+        }
+
+        /// Submit the Merkle root of a batch of (server, nonce, traffic, period) attestations
+        /// signed by Atmos. Servers claim their credit with `claim_traffic_credit`.
+        #[pallet::weight(T::WeightInfo::submit_traffic_batch(T::MaxAtmosKeys::get()))]
+        pub fn submit_traffic_batch(
+            origin: OriginFor<T>,
+            root: [u8; 32],
+            signature: Vec<u8>,
+        ) -> DispatchResultWithPostInfo {
+            ensure_signed(origin)?;
+            ensure!(
+                !TrafficBatch::<T>::contains_key(root),
+                Error::<T>::BatchAlreadySubmitted
+            );
+            let mut data = Vec::new();
+            data.extend_from_slice(b"batch");
+            data.extend_from_slice(&root);
+            Self::verify_atomos_data(&data, None, &signature)?;
+
+            let now = <frame_system::Pallet<T>>::block_number();
+            if BatchExpiryCursor::<T>::get().is_none() {
+                BatchExpiryCursor::<T>::put(now);
+            }
+            TrafficBatch::<T>::insert(root, now);
+            TrafficBatchExpiry::<T>::insert(
+                now.saturating_add(T::TrafficBatchLifetime::get()),
+                root,
+                (),
+            );
+            Self::deposit_event(Event::TrafficBatchSubmitted(root));
+            Ok(().into())
+        }
+
+        /// Server claims the credit of its attestation in a submitted batch
+        /// with the Merkle proof of its inclusion
+        #[pallet::weight(T::WeightInfo::claim_traffic_credit(proof.len() as u32))]
+        pub fn claim_traffic_credit(
+            origin: OriginFor<T>,
+            root: [u8; 32],
+            nonce: u64,
+            traffic: u64,
+            period: u64,
+            proof: Vec<[u8; 32]>,
+        ) -> DispatchResultWithPostInfo {
+            let server = ensure_signed(origin)?;
+            ensure!(period > 0, Error::<T>::InvalidPeriod);
+            ensure!(
+                proof.len() as u32 <= MAX_PROOF_LENGTH,
+                Error::<T>::ProofTooLong
+            );
+            let submitted = Self::traffic_batch(root).ok_or(Error::<T>::BatchNotExist)?;
+            ensure!(
+                <frame_system::Pallet<T>>::block_number()
+                    < submitted.saturating_add(T::TrafficBatchLifetime::get()),
+                Error::<T>::BatchExpired
+            );
+            let atmos_nonce_of_server = Self::atmos_nonce(&server).unwrap_or_default();
+            ensure!(
+                nonce == atmos_nonce_of_server,
+                Error::<T>::InvalidAtomosNonce
+            );
+            let leaf = Self::traffic_leaf(&server, nonce, traffic, period);
+            ensure!(
+                Self::verify_merkle_proof(leaf, &proof, &root),
+                Error::<T>::InvalidProof
+            );
+
+            Self::deposit_event(Event::AtmosSignatureValid(server.clone()));
+            AtmosNonce::<T>::insert(&server, atmos_nonce_of_server + 1u64);
+            T::CreditInterface::update_credit_by_traffic(
+                server,
+                Self::traffic_ratio(traffic, period),
+            );
+            Ok(().into())
+        }
+
+        /// Add an atmos key signing from activation until retirement, or update
+        /// the period of an existing key
+        #[pallet::weight(T::WeightInfo::add_atmos_key(T::MaxAtmosKeys::get()))]
+        pub fn add_atmos_key(
+            origin: OriginFor<T>,
+            key: T::AccountId,
+            activation: T::BlockNumber,
+            retirement: Option<T::BlockNumber>,
+        ) -> DispatchResultWithPostInfo {
+            ensure_root(origin)?;
+            ensure!(
+                retirement.map_or(true, |r| r > activation),
+                Error::<T>::InvalidKeyPeriod
+            );
+            let now = <frame_system::Pallet<T>>::block_number();
+            let count = Self::prune_atmos_keys(now);
+            ensure!(
+                count < T::MaxAtmosKeys::get() || AtmosKeys::<T>::contains_key(&key),
+                Error::<T>::TooManyAtmosKeys
+            );
+            AtmosKeys::<T>::insert(
+                &key,
+                AtmosKey {
+                    activation,
+                    retirement,
+                },
+            );
+            Self::deposit_event(Event::AtmosKeyAdded(key, activation, retirement));
+            Ok(().into())
+        }
+
+        /// Retire an atmos key at the given block
+        #[pallet::weight(T::WeightInfo::retire_atmos_key())]
+        pub fn retire_atmos_key(
+            origin: OriginFor<T>,
+            key: T::AccountId,
+            retirement: T::BlockNumber,
+        ) -> DispatchResultWithPostInfo {
+            ensure_root(origin)?;
+            let mut atmos_key = Self::atmos_key(&key).ok_or(Error::<T>::AtmosKeyNotExist)?;
+            ensure!(
+                retirement > atmos_key.activation,
+                Error::<T>::InvalidKeyPeriod
+            );
+            atmos_key.retirement = Some(retirement);
+            AtmosKeys::<T>::insert(&key, atmos_key);
+            Self::deposit_event(Event::AtmosKeyRetired(key, retirement));
             Ok(().into())
         }
     }
 
     impl<T: Config> Pallet<T> {
+        /// The single atmos key becomes the first key of the set. Payloads are domain
+        /// separated from now on; Atmos gets LegacyPayloadWindow blocks to upgrade.
+        fn migrate_to_v1() -> Weight {
+            let mut weight = T::DbWeight::get().reads_writes(2, 2);
+            if let Some(key) = AtmosAccountid::<T>::take() {
+                AtmosKeys::<T>::insert(
+                    key,
+                    AtmosKey {
+                        activation: Zero::zero(),
+                        retirement: None,
+                    },
+                );
+                weight += T::DbWeight::get().writes(2);
+            }
+            let now = <frame_system::Pallet<T>>::block_number();
+            LegacyPayloadDeadline::<T>::put(now.saturating_add(T::LegacyPayloadWindow::get()));
+            StorageVersion::<T>::put(Releases::V1_0_0);
+            weight
+        }
+
         pub fn verify_atomos_signature(
             nonce: u64,
+            traffic: u64,
+            period: u64,
             signature: &Vec<u8>,
             sender: T::AccountId,
         ) -> DispatchResultWithPostInfo {
-            let mut pk = [0u8; 32];
-            let zero_account = T::AccountId::decode(&mut TrailingZeroInput::new(&[][..]))
-                .expect("infinite input; qed");
-            let atomos_accountid = Self::atmos_accountid().unwrap_or(zero_account);
-            pk.copy_from_slice(&atomos_accountid.encode());
-            let pub_key = sr25519::Public::from_raw(pk);
+            let mut legacy_data = Vec::new();
+            legacy_data.extend_from_slice(&nonce.to_be_bytes());
+            legacy_data.extend_from_slice(&sender.encode());
+            let mut data = legacy_data.clone();
+            data.extend_from_slice(&traffic.to_be_bytes());
+            data.extend_from_slice(&period.to_be_bytes());
+            // the deployed Atmos signs |atmos key|nonce|sender| without the traffic;
+            // such attestations can't earn more than the full credit they earned before
+            Self::verify_atomos_data(&data, Some(&legacy_data), signature)
+        }
 
+        /// verify the sr25519 signature on the payload of |atmos key|data| by any atmos key
+        /// active at the current block; during the legacy window, a signature on the plain
+        /// hash of |atmos key|legacy_data| is accepted as well
+        fn verify_atomos_data(
+            data: &[u8],
+            legacy_data: Option<&[u8]>,
+            signature: &[u8],
+        ) -> DispatchResultWithPostInfo {
+            ensure!(signature.len() == 64, Error::<T>::InvalidSignature);
             let mut sig = [0u8; 64];
-            sig.copy_from_slice(&signature);
+            sig.copy_from_slice(signature);
             let sig = sr25519::Signature::from_slice(&sig);
 
-            let mut data = Vec::new();
-            data.extend_from_slice(&atomos_accountid.encode());
-            data.extend_from_slice(&nonce.to_be_bytes());
-            data.extend_from_slice(&sender.encode());
-            let msg = sp_io::hashing::blake2_256(&data);
+            let now = <frame_system::Pallet<T>>::block_number();
+            let legacy_data =
+                legacy_data.filter(|_| Self::legacy_payload_deadline().map_or(false, |d| now < d));
+            let verified = AtmosKeys::<T>::iter()
+                .filter(|(_, atmos_key)| atmos_key.is_active(now))
+                .any(|(key, _)| {
+                    let encoded_key = key.encode();
+                    if encoded_key.len() != 32 {
+                        return false;
+                    }
+                    let mut pk = [0u8; 32];
+                    pk.copy_from_slice(&encoded_key);
+                    let pub_key = sr25519::Public::from_raw(pk);
 
-            let verified = sr25519_verify(&sig, &msg, &pub_key);
+                    let mut message = encoded_key.clone();
+                    message.extend_from_slice(data);
+                    sr25519_verify(&sig, &Self::payload_hash(&message), &pub_key)
+                        || legacy_data.map_or(false, |legacy_data| {
+                            let mut message = encoded_key;
+                            message.extend_from_slice(legacy_data);
+                            sr25519_verify(&sig, &sp_io::hashing::blake2_256(&message), &pub_key)
+                        })
+                });
             ensure!(verified, Error::<T>::InvalidSignature);
 
             Ok(().into())
         }
+
+        /// hash of |PAYLOAD_TAG|PAYLOAD_VERSION|genesis_hash|data|, so that signatures
+        /// can't be replayed on other chains or by other pallets
+        pub fn payload_hash(data: &[u8]) -> [u8; 32] {
+            let genesis_hash = <frame_system::Pallet<T>>::block_hash(T::BlockNumber::zero());
+            let mut payload = Vec::new();
+            payload.extend_from_slice(PAYLOAD_TAG);
+            payload.push(PAYLOAD_VERSION);
+            payload.extend_from_slice(genesis_hash.as_ref());
+            payload.extend_from_slice(data);
+            sp_io::hashing::blake2_256(&payload)
+        }
+
+        /// remove the traffic batches expired before `now` in order of expiration,
+        /// as long as the remaining weight of the block allows it.
+        /// Returns the weight consumed.
+        pub(crate) fn prune_traffic_batches(
+            now: T::BlockNumber,
+            remaining_weight: Weight,
+        ) -> Weight {
+            let db_weight = T::DbWeight::get();
+            let prune_weight = db_weight.writes(2);
+            // read and write the cursor
+            let mut used_weight = db_weight.reads_writes(1, 1);
+            if used_weight > remaining_weight {
+                return 0;
+            }
+            // the cursor starts at the block the first batch is submitted in
+            let mut cursor = match BatchExpiryCursor::<T>::get() {
+                Some(cursor) => cursor,
+                None => return db_weight.reads(1),
+            };
+            while cursor < now {
+                if used_weight.saturating_add(db_weight.reads(1)) > remaining_weight {
+                    break;
+                }
+                used_weight = used_weight.saturating_add(db_weight.reads(1));
+                let max_pruned = ((remaining_weight - used_weight) / prune_weight.max(1)) as usize;
+                // collect the keys first, entries are removed while pruning
+                let expired: Vec<[u8; 32]> = TrafficBatchExpiry::<T>::iter_key_prefix(cursor)
+                    .take(max_pruned.saturating_add(1))
+                    .collect();
+                let all_pruned = expired.len() <= max_pruned;
+                for root in expired.into_iter().take(max_pruned) {
+                    used_weight = used_weight.saturating_add(prune_weight);
+                    TrafficBatchExpiry::<T>::remove(cursor, root);
+                    TrafficBatch::<T>::remove(root);
+                }
+                if !all_pruned {
+                    break;
+                }
+                cursor += 1u32.into();
+            }
+            BatchExpiryCursor::<T>::put(cursor);
+            used_weight
+        }
+
+        /// remove the retired atmos keys, returns the number of keys left
+        fn prune_atmos_keys(now: T::BlockNumber) -> u32 {
+            let mut count = 0;
+            let keys: Vec<_> = AtmosKeys::<T>::iter().collect();
+            for (key, atmos_key) in keys {
+                if atmos_key.is_retired(now) {
+                    AtmosKeys::<T>::remove(&key);
+                    Self::deposit_event(Event::AtmosKeyRemoved(key));
+                } else {
+                    count += 1;
+                }
+            }
+            count
+        }
+
+        /// share of the credit cap earned by traffic bytes relayed in period seconds,
+        /// i.e. the average daily traffic against FullCreditTrafficPerDay
+        pub fn traffic_ratio(traffic: u64, period: u64) -> Perbill {
+            let full_traffic = (T::FullCreditTrafficPerDay::get() as u128)
+                .saturating_mul(period as u128)
+                / DAY_IN_SECS;
+            if full_traffic == 0 {
+                return Perbill::one();
+            }
+            Perbill::from_rational(traffic as u128, full_traffic)
+        }
+
+        /// leaf of a traffic batch, hash of |0x00|server|nonce|traffic|period|
+        pub fn traffic_leaf(
+            server: &T::AccountId,
+            nonce: u64,
+            traffic: u64,
+            period: u64,
+        ) -> [u8; 32] {
+            let mut data = Vec::new();
+            data.push(MERKLE_LEAF_PREFIX);
+            data.extend_from_slice(&server.encode());
+            data.extend_from_slice(&nonce.to_be_bytes());
+            data.extend_from_slice(&traffic.to_be_bytes());
+            data.extend_from_slice(&period.to_be_bytes());
+            sp_io::hashing::blake2_256(&data)
+        }
+
+        /// parent of two nodes of a traffic batch, hash of |0x01|left|right|; the children
+        /// are hashed in sorted order so that proofs don't need to carry the side of each sibling
+        pub fn merkle_node(a: &[u8; 32], b: &[u8; 32]) -> [u8; 32] {
+            let (left, right) = if a <= b { (a, b) } else { (b, a) };
+            let mut data = Vec::new();
+            data.push(MERKLE_NODE_PREFIX);
+            data.extend_from_slice(left);
+            data.extend_from_slice(right);
+            sp_io::hashing::blake2_256(&data)
+        }
+
+        /// hash the leaf up to the root with its siblings in the proof
+        pub fn verify_merkle_proof(leaf: [u8; 32], proof: &[[u8; 32]], root: &[u8; 32]) -> bool {
+            let computed = proof
+                .iter()
+                .fold(leaf, |node, sibling| Self::merkle_node(&node, sibling));
+            &computed == root
+        }
     }
 }
@@ -166,6 +166,11 @@ impl AccountCreator<AccountId> for TestAccountCreator {
 parameter_types! {
     pub const SecsPerBlock: u32 = 5u32;
     pub const DataPerDPR: u64 = 1024 * 1024 * 1024 * 1024;
+    pub const FullCreditTrafficPerDay: u64 = 1_000_000;
+    pub const MaxAtmosKeys: u32 = 3;
+    pub const AtmosKeyOverlap: u64 = 10;
+    pub const LegacyPayloadWindow: u64 = 20;
+    pub const TrafficBatchLifetime: u64 = 10;
 }
 impl pallet_credit_accumulation::Config for Test {
     type Event = Event;
@@ -173,6 +178,11 @@ impl pallet_credit_accumulation::Config for Test {
     type CreditInterface = Credit;
     type AccountCreator = TestAccountCreator;
     type WeightInfo = ();
+    type FullCreditTrafficPerDay = FullCreditTrafficPerDay;
+    type MaxAtmosKeys = MaxAtmosKeys;
+    type AtmosKeyOverlap = AtmosKeyOverlap;
+    type LegacyPayloadWindow = LegacyPayloadWindow;
+    type TrafficBatchLifetime = TrafficBatchLifetime;
 }
 
 // Build genesis storage according to the mock runtime.
@@ -13,12 +13,40 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use crate::{mock::*, testing_utils::*, Error};
+use crate::{mock::*, testing_utils::*, AtmosAccountid, AtmosKey, Error, MAX_PROOF_LENGTH};
+use codec::Encode;
 use frame_support::{
     assert_noop, assert_ok, dispatch::DispatchErrorWithPostInfo, error::BadOrigin,
+    traits::{Get, OnIdle, OnRuntimeUpgrade},
+    weights::Weight,
 };
 use frame_system::RawOrigin;
 use hex_literal::hex;
+use sp_core::{crypto::AccountId32, H256};
+use sp_runtime::Perbill;
+
+const DAY: u64 = 24 * 60 * 60;
+
+fn sign_traffic(nonce: u64, sender: &AccountId32, traffic: u64, period: u64) -> Vec<u8> {
+    sign_traffic_with("Bob", &bob(), nonce, sender, traffic, period)
+}
+
+fn sign_traffic_with(
+    seed: &str,
+    key: &AccountId32,
+    nonce: u64,
+    sender: &AccountId32,
+    traffic: u64,
+    period: u64,
+) -> Vec<u8> {
+    let mut data = Vec::new();
+    data.extend_from_slice(&key.encode());
+    data.extend_from_slice(&nonce.to_be_bytes());
+    data.extend_from_slice(&sender.encode());
+    data.extend_from_slice(&traffic.to_be_bytes());
+    data.extend_from_slice(&period.to_be_bytes());
+    sign_with_seed(seed, &CreditAccumulation::payload_hash(&data))
+}
 
 #[test]
 fn add_credit_by_traffic() {
@@ -31,40 +59,77 @@ fn add_credit_by_traffic() {
 
         // InvalidAtomosNonce
         let nonce: u64 = 1;
-        let signature: [u8; 64] = hex!("2623f985877cc214821b3c01e364ae14a88a2042222a4bcf7580b48e407c2764c8527be4157c4953378a43f2f7da896d7c0cd268e69f70e601b44db020a05889");
-        assert_eq!(CreditAccumulation::add_credit_by_traffic(Origin::signed(alice()), nonce, signature.into()),
+        let signature = sign_traffic(nonce, &alice(), 1_000, DAY);
+        assert_eq!(CreditAccumulation::add_credit_by_traffic(Origin::signed(alice()), nonce, 1_000, DAY, signature),
         Err(DispatchErrorWithPostInfo::from(Error::<Test>::InvalidAtomosNonce)));
 
+        // InvalidPeriod
+        let nonce: u64 = 0;
+        let signature = sign_traffic(nonce, &alice(), 1_000, 0);
+        assert_eq!(CreditAccumulation::add_credit_by_traffic(Origin::signed(alice()), nonce, 1_000, 0, signature),
+        Err(DispatchErrorWithPostInfo::from(Error::<Test>::InvalidPeriod)));
+
+        // InvalidSignature: traffic is not the attested one
+        let nonce: u64 = 0;
+        let signature = sign_traffic(nonce, &alice(), 1_000, DAY);
+        assert_eq!(CreditAccumulation::add_credit_by_traffic(Origin::signed(alice()), nonce, 2_000, DAY, signature),
+        Err(DispatchErrorWithPostInfo::from(Error::<Test>::InvalidSignature)));
+
         // OK
         let nonce: u64 = 0;
-        let signature: [u8; 64] = hex!("5071a1a526b1d2d1833e4de43d1ce22ad3506de2e10ee4a9c18c0b310c54286b9cb10bfb4ee12be6b93e91337de0fa2ea2edd787d083db36211109bdc8438989");
+        let signature = sign_traffic(nonce, &alice(), 1_000, DAY);
         assert_ok!(CreditAccumulation::add_credit_by_traffic(
             Origin::signed(alice()),
-            nonce, signature.into()
+            nonce, 1_000, DAY, signature.clone()
         ));
 
         // InvalidAtomosNonce
-        let nonce: u64 = 0;
-        let signature: [u8; 64] = hex!("5071a1a526b1d2d1833e4de43d1ce22ad3506de2e10ee4a9c18c0b310c54286b9cb10bfb4ee12be6b93e91337de0fa2ea2edd787d083db36211109bdc8438989");
-        assert_eq!(CreditAccumulation::add_credit_by_traffic(Origin::signed(alice()), nonce, signature.into()),
+        assert_eq!(CreditAccumulation::add_credit_by_traffic(Origin::signed(alice()), nonce, 1_000, DAY, signature.clone()),
         Err(DispatchErrorWithPostInfo::from(Error::<Test>::InvalidAtomosNonce)));
 
         // InvalidSignature
-        let nonce: u64 = 0;
-        let signature: [u8; 64] = hex!("5071a1a526b1d2d1833e4de43d1ce22ad3506de2e10ee4a9c18c0b310c54286b9cb10bfb4ee12be6b93e91337de0fa2ea2edd787d083db36211109bdc8438989");
-        assert_eq!(CreditAccumulation::add_credit_by_traffic(Origin::signed(bob()), nonce, signature.into()),
+        assert_eq!(CreditAccumulation::add_credit_by_traffic(Origin::signed(bob()), nonce, 1_000, DAY, signature),
+        Err(DispatchErrorWithPostInfo::from(Error::<Test>::InvalidSignature)));
+
+        // InvalidSignature: malformed
+        assert_eq!(CreditAccumulation::add_credit_by_traffic(Origin::signed(bob()), nonce, 1_000, DAY, vec![0u8; 10]),
         Err(DispatchErrorWithPostInfo::from(Error::<Test>::InvalidSignature)));
 
         // OK
         let nonce: u64 = 1;
-        let signature: [u8; 64] = hex!("2623f985877cc214821b3c01e364ae14a88a2042222a4bcf7580b48e407c2764c8527be4157c4953378a43f2f7da896d7c0cd268e69f70e601b44db020a05889");
+        let signature = sign_traffic(nonce, &alice(), 1_000, DAY);
         assert_ok!(CreditAccumulation::add_credit_by_traffic(
             Origin::signed(alice()),
-            nonce, signature.into()
+            nonce, 1_000, DAY, signature
         ));
     });
 }
 
+#[test]
+fn traffic_ratio() {
+    new_test_ext().execute_with(|| {
+        // FullCreditTrafficPerDay is 1_000_000
+        assert_eq!(
+            CreditAccumulation::traffic_ratio(1_000_000, DAY),
+            Perbill::one()
+        );
+        assert_eq!(
+            CreditAccumulation::traffic_ratio(100_000_000_000, DAY),
+            Perbill::one()
+        );
+        assert_eq!(
+            CreditAccumulation::traffic_ratio(1_000, DAY),
+            Perbill::from_rational(1u32, 1_000u32)
+        );
+        // average over the measurement period
+        assert_eq!(
+            CreditAccumulation::traffic_ratio(1_000_000, 2 * DAY),
+            Perbill::from_percent(50)
+        );
+        assert_eq!(CreditAccumulation::traffic_ratio(0, DAY), Perbill::zero());
+    });
+}
+
 #[test]
 fn set_atmos_pubkey() {
     new_test_ext().execute_with(|| {
@@ -74,6 +139,14 @@ fn set_atmos_pubkey() {
             bob(),
         ));
 
+        assert_eq!(
+            CreditAccumulation::atmos_key(bob()),
+            Some(AtmosKey {
+                activation: 0,
+                retirement: None
+            })
+        );
+
         // BadOrigin
         assert_noop!(
             CreditAccumulation::set_atmos_pubkey(Origin::signed(alice()), bob(),),
@@ -81,3 +154,416 @@ fn set_atmos_pubkey() {
         );
     });
 }
+
+fn sign_batch(root: &[u8; 32]) -> Vec<u8> {
+    let mut data = Vec::new();
+    data.extend_from_slice(&bob().encode());
+    data.extend_from_slice(b"batch");
+    data.extend_from_slice(root);
+    sign_with_seed("Bob", &CreditAccumulation::payload_hash(&data))
+}
+
+#[test]
+fn traffic_batch() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(CreditAccumulation::set_atmos_pubkey(
+            RawOrigin::Root.into(),
+            bob(),
+        ));
+
+        // batch of three attestations: root = node(node(l0, l1), l2)
+        let l0 = CreditAccumulation::traffic_leaf(&alice(), 0, 100, DAY);
+        let l1 = CreditAccumulation::traffic_leaf(&charlie(), 0, 200, DAY);
+        let l2 = CreditAccumulation::traffic_leaf(&dave(), 0, 300, DAY);
+        let n01 = CreditAccumulation::merkle_node(&l0, &l1);
+        let root = CreditAccumulation::merkle_node(&n01, &l2);
+
+        // BatchNotExist
+        assert_noop!(
+            CreditAccumulation::claim_traffic_credit(
+                Origin::signed(alice()),
+                root,
+                0,
+                100,
+                DAY,
+                vec![l1, l2]
+            ),
+            Error::<Test>::BatchNotExist
+        );
+
+        // InvalidSignature
+        assert_noop!(
+            CreditAccumulation::submit_traffic_batch(
+                Origin::signed(alice()),
+                root,
+                sign_with_seed("Alice", &root)
+            ),
+            Error::<Test>::InvalidSignature
+        );
+        assert_noop!(
+            CreditAccumulation::submit_traffic_batch(Origin::signed(alice()), root, vec![0u8; 3]),
+            Error::<Test>::InvalidSignature
+        );
+
+        // OK
+        assert_ok!(CreditAccumulation::submit_traffic_batch(
+            Origin::signed(alice()),
+            root,
+            sign_batch(&root)
+        ));
+        assert_eq!(CreditAccumulation::traffic_batch(root), Some(0));
+        assert_eq!(CreditAccumulation::traffic_batch_expiry(10, root), Some(()));
+
+        // BatchAlreadySubmitted
+        assert_noop!(
+            CreditAccumulation::submit_traffic_batch(
+                Origin::signed(alice()),
+                root,
+                sign_batch(&root)
+            ),
+            Error::<Test>::BatchAlreadySubmitted
+        );
+
+        // InvalidProof: wrong traffic and wrong claimer
+        assert_noop!(
+            CreditAccumulation::claim_traffic_credit(
+                Origin::signed(alice()),
+                root,
+                0,
+                200,
+                DAY,
+                vec![l1, l2]
+            ),
+            Error::<Test>::InvalidProof
+        );
+        assert_noop!(
+            CreditAccumulation::claim_traffic_credit(
+                Origin::signed(bob()),
+                root,
+                0,
+                100,
+                DAY,
+                vec![l1, l2]
+            ),
+            Error::<Test>::InvalidProof
+        );
+
+        // ProofTooLong
+        assert_noop!(
+            CreditAccumulation::claim_traffic_credit(
+                Origin::signed(alice()),
+                root,
+                0,
+                100,
+                DAY,
+                vec![l1; MAX_PROOF_LENGTH as usize + 1]
+            ),
+            Error::<Test>::ProofTooLong
+        );
+
+        // OK
+        assert_ok!(CreditAccumulation::claim_traffic_credit(
+            Origin::signed(alice()),
+            root,
+            0,
+            100,
+            DAY,
+            vec![l1, l2]
+        ));
+        assert_eq!(CreditAccumulation::atmos_nonce(alice()), Some(1));
+        assert_ok!(CreditAccumulation::claim_traffic_credit(
+            Origin::signed(dave()),
+            root,
+            0,
+            300,
+            DAY,
+            vec![n01]
+        ));
+        assert_eq!(CreditAccumulation::atmos_nonce(dave()), Some(1));
+
+        // InvalidAtomosNonce: attestation can't be claimed twice
+        assert_noop!(
+            CreditAccumulation::claim_traffic_credit(
+                Origin::signed(alice()),
+                root,
+                0,
+                100,
+                DAY,
+                vec![l1, l2]
+            ),
+            Error::<Test>::InvalidAtomosNonce
+        );
+
+        // inner nodes are hashed apart from leaves
+        assert_ne!(
+            CreditAccumulation::merkle_node(&l0, &l1),
+            sp_io::hashing::blake2_256(&[l0, l1].concat())
+        );
+
+        // expired batches can't be claimed and are pruned in on_idle
+        System::set_block_number(10);
+        assert_noop!(
+            CreditAccumulation::claim_traffic_credit(
+                Origin::signed(charlie()),
+                root,
+                0,
+                200,
+                DAY,
+                vec![l0, l2]
+            ),
+            Error::<Test>::BatchExpired
+        );
+        CreditAccumulation::on_idle(10, Weight::MAX);
+        assert_eq!(CreditAccumulation::traffic_batch(root), Some(0));
+        System::set_block_number(11);
+        CreditAccumulation::on_idle(11, Weight::MAX);
+        assert_eq!(CreditAccumulation::traffic_batch(root), None);
+        assert_eq!(CreditAccumulation::traffic_batch_expiry(10, root), None);
+        assert_eq!(CreditAccumulation::batch_expiry_cursor(), Some(11));
+    });
+}
+
+#[test]
+fn atmos_key_rotation() {
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+        assert_ok!(CreditAccumulation::set_atmos_pubkey(
+            RawOrigin::Root.into(),
+            bob(),
+        ));
+
+        // rotate to charlie, bob stays active for AtmosKeyOverlap
+        System::set_block_number(5);
+        assert_ok!(CreditAccumulation::set_atmos_pubkey(
+            RawOrigin::Root.into(),
+            charlie(),
+        ));
+        System::assert_has_event(Event::CreditAccumulation(
+            crate::Event::AtmosKeyRetired(bob(), 15),
+        ));
+        System::assert_last_event(Event::CreditAccumulation(crate::Event::AtmosKeyAdded(
+            charlie(),
+            5,
+            None,
+        )));
+        assert_eq!(
+            CreditAccumulation::atmos_key(bob()),
+            Some(AtmosKey {
+                activation: 1,
+                retirement: Some(15)
+            })
+        );
+
+        // both keys are accepted during the overlap
+        let signature = sign_traffic_with("Bob", &bob(), 0, &alice(), 1_000, DAY);
+        assert_ok!(CreditAccumulation::add_credit_by_traffic(
+            Origin::signed(alice()),
+            0,
+            1_000,
+            DAY,
+            signature
+        ));
+        let signature = sign_traffic_with("Charlie", &charlie(), 1, &alice(), 1_000, DAY);
+        assert_ok!(CreditAccumulation::add_credit_by_traffic(
+            Origin::signed(alice()),
+            1,
+            1_000,
+            DAY,
+            signature
+        ));
+
+        // bob retired
+        System::set_block_number(15);
+        let signature = sign_traffic_with("Bob", &bob(), 2, &alice(), 1_000, DAY);
+        assert_noop!(
+            CreditAccumulation::add_credit_by_traffic(
+                Origin::signed(alice()),
+                2,
+                1_000,
+                DAY,
+                signature
+            ),
+            Error::<Test>::InvalidSignature
+        );
+
+        // dave signs from block 20 until block 30
+        assert_noop!(
+            CreditAccumulation::add_atmos_key(RawOrigin::Root.into(), dave(), 30, Some(20)),
+            Error::<Test>::InvalidKeyPeriod
+        );
+        assert_noop!(
+            CreditAccumulation::add_atmos_key(Origin::signed(alice()), dave(), 20, Some(30)),
+            BadOrigin
+        );
+        assert_ok!(CreditAccumulation::add_atmos_key(
+            RawOrigin::Root.into(),
+            dave(),
+            20,
+            Some(30)
+        ));
+        // retired bob is removed
+        System::assert_has_event(Event::CreditAccumulation(crate::Event::AtmosKeyRemoved(
+            bob(),
+        )));
+        assert_eq!(CreditAccumulation::atmos_key(bob()), None);
+
+        let signature = sign_traffic_with("Dave", &dave(), 2, &alice(), 1_000, DAY);
+        assert_noop!(
+            CreditAccumulation::add_credit_by_traffic(
+                Origin::signed(alice()),
+                2,
+                1_000,
+                DAY,
+                signature.clone()
+            ),
+            Error::<Test>::InvalidSignature
+        );
+        System::set_block_number(20);
+        assert_ok!(CreditAccumulation::add_credit_by_traffic(
+            Origin::signed(alice()),
+            2,
+            1_000,
+            DAY,
+            signature
+        ));
+
+        // TooManyAtmosKeys
+        assert_ok!(CreditAccumulation::add_atmos_key(
+            RawOrigin::Root.into(),
+            alice(),
+            20,
+            None
+        ));
+        assert_noop!(
+            CreditAccumulation::add_atmos_key(RawOrigin::Root.into(), eve(), 20, None),
+            Error::<Test>::TooManyAtmosKeys
+        );
+        // updating an existing key is allowed
+        assert_ok!(CreditAccumulation::add_atmos_key(
+            RawOrigin::Root.into(),
+            alice(),
+            25,
+            None
+        ));
+
+        // retire charlie
+        assert_noop!(
+            CreditAccumulation::retire_atmos_key(RawOrigin::Root.into(), eve(), 25),
+            Error::<Test>::AtmosKeyNotExist
+        );
+        assert_noop!(
+            CreditAccumulation::retire_atmos_key(RawOrigin::Root.into(), charlie(), 5),
+            Error::<Test>::InvalidKeyPeriod
+        );
+        assert_ok!(CreditAccumulation::retire_atmos_key(
+            RawOrigin::Root.into(),
+            charlie(),
+            25
+        ));
+        System::assert_last_event(Event::CreditAccumulation(
+            crate::Event::AtmosKeyRetired(charlie(), 25),
+        ));
+        System::set_block_number(25);
+        let signature = sign_traffic_with("Charlie", &charlie(), 3, &alice(), 1_000, DAY);
+        assert_noop!(
+            CreditAccumulation::add_credit_by_traffic(
+                Origin::signed(alice()),
+                3,
+                1_000,
+                DAY,
+                signature
+            ),
+            Error::<Test>::InvalidSignature
+        );
+    });
+}
+
+#[test]
+fn migrate_to_v1() {
+    new_test_ext().execute_with(|| {
+        AtmosAccountid::<Test>::put(bob());
+        CreditAccumulation::on_runtime_upgrade();
+        assert_eq!(CreditAccumulation::atmos_accountid(), None);
+        assert_eq!(
+            CreditAccumulation::atmos_key(bob()),
+            Some(AtmosKey {
+                activation: 0,
+                retirement: None
+            })
+        );
+
+        // payloads of the deployed Atmos, on |atmos key|nonce|sender|,
+        // are accepted until the deadline
+        let deadline = LegacyPayloadWindow::get();
+        assert_eq!(CreditAccumulation::legacy_payload_deadline(), Some(deadline));
+        let signature: [u8; 64] = hex!("5071a1a526b1d2d1833e4de43d1ce22ad3506de2e10ee4a9c18c0b310c54286b9cb10bfb4ee12be6b93e91337de0fa2ea2edd787d083db36211109bdc8438989");
+        assert_ok!(CreditAccumulation::add_credit_by_traffic(
+            Origin::signed(alice()),
+            0,
+            1_000,
+            DAY,
+            signature.into()
+        ));
+        let legacy_signature = |nonce: u64| {
+            let mut data = Vec::new();
+            data.extend_from_slice(&bob().encode());
+            data.extend_from_slice(&nonce.to_be_bytes());
+            data.extend_from_slice(&alice().encode());
+            sign_with_seed("Bob", &sp_io::hashing::blake2_256(&data))
+        };
+        assert_ok!(CreditAccumulation::add_credit_by_traffic(
+            Origin::signed(alice()),
+            1,
+            1_000,
+            DAY,
+            legacy_signature(1)
+        ));
+        // batches never had a legacy payload
+        let mut data = Vec::new();
+        data.extend_from_slice(&bob().encode());
+        data.extend_from_slice(b"batch");
+        data.extend_from_slice(&[1u8; 32]);
+        assert_noop!(
+            CreditAccumulation::submit_traffic_batch(
+                Origin::signed(alice()),
+                [1u8; 32],
+                sign_with_seed("Bob", &sp_io::hashing::blake2_256(&data))
+            ),
+            Error::<Test>::InvalidSignature
+        );
+
+        System::set_block_number(deadline);
+        assert_noop!(
+            CreditAccumulation::add_credit_by_traffic(
+                Origin::signed(alice()),
+                2,
+                1_000,
+                DAY,
+                legacy_signature(2)
+            ),
+            Error::<Test>::InvalidSignature
+        );
+        let signature = sign_traffic(2, &alice(), 1_000, DAY);
+        assert_ok!(CreditAccumulation::add_credit_by_traffic(
+            Origin::signed(alice()),
+            2,
+            1_000,
+            DAY,
+            signature
+        ));
+
+        // payloads are bound to the genesis hash of the chain
+        let signature = sign_traffic(3, &alice(), 1_000, DAY);
+        frame_system::BlockHash::<Test>::insert(0, H256::repeat_byte(1));
+        assert_noop!(
+            CreditAccumulation::add_credit_by_traffic(
+                Origin::signed(alice()),
+                3,
+                1_000,
+                DAY,
+                signature
+            ),
+            Error::<Test>::InvalidSignature
+        );
+    });
+}
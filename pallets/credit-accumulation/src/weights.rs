@@ -46,31 +46,109 @@ use sp_std::marker::PhantomData;
 
 /// Weight functions needed for pallet_credit_accumulation.
 pub trait WeightInfo {
-    fn add_credit_by_traffic() -> Weight;
-    fn set_atmos_pubkey() -> Weight;
+    fn add_credit_by_traffic(k: u32) -> Weight;
+    fn set_atmos_pubkey(k: u32) -> Weight;
+    fn submit_traffic_batch(k: u32) -> Weight;
+    fn claim_traffic_credit(p: u32) -> Weight;
+    fn add_atmos_key(k: u32) -> Weight;
+    fn retire_atmos_key() -> Weight;
 }
 
 /// Weights for pallet_credit_accumulation using the Substrate node and recommended hardware.
 pub struct SubstrateWeight<T>(PhantomData<T>);
 impl<T: frame_system::Config> WeightInfo for SubstrateWeight<T> {
-    fn add_credit_by_traffic() -> Weight {
-        (86_045_000 as Weight)
+    fn add_credit_by_traffic(k: u32) -> Weight {
+        (40_045_000 as Weight)
+            // Standard Error: 9_000
+            .saturating_add((46_000_000 as Weight).saturating_mul(k as Weight))
             .saturating_add(T::DbWeight::get().reads(3 as Weight))
+            .saturating_add(T::DbWeight::get().reads((1 as Weight).saturating_mul(k as Weight)))
             .saturating_add(T::DbWeight::get().writes(1 as Weight))
     }
-    fn set_atmos_pubkey() -> Weight {
-        (1_969_000 as Weight).saturating_add(T::DbWeight::get().writes(1 as Weight))
+    fn set_atmos_pubkey(k: u32) -> Weight {
+        (1_969_000 as Weight)
+            // Standard Error: 1_000
+            .saturating_add((1_310_000 as Weight).saturating_mul(k as Weight))
+            .saturating_add(T::DbWeight::get().reads((1 as Weight).saturating_mul(k as Weight)))
+            .saturating_add(T::DbWeight::get().writes(1 as Weight))
+            .saturating_add(T::DbWeight::get().writes((1 as Weight).saturating_mul(k as Weight)))
+    }
+    fn submit_traffic_batch(k: u32) -> Weight {
+        (38_512_000 as Weight)
+            // Standard Error: 9_000
+            .saturating_add((46_000_000 as Weight).saturating_mul(k as Weight))
+            .saturating_add(T::DbWeight::get().reads(2 as Weight))
+            .saturating_add(T::DbWeight::get().reads((1 as Weight).saturating_mul(k as Weight)))
+            .saturating_add(T::DbWeight::get().writes(3 as Weight))
+    }
+    fn claim_traffic_credit(p: u32) -> Weight {
+        (24_716_000 as Weight)
+            // Standard Error: 2_000
+            .saturating_add((1_120_000 as Weight).saturating_mul(p as Weight))
+            .saturating_add(T::DbWeight::get().reads(4 as Weight))
+            .saturating_add(T::DbWeight::get().writes(2 as Weight))
+    }
+    fn add_atmos_key(k: u32) -> Weight {
+        (2_104_000 as Weight)
+            // Standard Error: 1_000
+            .saturating_add((1_020_000 as Weight).saturating_mul(k as Weight))
+            .saturating_add(T::DbWeight::get().reads(1 as Weight))
+            .saturating_add(T::DbWeight::get().reads((1 as Weight).saturating_mul(k as Weight)))
+            .saturating_add(T::DbWeight::get().writes(1 as Weight))
+            .saturating_add(T::DbWeight::get().writes((1 as Weight).saturating_mul(k as Weight)))
+    }
+    fn retire_atmos_key() -> Weight {
+        (1_893_000 as Weight)
+            .saturating_add(T::DbWeight::get().reads(1 as Weight))
+            .saturating_add(T::DbWeight::get().writes(1 as Weight))
     }
 }
 
 // For backwards compatibility and tests
 impl WeightInfo for () {
-    fn add_credit_by_traffic() -> Weight {
-        (86_045_000 as Weight)
+    fn add_credit_by_traffic(k: u32) -> Weight {
+        (40_045_000 as Weight)
+            // Standard Error: 9_000
+            .saturating_add((46_000_000 as Weight).saturating_mul(k as Weight))
             .saturating_add(RocksDbWeight::get().reads(3 as Weight))
+            .saturating_add(RocksDbWeight::get().reads((1 as Weight).saturating_mul(k as Weight)))
             .saturating_add(RocksDbWeight::get().writes(1 as Weight))
     }
-    fn set_atmos_pubkey() -> Weight {
-        (1_969_000 as Weight).saturating_add(RocksDbWeight::get().writes(1 as Weight))
+    fn set_atmos_pubkey(k: u32) -> Weight {
+        (1_969_000 as Weight)
+            // Standard Error: 1_000
+            .saturating_add((1_310_000 as Weight).saturating_mul(k as Weight))
+            .saturating_add(RocksDbWeight::get().reads((1 as Weight).saturating_mul(k as Weight)))
+            .saturating_add(RocksDbWeight::get().writes(1 as Weight))
+            .saturating_add(RocksDbWeight::get().writes((1 as Weight).saturating_mul(k as Weight)))
+    }
+    fn submit_traffic_batch(k: u32) -> Weight {
+        (38_512_000 as Weight)
+            // Standard Error: 9_000
+            .saturating_add((46_000_000 as Weight).saturating_mul(k as Weight))
+            .saturating_add(RocksDbWeight::get().reads(2 as Weight))
+            .saturating_add(RocksDbWeight::get().reads((1 as Weight).saturating_mul(k as Weight)))
+            .saturating_add(RocksDbWeight::get().writes(3 as Weight))
+    }
+    fn claim_traffic_credit(p: u32) -> Weight {
+        (24_716_000 as Weight)
+            // Standard Error: 2_000
+            .saturating_add((1_120_000 as Weight).saturating_mul(p as Weight))
+            .saturating_add(RocksDbWeight::get().reads(4 as Weight))
+            .saturating_add(RocksDbWeight::get().writes(2 as Weight))
+    }
+    fn add_atmos_key(k: u32) -> Weight {
+        (2_104_000 as Weight)
+            // Standard Error: 1_000
+            .saturating_add((1_020_000 as Weight).saturating_mul(k as Weight))
+            .saturating_add(RocksDbWeight::get().reads(1 as Weight))
+            .saturating_add(RocksDbWeight::get().reads((1 as Weight).saturating_mul(k as Weight)))
+            .saturating_add(RocksDbWeight::get().writes(1 as Weight))
+            .saturating_add(RocksDbWeight::get().writes((1 as Weight).saturating_mul(k as Weight)))
+    }
+    fn retire_atmos_key() -> Weight {
+        (1_893_000 as Weight)
+            .saturating_add(RocksDbWeight::get().reads(1 as Weight))
+            .saturating_add(RocksDbWeight::get().writes(1 as Weight))
     }
 }
@@ -41,9 +41,10 @@ macro_rules! log {
 
 use codec::alloc::vec;
 use codec::{Decode, Encode};
-use sp_runtime::Percent;
+use sp_core::H256;
 #[cfg(feature = "std")]
 use sp_runtime::{Deserialize, Serialize};
+use sp_runtime::{Perbill, Percent};
 
 #[cfg(feature = "std")]
 use frame_support::traits::GenesisBuild;
@@ -106,6 +107,17 @@ pub struct CreditData {
     pub reward_eras: EraIndex, // reward eras since device gets online
 }
 
+/// Maximum number of penalties kept in the penalty history of an account
+pub const MAX_CREDIT_PENALTY_HISTORY: usize = 32;
+
+/// A credit penalty, the credit subtracted in an era for a reason
+#[derive(Decode, Encode, Clone, Debug, PartialEq, Eq, TypeInfo)]
+pub struct CreditPenalty {
+    pub era: EraIndex,
+    pub credit: u64,
+    pub reason: H256,
+}
+
 pub trait CreditInterface<AccountId, Balance> {
     fn get_credit_score(account_id: &AccountId) -> Option<u64>;
     fn pass_threshold(account_id: &AccountId) -> bool;
@@ -117,10 +129,12 @@ pub trait CreditInterface<AccountId, Balance> {
         to: EraIndex,
     ) -> (Option<(Balance, Balance)>, Weight);
     fn get_top_referee_reward(account_id: &AccountId) -> (Balance, Weight);
-    fn update_credit(micropayment: (AccountId, Balance));
-    fn update_credit_by_traffic(server: AccountId);
+    /// Returns the part of the micropayment balance converted into credit.
+    fn update_credit(micropayment: (AccountId, Balance)) -> Balance;
+    fn update_credit_by_traffic(server: AccountId, traffic_ratio: Perbill);
     fn get_current_era() -> EraIndex;
     fn update_credit_by_tip(who: AccountId, add_credit: u64);
+    fn update_credit_by_penalty(who: AccountId, sub_credit: u64, reason: H256);
     fn init_delegator_history(account_id: &AccountId, era: u32) -> bool;
 }
 
@@ -137,7 +151,7 @@ pub mod pallet {
     use pallet_deeper_node::NodeInterface;
     use sp_runtime::{
         traits::{Saturating, Zero},
-        Perbill,
+        PerThing, Perbill,
     };
     use sp_std::{cmp, collections::btree_map::BTreeMap, convert::TryInto};
 
@@ -195,6 +209,12 @@ pub mod pallet {
     pub type UserCreditHistory<T: Config> =
         StorageMap<_, Blake2_128Concat, T::AccountId, Vec<(EraIndex, CreditData)>, ValueQuery>;
 
+    /// the most recent MAX_CREDIT_PENALTY_HISTORY penalties of an account
+    #[pallet::storage]
+    #[pallet::getter(fn credit_penalty_history)]
+    pub type CreditPenaltyHistory<T: Config> =
+        StorageMap<_, Blake2_128Concat, T::AccountId, Vec<CreditPenalty>, ValueQuery>;
+
     #[pallet::storage]
     #[pallet::getter(fn credit_settings)]
     pub type CreditSettings<T: Config> = StorageDoubleMap<
@@ -231,6 +251,20 @@ pub mod pallet {
     pub type LastCreditUpdateTimestamp<T: Config> =
         StorageMap<_, Blake2_128Concat, T::AccountId, u64, OptionQuery>;
 
+    /// credit added by micro-payment and traffic in the window started at
+    /// LastCreditUpdateTimestamp, capped at CreditCapTwoEras
+    #[pallet::storage]
+    #[pallet::getter(fn credit_added_in_window)]
+    pub type CreditAddedInWindow<T: Config> =
+        StorageMap<_, Blake2_128Concat, T::AccountId, u64, ValueQuery>;
+
+    /// fraction of a credit earned by traffic but not added yet,
+    /// carried over to the next credit update by traffic
+    #[pallet::storage]
+    #[pallet::getter(fn traffic_credit_remainder)]
+    pub type TrafficCreditRemainder<T: Config> =
+        StorageMap<_, Blake2_128Concat, T::AccountId, Perbill, ValueQuery>;
+
     #[pallet::genesis_config]
     pub struct GenesisConfig<T: Config> {
         pub credit_settings: Vec<CreditSetting<BalanceOf<T>>>,
@@ -271,6 +305,8 @@ pub mod pallet {
         CreditScoreSlashed(T::AccountId, u64),
         CreditDataAddedByTraffic(T::AccountId, u64),
         CreditDataAddedByTip(T::AccountId, u64),
+        /// account, new credit, reason
+        CreditScorePenalized(T::AccountId, u64, H256),
         //Status: 1-Invalid Inputs; 2-InvalidCreditData; 3-NoReward; 4-InvalidCreditHistory; 5-ExpiryEra; 6-CreditMap is empty;
         GetRewardResult(T::AccountId, EraIndex, EraIndex, u8),
         CreditHistoryUpdateSuccess(T::AccountId, EraIndex),
@@ -593,32 +629,71 @@ pub mod pallet {
         }
 
         /// check the interval between two credit update as long enouth
-        /// return (u64,bool):
+        /// return Option<(u64,bool)>, None if the credit was never updated:
         /// the first means the inteval of eras ;
         /// the second means if use era for check (tobe deprecated)
         fn check_update_credit_interval(
             server_id: &T::AccountId,
             current_era: EraIndex,
-            onboard_era: EraIndex,
             now_as_secs: u64,
-        ) -> (u64, bool) {
-            let diffs;
-            let mut era_used = false;
+        ) -> Option<(u64, bool)> {
             if let Some(pre_update_timestamp) = Self::last_credit_update_timestamp(server_id) {
                 let era_block_count = TryInto::<u64>::try_into(T::BlocksPerEra::get())
                     .ok()
                     .unwrap();
                 let secs_per_block = T::SecsPerBlock::get() as u64;
-                diffs = now_as_secs.saturating_sub(pre_update_timestamp)
+                let diffs = now_as_secs.saturating_sub(pre_update_timestamp)
                     / era_block_count.saturating_mul(secs_per_block);
-            } else if let Some(last_credit_update_era) = Self::last_credit_update(&server_id) {
-                diffs = current_era.saturating_sub(last_credit_update_era) as u64;
-                era_used = true;
+                Some((diffs, false))
             } else {
-                // if this is the first update, we use onboard era as the last update era
-                diffs = current_era.saturating_sub(onboard_era) as u64;
+                Self::last_credit_update(server_id)
+                    .map(|last_era| (current_era.saturating_sub(last_era) as u64, true))
+            }
+        }
+
+        /// credit that micro-payment and traffic can still add to the server.
+        /// A window lasts two eras and allows CreditCapTwoEras, windows passed since the
+        /// last update add up, and the first update of a server opens its first window.
+        /// return (u64,bool,bool): the credit left, if a new window starts and if era is used
+        fn credit_window_budget(
+            server_id: &T::AccountId,
+            current_era: EraIndex,
+            now_as_secs: u64,
+        ) -> (u64, bool, bool) {
+            let cap: u64 = T::CreditCapTwoEras::get() as u64;
+            match Self::check_update_credit_interval(server_id, current_era, now_as_secs) {
+                None => (cap, true, false),
+                Some((time_eras, era_used)) if time_eras >= 2 => {
+                    (cap.saturating_mul(time_eras / 2), true, era_used)
+                }
+                Some((_, era_used)) => (
+                    cap.saturating_sub(Self::credit_added_in_window(server_id)),
+                    false,
+                    era_used,
+                ),
+            }
+        }
+
+        /// record the credit added in the current window, or start a new window with it
+        fn record_window_credit(
+            server_id: &T::AccountId,
+            credit: u64,
+            new_window: bool,
+            era_used: bool,
+            now_as_secs: u64,
+        ) {
+            if new_window {
+                LastCreditUpdateTimestamp::<T>::insert(server_id, now_as_secs);
+                CreditAddedInWindow::<T>::insert(server_id, credit);
+                // clear old
+                if era_used {
+                    LastCreditUpdate::<T>::remove(server_id);
+                }
+            } else {
+                CreditAddedInWindow::<T>::mutate(server_id, |added| {
+                    *added = added.saturating_add(credit)
+                });
             }
-            (diffs, era_used)
         }
     }
 
@@ -818,17 +893,16 @@ pub mod pallet {
         }
 
         /// update credit score based on micropayment tuple
-        fn update_credit(micropayment: (T::AccountId, BalanceOf<T>)) {
+        fn update_credit(micropayment: (T::AccountId, BalanceOf<T>)) -> BalanceOf<T> {
             let (server_id, balance) = micropayment;
-            let onboard_era = Self::get_onboard_era(&server_id);
-            if onboard_era.is_none() {
+            if Self::get_onboard_era(&server_id).is_none() {
                 // credit is not updated if the device is never online
                 log!(
                     info,
                     "update_credit account : {:?}, never online",
                     server_id
                 );
-                return;
+                return Zero::zero();
             }
             let balance_num = TryInto::<u128>::try_into(balance).ok().unwrap();
             let mut score_delta: u64 = balance_num
@@ -844,20 +918,9 @@ pub mod pallet {
             if score_delta > 0 {
                 let current_era = Self::get_current_era();
                 let now_as_secs = T::UnixTime::now().as_secs();
-                let (mut time_eras, era_used) = Self::check_update_credit_interval(
-                    &server_id,
-                    current_era,
-                    onboard_era.unwrap(),
-                    now_as_secs,
-                );
-
-                if time_eras < 2 && Self::last_credit_update_timestamp(&server_id).is_none() {
-                    // first update within 2 eras, we boost it to 2 eras so that credit can be updated
-                    time_eras = 2;
-                }
-                if time_eras >= 2 {
-                    let cap: u64 = T::CreditCapTwoEras::get() as u64;
-                    let total_cap = cap * (time_eras / 2);
+                let (total_cap, new_window, era_used) =
+                    Self::credit_window_budget(&server_id, current_era, now_as_secs);
+                if total_cap > 0 {
                     if score_delta > total_cap {
                         score_delta = total_cap;
                         log!(
@@ -872,11 +935,17 @@ pub mod pallet {
                         .unwrap_or(0)
                         .saturating_add(score_delta);
                     if Self::_update_credit(&server_id, new_credit) {
-                        LastCreditUpdateTimestamp::<T>::insert(&server_id, now_as_secs);
+                        Self::record_window_credit(
+                            &server_id,
+                            score_delta,
+                            new_window,
+                            era_used,
+                            now_as_secs,
+                        );
                         Self::update_credit_history(&server_id, current_era);
-                        if era_used {
-                            LastCreditUpdate::<T>::remove(server_id);
-                        }
+                        let credited = (score_delta as u128)
+                            .saturating_mul(T::MicropaymentToCreditFactor::get());
+                        return credited.try_into().unwrap_or(balance).min(balance);
                     } else {
                         log!(
                             error,
@@ -887,12 +956,14 @@ pub mod pallet {
                     }
                 }
             }
+            Zero::zero()
         }
 
-        /// update credit score by traffic
-        fn update_credit_by_traffic(server_id: T::AccountId) {
-            let onboard_era = Self::get_onboard_era(&server_id);
-            if onboard_era.is_none() {
+        /// update credit score by traffic, the credit added is traffic_ratio of CreditCapTwoEras
+        /// and shares the credit window with micro-payment. Fractions of a credit add up
+        /// over updates, and a window isn't started by an update adding no credit
+        fn update_credit_by_traffic(server_id: T::AccountId, traffic_ratio: Perbill) {
+            if Self::get_onboard_era(&server_id).is_none() {
                 // credit is not updated if the device is never online
                 log!(
                     info,
@@ -903,19 +974,38 @@ pub mod pallet {
             }
             let current_era = Self::get_current_era();
             let now_as_secs = T::UnixTime::now().as_secs();
-            let (time_eras, era_used) = Self::check_update_credit_interval(
-                &server_id,
-                current_era,
-                onboard_era.unwrap(),
-                now_as_secs,
-            );
-            if time_eras >= 2 {
+            let (total_cap, new_window, era_used) =
+                Self::credit_window_budget(&server_id, current_era, now_as_secs);
+            if total_cap > 0 {
                 let cap: u64 = T::CreditCapTwoEras::get() as u64;
+                // credit in billionths, including the fraction left by former updates
+                let accuracy = Perbill::ACCURACY as u64;
+                let carried = Self::traffic_credit_remainder(&server_id).deconstruct() as u64;
+                let parts = (traffic_ratio.deconstruct() as u64)
+                    .saturating_mul(cap)
+                    .saturating_add(carried);
+                let score_delta = (parts / accuracy).min(total_cap);
+                let remainder = Perbill::from_parts((parts % accuracy) as u32);
+                if score_delta == 0 {
+                    TrafficCreditRemainder::<T>::insert(&server_id, remainder);
+                    return;
+                }
                 let new_credit = Self::get_credit_score(&server_id)
                     .unwrap_or(0)
-                    .saturating_add(cap);
+                    .saturating_add(score_delta);
                 if Self::_update_credit(&server_id, new_credit) {
-                    LastCreditUpdateTimestamp::<T>::insert(&server_id, now_as_secs);
+                    Self::record_window_credit(
+                        &server_id,
+                        score_delta,
+                        new_window,
+                        era_used,
+                        now_as_secs,
+                    );
+                    if remainder.is_zero() {
+                        TrafficCreditRemainder::<T>::remove(&server_id);
+                    } else {
+                        TrafficCreditRemainder::<T>::insert(&server_id, remainder);
+                    }
                     Self::update_credit_history(&server_id, current_era);
                     Self::deposit_event(Event::CreditDataAddedByTraffic(
                         server_id.clone(),
@@ -929,10 +1019,6 @@ pub mod pallet {
                         server_id
                     );
                 }
-                // clear old
-                if era_used {
-                    LastCreditUpdate::<T>::remove(server_id);
-                }
             }
         }
 
@@ -965,6 +1051,36 @@ pub mod pallet {
             }
         }
 
+        fn update_credit_by_penalty(who: T::AccountId, sub_credit: u64, reason: H256) {
+            let credit = match Self::get_credit_score(&who) {
+                Some(credit) => credit,
+                None => {
+                    log!(
+                        info,
+                        "update_credit_by_penalty account : {:?}, no credit data",
+                        who
+                    );
+                    return;
+                }
+            };
+            let current_era = Self::get_current_era();
+            let new_credit = credit.saturating_sub(sub_credit);
+            if Self::_update_credit(&who, new_credit) {
+                Self::update_credit_history(&who, current_era);
+                CreditPenaltyHistory::<T>::mutate(&who, |history| {
+                    if history.len() >= MAX_CREDIT_PENALTY_HISTORY {
+                        history.remove(0);
+                    }
+                    history.push(CreditPenalty {
+                        era: current_era,
+                        credit: credit - new_credit,
+                        reason,
+                    });
+                });
+                Self::deposit_event(Event::CreditScorePenalized(who, new_credit, reason));
+            }
+        }
+
         fn init_delegator_history(account_id: &T::AccountId, era: u32) -> bool {
             let credit_data = Self::user_credit(account_id); // 1 db read
             if credit_data.is_none() {
@@ -13,13 +13,17 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use super::{CreditData, CreditLevel, CreditSetting, UserCredit};
-use crate::{mock::*, CreditInterface, Error, UserCreditHistory};
+use super::{CreditData, CreditLevel, CreditPenalty, CreditSetting, UserCredit};
+use crate::{
+    mock::*, CreditInterface, Error, LastCreditUpdate, UserCreditHistory,
+    MAX_CREDIT_PENALTY_HISTORY,
+};
 use frame_support::traits::Currency;
 use frame_support::{assert_noop, assert_ok, dispatch::DispatchErrorWithPostInfo};
 use frame_system::RawOrigin;
+use sp_core::H256;
 use sp_runtime::traits::BadOrigin;
-use sp_runtime::Percent;
+use sp_runtime::{Perbill, Percent};
 
 #[test]
 fn get_credit_level() {
@@ -324,24 +328,96 @@ fn update_credit() {
 #[test]
 fn update_credit_by_traffic() {
     new_test_ext().execute_with(|| {
-        Credit::update_credit_by_traffic(1);
+        Credit::update_credit_by_traffic(1, Perbill::one());
         assert_eq!(Credit::user_credit(&1).unwrap().credit, 0);
 
+        // the first update opens the first window
         assert_ok!(DeeperNode::im_online(Origin::signed(1)));
-        Credit::update_credit_by_traffic(1);
-        assert_eq!(Credit::user_credit(&1).unwrap().credit, 0);
+        Credit::update_credit_by_traffic(1, Perbill::one());
+        assert_eq!(Credit::user_credit(&1).unwrap().credit, 1); // 0 + 1
 
         run_to_block(BLOCKS_PER_ERA * 2);
-        Credit::update_credit_by_traffic(1);
-        assert_eq!(Credit::user_credit(&1).unwrap().credit, 1); // 0 + 1
+        Credit::update_credit_by_traffic(1, Perbill::one());
+        assert_eq!(Credit::user_credit(&1).unwrap().credit, 2); // 1 + 1
 
         run_to_block(BLOCKS_PER_ERA * 3);
-        Credit::update_credit_by_traffic(1);
-        assert_eq!(Credit::user_credit(&1).unwrap().credit, 1); // 1 + 0
+        Credit::update_credit_by_traffic(1, Perbill::one());
+        assert_eq!(Credit::user_credit(&1).unwrap().credit, 2); // 2 + 0
 
         run_to_block(BLOCKS_PER_ERA * 4);
-        Credit::update_credit_by_traffic(1);
+        Credit::update_credit_by_traffic(1, Perbill::one());
+        assert_eq!(Credit::user_credit(&1).unwrap().credit, 3); // 2 + 1
+
+        // credit added is proportional to the traffic, fractions add up
+        // and an update adding no credit doesn't start a window
+        let window_start = Credit::last_credit_update_timestamp(1);
+        run_to_block(BLOCKS_PER_ERA * 6);
+        Credit::update_credit_by_traffic(1, Perbill::from_percent(40));
+        assert_eq!(Credit::user_credit(&1).unwrap().credit, 3); // 3 + 0
+        assert_eq!(
+            Credit::traffic_credit_remainder(1),
+            Perbill::from_percent(40)
+        );
+        assert_eq!(Credit::last_credit_update_timestamp(1), window_start);
+
+        run_to_block(BLOCKS_PER_ERA * 7);
+        Credit::update_credit_by_traffic(1, Perbill::from_percent(70));
+        assert_eq!(Credit::user_credit(&1).unwrap().credit, 4); // 3 + 1
+        assert_eq!(
+            Credit::traffic_credit_remainder(1),
+            Perbill::from_percent(10)
+        );
+
+        run_to_block(BLOCKS_PER_ERA * 9);
+        Credit::update_credit_by_traffic(1, Perbill::from_percent(90));
+        assert_eq!(Credit::user_credit(&1).unwrap().credit, 5); // 4 + 1
+        assert_eq!(Credit::traffic_credit_remainder(1), Perbill::zero());
+    });
+}
+
+#[test]
+fn micropayment_and_traffic_share_the_credit_window() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(DeeperNode::im_online(Origin::signed(1)));
+        run_to_block(BLOCKS_PER_ERA * 10);
+        // a server without any update starts with one window, not one per era since onboarding
+        // and only the part of the payment converted into credit is returned
+        assert_eq!(
+            Credit::update_credit((1, 4 * 1_000_000_000_000_000)),
+            1_000_000_000_000_000
+        );
+        assert_eq!(Credit::user_credit(&1).unwrap().credit, 1);
+        assert_eq!(Credit::update_credit((1, 1_000_000_000_000_000)), 0);
+        Credit::update_credit_by_traffic(1, Perbill::one());
+        assert_eq!(Credit::user_credit(&1).unwrap().credit, 1); // 1 + 0
+        assert_eq!(Credit::credit_added_in_window(1), 1);
+
+        run_to_block(BLOCKS_PER_ERA * 12);
+        Credit::update_credit_by_traffic(1, Perbill::one());
         assert_eq!(Credit::user_credit(&1).unwrap().credit, 2); // 1 + 1
+        assert_eq!(Credit::update_credit((1, 1_000_000_000_000_000)), 0);
+        assert_eq!(Credit::user_credit(&1).unwrap().credit, 2);
+    });
+}
+
+#[test]
+fn legacy_era_record_kept_within_the_window() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(DeeperNode::im_online(Origin::signed(1)));
+        run_to_block(BLOCKS_PER_ERA * 5);
+        LastCreditUpdate::<Test>::insert(1, 4);
+        Credit::update_credit_by_traffic(1, Perbill::one());
+        assert_eq!(Credit::user_credit(&1).unwrap().credit, 1);
+        // the window of the era record is still open, so the record isn't cleared
+        assert_eq!(Credit::last_credit_update(1), Some(4));
+        assert_eq!(Credit::last_credit_update_timestamp(1), None);
+        assert_eq!(Credit::update_credit((1, 1_000_000_000_000_000)), 0);
+
+        run_to_block(BLOCKS_PER_ERA * 6);
+        Credit::update_credit_by_traffic(1, Perbill::one());
+        assert_eq!(Credit::user_credit(&1).unwrap().credit, 2);
+        assert_eq!(Credit::last_credit_update(1), None);
+        assert!(Credit::last_credit_update_timestamp(1).is_some());
     });
 }
 
@@ -357,6 +433,48 @@ fn update_credit_by_tip() {
     });
 }
 
+#[test]
+fn update_credit_by_penalty() {
+    new_test_ext().execute_with(|| {
+        let reason = H256::repeat_byte(1);
+        // no credit data
+        Credit::update_credit_by_penalty(100, 30, reason);
+        assert_eq!(Credit::user_credit(&100), None);
+        assert!(Credit::credit_penalty_history(&100).is_empty());
+
+        Credit::update_credit_by_penalty(3, 30, reason);
+        let credit_data = Credit::user_credit(&3).unwrap();
+        assert_eq!(credit_data.credit, 70);
+        assert_eq!(credit_data.current_credit_level, CreditLevel::Zero);
+        // the penalty saturates at zero credit
+        Credit::update_credit_by_penalty(3, 100, H256::repeat_byte(2));
+        assert_eq!(Credit::user_credit(&3).unwrap().credit, 0);
+        assert_eq!(
+            Credit::credit_penalty_history(&3),
+            vec![
+                CreditPenalty {
+                    era: 0,
+                    credit: 30,
+                    reason
+                },
+                CreditPenalty {
+                    era: 0,
+                    credit: 70,
+                    reason: H256::repeat_byte(2)
+                },
+            ]
+        );
+
+        // only the most recent penalties are kept
+        for _ in 0..MAX_CREDIT_PENALTY_HISTORY {
+            Credit::update_credit_by_penalty(3, 1, H256::repeat_byte(3));
+        }
+        let history = Credit::credit_penalty_history(&3);
+        assert_eq!(history.len(), MAX_CREDIT_PENALTY_HISTORY);
+        assert!(history.iter().all(|p| p.reason == H256::repeat_byte(3)));
+    });
+}
+
 #[test]
 fn get_reward_work() {
     new_test_ext().execute_with(|| {
@@ -21,12 +21,17 @@ use crate::Pallet as Micropayment;
 pub use frame_benchmarking::{
     account, benchmarks, impl_benchmark_test_suite, whitelist_account, whitelisted_caller,
 };
-use frame_support::traits::Currency;
+use frame_support::{
+    traits::{fungibles, Currency},
+    weights::Weight,
+};
 use frame_system::Pallet as System;
 use frame_system::RawOrigin;
 //use hex_literal::hex;
-use codec::Encode;
+use codec::{Decode, Encode};
+use pallet_credit::CreditLevel;
 use sp_core::sr25519;
+use sp_runtime::{traits::TrailingZeroInput, Percent};
 use sp_io::crypto::sr25519_sign;
 /// Grab a funded user with balance_factor DPR.
 pub fn create_funded_user<T: Config>(string: &'static str, balance_factor: u32) -> T::AccountId {
@@ -38,6 +43,7 @@ pub fn create_funded_user<T: Config>(string: &'static str, balance_factor: u32)
 }
 
 benchmarks! {
+    where_clause { where T::Assets: fungibles::Create<T::AccountId> }
     open_channel {
         let client = create_funded_user::<T>("Alice", 100);
         let server = create_funded_user::<T>("Bob", 100);
@@ -180,6 +186,209 @@ benchmarks! {
         );
 
     }
+
+    initiate_close {
+        let client = create_funded_user::<T>("Alice", 100);
+        let server = create_funded_user::<T>("Bob", 100);
+        let amount = T::Currency::minimum_balance() * 30u32.into();
+
+        Micropayment::<T>::open_channel(RawOrigin::Signed(client.clone()).into(), server.clone(), amount, 3600)?;
+    }: _(RawOrigin::Signed(client.clone()), server.clone())
+    verify {
+        assert!(ClosingChannel::<T>::contains_key(&client, &server));
+    }
+
+    submit_voucher {
+        let client = create_funded_user::<T>("Alice", 100);
+        let server = create_funded_user::<T>("Bob", 100);
+        let amount = T::Currency::minimum_balance() * 30u32.into();
+
+        Micropayment::<T>::open_channel(RawOrigin::Signed(client.clone()).into(), server.clone(), amount, 3600)?;
+
+        let nonce: u64 = 0;
+        let voucher_amount = T::Currency::minimum_balance() * 10u32.into();
+        let msg = Micropayment::<T>::construct_voucher_hash(&server, nonce, voucher_amount);
+
+        let mut pk = [0u8; 32];
+        pk.copy_from_slice(&client.encode());
+        let pub_key = sr25519::Public::from_raw(pk);
+
+        let signature: [u8; 64] = sr25519_sign(0.into(), &pub_key, &msg).unwrap().into();
+    }: _(RawOrigin::Signed(server.clone()), client.clone(), server.clone(), voucher_amount, signature.into())
+    verify {
+        assert_eq!(
+            Micropayment::<T>::closing_channel(&client, &server).map(|c| c.amount),
+            Some(voucher_amount)
+        );
+    }
+
+    finalize_close {
+        let client = create_funded_user::<T>("Alice", 100);
+        let server = create_funded_user::<T>("Bob", 100);
+        let amount = T::Currency::minimum_balance() * 30u32.into();
+
+        Micropayment::<T>::open_channel(RawOrigin::Signed(client.clone()).into(), server.clone(), amount, 3600)?;
+        Micropayment::<T>::initiate_close(RawOrigin::Signed(client.clone()).into(), server.clone())?;
+        let challenge_end = System::<T>::block_number() + T::ChallengePeriod::get();
+        System::<T>::set_block_number(challenge_end + 1u32.into());
+    }: _(RawOrigin::Signed(client.clone()), client.clone(), server.clone())
+    verify {
+        assert!(!Channel::<T>::contains_key(&client, &server));
+        assert!(!ClosingChannel::<T>::contains_key(&client, &server));
+    }
+
+    cooperative_close {
+        let client = create_funded_user::<T>("Alice", 100);
+        let server = create_funded_user::<T>("Bob", 100);
+        let amount = T::Currency::minimum_balance() * 30u32.into();
+
+        Micropayment::<T>::open_channel(RawOrigin::Signed(client.clone()).into(), server.clone(), amount, 3600)?;
+
+        let nonce: u64 = 0;
+        let close_amount = T::Currency::minimum_balance() * 10u32.into();
+        let msg = Micropayment::<T>::construct_close_hash(&client, &server, nonce, close_amount);
+
+        let mut pk = [0u8; 32];
+        pk.copy_from_slice(&client.encode());
+        let client_key = sr25519::Public::from_raw(pk);
+        pk.copy_from_slice(&server.encode());
+        let server_key = sr25519::Public::from_raw(pk);
+
+        let client_signature: [u8; 64] = sr25519_sign(0.into(), &client_key, &msg).unwrap().into();
+        let server_signature: [u8; 64] = sr25519_sign(0.into(), &server_key, &msg).unwrap().into();
+    }: _(RawOrigin::Signed(server.clone()), client.clone(), server.clone(), close_amount, client_signature.into(), server_signature.into())
+    verify {
+        assert!(!Channel::<T>::contains_key(&client, &server));
+    }
+
+    claim_htlc {
+        let client = create_funded_user::<T>("Alice", 100);
+        let server = create_funded_user::<T>("Bob", 100);
+        let amount = T::Currency::minimum_balance() * 30u32.into();
+
+        Micropayment::<T>::open_channel(RawOrigin::Signed(client.clone()).into(), server.clone(), amount, 3600)?;
+
+        let nonce: u64 = 0;
+        let htlc_amount = T::Currency::minimum_balance() * 10u32.into();
+        let preimage = [1u8; 32];
+        let hashlock = sp_io::hashing::blake2_256(&preimage);
+        let timelock: T::BlockNumber = 100u32.into();
+        let msg = Micropayment::<T>::construct_htlc_hash(&server, nonce, htlc_amount, &hashlock, timelock);
+
+        let mut pk = [0u8; 32];
+        pk.copy_from_slice(&client.encode());
+        let pub_key = sr25519::Public::from_raw(pk);
+
+        let signature: [u8; 64] = sr25519_sign(0.into(), &pub_key, &msg).unwrap().into();
+    }: _(RawOrigin::Signed(server.clone()), client.clone(), htlc_amount, timelock, preimage, signature.into())
+    verify {
+        assert_eq!(Micropayment::<T>::claimed_htlc((&client, &server), hashlock), Some(()));
+    }
+
+    withdraw_from_channel {
+        let client = create_funded_user::<T>("Alice", 100);
+        let server = create_funded_user::<T>("Bob", 100);
+        let amount = T::Currency::minimum_balance() * 30u32.into();
+
+        Micropayment::<T>::open_channel(RawOrigin::Signed(client.clone()).into(), server.clone(), amount, 3600)?;
+
+        let nonce: u64 = 0;
+        let withdraw_amount = T::Currency::minimum_balance() * 10u32.into();
+        let msg = Micropayment::<T>::construct_withdraw_hash(&client, &server, nonce, withdraw_amount);
+
+        let mut pk = [0u8; 32];
+        pk.copy_from_slice(&server.encode());
+        let server_key = sr25519::Public::from_raw(pk);
+
+        let server_signature: [u8; 64] = sr25519_sign(0.into(), &server_key, &msg).unwrap().into();
+    }: _(RawOrigin::Signed(client.clone()), server.clone(), withdraw_amount, Some(server_signature.into()))
+    verify {
+        assert_eq!(Micropayment::<T>::channel(&client, &server).balance, amount - withdraw_amount);
+    }
+
+    extend_channel {
+        let client = create_funded_user::<T>("Alice", 100);
+        let server = create_funded_user::<T>("Bob", 100);
+        let amount = T::Currency::minimum_balance() * 30u32.into();
+
+        Micropayment::<T>::open_channel(RawOrigin::Signed(client.clone()).into(), server.clone(), amount, 3600)?;
+    }: _(RawOrigin::Signed(client.clone()), server.clone(), 3600)
+    verify {
+        assert_eq!(Micropayment::<T>::channel(&client, &server).expiration, 1440u32.into());
+    }
+
+    settle_expired_channel {
+        let client = create_funded_user::<T>("Alice", 100);
+        let server = create_funded_user::<T>("Bob", 100);
+        let amount = T::Currency::minimum_balance() * 30u32.into();
+
+        Micropayment::<T>::open_channel(RawOrigin::Signed(client.clone()).into(), server.clone(), amount, 3600)?;
+        // only the expiration block of the channel is left to process
+        ExpiryCursor::<T>::put(T::BlockNumber::from(720u32));
+        System::<T>::set_block_number(721u32.into());
+    }: {
+        Micropayment::<T>::settle_expired_channels(721u32.into(), Weight::MAX);
+    }
+    verify {
+        assert!(!Channel::<T>::contains_key(client, server));
+    }
+
+    open_asset_channel {
+        let client = create_funded_user::<T>("Alice", 100);
+        let server = create_funded_user::<T>("Bob", 100);
+        let asset_id = T::AssetId::decode(&mut TrailingZeroInput::zeroes()).unwrap();
+        <T::Assets as fungibles::Create<_>>::create(asset_id, client.clone(), true, 1u32.into())?;
+        <T::Assets as fungibles::Mutate<_>>::mint_into(asset_id, &client, 1000u32.into())?;
+        let setting = AssetSettingOf::<T> {
+            burn: Percent::from_percent(10),
+            dpr_per_unit: T::Currency::minimum_balance(),
+            decimals: 0,
+        };
+        Micropayment::<T>::set_asset_setting(RawOrigin::Root.into(), asset_id, Some(setting))?;
+        // the first channel in the asset also funds the escrow's minimum balance
+        let amount: BalanceOf<T> = 100u32.into();
+    }: _(RawOrigin::Signed(client.clone()), server.clone(), asset_id, amount, 3600)
+    verify {
+        assert_eq!(Micropayment::<T>::channel(&client, &server).balance, amount);
+        assert_eq!(Micropayment::<T>::channel_asset(&client, &server), Some(asset_id));
+    }
+
+    set_asset_setting {
+        let asset_id = T::AssetId::decode(&mut TrailingZeroInput::zeroes()).unwrap();
+        let setting = AssetSettingOf::<T> {
+            burn: Percent::from_percent(10),
+            dpr_per_unit: T::Currency::minimum_balance(),
+            decimals: 0,
+        };
+    }: _(RawOrigin::Root, asset_id, Some(setting.clone()))
+    verify {
+        assert_eq!(Micropayment::<T>::asset_setting(asset_id), Some(setting));
+    }
+
+    set_fee_tier {
+    }: _(RawOrigin::Root, CreditLevel::Three, Some(Percent::from_percent(5)))
+    verify {
+        assert_eq!(Micropayment::<T>::fee_tier(CreditLevel::Three), Some(Percent::from_percent(5)));
+    }
+
+    set_fee_split {
+        let split = FeeSplit {
+            burn: Percent::from_percent(50),
+            treasury: Percent::from_percent(30),
+            validators: Percent::from_percent(20),
+        };
+    }: _(RawOrigin::Root, split.clone())
+    verify {
+        assert_eq!(Micropayment::<T>::fee_split(), split);
+    }
+
+    set_funding_source {
+        let account = create_funded_user::<T>("Alice", 100);
+        let source = create_funded_user::<T>("Bob", 100);
+    }: _(RawOrigin::Root, account.clone(), Some(source.clone()))
+    verify {
+        assert_eq!(Micropayment::<T>::funding_source(account), Some(source));
+    }
 }
 
 #[cfg(test)]
@@ -195,6 +404,15 @@ mod tests {
             assert_ok!(Pallet::<Test>::test_benchmark_close_channel());
             assert_ok!(Pallet::<Test>::test_benchmark_close_expired_channels());
             assert_ok!(Pallet::<Test>::test_benchmark_add_balance());
+            assert_ok!(Pallet::<Test>::test_benchmark_initiate_close());
+            assert_ok!(Pallet::<Test>::test_benchmark_finalize_close());
+            assert_ok!(Pallet::<Test>::test_benchmark_settle_expired_channel());
+            assert_ok!(Pallet::<Test>::test_benchmark_open_asset_channel());
+            assert_ok!(Pallet::<Test>::test_benchmark_set_asset_setting());
+            assert_ok!(Pallet::<Test>::test_benchmark_set_funding_source());
+            assert_ok!(Pallet::<Test>::test_benchmark_extend_channel());
+            assert_ok!(Pallet::<Test>::test_benchmark_set_fee_tier());
+            assert_ok!(Pallet::<Test>::test_benchmark_set_fee_split());
             //assert_ok!(test_benchmark_claim_payment::<Test>()); // Move to test.rs
         });
     }
@@ -51,20 +51,27 @@ pub mod pallet {
     use crate::AccountCreator;
     use frame_support::codec::{Decode, Encode};
     use frame_support::traits::{
-        tokens::currency::Currency, ExistenceRequirement, Get, OnUnbalanced, WithdrawReasons,
+        tokens::{currency::Currency, fungibles},
+        ExistenceRequirement, Get, Imbalance, OnUnbalanced, WithdrawReasons,
     };
     use frame_support::{
         dispatch::{DispatchError, DispatchResultWithPostInfo},
         pallet_prelude::*,
+        storage::{with_transaction, TransactionOutcome},
+        transactional, PalletId,
     };
     use frame_system::pallet_prelude::*;
     use log::error;
-    use pallet_credit::CreditInterface;
+    use pallet_credit::{CreditInterface, CreditLevel, EraIndex};
     use pallet_deeper_node::NodeInterface;
     use sp_core::sr25519;
     use sp_io::crypto::sr25519_verify;
-    use sp_runtime::Percent;
-    use sp_std::prelude::Vec;
+    use sp_runtime::{
+        helpers_128bit::multiply_by_rational,
+        traits::{AccountIdConversion, IdentifyAccount, Saturating, Verify, Zero},
+        Percent,
+    };
+    use sp_std::{convert::TryInto, prelude::Vec};
 
     /// Configure the pallet by specifying the parameters and types on which it depends.
     #[pallet::config]
@@ -85,11 +92,59 @@ pub mod pallet {
         /// NodeInterface of deeper-node pallet
         type NodeInterface: NodeInterface<Self::AccountId, Self::BlockNumber>;
 
+        /// Fee rate of payments out of channels whose server has no fee tier
         type MicropaymentBurn: Get<Percent>;
 
+        /// Receives the treasury share of fees
         type Slash: OnUnbalanced<NegativeImbalanceOf<Self>>;
+        /// Receives the validator share of fees
+        type ValidatorReward: OnUnbalanced<NegativeImbalanceOf<Self>>;
+
+        /// Number of blocks the counterparty has to submit a higher voucher
+        /// after a unilateral close is initiated.
+        #[pallet::constant]
+        type ChallengePeriod: Get<Self::BlockNumber>;
+
+        /// Signature type of vouchers and payment claims, e.g. `MultiSignature`.
+        type Signature: Verify<Signer = Self::Signer> + Decode;
+        /// Public key type matching `Signature`, e.g. `MultiSigner`.
+        type Signer: IdentifyAccount<AccountId = Self::AccountId>;
+
+        /// Identifier of the assets channels can be funded in.
+        type AssetId: Parameter + Copy;
+        /// Assets channels can be funded in besides native DPR.
+        type Assets: fungibles::Mutate<Self::AccountId, AssetId = Self::AssetId>
+            + fungibles::Transfer<Self::AccountId, AssetId = Self::AssetId>;
+
+        /// Minimum number of blocks a channel must have been open
+        /// before payments out of it count towards the server's credit.
+        #[pallet::constant]
+        type MinCreditChannelAge: Get<Self::BlockNumber>;
+        /// Maximum DPR value of payments between a client and a server
+        /// that counts towards the server's credit in one era.
+        #[pallet::constant]
+        type MaxCreditPaymentPerPair: Get<BalanceOf<Self>>;
+
+        /// Id of the escrow account holding the funds locked in channels.
+        #[pallet::constant]
+        type PalletId: Get<PalletId>;
+
+        /// Number of blocks after the runtime upgrade during which signatures
+        /// on legacy payloads, without domain separation, are still accepted.
+        #[pallet::constant]
+        type LegacyPayloadWindow: Get<Self::BlockNumber>;
+
+        /// Number of blocks after which the settlement of an expired channel
+        /// is retried when it fails.
+        #[pallet::constant]
+        type SettlementRetryDelay: Get<Self::BlockNumber>;
     }
 
+    /// Domain tag of the signing payloads of this pallet
+    pub const PAYLOAD_TAG: &[u8] = b"deeper/micropayment";
+    /// Version of the signing payloads
+    pub const PAYLOAD_VERSION: u8 = 1;
+
     type BalanceOf<T> =
         <<T as Config>::Currency as Currency<<T as frame_system::Config>::AccountId>>::Balance;
 
@@ -97,6 +152,12 @@ pub mod pallet {
         <T as frame_system::Config>::AccountId,
     >>::NegativeImbalance;
 
+    pub type AssetBalanceOf<T> = <<T as Config>::Assets as fungibles::Inspect<
+        <T as frame_system::Config>::AccountId,
+    >>::Balance;
+
+    pub type AssetSettingOf<T> = AssetSetting<BalanceOf<T>>;
+
     pub type ChannelOf<T> = Chan<
         <T as frame_system::Config>::AccountId,
         <T as frame_system::Config>::BlockNumber,
@@ -131,6 +192,59 @@ pub mod pallet {
         }
     }
 
+    pub type ChannelCloseOf<T> = ChannelClose<
+        <T as frame_system::Config>::AccountId,
+        <T as frame_system::Config>::BlockNumber,
+        BalanceOf<T>,
+    >;
+
+    // struct to store a pending unilateral close of a micro-payment channel
+    #[derive(Decode, Encode, Clone, Eq, PartialEq, Debug, scale_info::TypeInfo)]
+    pub struct ChannelClose<AccountId, BlockNumber, Balance> {
+        pub initiator: AccountId,
+        // highest cumulative voucher amount submitted so far
+        pub amount: Balance,
+        // last block in which a higher voucher can be submitted
+        pub challenge_end: BlockNumber,
+    }
+
+    // settings of an asset approved for funding channels
+    #[derive(Decode, Encode, Clone, Default, Eq, PartialEq, Debug, scale_info::TypeInfo)]
+    pub struct AssetSetting<Balance> {
+        // share of every payment out of the channel that is burned
+        pub burn: Percent,
+        // DPR value of one whole unit of the asset, used to convert payments into credit
+        pub dpr_per_unit: Balance,
+        // decimals of the asset, one whole unit is 10^decimals of its smallest unit
+        pub decimals: u8,
+    }
+
+    // split of the fees charged on payments out of native channels
+    #[derive(Decode, Encode, Clone, Eq, PartialEq, Debug, scale_info::TypeInfo)]
+    pub struct FeeSplit {
+        pub burn: Percent,
+        pub treasury: Percent,
+        pub validators: Percent,
+    }
+
+    impl Default for FeeSplit {
+        fn default() -> Self {
+            Self {
+                burn: Percent::from_percent(0),
+                treasury: Percent::from_percent(100),
+                validators: Percent::from_percent(0),
+            }
+        }
+    }
+
+    #[derive(Decode, Encode, Clone, Copy, Eq, PartialEq, Debug, scale_info::TypeInfo)]
+    pub enum Releases {
+        V1_0_0,
+        V2_0_0,
+        V3_0_0,
+        V4_0_0,
+    }
+
     #[pallet::pallet]
     #[pallet::generate_store(pub(super) trait Store)]
     #[pallet::without_storage_info]
@@ -168,6 +282,191 @@ pub mod pallet {
     pub(super) type TotalMicropaymentChannelBalance<T: Config> =
         StorageMap<_, Blake2_128Concat, T::AccountId, BalanceOf<T>, OptionQuery>;
 
+    // channels in the challenge period of a unilateral close
+    #[pallet::storage]
+    #[pallet::getter(fn closing_channel)]
+    pub(super) type ClosingChannel<T: Config> = StorageDoubleMap<
+        _,
+        Blake2_128Concat,
+        T::AccountId,
+        Blake2_128Concat,
+        T::AccountId,
+        ChannelCloseOf<T>,
+        OptionQuery,
+    >;
+
+    // assets approved for funding channels
+    #[pallet::storage]
+    #[pallet::getter(fn asset_setting)]
+    pub(super) type AssetSettings<T: Config> =
+        StorageMap<_, Blake2_128Concat, T::AssetId, AssetSettingOf<T>, OptionQuery>;
+
+    // asset of the channel; channels without an entry are funded in native DPR
+    #[pallet::storage]
+    #[pallet::getter(fn channel_asset)]
+    pub(super) type ChannelAsset<T: Config> = StorageDoubleMap<
+        _,
+        Blake2_128Concat,
+        T::AccountId,
+        Blake2_128Concat,
+        T::AccountId,
+        T::AssetId,
+        OptionQuery,
+    >;
+
+    // channels indexed by expiration block, settled automatically in on_idle
+    #[pallet::storage]
+    #[pallet::getter(fn channel_expiry)]
+    pub(super) type ChannelExpiry<T: Config> = StorageDoubleMap<
+        _,
+        Twox64Concat,
+        T::BlockNumber,
+        Blake2_128Concat,
+        (T::AccountId, T::AccountId),
+        (),
+        OptionQuery,
+    >;
+
+    // first expiration block whose channels have not all been settled yet
+    #[pallet::storage]
+    #[pallet::getter(fn expiry_cursor)]
+    pub(super) type ExpiryCursor<T: Config> = StorageValue<_, T::BlockNumber, ValueQuery>;
+
+    // funding source of an account as determined by governance;
+    // payments between accounts with the same funding source earn no credit
+    #[pallet::storage]
+    #[pallet::getter(fn funding_source)]
+    pub(super) type FundingSource<T: Config> =
+        StorageMap<_, Blake2_128Concat, T::AccountId, T::AccountId, OptionQuery>;
+
+    // era and DPR value of the payments of a client to a server credited in that era
+    #[pallet::storage]
+    #[pallet::getter(fn pair_credited_payment)]
+    pub(super) type PairCreditedPayment<T: Config> = StorageMap<
+        _,
+        Blake2_128Concat,
+        (T::AccountId, T::AccountId),
+        (EraIndex, BalanceOf<T>),
+        ValueQuery,
+    >;
+
+    // DPR value of the payments of a client to a server not converted into credit yet,
+    // summed until they are; counted against MaxCreditPaymentPerPair as well
+    #[pallet::storage]
+    #[pallet::getter(fn pair_pending_payment)]
+    pub(super) type PairPendingPayment<T: Config> = StorageMap<
+        _,
+        Blake2_128Concat,
+        (T::AccountId, T::AccountId),
+        BalanceOf<T>,
+        ValueQuery,
+    >;
+
+    // hashlocks of the conditional payments already claimed from a channel
+    #[pallet::storage]
+    #[pallet::getter(fn claimed_htlc)]
+    pub(super) type ClaimedHtlc<T: Config> = StorageDoubleMap<
+        _,
+        Blake2_128Concat,
+        (T::AccountId, T::AccountId),
+        Blake2_128Concat,
+        [u8; 32],
+        (),
+        OptionQuery,
+    >;
+
+    // total amount paid to the server out of the channel with claim_payment;
+    // vouchers are cumulative, so settlement only pays what exceeds it
+    #[pallet::storage]
+    #[pallet::getter(fn paid_amount)]
+    pub(super) type PaidAmount<T: Config> = StorageDoubleMap<
+        _,
+        Blake2_128Concat,
+        T::AccountId,
+        Blake2_128Concat,
+        T::AccountId,
+        BalanceOf<T>,
+        ValueQuery,
+    >;
+
+    // total amount paid to the server out of the channel with claim_htlc;
+    // conditional payments are not part of the vouchers
+    #[pallet::storage]
+    #[pallet::getter(fn htlc_paid_amount)]
+    pub(super) type HtlcPaidAmount<T: Config> = StorageDoubleMap<
+        _,
+        Blake2_128Concat,
+        T::AccountId,
+        Blake2_128Concat,
+        T::AccountId,
+        BalanceOf<T>,
+        ValueQuery,
+    >;
+
+    // withdrawal requested by the client without the server's signature,
+    // (amount, first block it can be executed)
+    #[pallet::storage]
+    #[pallet::getter(fn pending_withdrawal)]
+    pub(super) type PendingWithdrawal<T: Config> = StorageDoubleMap<
+        _,
+        Blake2_128Concat,
+        T::AccountId,
+        Blake2_128Concat,
+        T::AccountId,
+        (BalanceOf<T>, T::BlockNumber),
+        OptionQuery,
+    >;
+
+    // total amount the client has withdrawn from the channel with the server's signature
+    #[pallet::storage]
+    #[pallet::getter(fn withdrawn_amount)]
+    pub(super) type WithdrawnAmount<T: Config> = StorageDoubleMap<
+        _,
+        Blake2_128Concat,
+        T::AccountId,
+        Blake2_128Concat,
+        T::AccountId,
+        BalanceOf<T>,
+        ValueQuery,
+    >;
+
+    // fee rate of payments out of channels whose server has the credit level
+    #[pallet::storage]
+    #[pallet::getter(fn fee_tier)]
+    pub(super) type FeeTiers<T: Config> =
+        StorageMap<_, Twox64Concat, CreditLevel, Percent, OptionQuery>;
+
+    #[pallet::storage]
+    #[pallet::getter(fn fee_split)]
+    pub(super) type FeeDistribution<T: Config> = StorageValue<_, FeeSplit, ValueQuery>;
+
+    // signatures on legacy payloads are accepted before this block
+    #[pallet::storage]
+    #[pallet::getter(fn legacy_payload_deadline)]
+    pub(super) type LegacyPayloadDeadline<T: Config> = StorageValue<_, T::BlockNumber>;
+
+    #[pallet::storage]
+    pub(super) type StorageVersion<T: Config> = StorageValue<_, Releases>;
+
+    #[pallet::genesis_config]
+    pub struct GenesisConfig;
+
+    #[cfg(feature = "std")]
+    impl Default for GenesisConfig {
+        fn default() -> Self {
+            Self
+        }
+    }
+
+    #[pallet::genesis_build]
+    impl<T: Config> GenesisBuild<T> for GenesisConfig {
+        fn build(&self) {
+            // the escrow account must exist before it receives assets that are not sufficient
+            let _ = <frame_system::Pallet<T>>::inc_providers(&Pallet::<T>::escrow_account());
+            StorageVersion::<T>::put(Releases::V4_0_0);
+        }
+    }
+
     // Pallets use events to inform users when important changes are made.
     // https://substrate.dev/docs/en/knowledgebase/runtime/events
     #[pallet::event]
@@ -185,6 +484,30 @@ pub mod pallet {
         ChannelClosed(T::AccountId, T::AccountId, T::BlockNumber),
         ClaimPayment(T::AccountId, T::AccountId, BalanceOf<T>),
         BalanceAdded(T::AccountId, T::AccountId, BalanceOf<T>, T::BlockNumber),
+        /// client, server, initiator, challenge_end
+        ChannelCloseInitiated(T::AccountId, T::AccountId, T::AccountId, T::BlockNumber),
+        /// client, server, cumulative amount
+        VoucherSubmitted(T::AccountId, T::AccountId, BalanceOf<T>),
+        /// client, server, paid to server, refunded to client
+        ChannelSettled(T::AccountId, T::AccountId, BalanceOf<T>, BalanceOf<T>),
+        /// client, server, asset the channel is funded in
+        AssetChannelOpened(T::AccountId, T::AccountId, T::AssetId),
+        AssetSettingUpdated(T::AssetId, AssetSettingOf<T>),
+        AssetSettingRemoved(T::AssetId),
+        /// account, funding source
+        FundingSourceSet(T::AccountId, Option<T::AccountId>),
+        /// client, server, amount, hashlock, preimage
+        HtlcClaimed(T::AccountId, T::AccountId, BalanceOf<T>, [u8; 32], [u8; 32]),
+        /// client, server, amount, first block the withdrawal can be executed
+        WithdrawalRequested(T::AccountId, T::AccountId, BalanceOf<T>, T::BlockNumber),
+        /// client, server, amount
+        BalanceWithdrawn(T::AccountId, T::AccountId, BalanceOf<T>),
+        /// client, server, new expiration
+        ChannelExtended(T::AccountId, T::AccountId, T::BlockNumber),
+        FeeTierUpdated(CreditLevel, Option<Percent>),
+        FeeSplitUpdated(FeeSplit),
+        /// client, server, block the settlement of the expired channel is retried in
+        ExpiredChannelSettlementFailed(T::AccountId, T::AccountId, T::BlockNumber),
     }
 
     #[pallet::error]
@@ -203,10 +526,84 @@ pub mod pallet {
         SessionError,
         // Invalid signature
         InvalidSignature,
+        // Channel is in the challenge period of a unilateral close
+        ChannelClosing,
+        // Channel is not being closed
+        ChannelNotClosing,
+        // Voucher amount is not higher than the one already submitted
+        VoucherNotHigher,
+        // Challenge period has not ended yet
+        ChallengePeriodNotEnded,
+        // Challenge period has already ended
+        ChallengePeriodEnded,
+        // Signer is neither the client nor the server of the channel
+        NotChannelParty,
+        // Signature can not be decoded
+        MalformedSignature,
+        // Account is not an sr25519 public key
+        MalformedPublicKey,
+        // Asset is not approved for funding channels
+        AssetNotApproved,
+        // Amount does not fit into the asset balance type
+        AssetAmountOverflow,
+        // Timelock of the conditional payment has passed
+        HtlcExpired,
+        // Conditional payment has already been claimed
+        HtlcAlreadyClaimed,
+        // Notice period of the withdrawal has not ended yet
+        NoticePeriodNotEnded,
+        // Amount differs from the one of the pending withdrawal
+        WithdrawalMismatch,
+        // Channel has already expired
+        ChannelExpired,
+        // Shares of the fee split don't add up to 100%
+        InvalidFeeSplit,
+        // Asset transfer into or out of the channel failed
+        AssetTransferFailed,
     }
 
     #[pallet::hooks]
-    impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {}
+    impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {
+        fn on_idle(now: T::BlockNumber, remaining_weight: Weight) -> Weight {
+            Self::settle_expired_channels(now, remaining_weight)
+        }
+
+        fn on_runtime_upgrade() -> Weight {
+            let mut weight = 0;
+            if StorageVersion::<T>::get().is_none() {
+                weight += Self::migrate_to_v1();
+            }
+            if StorageVersion::<T>::get() == Some(Releases::V1_0_0) {
+                weight += Self::migrate_to_v2();
+            }
+            if StorageVersion::<T>::get() == Some(Releases::V2_0_0) {
+                weight += Self::migrate_to_v3();
+            }
+            if StorageVersion::<T>::get() == Some(Releases::V3_0_0) {
+                weight += Self::migrate_to_v4();
+            }
+            weight
+        }
+
+        #[cfg(feature = "try-runtime")]
+        fn pre_upgrade() -> Result<(), &'static str> {
+            let native: BalanceOf<T> = TotalMicropaymentChannelBalance::<T>::iter_values()
+                .fold(Zero::zero(), |sum, b| sum.saturating_add(b));
+            let channels = Channel::<T>::iter()
+                .filter(|(client, server, _)| !ChannelAsset::<T>::contains_key(client, server))
+                .fold(Zero::zero(), |sum: BalanceOf<T>, (_, _, c)| sum.saturating_add(c.balance));
+            ensure!(
+                native == channels,
+                "channel balances do not match TotalMicropaymentChannelBalance"
+            );
+            Ok(())
+        }
+
+        #[cfg(feature = "try-runtime")]
+        fn post_upgrade() -> Result<(), &'static str> {
+            Self::check_invariants()
+        }
+    }
 
     // Dispatchable functions allows users to interact with the pallet and invoke state changes.
     // These functions materialize as "extrinsics", which are often compared to transactions.
@@ -224,45 +621,7 @@ pub mod pallet {
             duration: u32, // duration is in units of seconds
         ) -> DispatchResultWithPostInfo {
             let client = ensure_signed(origin)?;
-            ensure!(
-                !Channel::<T>::contains_key(&client, &server),
-                Error::<T>::ChannelAlreadyOpened
-            );
-            ensure!(client != server, Error::<T>::SameChannelEnds);
-            let nonce = Nonce::<T>::get((&client, &server));
-            let start_block = <frame_system::Pallet<T>>::block_number();
-            let duration_blocks = duration / T::SecsPerBlock::get();
-            let expiration = start_block + T::BlockNumber::from(duration_blocks);
-            let chan = ChannelOf::<T> {
-                client: client.clone(),
-                server: server.clone(),
-                balance: lock_amount,
-                nonce: nonce.clone(),
-                opened: start_block.clone(),
-                expiration: expiration.clone(),
-            };
-            if !Self::take_from_account(&client, lock_amount) {
-                error!("Not enough free balance to open channel");
-                Err(Error::<T>::NotEnoughBalance)?
-            }
-            Channel::<T>::insert(&client, &server, chan);
-            if TotalMicropaymentChannelBalance::<T>::contains_key(&client) {
-                TotalMicropaymentChannelBalance::<T>::mutate_exists(&client, |b| {
-                    let total_balance = b.take().unwrap_or_default();
-                    *b = Some(total_balance + lock_amount);
-                });
-            } else {
-                TotalMicropaymentChannelBalance::<T>::insert(&client, lock_amount);
-            }
-            Self::deposit_event(Event::ChannelOpened(
-                client,
-                server,
-                lock_amount,
-                nonce,
-                start_block,
-                expiration,
-            ));
-            Ok(().into())
+            Self::_open_channel(client, server, None, lock_amount, duration)
         }
 
         /// Close the channel and settle the payment
@@ -279,17 +638,8 @@ pub mod pallet {
                 // signer is server
 
                 let chan = Channel::<T>::get(&account_id, &signer);
-                TotalMicropaymentChannelBalance::<T>::mutate_exists(&account_id, |b| {
-                    let total_balance = b.take().unwrap_or_default();
-
-                    *b = if total_balance > chan.balance {
-                        Some(total_balance - chan.balance)
-                    } else {
-                        None
-                    };
-                });
                 // return the remaining balance in the channel to the client
-                Self::deposit_into_account(&account_id, chan.balance)?;
+                Self::refund_from_channel(&account_id, &signer, chan.balance)?;
                 Self::_close_channel(&account_id, &signer);
                 let end_block = <frame_system::Pallet<T>>::block_number();
                 Self::deposit_event(Event::ChannelClosed(account_id, signer, end_block));
@@ -297,21 +647,17 @@ pub mod pallet {
                 return Ok(().into());
             } else if Channel::<T>::contains_key(&signer, &account_id) {
                 // signer is client
+                ensure!(
+                    !ClosingChannel::<T>::contains_key(&signer, &account_id),
+                    Error::<T>::ChannelClosing
+                );
                 let chan = Channel::<T>::get(&signer, &account_id);
                 let current_block = <frame_system::Pallet<T>>::block_number();
                 if chan.expiration < current_block
                     || T::NodeInterface::get_eras_offline(&chan.server) >= 1
                 {
-                    TotalMicropaymentChannelBalance::<T>::mutate_exists(&signer, |b| {
-                        let total_balance = b.take().unwrap_or_default();
-                        *b = if total_balance > chan.balance {
-                            Some(total_balance - chan.balance)
-                        } else {
-                            None
-                        };
-                    });
                     // return the remaining balance in the channel to the client
-                    Self::deposit_into_account(&signer, chan.balance)?;
+                    Self::refund_from_channel(&signer, &account_id, chan.balance)?;
                     Self::_close_channel(&signer, &account_id);
                     let end_block = current_block;
                     Self::deposit_event(Event::ChannelClosed(signer, account_id, end_block));
@@ -333,20 +679,11 @@ pub mod pallet {
             let client = ensure_signed(origin)?;
             for (server, chan) in Channel::<T>::iter_prefix(&client) {
                 let current_block = <frame_system::Pallet<T>>::block_number();
-                if chan.expiration < current_block {
-                    TotalMicropaymentChannelBalance::<T>::mutate_exists(&client, |b| {
-                        let total_balance = b.take().unwrap_or_default();
-                        *b = if total_balance > chan.balance {
-                            Some(total_balance - chan.balance)
-                        } else {
-                            None
-                        };
-                    });
-                    // return the remaining balance in the channel to the client
-                    Self::deposit_into_account(&client, chan.balance)?;
-                    Self::_close_channel(&client, &server);
-                    let end_block = current_block;
-                    Self::deposit_event(Event::ChannelClosed(client.clone(), server, end_block));
+                // channels being closed are settled by finalize_close
+                if chan.expiration < current_block
+                    && !ClosingChannel::<T>::contains_key(&client, &server)
+                {
+                    Self::refund_expired_channel(&client, &server, chan.balance, current_block)?;
                 }
             }
             Ok(().into())
@@ -364,17 +701,14 @@ pub mod pallet {
                 Channel::<T>::contains_key(&client, &server),
                 Error::<T>::ChannelNotExist
             );
-            if !Self::take_from_account(&client, amount) {
-                error!("Not enough free balance to add into channel");
-                Err(Error::<T>::NotEnoughBalance)?
-            }
+            ensure!(
+                !ClosingChannel::<T>::contains_key(&client, &server),
+                Error::<T>::ChannelClosing
+            );
+            Self::fund_channel(&client, ChannelAsset::<T>::get(&client, &server), amount)?;
             Channel::<T>::mutate(&client, &server, |c| {
                 c.balance += amount;
             });
-            TotalMicropaymentChannelBalance::<T>::mutate_exists(&client, |b| {
-                let total_balance = b.take().unwrap_or_default();
-                *b = Some(total_balance + amount);
-            });
             let end_block = <frame_system::Pallet<T>>::block_number();
             Self::deposit_event(Event::BalanceAdded(client, server, amount, end_block));
             Ok(().into())
@@ -394,22 +728,18 @@ pub mod pallet {
                 Channel::<T>::contains_key(&client, &server),
                 Error::<T>::ChannelNotExist
             );
+            ensure!(
+                !ClosingChannel::<T>::contains_key(&client, &server),
+                Error::<T>::ChannelClosing
+            );
 
             // close channel if it expires
             //let mut chan = Channel::<T>::get(&client, &server);
             let mut chan = Channel::<T>::get(&client, &server);
             let current_block = <frame_system::Pallet<T>>::block_number();
             if chan.expiration < current_block {
-                TotalMicropaymentChannelBalance::<T>::mutate_exists(&client, |b| {
-                    let total_balance = b.take().unwrap_or_default();
-                    *b = if total_balance > chan.balance {
-                        Some(total_balance - chan.balance)
-                    } else {
-                        None
-                    };
-                });
                 // return the remaining balance in the channel to the client
-                Self::deposit_into_account(&client, chan.balance)?;
+                Self::refund_from_channel(&client, &server, chan.balance)?;
                 Self::_close_channel(&client, &server);
                 let end_block = current_block;
                 Self::deposit_event(Event::ChannelClosed(client, server, end_block));
@@ -422,22 +752,13 @@ pub mod pallet {
                 Err(Error::<T>::SessionError)?
             }
             Self::verify_signature(&client, &server, chan.nonce, session_id, amount, &signature)?;
-            SessionId::<T>::insert((&client, &server), session_id); // mark session_id as used
 
             // if there is not enough balance in the channel
             if chan.balance < amount {
-                TotalMicropaymentChannelBalance::<T>::mutate_exists(&client, |b| {
-                    let total_balance = b.take().unwrap_or_default();
-                    *b = if total_balance > chan.balance {
-                        Some(total_balance - chan.balance)
-                    } else {
-                        None
-                    };
-                });
                 // deposit all the balance in the channel to the server's account
-                Self::deposit_into_account(&server, chan.balance)?;
-                // update server's credit TODO: reuse in future
-                //T::CreditInterface::update_credit((server.clone(), chan.balance));
+                Self::pay_from_channel(&client, &server, chan.balance)?;
+                // update server's credit
+                Self::credit_payment(&client, &server, chan.opened, chan.balance);
                 // no balance in channel now, just close it
                 Self::_close_channel(&client, &server);
                 let end_block = <frame_system::Pallet<T>>::block_number();
@@ -450,107 +771,1215 @@ pub mod pallet {
                 Err(Error::<T>::NotEnoughBalance)?
             }
 
+            // deposit the claimed amount to the server's account
+            Self::pay_from_channel(&client, &server, amount)?;
+            SessionId::<T>::insert((&client, &server), session_id); // mark session_id as used
             chan.balance -= amount;
+            let opened = chan.opened;
             Channel::<T>::insert(&client, &server, chan);
-            TotalMicropaymentChannelBalance::<T>::mutate_exists(&client, |b| {
-                let total_balance = b.take().unwrap_or_default();
-                *b = if total_balance > amount {
-                    Some(total_balance - amount)
-                } else {
-                    None
-                };
-            });
-            // deposit the claimed amount to the server's account
-            Self::deposit_into_account(&server, amount)?;
-            // update server's credit TODO: reuse in future
-            //T::CreditInterface::update_credit((server.clone(), amount));
+            PaidAmount::<T>::mutate(&client, &server, |paid| *paid = paid.saturating_add(amount));
+            // update server's credit
+            Self::credit_payment(&client, &server, opened, amount);
             Self::deposit_event(Event::ClaimPayment(client, server, amount));
 
             Ok(().into())
         }
-    }
-
-    impl<T: Config> Pallet<T> {
-        /// Close the channel between the client and server
-        fn _close_channel(client: &T::AccountId, server: &T::AccountId) {
-            // remove all the session_ids of given channel
-            SessionId::<T>::remove((client, server));
-            // remove the channel
-            Channel::<T>::remove(client, server);
-            // increment the nonce
-            Nonce::<T>::mutate((client, server), |v| *v += 1);
-        }
 
-        /// verify signature, signature is on hash of |server_addr|nonce|session_id|amount|
-        /// during one session_id, a client can send multiple accumulated
-        /// micropayments with the same session_id; the server can only claim one payment
-        /// of the same session_id, i.e. the latest accumulated micropayment.
-        pub fn verify_signature(
-            client: &T::AccountId,
-            server: &T::AccountId,
-            nonce: u64,
-            session_id: u32,
-            amount: BalanceOf<T>,
-            signature: &Vec<u8>,
+        /// Client or server starts a unilateral close of the channel.
+        /// The server can submit its latest voucher until the challenge period ends.
+        #[pallet::weight(T::WeightInfo::initiate_close())]
+        pub fn initiate_close(
+            origin: OriginFor<T>,
+            counterparty: T::AccountId,
         ) -> DispatchResultWithPostInfo {
-            let mut pk = [0u8; 32];
-            pk.copy_from_slice(&client.encode());
-            let pub_key = sr25519::Public::from_raw(pk);
-
-            let mut sig = [0u8; 64];
-            sig.copy_from_slice(&signature);
-            let sig = sr25519::Signature::from_slice(&sig);
-
-            let msg = Self::construct_byte_array_and_hash(server, nonce, session_id, amount);
-
-            let verified = sr25519_verify(&sig, &msg, &pub_key);
-            ensure!(verified, Error::<T>::InvalidSignature);
-
+            let signer = ensure_signed(origin)?;
+            let (client, server) = Self::channel_ends(&signer, &counterparty)?;
+            ensure!(
+                !ClosingChannel::<T>::contains_key(&client, &server),
+                Error::<T>::ChannelClosing
+            );
+            let challenge_end =
+                <frame_system::Pallet<T>>::block_number() + T::ChallengePeriod::get();
+            ClosingChannel::<T>::insert(
+                &client,
+                &server,
+                ChannelCloseOf::<T> {
+                    initiator: signer.clone(),
+                    amount: Zero::zero(),
+                    challenge_end,
+                },
+            );
+            Self::deposit_event(Event::ChannelCloseInitiated(
+                client,
+                server,
+                signer,
+                challenge_end,
+            ));
             Ok(().into())
         }
 
-        // construct data from |server_addr|session_id|amount| and hash it
-        pub fn construct_byte_array_and_hash(
-            address: &T::AccountId,
-            nonce: u64,
-            session_id: u32,
+        /// Submit a cumulative voucher signed by the client.
+        /// Starts a unilateral close if the channel is not being closed yet;
+        /// otherwise the voucher must be higher than the one already submitted.
+        #[pallet::weight(T::WeightInfo::submit_voucher())]
+        pub fn submit_voucher(
+            origin: OriginFor<T>,
+            client: T::AccountId,
+            server: T::AccountId,
             amount: BalanceOf<T>,
-        ) -> [u8; 32] {
+            signature: Vec<u8>,
+        ) -> DispatchResultWithPostInfo {
+            let signer = ensure_signed(origin)?;
+            ensure!(
+                signer == client || signer == server,
+                Error::<T>::NotChannelParty
+            );
+            ensure!(
+                Channel::<T>::contains_key(&client, &server),
+                Error::<T>::ChannelNotExist
+            );
+            let chan = Channel::<T>::get(&client, &server);
+            let current_block = <frame_system::Pallet<T>>::block_number();
+            let (mut close, initiated) = match ClosingChannel::<T>::get(&client, &server) {
+                Some(close) => (close, false),
+                None => (
+                    ChannelCloseOf::<T> {
+                        initiator: signer.clone(),
+                        amount: Zero::zero(),
+                        challenge_end: current_block + T::ChallengePeriod::get(),
+                    },
+                    true,
+                ),
+            };
+            ensure!(
+                current_block <= close.challenge_end,
+                Error::<T>::ChallengePeriodEnded
+            );
+            ensure!(amount > close.amount, Error::<T>::VoucherNotHigher);
+            let data = Self::construct_voucher_data(&server, chan.nonce, amount);
+            Self::verify_payload_signature(&client, &data, &signature)?;
+
+            close.amount = amount;
+            let challenge_end = close.challenge_end;
+            ClosingChannel::<T>::insert(&client, &server, close);
+            if initiated {
+                Self::deposit_event(Event::ChannelCloseInitiated(
+                    client.clone(),
+                    server.clone(),
+                    signer,
+                    challenge_end,
+                ));
+            }
+            Self::deposit_event(Event::VoucherSubmitted(client, server, amount));
+            Ok(().into())
+        }
+
+        /// Settle a channel whose challenge period has ended.
+        /// The server receives the highest voucher amount minus what it already claimed
+        /// and the rest is refunded to the client.
+        #[pallet::weight(T::WeightInfo::finalize_close())]
+        pub fn finalize_close(
+            origin: OriginFor<T>,
+            client: T::AccountId,
+            server: T::AccountId,
+        ) -> DispatchResultWithPostInfo {
+            ensure_signed(origin)?;
+            let close = ClosingChannel::<T>::get(&client, &server)
+                .ok_or(Error::<T>::ChannelNotClosing)?;
+            ensure!(
+                <frame_system::Pallet<T>>::block_number() > close.challenge_end,
+                Error::<T>::ChallengePeriodNotEnded
+            );
+            Self::settle_channel(&client, &server, close.amount)
+        }
+
+        /// Close the channel immediately with a settlement signed by both client and server.
+        /// Like a voucher, `amount` is the cumulative amount paid to the server.
+        #[pallet::weight(T::WeightInfo::cooperative_close())]
+        pub fn cooperative_close(
+            origin: OriginFor<T>,
+            client: T::AccountId,
+            server: T::AccountId,
+            amount: BalanceOf<T>,
+            client_signature: Vec<u8>,
+            server_signature: Vec<u8>,
+        ) -> DispatchResultWithPostInfo {
+            ensure_signed(origin)?;
+            ensure!(
+                Channel::<T>::contains_key(&client, &server),
+                Error::<T>::ChannelNotExist
+            );
+            let chan = Channel::<T>::get(&client, &server);
+            ensure!(
+                amount.saturating_sub(PaidAmount::<T>::get(&client, &server)) <= chan.balance,
+                Error::<T>::NotEnoughBalance
+            );
+            let data = Self::construct_close_data(&client, &server, chan.nonce, amount);
+            Self::verify_payload_signature(&client, &data, &client_signature)?;
+            Self::verify_payload_signature(&server, &data, &server_signature)?;
+            Self::settle_channel(&client, &server, amount)
+        }
+
+        /// Client opens a channel to the server by locking an approved asset inside the channel.
+        /// Payments out of the channel are made in the same asset.
+        #[pallet::weight(T::WeightInfo::open_asset_channel())]
+        pub fn open_asset_channel(
+            origin: OriginFor<T>,
+            server: T::AccountId,
+            asset_id: T::AssetId,
+            lock_amount: BalanceOf<T>,
+            duration: u32, // duration is in units of seconds
+        ) -> DispatchResultWithPostInfo {
+            let client = ensure_signed(origin)?;
+            ensure!(
+                AssetSettings::<T>::contains_key(asset_id),
+                Error::<T>::AssetNotApproved
+            );
+            Self::_open_channel(client, server, Some(asset_id), lock_amount, duration)
+        }
+
+        /// Approve an asset for funding channels, or remove it with `None`.
+        /// Channels already funded in a removed asset are settled without burn.
+        #[pallet::weight(T::WeightInfo::set_asset_setting())]
+        pub fn set_asset_setting(
+            origin: OriginFor<T>,
+            asset_id: T::AssetId,
+            setting: Option<AssetSettingOf<T>>,
+        ) -> DispatchResultWithPostInfo {
+            ensure_root(origin)?;
+            match setting {
+                Some(setting) => {
+                    AssetSettings::<T>::insert(asset_id, setting.clone());
+                    Self::deposit_event(Event::AssetSettingUpdated(asset_id, setting));
+                }
+                None => {
+                    AssetSettings::<T>::remove(asset_id);
+                    Self::deposit_event(Event::AssetSettingRemoved(asset_id));
+                }
+            }
+            Ok(().into())
+        }
+
+        /// Record the funding source of an account, or remove it with `None`.
+        /// Payments between a client and a server with the same funding source,
+        /// or where one is the funding source of the other, earn no credit.
+        #[pallet::weight(T::WeightInfo::set_funding_source())]
+        pub fn set_funding_source(
+            origin: OriginFor<T>,
+            account: T::AccountId,
+            source: Option<T::AccountId>,
+        ) -> DispatchResultWithPostInfo {
+            ensure_root(origin)?;
+            match source {
+                Some(ref source) => FundingSource::<T>::insert(&account, source),
+                None => FundingSource::<T>::remove(&account),
+            }
+            Self::deposit_event(Event::FundingSourceSet(account, source));
+            Ok(().into())
+        }
+
+        /// Server claims a conditional payment from the channel by revealing the preimage of
+        /// its hashlock no later than the timelock. A payment routed through several channels
+        /// carries the same hashlock on every hop, with shorter timelocks downstream, so the
+        /// preimage published in `HtlcClaimed` lets the previous hop claim its own payment.
+        /// Conditional payments only need to be claimed on-chain if they are disputed; they
+        /// are paid on top of the vouchers and can still be claimed until the challenge period
+        /// of a unilateral close ends.
+        #[pallet::weight(T::WeightInfo::claim_htlc())]
+        pub fn claim_htlc(
+            origin: OriginFor<T>,
+            client: T::AccountId,
+            amount: BalanceOf<T>,
+            timelock: T::BlockNumber,
+            preimage: [u8; 32],
+            signature: Vec<u8>,
+        ) -> DispatchResultWithPostInfo {
+            let server = ensure_signed(origin)?;
+            ensure!(
+                Channel::<T>::contains_key(&client, &server),
+                Error::<T>::ChannelNotExist
+            );
+            let current_block = <frame_system::Pallet<T>>::block_number();
+            if let Some(close) = ClosingChannel::<T>::get(&client, &server) {
+                ensure!(
+                    current_block <= close.challenge_end,
+                    Error::<T>::ChallengePeriodEnded
+                );
+            }
+            ensure!(current_block <= timelock, Error::<T>::HtlcExpired);
+            let hashlock = sp_io::hashing::blake2_256(&preimage);
+            ensure!(
+                !ClaimedHtlc::<T>::contains_key((&client, &server), hashlock),
+                Error::<T>::HtlcAlreadyClaimed
+            );
+            let mut chan = Channel::<T>::get(&client, &server);
+            ensure!(amount <= chan.balance, Error::<T>::NotEnoughBalance);
+            let data = Self::construct_htlc_data(&server, chan.nonce, amount, &hashlock, timelock);
+            Self::verify_payload_signature(&client, &data, &signature)?;
+
+            Self::pay_from_channel(&client, &server, amount)?;
+            ClaimedHtlc::<T>::insert((&client, &server), hashlock, ());
+            chan.balance -= amount;
+            let opened = chan.opened;
+            Channel::<T>::insert(&client, &server, chan);
+            HtlcPaidAmount::<T>::mutate(&client, &server, |paid| {
+                *paid = paid.saturating_add(amount)
+            });
+            Self::credit_payment(&client, &server, opened, amount);
+            Self::deposit_event(Event::HtlcClaimed(client, server, amount, hashlock, preimage));
+            Ok(().into())
+        }
+
+        /// Client withdraws part of the channel balance without closing the channel.
+        /// With the server's signature the withdrawal is immediate. Without it, the first
+        /// call requests the withdrawal and the server can still claim its latest payments
+        /// during `ChallengePeriod` blocks; calling again with the same amount afterwards
+        /// withdraws it, or whatever balance is left.
+        #[pallet::weight(T::WeightInfo::withdraw_from_channel())]
+        pub fn withdraw_from_channel(
+            origin: OriginFor<T>,
+            server: T::AccountId,
+            amount: BalanceOf<T>,
+            server_signature: Option<Vec<u8>>,
+        ) -> DispatchResultWithPostInfo {
+            let client = ensure_signed(origin)?;
+            ensure!(
+                Channel::<T>::contains_key(&client, &server),
+                Error::<T>::ChannelNotExist
+            );
+            ensure!(
+                !ClosingChannel::<T>::contains_key(&client, &server),
+                Error::<T>::ChannelClosing
+            );
+            let mut chan = Channel::<T>::get(&client, &server);
+            let current_block = <frame_system::Pallet<T>>::block_number();
+            let (amount, withdrawn) = match server_signature {
+                Some(signature) => {
+                    ensure!(amount <= chan.balance, Error::<T>::NotEnoughBalance);
+                    let withdrawn = WithdrawnAmount::<T>::get(&client, &server) + amount;
+                    let data =
+                        Self::construct_withdraw_data(&client, &server, chan.nonce, withdrawn);
+                    Self::verify_payload_signature(&server, &data, &signature)?;
+                    (amount, Some(withdrawn))
+                }
+                None => match PendingWithdrawal::<T>::get(&client, &server) {
+                    None => {
+                        ensure!(amount <= chan.balance, Error::<T>::NotEnoughBalance);
+                        let unlock_block = current_block + T::ChallengePeriod::get();
+                        PendingWithdrawal::<T>::insert(&client, &server, (amount, unlock_block));
+                        Self::deposit_event(Event::WithdrawalRequested(
+                            client,
+                            server,
+                            amount,
+                            unlock_block,
+                        ));
+                        return Ok(().into());
+                    }
+                    Some((pending, unlock_block)) => {
+                        ensure!(pending == amount, Error::<T>::WithdrawalMismatch);
+                        ensure!(
+                            current_block >= unlock_block,
+                            Error::<T>::NoticePeriodNotEnded
+                        );
+                        // the server may have claimed part of it during the notice period
+                        (amount.min(chan.balance), None)
+                    }
+                },
+            };
+            Self::refund_from_channel(&client, &server, amount)?;
+            match withdrawn {
+                Some(withdrawn) => WithdrawnAmount::<T>::insert(&client, &server, withdrawn),
+                None => PendingWithdrawal::<T>::remove(&client, &server),
+            }
+            chan.balance -= amount;
+            Channel::<T>::insert(&client, &server, chan);
+            Self::deposit_event(Event::BalanceWithdrawn(client, server, amount));
+            Ok(().into())
+        }
+
+        /// Client extends the expiration of an unexpired channel by duration seconds
+        #[pallet::weight(T::WeightInfo::extend_channel())]
+        pub fn extend_channel(
+            origin: OriginFor<T>,
+            server: T::AccountId,
+            duration: u32, // duration is in units of seconds
+        ) -> DispatchResultWithPostInfo {
+            let client = ensure_signed(origin)?;
+            ensure!(
+                Channel::<T>::contains_key(&client, &server),
+                Error::<T>::ChannelNotExist
+            );
+            ensure!(
+                !ClosingChannel::<T>::contains_key(&client, &server),
+                Error::<T>::ChannelClosing
+            );
+            let mut chan = Channel::<T>::get(&client, &server);
+            ensure!(
+                chan.expiration >= <frame_system::Pallet<T>>::block_number(),
+                Error::<T>::ChannelExpired
+            );
+            let duration_blocks = duration / T::SecsPerBlock::get();
+            let expiration = chan.expiration + T::BlockNumber::from(duration_blocks);
+            ChannelExpiry::<T>::remove(chan.expiration, (&client, &server));
+            ChannelExpiry::<T>::insert(expiration, (&client, &server), ());
+            chan.expiration = expiration;
+            Channel::<T>::insert(&client, &server, chan);
+            Self::deposit_event(Event::ChannelExtended(client, server, expiration));
+            Ok(().into())
+        }
+
+        /// Set the fee rate of channels whose server has the credit level,
+        /// or remove it with `None` so that `MicropaymentBurn` applies.
+        #[pallet::weight(T::WeightInfo::set_fee_tier())]
+        pub fn set_fee_tier(
+            origin: OriginFor<T>,
+            credit_level: CreditLevel,
+            fee: Option<Percent>,
+        ) -> DispatchResultWithPostInfo {
+            ensure_root(origin)?;
+            match fee {
+                Some(fee) => FeeTiers::<T>::insert(credit_level, fee),
+                None => FeeTiers::<T>::remove(credit_level),
+            }
+            Self::deposit_event(Event::FeeTierUpdated(credit_level, fee));
+            Ok(().into())
+        }
+
+        /// Set how fees of native channels are split between burn, treasury and validators
+        #[pallet::weight(T::WeightInfo::set_fee_split())]
+        pub fn set_fee_split(origin: OriginFor<T>, split: FeeSplit) -> DispatchResultWithPostInfo {
+            ensure_root(origin)?;
+            let total = split.burn.deconstruct() as u16
+                + split.treasury.deconstruct() as u16
+                + split.validators.deconstruct() as u16;
+            ensure!(total == 100, Error::<T>::InvalidFeeSplit);
+            FeeDistribution::<T>::put(split.clone());
+            Self::deposit_event(Event::FeeSplitUpdated(split));
+            Ok(().into())
+        }
+    }
+
+    impl<T: Config> Pallet<T> {
+        /// Index the channels opened before the expiry index existed
+        fn migrate_to_v1() -> Weight {
+            let mut cursor = <frame_system::Pallet<T>>::block_number();
+            let mut count: u64 = 0;
+            for (client, server, chan) in Channel::<T>::iter() {
+                ChannelExpiry::<T>::insert(chan.expiration, (&client, &server), ());
+                cursor = cursor.min(chan.expiration);
+                count += 1;
+            }
+            ExpiryCursor::<T>::put(cursor);
+            StorageVersion::<T>::put(Releases::V1_0_0);
+            T::DbWeight::get().reads_writes(count + 1, count + 2)
+        }
+
+        /// Channels used to be funded by withdrawing from the client, i.e. their funds
+        /// are not held anywhere. Mint whatever the escrow account is missing.
+        fn migrate_to_v2() -> Weight {
+            let escrow = Self::escrow_account();
+            let mut count: u64 = 0;
+            let mut native = BalanceOf::<T>::zero();
+            let mut assets: Vec<(T::AssetId, BalanceOf<T>)> = Vec::new();
+            for (client, server, chan) in Channel::<T>::iter() {
+                count += 1;
+                match ChannelAsset::<T>::get(&client, &server) {
+                    None => native = native.saturating_add(chan.balance),
+                    Some(asset_id) => match assets.iter_mut().find(|(id, _)| *id == asset_id) {
+                        Some((_, sum)) => *sum = sum.saturating_add(chan.balance),
+                        None => assets.push((asset_id, chan.balance)),
+                    },
+                }
+            }
+            if !native.is_zero() {
+                let expected = T::Currency::minimum_balance().saturating_add(native);
+                let free = T::Currency::free_balance(&escrow);
+                if free < expected {
+                    T::Currency::deposit_creating(&escrow, expected - free);
+                }
+            }
+            // assets are not minted, a shortfall has to be covered by the asset owner
+            for (asset_id, sum) in assets.iter() {
+                let result = Self::to_asset_balance(*sum).map(|sum| {
+                    let expected = <T::Assets as fungibles::Inspect<_>>::minimum_balance(*asset_id)
+                        .saturating_add(sum);
+                    let balance = <T::Assets as fungibles::Inspect<_>>::balance(*asset_id, &escrow);
+                    if balance < expected {
+                        error!(
+                            "escrow account of asset {:?} channels is short of {:?}",
+                            asset_id,
+                            expected - balance
+                        );
+                    }
+                });
+                if let Err(e) = result {
+                    error!("failed to check the escrow account of asset channels: {:?}", e);
+                }
+            }
+            StorageVersion::<T>::put(Releases::V2_0_0);
+            let asset_count = assets.len() as u64;
+            T::DbWeight::get().reads_writes(2 * count + 2 * asset_count + 2, 2)
+        }
+
+        /// Payloads are domain separated from now on; existing clients
+        /// get LegacyPayloadWindow blocks to upgrade.
+        fn migrate_to_v3() -> Weight {
+            let now = <frame_system::Pallet<T>>::block_number();
+            LegacyPayloadDeadline::<T>::put(now.saturating_add(T::LegacyPayloadWindow::get()));
+            StorageVersion::<T>::put(Releases::V3_0_0);
+            T::DbWeight::get().reads_writes(1, 2)
+        }
+
+        /// The escrow account only existed while it held native DPR, so a first channel
+        /// funded in an asset that is not sufficient could not be opened. Give it a provider.
+        fn migrate_to_v4() -> Weight {
+            let _ = <frame_system::Pallet<T>>::inc_providers(&Self::escrow_account());
+            StorageVersion::<T>::put(Releases::V4_0_0);
+            T::DbWeight::get().reads_writes(1, 2)
+        }
+
+        /// Account holding the funds locked in all channels
+        pub fn escrow_account() -> T::AccountId {
+            T::PalletId::get().into_account()
+        }
+
+        /// Check that the sum of channel balances equals `TotalMicropaymentChannelBalance`
+        /// and the balances of the escrow account above its existential deposit
+        #[cfg(any(feature = "try-runtime", test))]
+        pub fn check_invariants() -> Result<(), &'static str> {
+            let escrow = Self::escrow_account();
+            let mut native = BalanceOf::<T>::zero();
+            let mut assets: Vec<(T::AssetId, BalanceOf<T>)> = Vec::new();
+            for (client, server, chan) in Channel::<T>::iter() {
+                match ChannelAsset::<T>::get(&client, &server) {
+                    None => native = native.saturating_add(chan.balance),
+                    Some(asset_id) => match assets.iter_mut().find(|(id, _)| *id == asset_id) {
+                        Some((_, sum)) => *sum = sum.saturating_add(chan.balance),
+                        None => assets.push((asset_id, chan.balance)),
+                    },
+                }
+            }
+            let total: BalanceOf<T> = TotalMicropaymentChannelBalance::<T>::iter_values()
+                .fold(Zero::zero(), |sum, b| sum.saturating_add(b));
+            ensure!(
+                native == total,
+                "channel balances do not match TotalMicropaymentChannelBalance"
+            );
+            let escrow_free = T::Currency::free_balance(&escrow);
+            ensure!(
+                escrow_free.saturating_sub(T::Currency::minimum_balance()) == native,
+                "channel balances do not match the escrow balance"
+            );
+            for (asset_id, sum) in assets {
+                let balance = <T::Assets as fungibles::Inspect<_>>::balance(asset_id, &escrow)
+                    .saturating_sub(<T::Assets as fungibles::Inspect<_>>::minimum_balance(
+                        asset_id,
+                    ));
+                ensure!(
+                    Self::to_asset_balance(sum).ok() == Some(balance),
+                    "asset channel balances do not match the escrow balance"
+                );
+            }
+            Ok(())
+        }
+
+        fn _open_channel(
+            client: T::AccountId,
+            server: T::AccountId,
+            asset: Option<T::AssetId>,
+            lock_amount: BalanceOf<T>,
+            duration: u32,
+        ) -> DispatchResultWithPostInfo {
+            ensure!(
+                !Channel::<T>::contains_key(&client, &server),
+                Error::<T>::ChannelAlreadyOpened
+            );
+            ensure!(client != server, Error::<T>::SameChannelEnds);
+            let nonce = Nonce::<T>::get((&client, &server));
+            let start_block = <frame_system::Pallet<T>>::block_number();
+            let duration_blocks = duration / T::SecsPerBlock::get();
+            let expiration = start_block + T::BlockNumber::from(duration_blocks);
+            let chan = ChannelOf::<T> {
+                client: client.clone(),
+                server: server.clone(),
+                balance: lock_amount,
+                nonce: nonce.clone(),
+                opened: start_block.clone(),
+                expiration: expiration.clone(),
+            };
+            Self::fund_channel(&client, asset, lock_amount)?;
+            Channel::<T>::insert(&client, &server, chan);
+            ChannelExpiry::<T>::insert(expiration, (&client, &server), ());
+            if let Some(asset_id) = asset {
+                ChannelAsset::<T>::insert(&client, &server, asset_id);
+                Self::deposit_event(Event::AssetChannelOpened(
+                    client.clone(),
+                    server.clone(),
+                    asset_id,
+                ));
+            }
+            Self::deposit_event(Event::ChannelOpened(
+                client,
+                server,
+                lock_amount,
+                nonce,
+                start_block,
+                expiration,
+            ));
+            Ok(().into())
+        }
+
+        /// Close the channel between the client and server
+        fn _close_channel(client: &T::AccountId, server: &T::AccountId) {
+            // remove all the session_ids of given channel
+            SessionId::<T>::remove((client, server));
+            // remove the pending unilateral close, if any
+            ClosingChannel::<T>::remove(client, server);
+            // remove the asset of the channel, if any
+            ChannelAsset::<T>::remove(client, server);
+            // remove the claimed conditional payments
+            let _ = ClaimedHtlc::<T>::remove_prefix((client, server), None);
+            // remove the withdrawals of the channel
+            PendingWithdrawal::<T>::remove(client, server);
+            WithdrawnAmount::<T>::remove(client, server);
+            PaidAmount::<T>::remove(client, server);
+            HtlcPaidAmount::<T>::remove(client, server);
+            // remove the channel and its expiry index entry
+            let chan = Channel::<T>::take(client, server);
+            ChannelExpiry::<T>::remove(chan.expiration, (client, server));
+            // increment the nonce
+            Nonce::<T>::mutate((client, server), |v| *v += 1);
+        }
+
+        /// return the remaining balance of an expired channel to the client and close it
+        fn refund_expired_channel(
+            client: &T::AccountId,
+            server: &T::AccountId,
+            balance: BalanceOf<T>,
+            end_block: T::BlockNumber,
+        ) -> DispatchResultWithPostInfo {
+            Self::refund_from_channel(client, server, balance)?;
+            Self::_close_channel(client, server);
+            Self::deposit_event(Event::ChannelClosed(client.clone(), server.clone(), end_block));
+            Ok(().into())
+        }
+
+        /// settle the channels expired before `now` in order of expiration,
+        /// as long as the remaining weight of the block allows it.
+        /// Returns the weight consumed.
+        pub(crate) fn settle_expired_channels(
+            now: T::BlockNumber,
+            remaining_weight: Weight,
+        ) -> Weight {
+            let db_weight = T::DbWeight::get();
+            let settle_weight = T::WeightInfo::settle_expired_channel();
+            // read and write the cursor
+            let mut used_weight = db_weight.reads_writes(1, 1);
+            if used_weight > remaining_weight {
+                return 0;
+            }
+            let mut cursor = ExpiryCursor::<T>::get();
+            while cursor < now {
+                if used_weight.saturating_add(db_weight.reads(1)) > remaining_weight {
+                    break;
+                }
+                used_weight = used_weight.saturating_add(db_weight.reads(1));
+                let max_settled =
+                    ((remaining_weight - used_weight) / settle_weight.max(1)) as usize;
+                // collect the keys first, entries are removed while settling
+                let expired: Vec<(T::AccountId, T::AccountId)> =
+                    ChannelExpiry::<T>::iter_key_prefix(cursor)
+                        .take(max_settled.saturating_add(1))
+                        .collect();
+                let all_settled = expired.len() <= max_settled;
+                for (client, server) in expired.into_iter().take(max_settled) {
+                    used_weight = used_weight.saturating_add(settle_weight);
+                    Self::settle_expired_channel(cursor, &client, &server, now);
+                }
+                if !all_settled {
+                    break;
+                }
+                cursor += 1u32.into();
+            }
+            ExpiryCursor::<T>::put(cursor);
+            used_weight
+        }
+
+        fn settle_expired_channel(
+            expiration: T::BlockNumber,
+            client: &T::AccountId,
+            server: &T::AccountId,
+            now: T::BlockNumber,
+        ) {
+            ChannelExpiry::<T>::remove(expiration, (client, server));
+            // channels being closed are settled by finalize_close
+            if !Channel::<T>::contains_key(client, server)
+                || ClosingChannel::<T>::contains_key(client, server)
+            {
+                return;
+            }
+            let chan = Channel::<T>::get(client, server);
+            // left over by a failed settlement of a channel reopened since
+            if chan.expiration >= now {
+                return;
+            }
+            let result = with_transaction(|| {
+                match Self::refund_expired_channel(client, server, chan.balance, now) {
+                    Ok(_) => TransactionOutcome::Commit(Ok(())),
+                    Err(e) => TransactionOutcome::Rollback(Err(e)),
+                }
+            });
+            if let Err(e) = result {
+                // retry later, the client can still close the channel with close_expired_channels
+                error!(
+                    "failed to settle expired channel {:?} -> {:?}: {:?}",
+                    client, server, e
+                );
+                let retry = now.saturating_add(T::SettlementRetryDelay::get().max(1u32.into()));
+                ChannelExpiry::<T>::insert(retry, (client, server), ());
+                Self::deposit_event(Event::ExpiredChannelSettlementFailed(
+                    client.clone(),
+                    server.clone(),
+                    retry,
+                ));
+            }
+        }
+
+        /// verify signature, signature is on hash of |server_addr|nonce|session_id|amount|
+        /// during one session_id, a client can send multiple accumulated
+        /// micropayments with the same session_id; the server can only claim one payment
+        /// of the same session_id, i.e. the latest accumulated micropayment.
+        pub fn verify_signature(
+            client: &T::AccountId,
+            server: &T::AccountId,
+            nonce: u64,
+            session_id: u32,
+            amount: BalanceOf<T>,
+            signature: &Vec<u8>,
+        ) -> DispatchResultWithPostInfo {
+            let data = Self::construct_byte_array(server, nonce, session_id, amount);
+            Self::verify_payload_signature(client, &data, signature)
+        }
+
+        /// verify a signature of `signer` on the payload of data; during the
+        /// legacy window, a signature on the plain hash of data is accepted as well
+        pub fn verify_payload_signature(
+            signer: &T::AccountId,
+            data: &[u8],
+            signature: &[u8],
+        ) -> DispatchResultWithPostInfo {
+            let result =
+                Self::verify_account_signature(signer, &Self::payload_hash(data), signature);
+            if result.is_err() && Self::accepts_legacy_payload() {
+                let msg = sp_io::hashing::blake2_256(data);
+                return Self::verify_account_signature(signer, &msg, signature);
+            }
+            result
+        }
+
+        /// hash of |PAYLOAD_TAG|PAYLOAD_VERSION|genesis_hash|data|, so that signatures
+        /// can't be replayed on other chains or by other pallets
+        pub fn payload_hash(data: &[u8]) -> [u8; 32] {
+            let genesis_hash = <frame_system::Pallet<T>>::block_hash(T::BlockNumber::zero());
+            let mut payload = Vec::new();
+            payload.extend_from_slice(PAYLOAD_TAG);
+            payload.push(PAYLOAD_VERSION);
+            payload.extend_from_slice(genesis_hash.as_ref());
+            payload.extend_from_slice(data);
+            sp_io::hashing::blake2_256(&payload)
+        }
+
+        fn accepts_legacy_payload() -> bool {
+            Self::legacy_payload_deadline()
+                .map_or(false, |deadline| <frame_system::Pallet<T>>::block_number() < deadline)
+        }
+
+        /// verify a signature of `signer` on the hashed message.
+        /// A 64-byte signature is a raw sr25519 signature, as sent by existing clients;
+        /// any other signature is a SCALE encoded `T::Signature`, so that clients with
+        /// ed25519 or ecdsa keys can sign with `MultiSignature`.
+        pub fn verify_account_signature(
+            signer: &T::AccountId,
+            msg: &[u8; 32],
+            signature: &[u8],
+        ) -> DispatchResultWithPostInfo {
+            let verified = if signature.len() == 64 {
+                let pub_key = sr25519::Public::try_from(&signer.encode()[..])
+                    .map_err(|_| Error::<T>::MalformedPublicKey)?;
+                let sig = sr25519::Signature::try_from(signature)
+                    .map_err(|_| Error::<T>::MalformedSignature)?;
+                sr25519_verify(&sig, msg, &pub_key)
+            } else {
+                let mut input = signature;
+                let sig = T::Signature::decode(&mut input)
+                    .map_err(|_| Error::<T>::MalformedSignature)?;
+                // trailing bytes are not part of a valid signature
+                ensure!(input.is_empty(), Error::<T>::MalformedSignature);
+                sig.verify(&msg[..], signer)
+            };
+            ensure!(verified, Error::<T>::InvalidSignature);
+
+            Ok(().into())
+        }
+
+        // construct data from |server_addr|nonce|session_id|amount|
+        pub fn construct_byte_array(
+            address: &T::AccountId,
+            nonce: u64,
+            session_id: u32,
+            amount: BalanceOf<T>,
+        ) -> Vec<u8> {
             let mut data = Vec::new();
             data.extend_from_slice(&address.encode());
             data.extend_from_slice(&nonce.to_be_bytes());
             data.extend_from_slice(&session_id.to_be_bytes());
             data.extend_from_slice(&amount.encode());
-            let hash = sp_io::hashing::blake2_256(&data);
-            hash
+            data
         }
 
-        /// Deduct the amount from the account free balance
+        // construct data from |server_addr|nonce|session_id|amount| and hash its payload
+        pub fn construct_byte_array_and_hash(
+            address: &T::AccountId,
+            nonce: u64,
+            session_id: u32,
+            amount: BalanceOf<T>,
+        ) -> [u8; 32] {
+            Self::payload_hash(&Self::construct_byte_array(address, nonce, session_id, amount))
+        }
+
+        /// construct data from |"voucher"|server_addr|nonce|amount|;
+        /// amount is the cumulative amount paid in the channel so far
+        pub fn construct_voucher_data(
+            server: &T::AccountId,
+            nonce: u64,
+            amount: BalanceOf<T>,
+        ) -> Vec<u8> {
+            let mut data = Vec::new();
+            data.extend_from_slice(b"voucher");
+            data.extend_from_slice(&server.encode());
+            data.extend_from_slice(&nonce.to_be_bytes());
+            data.extend_from_slice(&amount.encode());
+            data
+        }
+
+        pub fn construct_voucher_hash(
+            server: &T::AccountId,
+            nonce: u64,
+            amount: BalanceOf<T>,
+        ) -> [u8; 32] {
+            Self::payload_hash(&Self::construct_voucher_data(server, nonce, amount))
+        }
+
+        /// construct data from |"close"|client_addr|server_addr|nonce|amount|;
+        /// both client and server sign it to close the channel cooperatively
+        pub fn construct_close_data(
+            client: &T::AccountId,
+            server: &T::AccountId,
+            nonce: u64,
+            amount: BalanceOf<T>,
+        ) -> Vec<u8> {
+            let mut data = Vec::new();
+            data.extend_from_slice(b"close");
+            data.extend_from_slice(&client.encode());
+            data.extend_from_slice(&server.encode());
+            data.extend_from_slice(&nonce.to_be_bytes());
+            data.extend_from_slice(&amount.encode());
+            data
+        }
+
+        pub fn construct_close_hash(
+            client: &T::AccountId,
+            server: &T::AccountId,
+            nonce: u64,
+            amount: BalanceOf<T>,
+        ) -> [u8; 32] {
+            Self::payload_hash(&Self::construct_close_data(client, server, nonce, amount))
+        }
+
+        /// construct data from |"withdraw"|client_addr|server_addr|nonce|withdrawn|;
+        /// withdrawn is the total amount withdrawn from the channel including this withdrawal,
+        /// so the server's signature can't be replayed
+        pub fn construct_withdraw_data(
+            client: &T::AccountId,
+            server: &T::AccountId,
+            nonce: u64,
+            withdrawn: BalanceOf<T>,
+        ) -> Vec<u8> {
+            let mut data = Vec::new();
+            data.extend_from_slice(b"withdraw");
+            data.extend_from_slice(&client.encode());
+            data.extend_from_slice(&server.encode());
+            data.extend_from_slice(&nonce.to_be_bytes());
+            data.extend_from_slice(&withdrawn.encode());
+            data
+        }
+
+        pub fn construct_withdraw_hash(
+            client: &T::AccountId,
+            server: &T::AccountId,
+            nonce: u64,
+            withdrawn: BalanceOf<T>,
+        ) -> [u8; 32] {
+            Self::payload_hash(&Self::construct_withdraw_data(client, server, nonce, withdrawn))
+        }
+
+        /// construct data from |"htlc"|server_addr|nonce|amount|hashlock|timelock|;
+        /// the client signs it to pay the server once the preimage of hashlock is revealed
+        pub fn construct_htlc_data(
+            server: &T::AccountId,
+            nonce: u64,
+            amount: BalanceOf<T>,
+            hashlock: &[u8; 32],
+            timelock: T::BlockNumber,
+        ) -> Vec<u8> {
+            let mut data = Vec::new();
+            data.extend_from_slice(b"htlc");
+            data.extend_from_slice(&server.encode());
+            data.extend_from_slice(&nonce.to_be_bytes());
+            data.extend_from_slice(&amount.encode());
+            data.extend_from_slice(hashlock);
+            data.extend_from_slice(&timelock.encode());
+            data
+        }
+
+        pub fn construct_htlc_hash(
+            server: &T::AccountId,
+            nonce: u64,
+            amount: BalanceOf<T>,
+            hashlock: &[u8; 32],
+            timelock: T::BlockNumber,
+        ) -> [u8; 32] {
+            Self::payload_hash(&Self::construct_htlc_data(
+                server, nonce, amount, hashlock, timelock,
+            ))
+        }
+
+        /// Return (client, server) of the channel between signer and counterparty.
+        /// Like close_channel, signer is taken as the server first.
+        fn channel_ends(
+            signer: &T::AccountId,
+            counterparty: &T::AccountId,
+        ) -> Result<(T::AccountId, T::AccountId), DispatchError> {
+            if Channel::<T>::contains_key(counterparty, signer) {
+                Ok((counterparty.clone(), signer.clone()))
+            } else if Channel::<T>::contains_key(signer, counterparty) {
+                Ok((signer.clone(), counterparty.clone()))
+            } else {
+                Err(Error::<T>::ChannelNotExist.into())
+            }
+        }
+
+        /// Pay the part of the cumulative `amount` the server has not claimed yet,
+        /// refund the rest of the channel balance to the client and close the channel.
+        /// Nothing is paid unless both transfers succeed.
+        #[transactional]
+        fn settle_channel(
+            client: &T::AccountId,
+            server: &T::AccountId,
+            amount: BalanceOf<T>,
+        ) -> DispatchResultWithPostInfo {
+            let chan = Channel::<T>::get(client, server);
+            let paid = amount
+                .saturating_sub(PaidAmount::<T>::get(client, server))
+                .min(chan.balance);
+            let refund = chan.balance - paid;
+            if !paid.is_zero() {
+                Self::pay_from_channel(client, server, paid)?;
+                Self::credit_payment(client, server, chan.opened, paid);
+            }
+            if !refund.is_zero() {
+                Self::refund_from_channel(client, server, refund)?;
+            }
+            Self::_close_channel(client, server);
+            let end_block = <frame_system::Pallet<T>>::block_number();
+            Self::deposit_event(Event::ChannelSettled(
+                client.clone(),
+                server.clone(),
+                paid,
+                refund,
+            ));
+            Self::deposit_event(Event::ChannelClosed(
+                client.clone(),
+                server.clone(),
+                end_block,
+            ));
+            Ok(().into())
+        }
+
+        /// Count a payment to the server towards its credit.
+        /// Payments are skipped if client and server share a funding source or the
+        /// channel is too young, and are capped per client and server in every era.
+        /// Only the part converted into credit is recorded as credited, the rest is
+        /// pending until it adds up to credit.
+        /// Payments in assets are valued in DPR with the asset's `dpr_per_unit` and `decimals`.
+        fn credit_payment(
+            client: &T::AccountId,
+            server: &T::AccountId,
+            opened: T::BlockNumber,
+            amount: BalanceOf<T>,
+        ) {
+            let current_block = <frame_system::Pallet<T>>::block_number();
+            if amount.is_zero()
+                || current_block.saturating_sub(opened) < T::MinCreditChannelAge::get()
+                || Self::share_funding_source(client, server)
+            {
+                return;
+            }
+            let dpr_amount = match ChannelAsset::<T>::get(client, server) {
+                None => amount,
+                Some(asset_id) => match AssetSettings::<T>::get(asset_id)
+                    .and_then(|setting| Self::asset_to_dpr(amount, &setting))
+                {
+                    Some(dpr_amount) => dpr_amount,
+                    None => return,
+                },
+            };
+            let current_era = T::CreditInterface::get_current_era();
+            let (era, credited) = PairCreditedPayment::<T>::get((client, server));
+            let credited = if era == current_era {
+                credited
+            } else {
+                Zero::zero()
+            };
+            let pending = PairPendingPayment::<T>::get((client, server));
+            let creditable = T::MaxCreditPaymentPerPair::get()
+                .saturating_sub(credited.saturating_add(pending))
+                .min(dpr_amount);
+            let pending = pending.saturating_add(creditable);
+            if pending.is_zero() {
+                return;
+            }
+            // small payments are summed until they add up to credit
+            let converted = T::CreditInterface::update_credit((server.clone(), pending));
+            if !converted.is_zero() {
+                PairCreditedPayment::<T>::insert(
+                    (client, server),
+                    (current_era, credited.saturating_add(converted)),
+                );
+            }
+            let pending = pending.saturating_sub(converted);
+            if pending.is_zero() {
+                PairPendingPayment::<T>::remove((client, server));
+            } else {
+                PairPendingPayment::<T>::insert((client, server), pending);
+            }
+        }
+
+        /// Accounts are related if they have the same funding source
+        /// or one of them is the funding source of the other.
+        pub fn share_funding_source(a: &T::AccountId, b: &T::AccountId) -> bool {
+            let source_a = FundingSource::<T>::get(a).unwrap_or_else(|| a.clone());
+            let source_b = FundingSource::<T>::get(b).unwrap_or_else(|| b.clone());
+            source_a == source_b || &source_a == b || &source_b == a
+        }
+
+        /// Lock the amount of client's funds into a channel,
+        /// in native DPR or in the channel's asset
+        fn fund_channel(
+            client: &T::AccountId,
+            asset: Option<T::AssetId>,
+            amount: BalanceOf<T>,
+        ) -> Result<(), DispatchError> {
+            match asset {
+                None => {
+                    if !Self::take_from_account(client, amount) {
+                        error!("Not enough free balance to fund channel");
+                        Err(Error::<T>::NotEnoughBalance)?
+                    }
+                    TotalMicropaymentChannelBalance::<T>::mutate_exists(client, |b| {
+                        let total_balance = b.take().unwrap_or_default();
+                        *b = Some(total_balance + amount);
+                    });
+                }
+                Some(asset_id) => {
+                    let mut asset_amount = Self::to_asset_balance(amount)?;
+                    let escrow = Self::escrow_account();
+                    // the escrow account is kept alive with the minimum balance of the asset,
+                    // paid by the first client funding a channel in it
+                    if <T::Assets as fungibles::Inspect<_>>::balance(asset_id, &escrow).is_zero() {
+                        asset_amount = asset_amount.saturating_add(
+                            <T::Assets as fungibles::Inspect<_>>::minimum_balance(asset_id),
+                        );
+                    }
+                    if <T::Assets as fungibles::Inspect<_>>::reducible_balance(
+                        asset_id, client, true,
+                    ) < asset_amount
+                    {
+                        error!("Not enough asset balance to fund channel");
+                        Err(Error::<T>::NotEnoughBalance)?
+                    }
+                    <T::Assets as fungibles::Transfer<_>>::transfer(
+                        asset_id,
+                        client,
+                        &escrow,
+                        asset_amount,
+                        true,
+                    )
+                    .map_err(|e| {
+                        error!("Failed to transfer asset into channel: {:?}", e);
+                        Error::<T>::AssetTransferFailed
+                    })?;
+                }
+            }
+            Ok(())
+        }
+
+        /// Pay the amount out of the channel between client and server to the server,
+        /// in the asset the channel is funded in. Nothing is written if the transfer fails.
+        #[transactional]
+        fn pay_from_channel(
+            client: &T::AccountId,
+            server: &T::AccountId,
+            amount: BalanceOf<T>,
+        ) -> Result<(), DispatchError> {
+            match ChannelAsset::<T>::get(client, server) {
+                None => {
+                    Self::reduce_total_channel_balance(client, amount);
+                    Self::deposit_into_account(server, amount)
+                }
+                Some(asset_id) => Self::deposit_asset_into_account(asset_id, server, amount),
+            }
+        }
+
+        /// Refund the amount out of the channel between client and server to the client,
+        /// without the fee or burn charged on payments. Nothing is written if the transfer fails.
+        #[transactional]
+        fn refund_from_channel(
+            client: &T::AccountId,
+            server: &T::AccountId,
+            amount: BalanceOf<T>,
+        ) -> Result<(), DispatchError> {
+            let escrow = Self::escrow_account();
+            match ChannelAsset::<T>::get(client, server) {
+                None => {
+                    Self::reduce_total_channel_balance(client, amount);
+                    T::Currency::transfer(&escrow, client, amount, ExistenceRequirement::KeepAlive)
+                }
+                Some(asset_id) => <T::Assets as fungibles::Transfer<_>>::transfer(
+                    asset_id,
+                    &escrow,
+                    client,
+                    Self::to_asset_balance(amount)?,
+                    true,
+                )
+                .map(|_| ()),
+            }
+        }
+
+        fn reduce_total_channel_balance(client: &T::AccountId, amount: BalanceOf<T>) {
+            TotalMicropaymentChannelBalance::<T>::mutate_exists(client, |b| {
+                let total_balance = b.take().unwrap_or_default();
+                *b = if total_balance > amount {
+                    Some(total_balance - amount)
+                } else {
+                    None
+                };
+            });
+        }
+
+        fn to_asset_balance(amount: BalanceOf<T>) -> Result<AssetBalanceOf<T>, DispatchError> {
+            TryInto::<u128>::try_into(amount)
+                .ok()
+                .and_then(|amount| TryInto::<AssetBalanceOf<T>>::try_into(amount).ok())
+                .ok_or_else(|| Error::<T>::AssetAmountOverflow.into())
+        }
+
+        /// Value an amount in the smallest unit of an asset in DPR
+        fn asset_to_dpr(amount: BalanceOf<T>, setting: &AssetSettingOf<T>) -> Option<BalanceOf<T>> {
+            let amount = TryInto::<u128>::try_into(amount).ok()?;
+            let dpr_per_unit = TryInto::<u128>::try_into(setting.dpr_per_unit).ok()?;
+            let unit = 10u128.checked_pow(setting.decimals.into())?;
+            multiply_by_rational(amount, dpr_per_unit, unit)
+                .ok()
+                .and_then(|dpr_amount| dpr_amount.try_into().ok())
+        }
+
+        /// Transfer the amount of asset from the escrow account to the account;
+        /// the asset's burn share is burned from the escrow account
+        fn deposit_asset_into_account(
+            asset_id: T::AssetId,
+            account: &T::AccountId,
+            amount: BalanceOf<T>,
+        ) -> Result<(), DispatchError> {
+            let escrow = Self::escrow_account();
+            let setting = AssetSettings::<T>::get(asset_id).unwrap_or_default();
+            let fee = Self::to_asset_balance(setting.burn * amount)?;
+            let asset_amount = Self::to_asset_balance(amount)?.saturating_sub(fee);
+            if !fee.is_zero() {
+                <T::Assets as fungibles::Mutate<_>>::burn_from(asset_id, &escrow, fee)?;
+            }
+            if !asset_amount.is_zero() {
+                <T::Assets as fungibles::Transfer<_>>::transfer(
+                    asset_id,
+                    &escrow,
+                    account,
+                    asset_amount,
+                    true,
+                )?;
+            }
+            Ok(())
+        }
+
+        /// Move the amount from the account free balance into the escrow account
         fn take_from_account(account: &T::AccountId, amount: BalanceOf<T>) -> bool {
-            T::Currency::withdraw(
-                account,
-                amount,
-                WithdrawReasons::FEE,
-                ExistenceRequirement::KeepAlive,
-            )
-            .map_or(false, |_| true)
+            let escrow = Self::escrow_account();
+            // the escrow account is kept alive with the existential deposit,
+            // paid by the first client funding a channel
+            let total = if T::Currency::free_balance(&escrow).is_zero() {
+                amount.saturating_add(T::Currency::minimum_balance())
+            } else {
+                amount
+            };
+            T::Currency::transfer(account, &escrow, total, ExistenceRequirement::KeepAlive)
+                .is_ok()
         }
 
-        /// Deposit the amount to the account free balance
-        /// some additional fee should be charged
+        /// Fee rate of the channels of the server, from the fee tier of its credit level.
+        /// Servers without a fee tier or which have never been online pay `MicropaymentBurn`.
+        pub fn fee_rate(server: &T::AccountId) -> Percent {
+            if T::NodeInterface::im_ever_online(server) {
+                if let Some(score) = T::CreditInterface::get_credit_score(server) {
+                    let credit_level = T::CreditInterface::get_credit_level(score);
+                    if let Some(fee) = FeeTiers::<T>::get(credit_level) {
+                        return fee;
+                    }
+                }
+            }
+            T::MicropaymentBurn::get()
+        }
+
+        /// Split the fee between burn, treasury and validators
+        fn distribute_fee(fee: NegativeImbalanceOf<T>) {
+            let split = FeeDistribution::<T>::get();
+            let amount = fee.peek();
+            let (treasury, rest) = fee.split(split.treasury * amount);
+            let (validators, _burned) = rest.split(split.validators * amount);
+            T::Slash::on_unbalanced(treasury);
+            T::ValidatorReward::on_unbalanced(validators);
+        }
+
+        /// Transfer the amount from the escrow account to the server's free balance
+        /// minus the server's fee
         fn deposit_into_account(
-            account: &T::AccountId,
+            server: &T::AccountId,
             amount: BalanceOf<T>,
         ) -> Result<(), DispatchError> {
-            T::Currency::deposit_creating(account, amount);
-            let fee = T::MicropaymentBurn::get() * amount;
-            let burned = T::Currency::withdraw(
-                account,
+            let escrow = Self::escrow_account();
+            let fee = Self::fee_rate(server) * amount;
+            let fee = T::Currency::withdraw(
+                &escrow,
                 fee,
                 WithdrawReasons::FEE,
                 ExistenceRequirement::KeepAlive,
             )?;
-            T::Slash::on_unbalanced(burned);
+            let fee_amount = fee.peek();
+            Self::distribute_fee(fee);
+            T::Currency::transfer(
+                &escrow,
+                server,
+                amount - fee_amount,
+                ExistenceRequirement::KeepAlive,
+            )?;
             Ok(())
         }
     }
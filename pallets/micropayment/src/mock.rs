@@ -15,7 +15,7 @@
 
 use crate as pallet_micropayment;
 use crate::testing_utils::*;
-use frame_support::traits::ConstU32;
+use frame_support::traits::{ConstU128, ConstU32};
 use frame_support::{
     pallet_prelude::GenesisBuild,
     parameter_types,
@@ -23,11 +23,11 @@ use frame_support::{
     PalletId,
 };
 use frame_system as system;
-use node_primitives::{Balance, Moment};
+use node_primitives::{Balance, Moment, Signature};
 use sp_core::{crypto::AccountId32, sr25519, H256};
 use sp_runtime::{
     testing::Header,
-    traits::{BlakeTwo256, IdentityLookup},
+    traits::{BlakeTwo256, IdentityLookup, Verify},
     Percent, Permill,
 };
 
@@ -48,6 +48,7 @@ frame_support::construct_runtime!(
         Micropayment: pallet_micropayment::{Pallet, Call, Storage, Event<T>},
         Timestamp: pallet_timestamp::{Pallet, Call, Storage, Inherent},
         Treasury: pallet_treasury::{Pallet, Call, Storage, Config, Event<T>},
+        Assets: pallet_assets::{Pallet, Call, Storage, Event<T>},
     }
 );
 
@@ -130,6 +131,31 @@ impl pallet_balances::Config for Test {
     type WeightInfo = (); //pallet_balances::weights::SubstrateWeight<Test>;
 }
 
+parameter_types! {
+    pub const AssetDeposit: Balance = 1;
+    pub const ApprovalDeposit: Balance = 1;
+    pub const StringLimit: u32 = 50;
+    pub const MetadataDepositBase: Balance = 1;
+    pub const MetadataDepositPerByte: Balance = 1;
+}
+
+impl pallet_assets::Config for Test {
+    type Event = Event;
+    type Balance = u64;
+    type AssetId = u32;
+    type Currency = Balances;
+    type ForceOrigin = frame_system::EnsureRoot<AccountId>;
+    type AssetDeposit = AssetDeposit;
+    type AssetAccountDeposit = ConstU128<1>;
+    type StringLimit = StringLimit;
+    type MetadataDepositBase = MetadataDepositBase;
+    type MetadataDepositPerByte = MetadataDepositPerByte;
+    type ApprovalDeposit = ApprovalDeposit;
+    type Freezer = ();
+    type Extra = ();
+    type WeightInfo = ();
+}
+
 type BlockNumber = u64;
 
 const MILLISECS_PER_BLOCK: Moment = 5000;
@@ -201,6 +227,12 @@ parameter_types! {
     pub const SecsPerBlock: u32 = 5u32;
     pub const DataPerDPR: u64 = 1024 * 1024 * 1024 * 1024;
     pub const MicropaymentBurn: Percent = Percent::from_percent(10);
+    pub const ChallengePeriod: BlockNumber = 10;
+    pub const MinCreditChannelAge: BlockNumber = 10;
+    pub const MaxCreditPaymentPerPair: Balance = 100;
+    pub const MicropaymentPalletId: PalletId = PalletId(*b"dp/mcpay");
+    pub const LegacyPayloadWindow: BlockNumber = 20;
+    pub const SettlementRetryDelay: BlockNumber = 10;
 }
 impl pallet_micropayment::Config for Test {
     type Event = Event;
@@ -213,6 +245,17 @@ impl pallet_micropayment::Config for Test {
     type NodeInterface = DeeperNode;
     type MicropaymentBurn = MicropaymentBurn;
     type Slash = Treasury;
+    type ValidatorReward = ();
+    type ChallengePeriod = ChallengePeriod;
+    type Signature = Signature;
+    type Signer = <Signature as Verify>::Signer;
+    type AssetId = u32;
+    type Assets = Assets;
+    type MinCreditChannelAge = MinCreditChannelAge;
+    type MaxCreditPaymentPerPair = MaxCreditPaymentPerPair;
+    type PalletId = MicropaymentPalletId;
+    type LegacyPayloadWindow = LegacyPayloadWindow;
+    type SettlementRetryDelay = SettlementRetryDelay;
 }
 
 // Build genesis storage according to the mock runtime.
@@ -226,6 +269,8 @@ pub fn new_test_ext() -> sp_io::TestExternalities {
             (bob(), 500),
             (charlie(), 500),
             (dave(), 500),
+            // existential deposit of the escrow account
+            (Micropayment::escrow_account(), 100),
         ],
     }
     .assimilate_storage(&mut storage);
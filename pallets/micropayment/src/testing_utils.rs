@@ -13,6 +13,7 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use codec::Encode;
 use node_primitives::Signature;
 use sp_core::{crypto::AccountId32, sr25519, Pair, Public};
 use sp_runtime::traits::{IdentifyAccount, Verify};
@@ -51,3 +52,26 @@ pub fn charlie() -> AccountId {
 pub fn dave() -> AccountId {
     get_account_id_from_seed::<sr25519::Public>("Dave")
 }
+
+/// Helper function to sign a message with the sr25519 key generated from seed
+pub fn sign_with_seed(seed: &str, msg: &[u8]) -> Vec<u8> {
+    sr25519::Pair::from_string(&format!("//{}", seed), None)
+        .expect("static values are valid; qed")
+        .sign(msg)
+        .0
+        .to_vec()
+}
+
+/// Helper function to sign a message with the key generated from seed,
+/// returning the SCALE encoded `MultiSignature`
+pub fn multi_sign_with_seed<TPublic: Public>(seed: &str, msg: &[u8]) -> Vec<u8>
+where
+    Signature: From<<TPublic::Pair as Pair>::Signature>,
+{
+    Signature::from(
+        TPublic::Pair::from_string(&format!("//{}", seed), None)
+            .expect("static values are valid; qed")
+            .sign(msg),
+    )
+    .encode()
+}
@@ -13,13 +13,24 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use super::Chan;
-use crate::{mock::*, testing_utils::*, Error};
-use frame_support::{assert_ok, dispatch::DispatchErrorWithPostInfo};
+use super::{AssetSetting, Chan, Channel, ChannelClose, ChannelExpiry, ExpiryCursor, FeeSplit};
+use crate::{mock::*, testing_utils::*, weights::WeightInfo, Error};
+use frame_support::{
+    assert_noop, assert_ok,
+    dispatch::DispatchErrorWithPostInfo,
+    error::BadOrigin,
+    traits::{Currency, Get, OnIdle, OnRuntimeUpgrade},
+    weights::Weight,
+};
 use hex_literal::hex;
-use sp_core::sr25519::{Public, Signature};
+use pallet_credit::{CreditData, CreditLevel};
+use sp_core::{
+    ecdsa, ed25519,
+    sr25519::{Public, Signature},
+    H256,
+};
 use sp_io::crypto::sr25519_verify;
-use sp_runtime::{DispatchError, ModuleError};
+use sp_runtime::{DispatchError, ModuleError, Percent};
 
 #[test]
 fn open_channel() {
@@ -94,6 +105,7 @@ fn open_channel() {
                 })
             );
         }
+        assert_ok!(Micropayment::check_invariants());
     });
 }
 
@@ -168,6 +180,7 @@ fn close_channel() {
         run_to_block(24 * 720 + 2 + crate::mock::BLOCKS_PER_ERA);
         // can close channel when server is offline longer then 1 era
         assert_ok!(Micropayment::close_channel(Origin::signed(alice()), bob()));
+        assert_ok!(Micropayment::check_invariants());
     });
 }
 
@@ -198,6 +211,149 @@ fn close_expired_channels() {
         assert_ok!(Micropayment::close_expired_channels(
             Origin::signed(alice())
         ));
+        assert_ok!(Micropayment::check_invariants());
+    });
+}
+
+#[test]
+fn settle_expired_channels_on_idle() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(Micropayment::open_channel(
+            Origin::signed(alice()),
+            bob(),
+            100,
+            3600
+        ));
+        assert_ok!(Micropayment::open_channel(
+            Origin::signed(alice()),
+            charlie(),
+            100,
+            7200
+        ));
+        assert_ok!(Micropayment::open_channel(
+            Origin::signed(charlie()),
+            dave(),
+            100,
+            3600
+        ));
+        assert_ok!(Micropayment::initiate_close(
+            Origin::signed(charlie()),
+            dave()
+        ));
+        assert_eq!(Micropayment::channel_expiry(720, (alice(), bob())), Some(()));
+
+        // nothing has expired yet
+        Micropayment::on_idle(720, Weight::MAX);
+        assert!(Channel::<Test>::contains_key(alice(), bob()));
+        assert_eq!(Micropayment::expiry_cursor(), 720);
+
+        run_to_block(721);
+        // no weight left in the block
+        assert_eq!(Micropayment::on_idle(721, 0), 0);
+        assert!(Channel::<Test>::contains_key(alice(), bob()));
+
+        Micropayment::on_idle(721, Weight::MAX);
+        assert!(!Channel::<Test>::contains_key(alice(), bob()));
+        assert_eq!(Micropayment::channel_expiry(720, (alice(), bob())), None);
+        assert_eq!(Micropayment::nonce((alice(), bob())), 1);
+        // the client is refunded without the fee
+        assert_eq!(400, Balances::free_balance(alice()));
+        // channels being closed are left to finalize_close
+        assert!(Channel::<Test>::contains_key(charlie(), dave()));
+        assert_eq!(Micropayment::channel_expiry(720, (charlie(), dave())), None);
+        assert!(Channel::<Test>::contains_key(alice(), charlie()));
+        assert_eq!(Micropayment::expiry_cursor(), 721);
+        assert_ok!(Micropayment::check_invariants());
+    });
+}
+
+#[test]
+fn settle_expired_channel_retried() {
+    new_test_ext().execute_with(|| {
+        let setting = AssetSetting {
+            burn: Percent::from_percent(10),
+            dpr_per_unit: 1,
+            decimals: 0,
+        };
+        assert_ok!(Assets::force_create(Origin::root(), 1, alice(), true, 1));
+        assert_ok!(Assets::mint(Origin::signed(alice()), 1, alice(), 1000));
+        assert_ok!(Micropayment::set_asset_setting(
+            Origin::root(),
+            1,
+            Some(setting)
+        ));
+        assert_ok!(Micropayment::open_asset_channel(
+            Origin::signed(alice()),
+            bob(),
+            1,
+            100,
+            3600
+        ));
+        let escrow = Micropayment::escrow_account();
+        assert_ok!(Assets::freeze(Origin::signed(alice()), 1, escrow.clone()));
+
+        // the failed settlement is retried SettlementRetryDelay blocks later
+        run_to_block(721);
+        Micropayment::on_idle(721, Weight::MAX);
+        assert!(Channel::<Test>::contains_key(alice(), bob()));
+        assert_eq!(Micropayment::channel_expiry(720, (alice(), bob())), None);
+        assert_eq!(Micropayment::channel_expiry(731, (alice(), bob())), Some(()));
+        System::assert_last_event(Event::Micropayment(
+            crate::Event::ExpiredChannelSettlementFailed(alice(), bob(), 731),
+        ));
+
+        assert_ok!(Assets::thaw(Origin::signed(alice()), 1, escrow));
+        run_to_block(732);
+        Micropayment::on_idle(732, Weight::MAX);
+        assert!(!Channel::<Test>::contains_key(alice(), bob()));
+        assert_eq!(Micropayment::channel_expiry(731, (alice(), bob())), None);
+        assert_eq!(Assets::balance(1, alice()), 999);
+        assert_ok!(Micropayment::check_invariants());
+    });
+}
+
+#[test]
+fn settle_expired_channels_bounded_by_weight() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(Micropayment::open_channel(
+            Origin::signed(alice()),
+            bob(),
+            100,
+            3600
+        ));
+        assert_ok!(Micropayment::open_channel(
+            Origin::signed(alice()),
+            charlie(),
+            100,
+            3600
+        ));
+        // closed channels are removed from the index
+        assert_ok!(Micropayment::open_channel(
+            Origin::signed(charlie()),
+            dave(),
+            100,
+            3600
+        ));
+        assert_ok!(Micropayment::close_channel(
+            Origin::signed(dave()),
+            charlie()
+        ));
+        assert_eq!(Micropayment::channel_expiry(720, (charlie(), dave())), None);
+
+        ExpiryCursor::<Test>::put(720);
+        run_to_block(721);
+        let db_weight = <Test as frame_system::Config>::DbWeight::get();
+        let one_channel = db_weight.reads_writes(2, 1) + <() as WeightInfo>::settle_expired_channel();
+        assert_eq!(Micropayment::on_idle(721, one_channel), one_channel);
+        assert_eq!(ChannelExpiry::<Test>::iter_prefix(720).count(), 1);
+        assert_eq!(Micropayment::expiry_cursor(), 720);
+
+        Micropayment::on_idle(721, Weight::MAX);
+        assert!(!Channel::<Test>::contains_key(alice(), bob()));
+        assert!(!Channel::<Test>::contains_key(alice(), charlie()));
+        assert_eq!(500, Balances::free_balance(alice()));
+        assert_eq!(Micropayment::expiry_cursor(), 721);
+        assert_ok!(Micropayment::check_invariants());
     });
 }
 
@@ -250,6 +406,7 @@ fn add_balance() {
                 })
             );
         }
+        assert_ok!(Micropayment::check_invariants());
     });
 }
 
@@ -267,15 +424,466 @@ fn claim_payment() {
         let nonce: u64 = 0;
         let claim_amount = 30;
         let msg = Micropayment::construct_byte_array_and_hash(&bob(), nonce, session_id, claim_amount);
-        println!("{:#02x?}", msg);
-        let signature: [u8; 64] = hex!("1a2157be0e159a600502c5c6435539672bcbce956355a1ca35201762fd1fb72e0b48e853e812011919e5d25b07e4056b9b98e6b2de612652d450bd14063a6185");
+        let signature = sign_with_seed("Alice", &msg);
         assert_ok!(Micropayment::claim_payment(
             Origin::signed(bob()),
-            alice(), session_id, claim_amount, signature.into()
+            alice(), session_id, claim_amount, signature
         ));
 
         assert_eq!(527, Balances::free_balance(bob()));
         assert_eq!(3, Treasury::pot());
+        assert_ok!(Micropayment::check_invariants());
+    });
+}
+
+#[test]
+fn legacy_payload_window() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(Micropayment::open_channel(
+            Origin::signed(alice()),
+            bob(),
+            300,
+            3600
+        ));
+        let claim = |session_id: u32, signature: Vec<u8>| {
+            Micropayment::claim_payment(Origin::signed(bob()), alice(), session_id, 30, signature)
+        };
+        // signature of an existing client on the hash of |server|nonce|session_id|amount|
+        let legacy_signature: [u8; 64] = hex!("1a2157be0e159a600502c5c6435539672bcbce956355a1ca35201762fd1fb72e0b48e853e812011919e5d25b07e4056b9b98e6b2de612652d450bd14063a6185");
+        assert_noop!(
+            claim(1, legacy_signature.into()),
+            Error::<Test>::InvalidSignature
+        );
+
+        // the runtime upgrade opens the legacy window
+        Micropayment::on_runtime_upgrade();
+        let deadline = System::block_number() + LegacyPayloadWindow::get();
+        assert_eq!(Micropayment::legacy_payload_deadline(), Some(deadline));
+        assert_ok!(claim(1, legacy_signature.into()));
+
+        // legacy payloads are rejected after the window
+        run_to_block(deadline);
+        let data = Micropayment::construct_byte_array(&bob(), 0, 2, 30);
+        let legacy_signature = sign_with_seed("Alice", &sp_io::hashing::blake2_256(&data));
+        assert_noop!(claim(2, legacy_signature), Error::<Test>::InvalidSignature);
+
+        // payloads are bound to the genesis hash of the chain
+        let msg = Micropayment::construct_byte_array_and_hash(&bob(), 0, 2, 30);
+        let signature = sign_with_seed("Alice", &msg);
+        let genesis_hash = System::block_hash(0);
+        frame_system::BlockHash::<Test>::insert(0, H256::repeat_byte(1));
+        assert_noop!(claim(2, signature.clone()), Error::<Test>::InvalidSignature);
+        frame_system::BlockHash::<Test>::insert(0, genesis_hash);
+        assert_ok!(claim(2, signature));
+    });
+}
+
+#[test]
+fn claim_payment_credit_safeguards() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(Micropayment::open_channel(
+            Origin::signed(alice()),
+            bob(),
+            300,
+            3600
+        ));
+        let claim = |session_id: u32, amount: u128| {
+            let msg = Micropayment::construct_byte_array_and_hash(&bob(), 0, session_id, amount);
+            Micropayment::claim_payment(
+                Origin::signed(bob()),
+                alice(),
+                session_id,
+                amount,
+                sign_with_seed("Alice", &msg),
+            )
+        };
+        // channel is too young to earn credit
+        assert_ok!(claim(1, 30));
+        assert_eq!(Micropayment::pair_pending_payment((alice(), bob())), 0);
+
+        // payments below MicropaymentToCreditFactor are pending, not credited
+        run_to_block(10);
+        assert_ok!(claim(2, 30));
+        assert_eq!(Micropayment::pair_pending_payment((alice(), bob())), 30);
+        assert_eq!(Micropayment::pair_credited_payment((alice(), bob())).1, 0);
+        // payments of the pair are capped in every era
+        assert_ok!(claim(3, 80));
+        assert_eq!(Micropayment::pair_pending_payment((alice(), bob())), 100);
+        assert_ok!(claim(4, 10));
+        assert_eq!(Micropayment::pair_pending_payment((alice(), bob())), 100);
+        assert_eq!(Micropayment::pair_credited_payment((alice(), bob())).1, 0);
+
+        // client and server funded from the same source earn no credit
+        assert_noop!(
+            Micropayment::set_funding_source(Origin::signed(alice()), dave(), Some(charlie())),
+            BadOrigin
+        );
+        assert_ok!(Micropayment::set_funding_source(
+            Origin::root(),
+            dave(),
+            Some(charlie())
+        ));
+        assert_eq!(Micropayment::funding_source(dave()), Some(charlie()));
+        assert!(Micropayment::share_funding_source(&charlie(), &dave()));
+        assert!(!Micropayment::share_funding_source(&alice(), &bob()));
+        assert_ok!(Micropayment::open_channel(
+            Origin::signed(charlie()),
+            dave(),
+            300,
+            3600
+        ));
+        run_to_block(20);
+        let msg = Micropayment::construct_byte_array_and_hash(&dave(), 0, 1, 30);
+        assert_ok!(Micropayment::claim_payment(
+            Origin::signed(dave()),
+            charlie(),
+            1,
+            30,
+            sign_with_seed("Charlie", &msg)
+        ));
+        assert_eq!(Micropayment::pair_pending_payment((charlie(), dave())), 0);
+        assert_ok!(Micropayment::check_invariants());
+    });
+}
+
+#[test]
+fn channel_funds_held_in_escrow() {
+    new_test_ext().execute_with(|| {
+        let escrow = Micropayment::escrow_account();
+        Balances::make_free_balance_be(&escrow, 0);
+        let issuance = Balances::total_issuance();
+        assert_ok!(Micropayment::open_channel(
+            Origin::signed(alice()),
+            bob(),
+            300,
+            3600
+        ));
+        // the first client pays the existential deposit of the escrow account
+        assert_eq!(Balances::total_issuance(), issuance);
+        assert_eq!(Balances::free_balance(&escrow), 400);
+        assert_eq!(Balances::free_balance(alice()), 100);
+        assert_ok!(Micropayment::open_channel(
+            Origin::signed(charlie()),
+            bob(),
+            200,
+            3600
+        ));
+        assert_ok!(Micropayment::add_balance(Origin::signed(charlie()), bob(), 50));
+        assert_eq!(Balances::free_balance(&escrow), 650);
+        assert_ok!(Micropayment::check_invariants());
+
+        let msg = Micropayment::construct_byte_array_and_hash(&bob(), 0, 1, 30);
+        assert_ok!(Micropayment::claim_payment(
+            Origin::signed(bob()),
+            alice(),
+            1,
+            30,
+            sign_with_seed("Alice", &msg)
+        ));
+        assert_eq!(Balances::free_balance(&escrow), 620);
+        assert_eq!(527, Balances::free_balance(bob()));
+        assert_ok!(Micropayment::check_invariants());
+
+        assert_ok!(Micropayment::close_channel(Origin::signed(bob()), alice()));
+        assert_ok!(Micropayment::close_channel(Origin::signed(bob()), charlie()));
+        assert_eq!(Balances::free_balance(&escrow), 100);
+        assert_eq!(Micropayment::total_micropayment_chanel_balance(alice()), None);
+        assert_ok!(Micropayment::check_invariants());
+        // burned shares go to the treasury, nothing is minted or destroyed
+        assert_eq!(Balances::total_issuance(), issuance);
+    });
+}
+
+#[test]
+fn multi_hop_htlc() {
+    new_test_ext().execute_with(|| {
+        // charlie pays bob through alice
+        assert_ok!(Micropayment::open_channel(
+            Origin::signed(charlie()),
+            alice(),
+            300,
+            3600
+        ));
+        assert_ok!(Micropayment::open_channel(
+            Origin::signed(alice()),
+            bob(),
+            300,
+            3600
+        ));
+        let preimage = [7u8; 32];
+        let hashlock = sp_io::hashing::blake2_256(&preimage);
+        // the downstream hop has a shorter timelock
+        let msg = Micropayment::construct_htlc_hash(&bob(), 0, 50, &hashlock, 20);
+        let alice_signature = sign_with_seed("Alice", &msg);
+        let msg = Micropayment::construct_htlc_hash(&alice(), 0, 60, &hashlock, 30);
+        let charlie_signature = sign_with_seed("Charlie", &msg);
+
+        assert_eq!(
+            Micropayment::claim_htlc(
+                Origin::signed(bob()),
+                alice(),
+                50,
+                20,
+                [8u8; 32],
+                alice_signature.clone()
+            ),
+            Err(DispatchErrorWithPostInfo::from(
+                Error::<Test>::InvalidSignature
+            ))
+        );
+        assert_ok!(Micropayment::claim_htlc(
+            Origin::signed(bob()),
+            alice(),
+            50,
+            20,
+            preimage,
+            alice_signature.clone()
+        ));
+        assert_eq!(545, Balances::free_balance(bob()));
+        assert_eq!(Micropayment::channel(alice(), bob()).balance, 250);
+        assert_eq!(
+            Micropayment::claim_htlc(
+                Origin::signed(bob()),
+                alice(),
+                50,
+                20,
+                preimage,
+                alice_signature
+            ),
+            Err(DispatchErrorWithPostInfo::from(
+                Error::<Test>::HtlcAlreadyClaimed
+            ))
+        );
+
+        // alice learns the preimage from the chain and claims upstream
+        run_to_block(30);
+        assert_ok!(Micropayment::claim_htlc(
+            Origin::signed(alice()),
+            charlie(),
+            60,
+            30,
+            preimage,
+            charlie_signature.clone()
+        ));
+        assert_eq!(254, Balances::free_balance(alice()));
+        assert_eq!(Micropayment::claimed_htlc((charlie(), alice()), hashlock), Some(()));
+
+        // claims after the timelock are rejected
+        run_to_block(31);
+        let msg = Micropayment::construct_htlc_hash(&alice(), 0, 10, &[0u8; 32], 30);
+        assert_eq!(
+            Micropayment::claim_htlc(
+                Origin::signed(alice()),
+                charlie(),
+                10,
+                30,
+                [9u8; 32],
+                sign_with_seed("Charlie", &msg)
+            ),
+            Err(DispatchErrorWithPostInfo::from(Error::<Test>::HtlcExpired))
+        );
+
+        // claimed hashlocks are cleared with the channel
+        assert_ok!(Micropayment::close_channel(Origin::signed(alice()), charlie()));
+        assert_eq!(Micropayment::claimed_htlc((charlie(), alice()), hashlock), None);
+        assert_ok!(Micropayment::check_invariants());
+    });
+}
+
+#[test]
+fn withdraw_from_channel() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(Micropayment::open_channel(
+            Origin::signed(alice()),
+            bob(),
+            300,
+            3600
+        ));
+        // immediate withdrawal co-signed by the server
+        let msg = Micropayment::construct_withdraw_hash(&alice(), &bob(), 0, 100);
+        let signature = sign_with_seed("Bob", &msg);
+        assert_eq!(
+            Micropayment::withdraw_from_channel(
+                Origin::signed(alice()),
+                bob(),
+                100,
+                Some(sign_with_seed("Alice", &msg))
+            ),
+            Err(DispatchErrorWithPostInfo::from(
+                Error::<Test>::InvalidSignature
+            ))
+        );
+        assert_ok!(Micropayment::withdraw_from_channel(
+            Origin::signed(alice()),
+            bob(),
+            100,
+            Some(signature.clone())
+        ));
+        assert_eq!(Micropayment::channel(alice(), bob()).balance, 200);
+        // withdrawals are refunded without the fee
+        assert_eq!(300, Balances::free_balance(alice()));
+        // the signature can't be replayed
+        assert_eq!(
+            Micropayment::withdraw_from_channel(
+                Origin::signed(alice()),
+                bob(),
+                100,
+                Some(signature)
+            ),
+            Err(DispatchErrorWithPostInfo::from(
+                Error::<Test>::InvalidSignature
+            ))
+        );
+
+        // withdrawal after a notice period
+        assert_ok!(Micropayment::withdraw_from_channel(
+            Origin::signed(alice()),
+            bob(),
+            50,
+            None
+        ));
+        assert_eq!(Micropayment::pending_withdrawal(alice(), bob()), Some((50, 10)));
+        assert_eq!(
+            Micropayment::withdraw_from_channel(Origin::signed(alice()), bob(), 50, None),
+            Err(DispatchErrorWithPostInfo::from(
+                Error::<Test>::NoticePeriodNotEnded
+            ))
+        );
+        assert_eq!(
+            Micropayment::withdraw_from_channel(Origin::signed(alice()), bob(), 60, None),
+            Err(DispatchErrorWithPostInfo::from(
+                Error::<Test>::WithdrawalMismatch
+            ))
+        );
+        // the server claims its latest payment during the notice period
+        let msg = Micropayment::construct_byte_array_and_hash(&bob(), 0, 1, 30);
+        assert_ok!(Micropayment::claim_payment(
+            Origin::signed(bob()),
+            alice(),
+            1,
+            30,
+            sign_with_seed("Alice", &msg)
+        ));
+        run_to_block(10);
+        assert_ok!(Micropayment::withdraw_from_channel(
+            Origin::signed(alice()),
+            bob(),
+            50,
+            None
+        ));
+        assert_eq!(Micropayment::pending_withdrawal(alice(), bob()), None);
+        assert_eq!(Micropayment::channel(alice(), bob()).balance, 120);
+        assert_eq!(350, Balances::free_balance(alice()));
+        assert_ok!(Micropayment::check_invariants());
+
+        assert_ok!(Micropayment::close_channel(Origin::signed(bob()), alice()));
+        assert_eq!(Micropayment::withdrawn_amount(alice(), bob()), 0);
+    });
+}
+
+#[test]
+fn extend_channel() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(Micropayment::open_channel(
+            Origin::signed(alice()),
+            bob(),
+            300,
+            3600
+        ));
+        assert_ok!(Micropayment::extend_channel(Origin::signed(alice()), bob(), 3600));
+        assert_eq!(Micropayment::channel(alice(), bob()).expiration, 1440);
+        assert_eq!(Micropayment::channel_expiry(720, (alice(), bob())), None);
+        assert_eq!(Micropayment::channel_expiry(1440, (alice(), bob())), Some(()));
+
+        // the channel is not settled at its former expiration
+        run_to_block(721);
+        Micropayment::on_idle(721, Weight::MAX);
+        assert!(Channel::<Test>::contains_key(alice(), bob()));
+
+        run_to_block(1441);
+        assert_eq!(
+            Micropayment::extend_channel(Origin::signed(alice()), bob(), 3600),
+            Err(DispatchErrorWithPostInfo::from(Error::<Test>::ChannelExpired))
+        );
+        Micropayment::on_idle(1441, Weight::MAX);
+        assert!(!Channel::<Test>::contains_key(alice(), bob()));
+        assert_ok!(Micropayment::check_invariants());
+    });
+}
+
+#[test]
+fn fee_tiers() {
+    new_test_ext().execute_with(|| {
+        assert_noop!(
+            Micropayment::set_fee_split(
+                Origin::root(),
+                FeeSplit {
+                    burn: Percent::from_percent(50),
+                    treasury: Percent::from_percent(30),
+                    validators: Percent::from_percent(30),
+                }
+            ),
+            Error::<Test>::InvalidFeeSplit
+        );
+        assert_ok!(Micropayment::set_fee_split(
+            Origin::root(),
+            FeeSplit {
+                burn: Percent::from_percent(50),
+                treasury: Percent::from_percent(30),
+                validators: Percent::from_percent(20),
+            }
+        ));
+        assert_noop!(
+            Micropayment::set_fee_tier(
+                Origin::signed(alice()),
+                CreditLevel::Three,
+                Some(Percent::from_percent(5))
+            ),
+            BadOrigin
+        );
+        assert_ok!(Micropayment::set_fee_tier(
+            Origin::root(),
+            CreditLevel::Three,
+            Some(Percent::from_percent(5))
+        ));
+        // the tier only applies to servers that have been online
+        assert_ok!(Credit::add_or_update_credit_data(
+            Origin::root(),
+            bob(),
+            CreditData {
+                credit: 300,
+                current_credit_level: CreditLevel::Three,
+                ..Default::default()
+            }
+        ));
+        assert_eq!(Micropayment::fee_rate(&bob()), Percent::from_percent(10));
+        assert_ok!(DeeperNode::im_online(Origin::signed(bob())));
+        assert_eq!(Micropayment::fee_rate(&bob()), Percent::from_percent(5));
+
+        assert_ok!(Micropayment::open_channel(
+            Origin::signed(alice()),
+            bob(),
+            300,
+            3600
+        ));
+        let issuance = Balances::total_issuance();
+        let msg = Micropayment::construct_byte_array_and_hash(&bob(), 0, 1, 200);
+        assert_ok!(Micropayment::claim_payment(
+            Origin::signed(bob()),
+            alice(),
+            1,
+            200,
+            sign_with_seed("Alice", &msg)
+        ));
+        assert_eq!(690, Balances::free_balance(bob()));
+        // 3 to the treasury, the burned 5 and the validators' 2 leave the mock's issuance
+        assert_eq!(3, Treasury::pot());
+        assert_eq!(Balances::total_issuance(), issuance - 7);
+
+        // servers without a tier pay MicropaymentBurn
+        assert_ok!(Micropayment::set_fee_tier(Origin::root(), CreditLevel::Three, None));
+        assert_eq!(Micropayment::fee_rate(&bob()), Percent::from_percent(10));
+        assert_ok!(Micropayment::check_invariants());
     });
 }
 
@@ -325,3 +933,563 @@ fn signature() {
     let verified = sr25519_verify(&sig, &msg, &pk);
     assert!(verified);
 }
+
+#[test]
+fn unilateral_close_with_voucher() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(Micropayment::open_channel(
+            Origin::signed(alice()),
+            bob(),
+            300,
+            3600
+        ));
+        assert_ok!(Micropayment::initiate_close(Origin::signed(alice()), bob()));
+        assert_eq!(
+            Micropayment::initiate_close(Origin::signed(bob()), alice()),
+            Err(DispatchErrorWithPostInfo::from(Error::<Test>::ChannelClosing))
+        );
+        // channel can't be topped up while closing
+        assert_eq!(
+            Micropayment::add_balance(Origin::signed(alice()), bob(), 50),
+            Err(DispatchErrorWithPostInfo::from(Error::<Test>::ChannelClosing))
+        );
+
+        let msg = Micropayment::construct_voucher_hash(&bob(), 0, 50);
+        assert_ok!(Micropayment::submit_voucher(
+            Origin::signed(bob()),
+            alice(),
+            bob(),
+            50,
+            sign_with_seed("Alice", &msg)
+        ));
+        // only a higher voucher is accepted
+        let msg = Micropayment::construct_voucher_hash(&bob(), 0, 40);
+        assert_eq!(
+            Micropayment::submit_voucher(
+                Origin::signed(bob()),
+                alice(),
+                bob(),
+                40,
+                sign_with_seed("Alice", &msg)
+            ),
+            Err(DispatchErrorWithPostInfo::from(
+                Error::<Test>::VoucherNotHigher
+            ))
+        );
+        // voucher must be signed by the client
+        let msg = Micropayment::construct_voucher_hash(&bob(), 0, 60);
+        assert_eq!(
+            Micropayment::submit_voucher(
+                Origin::signed(bob()),
+                alice(),
+                bob(),
+                60,
+                sign_with_seed("Bob", &msg)
+            ),
+            Err(DispatchErrorWithPostInfo::from(
+                Error::<Test>::InvalidSignature
+            ))
+        );
+        assert_eq!(
+            Micropayment::finalize_close(Origin::signed(charlie()), alice(), bob()),
+            Err(DispatchErrorWithPostInfo::from(
+                Error::<Test>::ChallengePeriodNotEnded
+            ))
+        );
+
+        run_to_block(11);
+        // conditional payments can only be claimed during the challenge period
+        let preimage = [7u8; 32];
+        let hashlock = sp_io::hashing::blake2_256(&preimage);
+        let msg = Micropayment::construct_htlc_hash(&bob(), 0, 20, &hashlock, 20);
+        assert_eq!(
+            Micropayment::claim_htlc(
+                Origin::signed(bob()),
+                alice(),
+                20,
+                20,
+                preimage,
+                sign_with_seed("Alice", &msg)
+            ),
+            Err(DispatchErrorWithPostInfo::from(
+                Error::<Test>::ChallengePeriodEnded
+            ))
+        );
+        assert_ok!(Micropayment::finalize_close(
+            Origin::signed(charlie()),
+            alice(),
+            bob()
+        ));
+        assert!(!Channel::<Test>::contains_key(alice(), bob()));
+        assert_eq!(Micropayment::closing_channel(alice(), bob()), None);
+        assert_eq!(Micropayment::nonce((alice(), bob())), 1);
+        assert_eq!(545, Balances::free_balance(bob()));
+        assert_eq!(450, Balances::free_balance(alice()));
+        assert_eq!(5, Treasury::pot());
+        assert_ok!(Micropayment::check_invariants());
+    });
+}
+
+#[test]
+fn settle_after_claims() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(Micropayment::open_channel(
+            Origin::signed(alice()),
+            bob(),
+            300,
+            3600
+        ));
+        let msg = Micropayment::construct_byte_array_and_hash(&bob(), 0, 1, 30);
+        assert_ok!(Micropayment::claim_payment(
+            Origin::signed(bob()),
+            alice(),
+            1,
+            30,
+            sign_with_seed("Alice", &msg)
+        ));
+        let msg = Micropayment::construct_voucher_hash(&bob(), 0, 80);
+        assert_ok!(Micropayment::submit_voucher(
+            Origin::signed(bob()),
+            alice(),
+            bob(),
+            80,
+            sign_with_seed("Alice", &msg)
+        ));
+        // the preimage revealed downstream is claimed while the channel is closing
+        let preimage = [7u8; 32];
+        let hashlock = sp_io::hashing::blake2_256(&preimage);
+        let msg = Micropayment::construct_htlc_hash(&bob(), 0, 20, &hashlock, 20);
+        assert_ok!(Micropayment::claim_htlc(
+            Origin::signed(bob()),
+            alice(),
+            20,
+            20,
+            preimage,
+            sign_with_seed("Alice", &msg)
+        ));
+        assert_eq!(545, Balances::free_balance(bob()));
+        assert_eq!(Micropayment::paid_amount(alice(), bob()), 30);
+        assert_eq!(Micropayment::htlc_paid_amount(alice(), bob()), 20);
+
+        // the voucher is cumulative over claims, only the 50 not claimed yet is paid;
+        // conditional payments come on top of it
+        run_to_block(11);
+        assert_ok!(Micropayment::finalize_close(
+            Origin::signed(charlie()),
+            alice(),
+            bob()
+        ));
+        assert_eq!(590, Balances::free_balance(bob()));
+        // the 200 left is refunded without the fee
+        assert_eq!(400, Balances::free_balance(alice()));
+        assert_eq!(10, Treasury::pot());
+        assert_eq!(Micropayment::paid_amount(alice(), bob()), 0);
+        assert_eq!(Micropayment::htlc_paid_amount(alice(), bob()), 0);
+        assert_ok!(Micropayment::check_invariants());
+    });
+}
+
+#[test]
+fn submit_voucher_starts_close() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(Micropayment::open_channel(
+            Origin::signed(alice()),
+            bob(),
+            300,
+            3600
+        ));
+        let msg = Micropayment::construct_voucher_hash(&bob(), 0, 50);
+        assert_eq!(
+            Micropayment::submit_voucher(
+                Origin::signed(charlie()),
+                alice(),
+                bob(),
+                50,
+                sign_with_seed("Alice", &msg)
+            ),
+            Err(DispatchErrorWithPostInfo::from(
+                Error::<Test>::NotChannelParty
+            ))
+        );
+        assert_ok!(Micropayment::submit_voucher(
+            Origin::signed(bob()),
+            alice(),
+            bob(),
+            50,
+            sign_with_seed("Alice", &msg)
+        ));
+        assert_eq!(
+            Micropayment::closing_channel(alice(), bob()),
+            Some(ChannelClose {
+                initiator: bob(),
+                amount: 50,
+                challenge_end: 10
+            })
+        );
+
+        // client can't close the expired channel before it is settled
+        run_to_block(24 * 720);
+        assert_ok!(Micropayment::close_expired_channels(Origin::signed(
+            alice()
+        )));
+        assert!(Channel::<Test>::contains_key(alice(), bob()));
+        let msg = Micropayment::construct_voucher_hash(&bob(), 0, 60);
+        assert_eq!(
+            Micropayment::submit_voucher(
+                Origin::signed(bob()),
+                alice(),
+                bob(),
+                60,
+                sign_with_seed("Alice", &msg)
+            ),
+            Err(DispatchErrorWithPostInfo::from(
+                Error::<Test>::ChallengePeriodEnded
+            ))
+        );
+        assert_ok!(Micropayment::finalize_close(
+            Origin::signed(alice()),
+            alice(),
+            bob()
+        ));
+        assert_eq!(545, Balances::free_balance(bob()));
+        assert_ok!(Micropayment::check_invariants());
+    });
+}
+
+#[test]
+fn cooperative_close() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(Micropayment::open_channel(
+            Origin::signed(alice()),
+            bob(),
+            300,
+            3600
+        ));
+        let msg = Micropayment::construct_close_hash(&alice(), &bob(), 0, 100);
+        assert_eq!(
+            Micropayment::cooperative_close(
+                Origin::signed(charlie()),
+                alice(),
+                bob(),
+                100,
+                sign_with_seed("Alice", &msg),
+                sign_with_seed("Charlie", &msg)
+            ),
+            Err(DispatchErrorWithPostInfo::from(
+                Error::<Test>::InvalidSignature
+            ))
+        );
+        let too_much = Micropayment::construct_close_hash(&alice(), &bob(), 0, 301);
+        assert_eq!(
+            Micropayment::cooperative_close(
+                Origin::signed(charlie()),
+                alice(),
+                bob(),
+                301,
+                sign_with_seed("Alice", &too_much),
+                sign_with_seed("Bob", &too_much)
+            ),
+            Err(DispatchErrorWithPostInfo::from(
+                Error::<Test>::NotEnoughBalance
+            ))
+        );
+        assert_ok!(Micropayment::cooperative_close(
+            Origin::signed(charlie()),
+            alice(),
+            bob(),
+            100,
+            sign_with_seed("Alice", &msg),
+            sign_with_seed("Bob", &msg)
+        ));
+        assert!(!Channel::<Test>::contains_key(alice(), bob()));
+        assert_eq!(590, Balances::free_balance(bob()));
+        assert_eq!(400, Balances::free_balance(alice()));
+        assert_ok!(Micropayment::check_invariants());
+    });
+}
+
+#[test]
+fn claim_payment_with_multi_signature() {
+    new_test_ext().execute_with(|| {
+        let ed_client = get_account_id_from_seed::<ed25519::Public>("Eve");
+        let ecdsa_client = get_account_id_from_seed::<ecdsa::Public>("Eve");
+        assert_ok!(Balances::set_balance(
+            Origin::root(),
+            ed_client.clone(),
+            500,
+            0
+        ));
+        assert_ok!(Balances::set_balance(
+            Origin::root(),
+            ecdsa_client.clone(),
+            500,
+            0
+        ));
+        assert_ok!(Micropayment::open_channel(
+            Origin::signed(ed_client.clone()),
+            bob(),
+            300,
+            3600
+        ));
+        assert_ok!(Micropayment::open_channel(
+            Origin::signed(ecdsa_client.clone()),
+            bob(),
+            300,
+            3600
+        ));
+
+        let msg = Micropayment::construct_byte_array_and_hash(&bob(), 0, 1, 30);
+        assert_ok!(Micropayment::claim_payment(
+            Origin::signed(bob()),
+            ed_client,
+            1,
+            30,
+            multi_sign_with_seed::<ed25519::Public>("Eve", &msg)
+        ));
+        assert_ok!(Micropayment::claim_payment(
+            Origin::signed(bob()),
+            ecdsa_client.clone(),
+            1,
+            30,
+            multi_sign_with_seed::<ecdsa::Public>("Eve", &msg)
+        ));
+        assert_eq!(554, Balances::free_balance(bob()));
+
+        // signed by another key of the same type
+        let msg = Micropayment::construct_byte_array_and_hash(&bob(), 0, 2, 30);
+        assert_eq!(
+            Micropayment::claim_payment(
+                Origin::signed(bob()),
+                ecdsa_client.clone(),
+                2,
+                30,
+                multi_sign_with_seed::<ecdsa::Public>("Ferdie", &msg)
+            ),
+            Err(DispatchErrorWithPostInfo::from(
+                Error::<Test>::InvalidSignature
+            ))
+        );
+        // raw sr25519 signature for an ecdsa account
+        assert_eq!(
+            Micropayment::claim_payment(
+                Origin::signed(bob()),
+                ecdsa_client.clone(),
+                2,
+                30,
+                sign_with_seed("Eve", &msg)
+            ),
+            Err(DispatchErrorWithPostInfo::from(
+                Error::<Test>::InvalidSignature
+            ))
+        );
+        // malformed signatures
+        assert_eq!(
+            Micropayment::claim_payment(
+                Origin::signed(bob()),
+                ecdsa_client.clone(),
+                2,
+                30,
+                vec![1u8; 10]
+            ),
+            Err(DispatchErrorWithPostInfo::from(
+                Error::<Test>::MalformedSignature
+            ))
+        );
+        let mut signature = multi_sign_with_seed::<ecdsa::Public>("Eve", &msg);
+        signature.push(0);
+        assert_eq!(
+            Micropayment::claim_payment(Origin::signed(bob()), ecdsa_client, 2, 30, signature),
+            Err(DispatchErrorWithPostInfo::from(
+                Error::<Test>::MalformedSignature
+            ))
+        );
+    });
+}
+
+#[test]
+fn asset_channel() {
+    new_test_ext().execute_with(|| {
+        let setting = AssetSetting {
+            burn: Percent::from_percent(10),
+            dpr_per_unit: 1,
+            decimals: 0,
+        };
+        assert_noop!(
+            Micropayment::set_asset_setting(Origin::signed(alice()), 1, Some(setting.clone())),
+            BadOrigin
+        );
+        assert_ok!(Assets::force_create(Origin::root(), 1, alice(), true, 1));
+        assert_ok!(Assets::mint(Origin::signed(alice()), 1, alice(), 1000));
+        assert_eq!(
+            Micropayment::open_asset_channel(Origin::signed(alice()), bob(), 1, 300, 3600),
+            Err(DispatchErrorWithPostInfo::from(
+                Error::<Test>::AssetNotApproved
+            ))
+        );
+
+        assert_ok!(Micropayment::set_asset_setting(
+            Origin::root(),
+            1,
+            Some(setting)
+        ));
+        assert_ok!(Micropayment::open_asset_channel(
+            Origin::signed(alice()),
+            bob(),
+            1,
+            300,
+            3600
+        ));
+        assert_eq!(Micropayment::channel_asset(alice(), bob()), Some(1));
+        assert_ok!(Micropayment::add_balance(Origin::signed(alice()), bob(), 100));
+        // the first client pays the asset's minimum balance keeping the escrow account alive
+        assert_eq!(Assets::balance(1, alice()), 599);
+        assert_eq!(Assets::balance(1, Micropayment::escrow_account()), 401);
+        // native balance is untouched
+        assert_eq!(Balances::free_balance(alice()), 500);
+        assert_eq!(Micropayment::total_micropayment_chanel_balance(alice()), None);
+        assert_eq!(
+            Micropayment::add_balance(Origin::signed(alice()), bob(), 700),
+            Err(DispatchErrorWithPostInfo::from(
+                Error::<Test>::NotEnoughBalance
+            ))
+        );
+
+        let msg = Micropayment::construct_byte_array_and_hash(&bob(), 0, 1, 30);
+        assert_ok!(Micropayment::claim_payment(
+            Origin::signed(bob()),
+            alice(),
+            1,
+            30,
+            sign_with_seed("Alice", &msg)
+        ));
+        assert_eq!(Assets::balance(1, bob()), 27);
+        assert_eq!(Balances::free_balance(bob()), 500);
+
+        assert_ok!(Micropayment::close_channel(Origin::signed(bob()), alice()));
+        // the refund is not burned
+        assert_eq!(Assets::balance(1, alice()), 969);
+        assert_eq!(Micropayment::channel_asset(alice(), bob()), None);
+        assert_eq!(Assets::balance(1, Micropayment::escrow_account()), 1);
+        assert_ok!(Micropayment::check_invariants());
+    });
+}
+
+#[test]
+fn first_channel_in_asset_not_sufficient() {
+    new_test_ext().execute_with(|| {
+        let setting = AssetSetting {
+            burn: Percent::from_percent(0),
+            dpr_per_unit: 1,
+            decimals: 0,
+        };
+        // the escrow account holds no DPR before the first native channel
+        let escrow = Micropayment::escrow_account();
+        Balances::make_free_balance_be(&escrow, 0);
+        assert_eq!(System::providers(&escrow), 0);
+        assert_ok!(Assets::force_create(Origin::root(), 1, alice(), false, 1));
+        assert_ok!(Assets::mint(Origin::signed(alice()), 1, alice(), 1000));
+        assert_ok!(Micropayment::set_asset_setting(
+            Origin::root(),
+            1,
+            Some(setting)
+        ));
+        assert_noop!(
+            Micropayment::open_asset_channel(Origin::signed(alice()), bob(), 1, 300, 3600),
+            Error::<Test>::AssetTransferFailed
+        );
+        assert_noop!(
+            Micropayment::open_asset_channel(Origin::signed(alice()), bob(), 1, 1000, 3600),
+            Error::<Test>::NotEnoughBalance
+        );
+
+        // the runtime upgrade gives the escrow account a provider
+        Micropayment::on_runtime_upgrade();
+        assert_eq!(System::providers(&escrow), 1);
+        assert_ok!(Micropayment::open_asset_channel(
+            Origin::signed(alice()),
+            bob(),
+            1,
+            300,
+            3600
+        ));
+        assert_eq!(Assets::balance(1, escrow), 301);
+        assert_ok!(Micropayment::check_invariants());
+    });
+}
+
+#[test]
+fn failed_transfers_leave_channel_untouched() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(Micropayment::open_channel(
+            Origin::signed(alice()),
+            bob(),
+            300,
+            3600
+        ));
+        // payments below the existential deposit can't reach a reaped account
+        Balances::make_free_balance_be(&bob(), 0);
+        let msg = Micropayment::construct_byte_array_and_hash(&bob(), 0, 1, 30);
+        assert_noop!(
+            Micropayment::claim_payment(
+                Origin::signed(bob()),
+                alice(),
+                1,
+                30,
+                sign_with_seed("Alice", &msg)
+            ),
+            pallet_balances::Error::<Test>::ExistentialDeposit
+        );
+        let preimage = [7u8; 32];
+        let hashlock = sp_io::hashing::blake2_256(&preimage);
+        let msg = Micropayment::construct_htlc_hash(&bob(), 0, 20, &hashlock, 20);
+        assert_noop!(
+            Micropayment::claim_htlc(
+                Origin::signed(bob()),
+                alice(),
+                20,
+                20,
+                preimage,
+                sign_with_seed("Alice", &msg)
+            ),
+            pallet_balances::Error::<Test>::ExistentialDeposit
+        );
+        Balances::make_free_balance_be(&bob(), 500);
+
+        Balances::make_free_balance_be(&alice(), 0);
+        let msg = Micropayment::construct_withdraw_hash(&alice(), &bob(), 0, 50);
+        assert_noop!(
+            Micropayment::withdraw_from_channel(
+                Origin::signed(alice()),
+                bob(),
+                50,
+                Some(sign_with_seed("Bob", &msg))
+            ),
+            pallet_balances::Error::<Test>::ExistentialDeposit
+        );
+
+        // the server is not paid if the refund to the client fails
+        let msg = Micropayment::construct_voucher_hash(&bob(), 0, 250);
+        assert_ok!(Micropayment::submit_voucher(
+            Origin::signed(bob()),
+            alice(),
+            bob(),
+            250,
+            sign_with_seed("Alice", &msg)
+        ));
+        run_to_block(11);
+        assert_noop!(
+            Micropayment::finalize_close(Origin::signed(bob()), alice(), bob()),
+            pallet_balances::Error::<Test>::ExistentialDeposit
+        );
+        assert_eq!(Micropayment::paid_amount(alice(), bob()), 0);
+
+        Balances::make_free_balance_be(&alice(), 500);
+        assert_ok!(Micropayment::finalize_close(
+            Origin::signed(bob()),
+            alice(),
+            bob()
+        ));
+        assert!(!Channel::<Test>::contains_key(alice(), bob()));
+        assert_ok!(Micropayment::check_invariants());
+    });
+}
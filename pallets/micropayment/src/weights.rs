@@ -51,6 +51,19 @@ pub trait WeightInfo {
     fn close_expired_channels() -> Weight;
     fn add_balance() -> Weight;
     fn claim_payment() -> Weight;
+    fn initiate_close() -> Weight;
+    fn submit_voucher() -> Weight;
+    fn finalize_close() -> Weight;
+    fn cooperative_close() -> Weight;
+    fn open_asset_channel() -> Weight;
+    fn set_asset_setting() -> Weight;
+    fn settle_expired_channel() -> Weight;
+    fn set_funding_source() -> Weight;
+    fn claim_htlc() -> Weight;
+    fn withdraw_from_channel() -> Weight;
+    fn extend_channel() -> Weight;
+    fn set_fee_tier() -> Weight;
+    fn set_fee_split() -> Weight;
 }
 
 /// Weights for pallet_micropayment using the Substrate node and recommended hardware.
@@ -58,28 +71,85 @@ pub struct SubstrateWeight<T>(PhantomData<T>);
 impl<T: frame_system::Config> WeightInfo for SubstrateWeight<T> {
     fn open_channel() -> Weight {
         (30_291_000 as Weight)
-            .saturating_add(T::DbWeight::get().reads(4 as Weight))
-            .saturating_add(T::DbWeight::get().writes(3 as Weight))
+            .saturating_add(T::DbWeight::get().reads(5 as Weight))
+            .saturating_add(T::DbWeight::get().writes(5 as Weight))
     }
     fn close_channel() -> Weight {
         (50_488_000 as Weight)
             .saturating_add(T::DbWeight::get().reads(4 as Weight))
-            .saturating_add(T::DbWeight::get().writes(5 as Weight))
+            .saturating_add(T::DbWeight::get().writes(7 as Weight))
     }
     fn close_expired_channels() -> Weight {
         (58_612_000 as Weight)
             .saturating_add(T::DbWeight::get().reads(5 as Weight))
-            .saturating_add(T::DbWeight::get().writes(5 as Weight))
+            .saturating_add(T::DbWeight::get().writes(7 as Weight))
     }
     fn add_balance() -> Weight {
         (29_816_000 as Weight)
-            .saturating_add(T::DbWeight::get().reads(3 as Weight))
-            .saturating_add(T::DbWeight::get().writes(3 as Weight))
+            .saturating_add(T::DbWeight::get().reads(4 as Weight))
+            .saturating_add(T::DbWeight::get().writes(4 as Weight))
     }
     fn claim_payment() -> Weight {
         (114_561_000 as Weight)
-            .saturating_add(T::DbWeight::get().reads(4 as Weight))
-            .saturating_add(T::DbWeight::get().writes(4 as Weight))
+            .saturating_add(T::DbWeight::get().reads(15 as Weight))
+            .saturating_add(T::DbWeight::get().writes(9 as Weight))
+    }
+    fn initiate_close() -> Weight {
+        (24_520_000 as Weight)
+            .saturating_add(T::DbWeight::get().reads(3 as Weight))
+            .saturating_add(T::DbWeight::get().writes(1 as Weight))
+    }
+    fn submit_voucher() -> Weight {
+        (98_274_000 as Weight)
+            .saturating_add(T::DbWeight::get().reads(3 as Weight))
+            .saturating_add(T::DbWeight::get().writes(1 as Weight))
+    }
+    fn finalize_close() -> Weight {
+        (62_931_000 as Weight)
+            .saturating_add(T::DbWeight::get().reads(17 as Weight))
+            .saturating_add(T::DbWeight::get().writes(14 as Weight))
+    }
+    fn cooperative_close() -> Weight {
+        (171_406_000 as Weight)
+            .saturating_add(T::DbWeight::get().reads(17 as Weight))
+            .saturating_add(T::DbWeight::get().writes(14 as Weight))
+    }
+    fn open_asset_channel() -> Weight {
+        (41_872_000 as Weight)
+            .saturating_add(T::DbWeight::get().reads(8 as Weight))
+            .saturating_add(T::DbWeight::get().writes(7 as Weight))
+    }
+    fn set_asset_setting() -> Weight {
+        (12_045_000 as Weight).saturating_add(T::DbWeight::get().writes(1 as Weight))
+    }
+    fn settle_expired_channel() -> Weight {
+        (61_337_000 as Weight)
+            .saturating_add(T::DbWeight::get().reads(6 as Weight))
+            .saturating_add(T::DbWeight::get().writes(8 as Weight))
+    }
+    fn set_funding_source() -> Weight {
+        (11_874_000 as Weight).saturating_add(T::DbWeight::get().writes(1 as Weight))
+    }
+    fn claim_htlc() -> Weight {
+        (117_043_000 as Weight)
+            .saturating_add(T::DbWeight::get().reads(17 as Weight))
+            .saturating_add(T::DbWeight::get().writes(10 as Weight))
+    }
+    fn withdraw_from_channel() -> Weight {
+        (104_712_000 as Weight)
+            .saturating_add(T::DbWeight::get().reads(10 as Weight))
+            .saturating_add(T::DbWeight::get().writes(5 as Weight))
+    }
+    fn extend_channel() -> Weight {
+        (27_361_000 as Weight)
+            .saturating_add(T::DbWeight::get().reads(2 as Weight))
+            .saturating_add(T::DbWeight::get().writes(3 as Weight))
+    }
+    fn set_fee_tier() -> Weight {
+        (11_602_000 as Weight).saturating_add(T::DbWeight::get().writes(1 as Weight))
+    }
+    fn set_fee_split() -> Weight {
+        (11_218_000 as Weight).saturating_add(T::DbWeight::get().writes(1 as Weight))
     }
 }
 
@@ -87,27 +157,84 @@ impl<T: frame_system::Config> WeightInfo for SubstrateWeight<T> {
 impl WeightInfo for () {
     fn open_channel() -> Weight {
         (30_291_000 as Weight)
-            .saturating_add(RocksDbWeight::get().reads(4 as Weight))
-            .saturating_add(RocksDbWeight::get().writes(3 as Weight))
+            .saturating_add(RocksDbWeight::get().reads(5 as Weight))
+            .saturating_add(RocksDbWeight::get().writes(5 as Weight))
     }
     fn close_channel() -> Weight {
         (50_488_000 as Weight)
             .saturating_add(RocksDbWeight::get().reads(4 as Weight))
-            .saturating_add(RocksDbWeight::get().writes(5 as Weight))
+            .saturating_add(RocksDbWeight::get().writes(7 as Weight))
     }
     fn close_expired_channels() -> Weight {
         (58_612_000 as Weight)
             .saturating_add(RocksDbWeight::get().reads(5 as Weight))
-            .saturating_add(RocksDbWeight::get().writes(5 as Weight))
+            .saturating_add(RocksDbWeight::get().writes(7 as Weight))
     }
     fn add_balance() -> Weight {
         (29_816_000 as Weight)
-            .saturating_add(RocksDbWeight::get().reads(3 as Weight))
-            .saturating_add(RocksDbWeight::get().writes(3 as Weight))
+            .saturating_add(RocksDbWeight::get().reads(4 as Weight))
+            .saturating_add(RocksDbWeight::get().writes(4 as Weight))
     }
     fn claim_payment() -> Weight {
         (114_561_000 as Weight)
-            .saturating_add(RocksDbWeight::get().reads(4 as Weight))
-            .saturating_add(RocksDbWeight::get().writes(4 as Weight))
+            .saturating_add(RocksDbWeight::get().reads(15 as Weight))
+            .saturating_add(RocksDbWeight::get().writes(9 as Weight))
+    }
+    fn initiate_close() -> Weight {
+        (24_520_000 as Weight)
+            .saturating_add(RocksDbWeight::get().reads(3 as Weight))
+            .saturating_add(RocksDbWeight::get().writes(1 as Weight))
+    }
+    fn submit_voucher() -> Weight {
+        (98_274_000 as Weight)
+            .saturating_add(RocksDbWeight::get().reads(3 as Weight))
+            .saturating_add(RocksDbWeight::get().writes(1 as Weight))
+    }
+    fn finalize_close() -> Weight {
+        (62_931_000 as Weight)
+            .saturating_add(RocksDbWeight::get().reads(17 as Weight))
+            .saturating_add(RocksDbWeight::get().writes(14 as Weight))
+    }
+    fn cooperative_close() -> Weight {
+        (171_406_000 as Weight)
+            .saturating_add(RocksDbWeight::get().reads(17 as Weight))
+            .saturating_add(RocksDbWeight::get().writes(14 as Weight))
+    }
+    fn open_asset_channel() -> Weight {
+        (41_872_000 as Weight)
+            .saturating_add(RocksDbWeight::get().reads(8 as Weight))
+            .saturating_add(RocksDbWeight::get().writes(7 as Weight))
+    }
+    fn set_asset_setting() -> Weight {
+        (12_045_000 as Weight).saturating_add(RocksDbWeight::get().writes(1 as Weight))
+    }
+    fn settle_expired_channel() -> Weight {
+        (61_337_000 as Weight)
+            .saturating_add(RocksDbWeight::get().reads(6 as Weight))
+            .saturating_add(RocksDbWeight::get().writes(8 as Weight))
+    }
+    fn set_funding_source() -> Weight {
+        (11_874_000 as Weight).saturating_add(RocksDbWeight::get().writes(1 as Weight))
+    }
+    fn claim_htlc() -> Weight {
+        (117_043_000 as Weight)
+            .saturating_add(RocksDbWeight::get().reads(17 as Weight))
+            .saturating_add(RocksDbWeight::get().writes(10 as Weight))
+    }
+    fn withdraw_from_channel() -> Weight {
+        (104_712_000 as Weight)
+            .saturating_add(RocksDbWeight::get().reads(10 as Weight))
+            .saturating_add(RocksDbWeight::get().writes(5 as Weight))
+    }
+    fn extend_channel() -> Weight {
+        (27_361_000 as Weight)
+            .saturating_add(RocksDbWeight::get().reads(2 as Weight))
+            .saturating_add(RocksDbWeight::get().writes(3 as Weight))
+    }
+    fn set_fee_tier() -> Weight {
+        (11_602_000 as Weight).saturating_add(RocksDbWeight::get().writes(1 as Weight))
+    }
+    fn set_fee_split() -> Weight {
+        (11_218_000 as Weight).saturating_add(RocksDbWeight::get().writes(1 as Weight))
     }
 }
@@ -22,10 +22,11 @@
 use super::*;
 
 use frame_benchmarking::{account, benchmarks, Zero};
-use frame_support::traits::{Currency, LockableCurrency, ReservableCurrency, WithdrawReasons};
+use frame_support::traits::{
+    Currency, Hooks, LockableCurrency, NamedReservableCurrency, WithdrawReasons,
+};
 use frame_system::RawOrigin;
-use sp_runtime::traits::Saturating;
-use sp_runtime::traits::StaticLookup;
+use sp_runtime::traits::{Bounded, Saturating, StaticLookup};
 
 use crate::Pallet as Op;
 
@@ -35,6 +36,43 @@ const ED_MULTIPLIER: u32 = 10;
 
 const FORCE_LOCK_ID: [u8; 8] = *b"abcdefgh";
 
+fn add_member<T: Config>(name: &'static str) -> T::AccountId {
+    let member: T::AccountId = account(name, 0, SEED);
+    let _ = T::Currency::make_free_balance_be(&member, T::Currency::minimum_balance());
+    let _ = Op::<T>::add_reserve_member(
+        RawOrigin::Root.into(),
+        member.clone(),
+        Some(Bounded::max_value()),
+    );
+    member
+}
+
+/// Reserve from a new account by member, released at release_at.
+fn add_reserve<T: Config>(member: &T::AccountId, i: u32, release_at: T::BlockNumber) -> ReserveId {
+    let existential_deposit = T::Currency::minimum_balance();
+    let who: T::AccountId = account("reserved", i, SEED);
+    let _ = T::Currency::make_free_balance_be(
+        &who,
+        existential_deposit.saturating_mul(ED_MULTIPLIER.into()),
+    );
+    let _ = T::Currency::reserve_named(&OPERATION_RESERVE_ID, &who, existential_deposit);
+    let id = Op::<T>::next_reserve_id();
+    NextReserveId::<T>::put(id + 1);
+    Reserves::<T>::insert(
+        id,
+        Reserve {
+            member: member.clone(),
+            who,
+            amount: existential_deposit,
+            reason: 0,
+            release_at,
+        },
+    );
+    MemberReserves::<T>::mutate(member, |ids| ids.try_push(id).unwrap());
+    ReleaseSchedule::<T>::mutate(release_at, |ids| ids.try_push(id).unwrap());
+    id
+}
+
 benchmarks! {
     where_clause { where T: Config, T: pallet_balances::Config }
     force_reserve_by_member {
@@ -47,7 +85,8 @@ benchmarks! {
         // Give some multiple of the existential deposit + creation fee + transfer fee
         let balance = existential_deposit.saturating_mul(ED_MULTIPLIER.into());
         let _ = T::Currency::make_free_balance_be(&source, balance);
-    }: force_reserve_by_member(RawOrigin::Signed(unlocker), source_lookup, balance)
+        let release_at = frame_system::Pallet::<T>::block_number() + 1u32.into();
+    }: force_reserve_by_member(RawOrigin::Signed(unlocker), source_lookup, balance, 1, release_at)
     verify {
         assert_eq!(pallet_balances::Account::<T>::get(&source).free, T::Balance::zero());
     }
@@ -78,5 +117,84 @@ benchmarks! {
         assert_eq!(Op::<T>::lock_member_whitelist().len(), 2);
     }
 
+    unreserve_by_member {
+        let member = add_member::<T>("member");
+        let release_at = frame_system::Pallet::<T>::block_number() + 1u32.into();
+        let id = add_reserve::<T>(&member, 0, release_at);
+    }: _(RawOrigin::Signed(member), id)
+    verify {
+        assert!(Op::<T>::reserves(id).is_none());
+    }
+
+    add_reserve_member {
+        let member: T::AccountId = account("member", 0, SEED);
+    }: _(RawOrigin::Root, member.clone(), Some(Bounded::max_value()))
+    verify {
+        assert!(Op::<T>::lock_member_whitelist().contains(&member));
+    }
+
+    remove_reserve_member {
+        let member = add_member::<T>("member");
+    }: _(RawOrigin::Root, member.clone())
+    verify {
+        assert!(!Op::<T>::lock_member_whitelist().contains(&member));
+    }
+
+    revoke_reserve_member {
+        let r in 0 .. T::MaxReservesPerMember::get();
+        let member = add_member::<T>("member");
+        let now = frame_system::Pallet::<T>::block_number();
+        for i in 0..r {
+            add_reserve::<T>(&member, i, now + (i + 1).into());
+        }
+    }: _(RawOrigin::Root, member.clone())
+    verify {
+        assert!(Op::<T>::member_reserves(&member).is_empty());
+    }
+
+    release_reserves {
+        let n in 1 .. T::MaxReleasesPerBlock::get();
+        let release_at = frame_system::Pallet::<T>::block_number() + 1u32.into();
+        for i in 0..n {
+            let member: T::AccountId = account("member", i, SEED);
+            add_reserve::<T>(&member, i, release_at);
+        }
+    }: {
+        Op::<T>::on_initialize(release_at);
+    }
+    verify {
+        assert!(Op::<T>::release_schedule(release_at).is_empty());
+    }
+
+    freeze_account {
+        let member = add_member::<T>("member");
+        let who: T::AccountId = account("frozen", 0, SEED);
+        let who_lookup: <T::Lookup as StaticLookup>::Source = T::Lookup::unlookup(who.clone());
+        let expiry = frame_system::Pallet::<T>::block_number() + 1u32.into();
+    }: _(RawOrigin::Signed(member), who_lookup, 1, expiry)
+    verify {
+        assert!(Op::<T>::is_frozen(&who));
+    }
+
+    unfreeze_account {
+        let member = add_member::<T>("member");
+        let who: T::AccountId = account("frozen", 0, SEED);
+        let who_lookup: <T::Lookup as StaticLookup>::Source = T::Lookup::unlookup(who.clone());
+        let expiry = frame_system::Pallet::<T>::block_number() + 1u32.into();
+        let _ = Op::<T>::freeze_account(RawOrigin::Signed(member.clone()).into(), who_lookup.clone(), 1, expiry);
+    }: _(RawOrigin::Signed(member), who_lookup)
+    verify {
+        assert!(!Op::<T>::is_frozen(&who));
+    }
+
+    force_unreserve {
+        let member = add_member::<T>("member");
+        let release_at = frame_system::Pallet::<T>::block_number() + 1u32.into();
+        let id = add_reserve::<T>(&member, 0, release_at);
+    }: _(RawOrigin::Root, id)
+    verify {
+        assert!(Op::<T>::reserves(id).is_none());
+    }
+
     impl_benchmark_test_suite!(Op, crate::tests::new_test_ext(), crate::tests::Test);
 }
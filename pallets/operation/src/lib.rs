@@ -23,33 +23,86 @@ mod tests;
 pub mod benchmarking;
 
 pub mod weights;
+use codec::{Decode, Encode};
 use scale_info::TypeInfo;
-use sp_std::prelude::*;
+use sp_runtime::{
+    traits::{DispatchInfoOf, SignedExtension},
+    transaction_validity::{
+        InvalidTransaction, TransactionValidity, TransactionValidityError, ValidTransaction,
+    },
+};
+use sp_std::{marker::PhantomData, prelude::*};
 pub use weights::WeightInfo;
 
+/// Custom error code of transactions sent by a frozen account
+pub const FROZEN_ACCOUNT: u8 = 90;
+
+/// Identifier of the balance reserved by operation members
+pub const OPERATION_RESERVE_ID: [u8; 8] = *b"dp/opres";
+
+/// Identifier of the lock holding the balance of a frozen account
+pub const FREEZE_LOCK_ID: frame_support::traits::LockIdentifier = *b"dp/frzen";
+
 #[frame_support::pallet]
 pub mod pallet {
     use super::*;
     use codec::{Decode, Encode, MaxEncodedLen};
     use frame_support::traits::{
-        Currency, Get, LockIdentifier, LockableCurrency, ReservableCurrency,
+        Currency, Get, LockIdentifier, LockableCurrency, NamedReservableCurrency, WithdrawReasons,
+    };
+    use frame_support::{
+        dispatch::DispatchResultWithPostInfo, ensure, pallet_prelude::*, BoundedVec, WeakBoundedVec,
     };
-    use frame_support::WeakBoundedVec;
-    use frame_support::{dispatch::DispatchResultWithPostInfo, ensure, pallet_prelude::*};
     use frame_system::pallet_prelude::*;
     use frame_system::{self, ensure_signed};
-    use sp_runtime::{traits::StaticLookup, RuntimeDebug};
+    use sp_runtime::{
+        traits::{Bounded, One, Saturating, StaticLookup, Zero},
+        RuntimeDebug,
+    };
 
     type BalanceOf<T> = <<T as pallet::Config>::Currency as Currency<
         <T as frame_system::Config>::AccountId,
     >>::Balance;
+    pub type ReserveId = u64;
+    /// Reason code of a reserve, defined by the operation team
+    pub type ReasonCode = u32;
+    pub type ReserveOf<T> = Reserve<
+        <T as frame_system::Config>::AccountId,
+        BalanceOf<T>,
+        <T as frame_system::Config>::BlockNumber,
+    >;
+    pub type FreezeOf<T> =
+        Freeze<<T as frame_system::Config>::AccountId, <T as frame_system::Config>::BlockNumber>;
+
     /// Configure the pallet by specifying the parameters and types on which it depends.
     #[pallet::config]
     pub trait Config: frame_system::Config {
         type Event: From<Event<Self>> + IsType<<Self as frame_system::Config>::Event>;
-        type Currency: LockableCurrency<Self::AccountId> + ReservableCurrency<Self::AccountId>;
+        type Currency: LockableCurrency<Self::AccountId>
+            + NamedReservableCurrency<Self::AccountId, ReserveIdentifier = [u8; 8]>;
         type MaxMember: Get<u32>;
         type WeightInfo: WeightInfo;
+        /// Value a member may reserve in one quota period unless set otherwise
+        #[pallet::constant]
+        type DefaultMemberQuota: Get<BalanceOf<Self>>;
+        /// Length of the quota period in blocks, e.g. one era
+        #[pallet::constant]
+        type QuotaPeriod: Get<Self::BlockNumber>;
+        /// Maximum number of blocks a reserve is held before it's released
+        #[pallet::constant]
+        type MaxReserveDuration: Get<Self::BlockNumber>;
+        /// Maximum number of outstanding reserves of a member
+        #[pallet::constant]
+        type MaxReservesPerMember: Get<u32>;
+        /// Maximum number of reserves released in one block
+        #[pallet::constant]
+        type MaxReleasesPerBlock: Get<u32>;
+        /// Maximum number of blocks a member may freeze an account for
+        #[pallet::constant]
+        type MaxFreezeDuration: Get<Self::BlockNumber>;
+        /// Maximum number of freezes expiring in one block
+        #[pallet::constant]
+        type MaxFreezeExpiriesPerBlock: Get<u32>;
     }
 
     #[pallet::pallet]
@@ -63,6 +116,33 @@ pub mod pallet {
         Locked(T::AccountId, BalanceOf<T>),
         UnLocked(T::AccountId),
         Unreserve(T::AccountId, BalanceOf<T>),
+        /// reserve id, member, who, amount, reason, release block
+        Reserved(
+            ReserveId,
+            T::AccountId,
+            T::AccountId,
+            BalanceOf<T>,
+            ReasonCode,
+            T::BlockNumber,
+        ),
+        /// reserve id, member, who, amount
+        ReserveReleased(ReserveId, T::AccountId, T::AccountId, BalanceOf<T>),
+        /// member, quota
+        MemberAdded(T::AccountId, BalanceOf<T>),
+        MemberRemoved(T::AccountId),
+        /// member, number of reserves released
+        MemberRevoked(T::AccountId, u32),
+        /// who, member (None if by root), reason, expiry block
+        AccountFrozen(
+            T::AccountId,
+            Option<T::AccountId>,
+            ReasonCode,
+            Option<T::BlockNumber>,
+        ),
+        /// who, member (None if by root)
+        AccountUnfrozen(T::AccountId, Option<T::AccountId>),
+        /// who, whose freeze reached its expiry
+        FreezeExpired(T::AccountId),
     }
 
     // Errors inform users that something went wrong.
@@ -70,6 +150,32 @@ pub mod pallet {
     pub enum Error<T> {
         /// not in locker members
         NotLockMember,
+        /// number of members reaches MaxMember
+        TooManyMembers,
+        /// already a member
+        AlreadyMember,
+        /// reserve would exceed the member's quota of the period
+        QuotaExceeded,
+        /// release block is not in (now, now + MaxReserveDuration]
+        InvalidReleaseBlock,
+        /// member has MaxReservesPerMember outstanding reserves
+        TooManyReserves,
+        /// MaxReleasesPerBlock reserves are released at the release block
+        ReleaseScheduleFull,
+        /// reserve does not exist
+        ReserveNotExist,
+        /// reserve was not made by the sender
+        NotReserveOwner,
+        /// account is already frozen
+        AlreadyFrozen,
+        /// account is not frozen
+        NotFrozen,
+        /// expiry block is not in (now, now + MaxFreezeDuration]
+        InvalidFreezeExpiry,
+        /// freeze was not made by the sender
+        NotFreezeOwner,
+        /// MaxFreezeExpiriesPerBlock freezes expire at the expiry block
+        FreezeScheduleFull,
     }
 
     #[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, RuntimeDebug, MaxEncodedLen, TypeInfo)]
@@ -77,6 +183,25 @@ pub mod pallet {
         V1_0_0,
     }
 
+    /// A reserve made by an operation member
+    #[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo)]
+    pub struct Reserve<AccountId, Balance, BlockNumber> {
+        pub member: AccountId,
+        pub who: AccountId,
+        pub amount: Balance,
+        pub reason: ReasonCode,
+        pub release_at: BlockNumber,
+    }
+
+    /// A freeze of an account, made by a member or by root if member is None
+    #[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo)]
+    pub struct Freeze<AccountId, BlockNumber> {
+        pub member: Option<AccountId>,
+        pub reason: ReasonCode,
+        pub frozen_at: BlockNumber,
+        pub expiry: Option<BlockNumber>,
+    }
+
     #[pallet::storage]
     #[pallet::getter(fn lock_member_whitelist)]
     pub(super) type LockMemberWhiteList<T: Config> =
@@ -85,8 +210,92 @@ pub mod pallet {
     #[pallet::storage]
     pub(super) type StorageVersion<T: Config> = StorageValue<_, Releases>;
 
+    // quota of a member if not DefaultMemberQuota
+    #[pallet::storage]
+    #[pallet::getter(fn member_quota)]
+    pub(super) type MemberQuota<T: Config> =
+        StorageMap<_, Blake2_128Concat, T::AccountId, BalanceOf<T>, OptionQuery>;
+
+    // quota period index and value reserved by a member in it
+    #[pallet::storage]
+    #[pallet::getter(fn member_usage)]
+    pub(super) type MemberUsage<T: Config> =
+        StorageMap<_, Blake2_128Concat, T::AccountId, (T::BlockNumber, BalanceOf<T>), ValueQuery>;
+
+    #[pallet::storage]
+    #[pallet::getter(fn next_reserve_id)]
+    pub(super) type NextReserveId<T: Config> = StorageValue<_, ReserveId, ValueQuery>;
+
+    #[pallet::storage]
+    #[pallet::getter(fn reserves)]
+    pub(super) type Reserves<T: Config> =
+        StorageMap<_, Twox64Concat, ReserveId, ReserveOf<T>, OptionQuery>;
+
+    // outstanding reserves made by a member
+    #[pallet::storage]
+    #[pallet::getter(fn member_reserves)]
+    pub(super) type MemberReserves<T: Config> = StorageMap<
+        _,
+        Blake2_128Concat,
+        T::AccountId,
+        BoundedVec<ReserveId, T::MaxReservesPerMember>,
+        ValueQuery,
+    >;
+
+    // reserves released at a block
+    #[pallet::storage]
+    #[pallet::getter(fn release_schedule)]
+    pub(super) type ReleaseSchedule<T: Config> = StorageMap<
+        _,
+        Twox64Concat,
+        T::BlockNumber,
+        BoundedVec<ReserveId, T::MaxReleasesPerBlock>,
+        ValueQuery,
+    >;
+
+    // frozen accounts, whose transactions are rejected until expiry
+    #[pallet::storage]
+    #[pallet::getter(fn blacklist)]
+    pub(super) type Blacklist<T: Config> =
+        StorageMap<_, Blake2_128Concat, T::AccountId, FreezeOf<T>, OptionQuery>;
+
+    // freezes expiring at a block
+    #[pallet::storage]
+    pub(super) type FreezeExpiry<T: Config> = StorageDoubleMap<
+        _,
+        Twox64Concat,
+        T::BlockNumber,
+        Blake2_128Concat,
+        T::AccountId,
+        (),
+        OptionQuery,
+    >;
+
+    // number of freezes expiring at a block
+    #[pallet::storage]
+    #[pallet::getter(fn freeze_expiry_count)]
+    pub(super) type FreezeExpiryCount<T: Config> =
+        StorageMap<_, Twox64Concat, T::BlockNumber, u32, ValueQuery>;
+
+    // first block whose freezes may not all have expired yet
+    #[pallet::storage]
+    #[pallet::getter(fn freeze_expiry_cursor)]
+    pub(super) type FreezeExpiryCursor<T: Config> = StorageValue<_, T::BlockNumber, OptionQuery>;
+
     #[pallet::hooks]
     impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {
+        fn on_initialize(now: T::BlockNumber) -> Weight {
+            let weight = Self::expire_freezes(now);
+            let ids = ReleaseSchedule::<T>::take(now);
+            if ids.is_empty() {
+                return weight.saturating_add(T::DbWeight::get().reads(1));
+            }
+            for id in ids.iter() {
+                Self::release_reserve(*id);
+            }
+            weight.saturating_add(T::WeightInfo::release_reserves(ids.len() as u32))
+        }
+
         fn on_runtime_upgrade() -> Weight {
             if StorageVersion::<T>::get().is_none() {
                 frame_support::storage::migration::move_storage_from_pallet(
@@ -116,29 +325,169 @@ pub mod pallet {
             Ok(().into())
         }
 
-        #[pallet::weight(T::WeightInfo::set_reserve_members())]
+        /// Replace the members, the quotas and usage of the members left out are removed
+        #[pallet::weight(T::WeightInfo::set_reserve_members().saturating_add(
+            T::DbWeight::get().reads_writes(1, 2 * T::MaxMember::get() as Weight)
+        ))]
         pub fn set_reserve_members(
             origin: OriginFor<T>,
             whitelist: Vec<T::AccountId>,
         ) -> DispatchResultWithPostInfo {
             ensure_root(origin)?;
-            let whitelist = WeakBoundedVec::<_, T::MaxMember>::force_from(
-                whitelist,
-                Some("Balances Update lock function whitelist"),
-            );
-
-            if whitelist.len() as u32 > T::MaxMember::get() {
-                log::warn!("Whitelist too large.");
+            let whitelist = WeakBoundedVec::<_, T::MaxMember>::try_from(whitelist)
+                .map_err(|_| Error::<T>::TooManyMembers)?;
+            for member in <LockMemberWhiteList<T>>::get().iter() {
+                if !whitelist.contains(member) {
+                    MemberQuota::<T>::remove(member);
+                    MemberUsage::<T>::remove(member);
+                }
             }
             <LockMemberWhiteList<T>>::put(whitelist);
             Ok(().into())
         }
 
+        /// Member reserves value from who until release_at, the reserve is released
+        /// automatically at that block
         #[pallet::weight(T::WeightInfo::force_reserve_by_member())]
         pub fn force_reserve_by_member(
             origin: OriginFor<T>,
             who: <T::Lookup as StaticLookup>::Source,
             #[pallet::compact] value: BalanceOf<T>,
+            reason: ReasonCode,
+            release_at: T::BlockNumber,
+        ) -> DispatchResultWithPostInfo {
+            let sender = ensure_signed(origin)?;
+            ensure!(
+                <LockMemberWhiteList<T>>::get().contains(&sender),
+                Error::<T>::NotLockMember
+            );
+            let who = T::Lookup::lookup(who)?;
+            let now = <frame_system::Pallet<T>>::block_number();
+            ensure!(
+                release_at > now && release_at <= now.saturating_add(T::MaxReserveDuration::get()),
+                Error::<T>::InvalidReleaseBlock
+            );
+
+            let period = now / T::QuotaPeriod::get().max(1u32.into());
+            let (usage_period, used) = MemberUsage::<T>::get(&sender);
+            let used = if usage_period == period {
+                used
+            } else {
+                Zero::zero()
+            };
+            let used = used.saturating_add(value);
+            let quota = Self::member_quota(&sender).unwrap_or_else(T::DefaultMemberQuota::get);
+            ensure!(used <= quota, Error::<T>::QuotaExceeded);
+
+            let id = Self::next_reserve_id();
+            let mut member_reserves = MemberReserves::<T>::get(&sender);
+            member_reserves
+                .try_push(id)
+                .map_err(|_| Error::<T>::TooManyReserves)?;
+            let mut schedule = ReleaseSchedule::<T>::get(release_at);
+            schedule
+                .try_push(id)
+                .map_err(|_| Error::<T>::ReleaseScheduleFull)?;
+            T::Currency::reserve_named(&OPERATION_RESERVE_ID, &who, value)?;
+
+            MemberReserves::<T>::insert(&sender, member_reserves);
+            ReleaseSchedule::<T>::insert(release_at, schedule);
+            MemberUsage::<T>::insert(&sender, (period, used));
+            NextReserveId::<T>::put(id + 1);
+            Reserves::<T>::insert(
+                id,
+                Reserve {
+                    member: sender.clone(),
+                    who: who.clone(),
+                    amount: value,
+                    reason,
+                    release_at,
+                },
+            );
+            Self::deposit_event(Event::Reserved(id, sender, who, value, reason, release_at));
+            Ok(().into())
+        }
+
+        /// Member releases a reserve it made before the release block
+        #[pallet::weight(T::WeightInfo::unreserve_by_member())]
+        pub fn unreserve_by_member(
+            origin: OriginFor<T>,
+            id: ReserveId,
+        ) -> DispatchResultWithPostInfo {
+            let sender = ensure_signed(origin)?;
+            let reserve = Self::reserves(id).ok_or(Error::<T>::ReserveNotExist)?;
+            ensure!(reserve.member == sender, Error::<T>::NotReserveOwner);
+            ReleaseSchedule::<T>::mutate(reserve.release_at, |ids| ids.retain(|i| *i != id));
+            Self::release_reserve(id);
+            Ok(().into())
+        }
+
+        /// Add a member who can reserve up to quota, or DefaultMemberQuota, per quota period
+        #[pallet::weight(T::WeightInfo::add_reserve_member())]
+        pub fn add_reserve_member(
+            origin: OriginFor<T>,
+            member: T::AccountId,
+            quota: Option<BalanceOf<T>>,
+        ) -> DispatchResultWithPostInfo {
+            ensure_root(origin)?;
+            let mut whitelist = <LockMemberWhiteList<T>>::get();
+            ensure!(!whitelist.contains(&member), Error::<T>::AlreadyMember);
+            whitelist
+                .try_push(member.clone())
+                .map_err(|_| Error::<T>::TooManyMembers)?;
+            <LockMemberWhiteList<T>>::put(whitelist);
+            match quota {
+                Some(quota) => MemberQuota::<T>::insert(&member, quota),
+                None => MemberQuota::<T>::remove(&member),
+            }
+            let quota = quota.unwrap_or_else(T::DefaultMemberQuota::get);
+            Self::deposit_event(Event::MemberAdded(member, quota));
+            Ok(().into())
+        }
+
+        /// Remove a member, its outstanding reserves are released at their release blocks
+        #[pallet::weight(T::WeightInfo::remove_reserve_member())]
+        pub fn remove_reserve_member(
+            origin: OriginFor<T>,
+            member: T::AccountId,
+        ) -> DispatchResultWithPostInfo {
+            ensure_root(origin)?;
+            Self::remove_member(&member)?;
+            Self::deposit_event(Event::MemberRemoved(member));
+            Ok(().into())
+        }
+
+        /// Remove a member and release all its outstanding reserves now;
+        /// the reserves of a member removed already are released as well
+        #[pallet::weight(T::WeightInfo::revoke_reserve_member(T::MaxReservesPerMember::get()))]
+        pub fn revoke_reserve_member(
+            origin: OriginFor<T>,
+            member: T::AccountId,
+        ) -> DispatchResultWithPostInfo {
+            ensure_root(origin)?;
+            let ids = MemberReserves::<T>::get(&member);
+            if Self::remove_member(&member).is_err() {
+                ensure!(!ids.is_empty(), Error::<T>::NotLockMember);
+            }
+            for id in ids.iter() {
+                if let Some(reserve) = Self::reserves(id) {
+                    ReleaseSchedule::<T>::mutate(reserve.release_at, |schedule| {
+                        schedule.retain(|i| i != id)
+                    });
+                }
+                Self::release_reserve(*id);
+            }
+            Self::deposit_event(Event::MemberRevoked(member, ids.len() as u32));
+            Ok(Some(T::WeightInfo::revoke_reserve_member(ids.len() as u32)).into())
+        }
+
+        /// Member freezes who until expiry, which must be within MaxFreezeDuration
+        #[pallet::weight(T::WeightInfo::freeze_account())]
+        pub fn freeze_account(
+            origin: OriginFor<T>,
+            who: <T::Lookup as StaticLookup>::Source,
+            reason: ReasonCode,
+            expiry: T::BlockNumber,
         ) -> DispatchResultWithPostInfo {
             let sender = ensure_signed(origin)?;
             ensure!(
@@ -146,9 +495,273 @@ pub mod pallet {
                 Error::<T>::NotLockMember
             );
             let who = T::Lookup::lookup(who)?;
-            <T::Currency as ReservableCurrency<_>>::reserve(&who, value)?;
-            Self::deposit_event(Event::Locked(sender, value));
+            ensure!(!Self::is_frozen(&who), Error::<T>::AlreadyFrozen);
+            let now = <frame_system::Pallet<T>>::block_number();
+            ensure!(
+                expiry > now && expiry <= now.saturating_add(T::MaxFreezeDuration::get()),
+                Error::<T>::InvalidFreezeExpiry
+            );
+            Self::freeze(who, Some(sender), reason, Some(expiry))?;
+            Ok(().into())
+        }
+
+        /// Root freezes who until expiry, or until unfrozen if expiry is None.
+        /// A freeze made by a member is overridden
+        #[pallet::weight(T::WeightInfo::freeze_account())]
+        pub fn force_freeze_account(
+            origin: OriginFor<T>,
+            who: <T::Lookup as StaticLookup>::Source,
+            reason: ReasonCode,
+            expiry: Option<T::BlockNumber>,
+        ) -> DispatchResultWithPostInfo {
+            ensure_root(origin)?;
+            let who = T::Lookup::lookup(who)?;
+            let now = <frame_system::Pallet<T>>::block_number();
+            ensure!(
+                expiry.map_or(true, |e| e > now),
+                Error::<T>::InvalidFreezeExpiry
+            );
+            Self::freeze(who, None, reason, expiry)?;
             Ok(().into())
         }
+
+        /// Unfreeze who, by root or by the member who froze it
+        #[pallet::weight(T::WeightInfo::unfreeze_account())]
+        pub fn unfreeze_account(
+            origin: OriginFor<T>,
+            who: <T::Lookup as StaticLookup>::Source,
+        ) -> DispatchResultWithPostInfo {
+            let sender = match ensure_root(origin.clone()) {
+                Ok(()) => None,
+                Err(_) => Some(ensure_signed(origin)?),
+            };
+            let who = T::Lookup::lookup(who)?;
+            let freeze = Self::blacklist(&who).ok_or(Error::<T>::NotFrozen)?;
+            if sender.is_some() {
+                ensure!(freeze.member == sender, Error::<T>::NotFreezeOwner);
+            }
+            Self::unfreeze(&who, &freeze);
+            Self::deposit_event(Event::AccountUnfrozen(who, sender));
+            Ok(().into())
+        }
+
+        /// Root releases a reserve before its release block, whoever made it
+        #[pallet::weight(T::WeightInfo::force_unreserve())]
+        pub fn force_unreserve(origin: OriginFor<T>, id: ReserveId) -> DispatchResultWithPostInfo {
+            ensure_root(origin)?;
+            let reserve = Self::reserves(id).ok_or(Error::<T>::ReserveNotExist)?;
+            ReleaseSchedule::<T>::mutate(reserve.release_at, |ids| ids.retain(|i| *i != id));
+            Self::release_reserve(id);
+            Ok(().into())
+        }
+    }
+
+    impl<T: Config> Pallet<T> {
+        /// whether who is frozen at the current block
+        pub fn is_frozen(who: &T::AccountId) -> bool {
+            match Self::blacklist(who) {
+                Some(freeze) => {
+                    let now = <frame_system::Pallet<T>>::block_number();
+                    freeze.expiry.map_or(true, |expiry| now < expiry)
+                }
+                None => false,
+            }
+        }
+
+        fn freeze(
+            who: T::AccountId,
+            member: Option<T::AccountId>,
+            reason: ReasonCode,
+            expiry: Option<T::BlockNumber>,
+        ) -> DispatchResult {
+            let old_expiry = Self::blacklist(&who).and_then(|f| f.expiry);
+            if let Some(expiry) = expiry.filter(|e| old_expiry != Some(*e)) {
+                Self::schedule_freeze_expiry(&who, expiry)?;
+            }
+            // a freeze overridden by root no longer expires at its own expiry
+            if let Some(old_expiry) = old_expiry.filter(|e| expiry != Some(*e)) {
+                Self::unschedule_freeze_expiry(&who, old_expiry);
+            }
+            Blacklist::<T>::insert(
+                &who,
+                Freeze {
+                    member: member.clone(),
+                    reason,
+                    frozen_at: <frame_system::Pallet<T>>::block_number(),
+                    expiry,
+                },
+            );
+            // the lock keeps the balance in place whatever the origin of a call
+            // dispatched on behalf of who, e.g. through proxy, multisig or utility
+            T::Currency::set_lock(
+                FREEZE_LOCK_ID,
+                &who,
+                BalanceOf::<T>::max_value(),
+                WithdrawReasons::all(),
+            );
+            Self::deposit_event(Event::AccountFrozen(who, member, reason, expiry));
+            Ok(())
+        }
+
+        fn unfreeze(who: &T::AccountId, freeze: &FreezeOf<T>) {
+            if let Some(expiry) = freeze.expiry {
+                Self::unschedule_freeze_expiry(who, expiry);
+            }
+            Blacklist::<T>::remove(who);
+            T::Currency::remove_lock(FREEZE_LOCK_ID, who);
+        }
+
+        fn schedule_freeze_expiry(who: &T::AccountId, expiry: T::BlockNumber) -> DispatchResult {
+            FreezeExpiryCount::<T>::try_mutate(expiry, |count| -> DispatchResult {
+                ensure!(
+                    *count < T::MaxFreezeExpiriesPerBlock::get(),
+                    Error::<T>::FreezeScheduleFull
+                );
+                *count += 1;
+                Ok(())
+            })?;
+            FreezeExpiry::<T>::insert(expiry, who, ());
+            Ok(())
+        }
+
+        fn unschedule_freeze_expiry(who: &T::AccountId, expiry: T::BlockNumber) {
+            if FreezeExpiry::<T>::take(expiry, who).is_some() {
+                FreezeExpiryCount::<T>::mutate_exists(expiry, |count| {
+                    *count = count.map(|c| c.saturating_sub(1)).filter(|c| *c > 0);
+                });
+            }
+        }
+
+        /// lift the freezes expiring up to now, at most MaxFreezeExpiriesPerBlock of them;
+        /// the rest, e.g. left when the maximum is lowered, are carried to the next block
+        fn expire_freezes(now: T::BlockNumber) -> Weight {
+            let max = T::MaxFreezeExpiriesPerBlock::get() as usize;
+            let mut cursor = Self::freeze_expiry_cursor().unwrap_or(now);
+            let mut reads: Weight = 1;
+            let mut expired: usize = 0;
+            while cursor <= now {
+                reads += 1;
+                let left = max.saturating_sub(expired);
+                let due: Vec<T::AccountId> = FreezeExpiry::<T>::iter_key_prefix(cursor)
+                    .take(left.saturating_add(1))
+                    .collect();
+                let all_expired = due.len() <= left;
+                for who in due.into_iter().take(left) {
+                    expired += 1;
+                    Self::unschedule_freeze_expiry(&who, cursor);
+                    Blacklist::<T>::remove(&who);
+                    T::Currency::remove_lock(FREEZE_LOCK_ID, &who);
+                    Self::deposit_event(Event::FreezeExpired(who));
+                }
+                if !all_expired {
+                    break;
+                }
+                cursor += One::one();
+            }
+            FreezeExpiryCursor::<T>::put(cursor);
+            let expired = expired as Weight;
+            T::DbWeight::get().reads_writes(
+                reads.saturating_add(expired.saturating_mul(2)),
+                expired.saturating_mul(5).saturating_add(1),
+            )
+        }
+
+        fn remove_member(member: &T::AccountId) -> DispatchResult {
+            let mut whitelist = <LockMemberWhiteList<T>>::get();
+            ensure!(whitelist.contains(member), Error::<T>::NotLockMember);
+            whitelist.retain(|m| m != member);
+            <LockMemberWhiteList<T>>::put(whitelist);
+            MemberQuota::<T>::remove(member);
+            MemberUsage::<T>::remove(member);
+            Ok(())
+        }
+
+        /// unreserve the reserve and remove it, the caller removes it from ReleaseSchedule
+        fn release_reserve(id: ReserveId) {
+            if let Some(reserve) = Reserves::<T>::take(id) {
+                // the reserved balance may have been slashed in the meantime
+                T::Currency::unreserve_named(&OPERATION_RESERVE_ID, &reserve.who, reserve.amount);
+                MemberReserves::<T>::mutate_exists(&reserve.member, |ids| {
+                    if let Some(list) = ids {
+                        list.retain(|i| *i != id);
+                        if list.is_empty() {
+                            *ids = None;
+                        }
+                    }
+                });
+                Self::deposit_event(Event::ReserveReleased(
+                    id,
+                    reserve.member,
+                    reserve.who,
+                    reserve.amount,
+                ));
+            }
+        }
+    }
+}
+
+/// Reject transactions signed by an account frozen by the operation pallet.
+/// Calls dispatched on its behalf by other accounts are stopped by the freeze lock.
+#[derive(Encode, Decode, Clone, Eq, PartialEq, TypeInfo)]
+#[scale_info(skip_type_params(T))]
+pub struct CheckFrozen<T: Config + Send + Sync>(PhantomData<T>);
+
+impl<T: Config + Send + Sync> CheckFrozen<T> {
+    /// Creates new `SignedExtension` to check frozen accounts.
+    pub fn new() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<T: Config + Send + Sync> Default for CheckFrozen<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Config + Send + Sync> sp_std::fmt::Debug for CheckFrozen<T> {
+    #[cfg(feature = "std")]
+    fn fmt(&self, f: &mut sp_std::fmt::Formatter) -> sp_std::fmt::Result {
+        write!(f, "CheckFrozen")
+    }
+
+    #[cfg(not(feature = "std"))]
+    fn fmt(&self, _: &mut sp_std::fmt::Formatter) -> sp_std::fmt::Result {
+        Ok(())
+    }
+}
+
+impl<T: Config + Send + Sync> SignedExtension for CheckFrozen<T> {
+    const IDENTIFIER: &'static str = "CheckFrozen";
+    type AccountId = T::AccountId;
+    type Call = T::Call;
+    type AdditionalSigned = ();
+    type Pre = ();
+
+    fn additional_signed(&self) -> Result<(), TransactionValidityError> {
+        Ok(())
+    }
+
+    fn validate(
+        &self,
+        who: &Self::AccountId,
+        _call: &Self::Call,
+        _info: &DispatchInfoOf<Self::Call>,
+        _len: usize,
+    ) -> TransactionValidity {
+        if Pallet::<T>::is_frozen(who) {
+            return Err(InvalidTransaction::Custom(FROZEN_ACCOUNT).into());
+        }
+        Ok(ValidTransaction::default())
+    }
+
+    fn pre_dispatch(
+        self,
+        who: &Self::AccountId,
+        call: &Self::Call,
+        info: &DispatchInfoOf<Self::Call>,
+        len: usize,
+    ) -> Result<(), TransactionValidityError> {
+        self.validate(who, call, info, len).map(|_| ())
     }
 }
@@ -26,8 +26,11 @@ use sp_runtime::{
     Perbill,
 };
 
-use frame_support::traits::ConstU32;
-use frame_support::{assert_ok, parameter_types, weights::Weight};
+use frame_support::traits::{ConstU32, NamedReservableCurrency, OnInitialize};
+use frame_support::{
+    assert_noop, assert_ok, parameter_types,
+    weights::{DispatchInfo, Weight},
+};
 
 use super::*;
 use crate::{self as pallet_operation};
@@ -43,6 +46,7 @@ frame_support::construct_runtime!(
         System: frame_system::{Pallet, Call, Config, Storage, Event<T>},
         Balances: pallet_balances::{Pallet, Call, Storage, Config<T>, Event<T>},
         Operation: pallet_operation::{Pallet, Call, Event<T>},
+        Proxy: pallet_proxy::{Pallet, Call, Storage, Event<T>},
     }
 );
 
@@ -88,11 +92,34 @@ impl pallet_balances::Config for Test {
     type DustRemoval = ();
     type ExistentialDeposit = ExistentialDeposit;
     type AccountStore = System;
-    type MaxReserves = ();
+    type MaxReserves = ConstU32<10>;
     type ReserveIdentifier = [u8; 8];
     type WeightInfo = ();
 }
 
+parameter_types! {
+    pub const ProxyDepositBase: u64 = 1;
+    pub const ProxyDepositFactor: u64 = 1;
+    pub const MaxProxies: u32 = 4;
+    pub const MaxPending: u32 = 2;
+    pub const AnnouncementDepositBase: u64 = 1;
+    pub const AnnouncementDepositFactor: u64 = 1;
+}
+impl pallet_proxy::Config for Test {
+    type Event = Event;
+    type Call = Call;
+    type Currency = Balances;
+    type ProxyType = ();
+    type ProxyDepositBase = ProxyDepositBase;
+    type ProxyDepositFactor = ProxyDepositFactor;
+    type MaxProxies = MaxProxies;
+    type WeightInfo = ();
+    type MaxPending = MaxPending;
+    type CallHasher = BlakeTwo256;
+    type AnnouncementDepositBase = AnnouncementDepositBase;
+    type AnnouncementDepositFactor = AnnouncementDepositFactor;
+}
+
 // parameter_types! {
 //     pub const MinimumPeriod: u64 = 5;
 // }
@@ -103,7 +130,14 @@ impl pallet_balances::Config for Test {
 //     type WeightInfo = ();
 // }
 parameter_types! {
-    pub const MaxMember: u32 = 100;
+    pub const MaxMember: u32 = 3;
+    pub const DefaultMemberQuota: u64 = 1_000;
+    pub const QuotaPeriod: u64 = 10;
+    pub const MaxReserveDuration: u64 = 100;
+    pub const MaxReservesPerMember: u32 = 3;
+    pub const MaxReleasesPerBlock: u32 = 2;
+    pub const MaxFreezeDuration: u64 = 50;
+    pub static MaxFreezeExpiriesPerBlock: u32 = 2;
 }
 
 impl Config for Test {
@@ -111,6 +145,13 @@ impl Config for Test {
     type WeightInfo = ();
     type MaxMember = MaxMember;
     type Currency = Balances;
+    type DefaultMemberQuota = DefaultMemberQuota;
+    type QuotaPeriod = QuotaPeriod;
+    type MaxReserveDuration = MaxReserveDuration;
+    type MaxReservesPerMember = MaxReservesPerMember;
+    type MaxReleasesPerBlock = MaxReleasesPerBlock;
+    type MaxFreezeDuration = MaxFreezeDuration;
+    type MaxFreezeExpiriesPerBlock = MaxFreezeExpiriesPerBlock;
 }
 
 pub fn new_test_ext() -> sp_io::TestExternalities {
@@ -131,9 +172,440 @@ fn set_lock_members_works() {
     new_test_ext().execute_with(|| {
         assert_ok!(Balances::set_balance(Origin::root(), 1, 1_000, 0));
         assert_ok!(Operation::set_reserve_members(Origin::root(), vec!(2)));
-        assert_ok!(Operation::force_reserve_by_member(Some(2).into(), 1, 500));
+        assert_ok!(Operation::force_reserve_by_member(
+            Some(2).into(),
+            1,
+            500,
+            0,
+            10
+        ));
         assert_eq!(Balances::free_balance(&1), 500);
         assert_ok!(Balances::force_unreserve(Origin::root(), 1, 500));
         assert_eq!(Balances::free_balance(&1), 1000);
     });
 }
+
+#[test]
+fn reserve_released_at_release_block() {
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+        assert_ok!(Operation::add_reserve_member(Origin::root(), 2, None));
+        System::assert_last_event(Event::Operation(crate::Event::MemberAdded(2, 1_000)));
+
+        // InvalidReleaseBlock
+        assert_noop!(
+            Operation::force_reserve_by_member(Some(2).into(), 1, 50, 7, 1),
+            Error::<Test>::InvalidReleaseBlock
+        );
+        assert_noop!(
+            Operation::force_reserve_by_member(Some(2).into(), 1, 50, 7, 102),
+            Error::<Test>::InvalidReleaseBlock
+        );
+        // NotLockMember
+        assert_noop!(
+            Operation::force_reserve_by_member(Some(3).into(), 1, 50, 7, 5),
+            Error::<Test>::NotLockMember
+        );
+
+        assert_ok!(Operation::force_reserve_by_member(
+            Some(2).into(),
+            1,
+            50,
+            7,
+            5
+        ));
+        System::assert_last_event(Event::Operation(crate::Event::Reserved(0, 2, 1, 50, 7, 5)));
+        assert_eq!(Balances::reserved_balance(&1), 50);
+        assert_eq!(
+            Operation::reserves(0),
+            Some(Reserve {
+                member: 2,
+                who: 1,
+                amount: 50,
+                reason: 7,
+                release_at: 5
+            })
+        );
+
+        Operation::on_initialize(4);
+        assert_eq!(Balances::reserved_balance(&1), 50);
+        Operation::on_initialize(5);
+        System::assert_last_event(Event::Operation(crate::Event::ReserveReleased(0, 2, 1, 50)));
+        assert_eq!(Balances::reserved_balance(&1), 0);
+        assert_eq!(Operation::reserves(0), None);
+        assert!(Operation::member_reserves(2).is_empty());
+        assert!(Operation::release_schedule(5).is_empty());
+    });
+}
+
+#[test]
+fn unreserve_by_member() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(Operation::add_reserve_member(Origin::root(), 2, None));
+        assert_ok!(Operation::add_reserve_member(Origin::root(), 0, None));
+        assert_ok!(Operation::force_reserve_by_member(
+            Some(2).into(),
+            1,
+            50,
+            7,
+            5
+        ));
+
+        assert_noop!(
+            Operation::unreserve_by_member(Some(0).into(), 0),
+            Error::<Test>::NotReserveOwner
+        );
+        assert_noop!(
+            Operation::unreserve_by_member(Some(2).into(), 1),
+            Error::<Test>::ReserveNotExist
+        );
+        assert_ok!(Operation::unreserve_by_member(Some(2).into(), 0));
+        assert_eq!(Balances::reserved_balance(&1), 0);
+        assert!(Operation::release_schedule(5).is_empty());
+    });
+}
+
+#[test]
+fn member_quota() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(Balances::set_balance(Origin::root(), 1, 1_000, 0));
+        assert_ok!(Operation::add_reserve_member(Origin::root(), 2, Some(300)));
+        assert_ok!(Operation::force_reserve_by_member(
+            Some(2).into(),
+            1,
+            200,
+            0,
+            20
+        ));
+        // unreserving doesn't restore the quota of the period
+        assert_ok!(Operation::unreserve_by_member(Some(2).into(), 0));
+        assert_noop!(
+            Operation::force_reserve_by_member(Some(2).into(), 1, 200, 0, 20),
+            Error::<Test>::QuotaExceeded
+        );
+        assert_ok!(Operation::force_reserve_by_member(
+            Some(2).into(),
+            1,
+            100,
+            0,
+            20
+        ));
+
+        // quota is renewed in the next period
+        System::set_block_number(10);
+        assert_ok!(Operation::force_reserve_by_member(
+            Some(2).into(),
+            1,
+            200,
+            0,
+            20
+        ));
+
+        // TooManyReserves
+        assert_ok!(Operation::force_reserve_by_member(
+            Some(2).into(),
+            1,
+            10,
+            0,
+            21
+        ));
+        assert_noop!(
+            Operation::force_reserve_by_member(Some(2).into(), 1, 10, 0, 22),
+            Error::<Test>::TooManyReserves
+        );
+
+        // ReleaseScheduleFull
+        assert_ok!(Operation::add_reserve_member(Origin::root(), 0, None));
+        assert_noop!(
+            Operation::force_reserve_by_member(Some(0).into(), 1, 10, 0, 20),
+            Error::<Test>::ReleaseScheduleFull
+        );
+    });
+}
+
+#[test]
+fn bounded_membership() {
+    new_test_ext().execute_with(|| {
+        assert_noop!(
+            Operation::set_reserve_members(Origin::root(), vec![0, 1, 2, 3]),
+            Error::<Test>::TooManyMembers
+        );
+        assert_ok!(Operation::set_reserve_members(Origin::root(), vec![0, 1]));
+        assert_noop!(
+            Operation::add_reserve_member(Some(0).into(), 2, None),
+            sp_runtime::traits::BadOrigin
+        );
+        assert_noop!(
+            Operation::add_reserve_member(Origin::root(), 1, None),
+            Error::<Test>::AlreadyMember
+        );
+        assert_ok!(Operation::add_reserve_member(Origin::root(), 2, None));
+        assert_noop!(
+            Operation::add_reserve_member(Origin::root(), 3, None),
+            Error::<Test>::TooManyMembers
+        );
+
+        assert_ok!(Operation::remove_reserve_member(Origin::root(), 1));
+        assert_eq!(Operation::lock_member_whitelist().to_vec(), vec![0, 2]);
+        assert_noop!(
+            Operation::remove_reserve_member(Origin::root(), 1),
+            Error::<Test>::NotLockMember
+        );
+    });
+}
+
+#[test]
+fn revoke_reserve_member() {
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+        assert_ok!(Operation::add_reserve_member(Origin::root(), 2, None));
+        assert_ok!(Operation::force_reserve_by_member(
+            Some(2).into(),
+            1,
+            50,
+            0,
+            5
+        ));
+        assert_ok!(Operation::force_reserve_by_member(
+            Some(2).into(),
+            0,
+            60,
+            0,
+            6
+        ));
+        assert_eq!(Operation::member_reserves(2).to_vec(), vec![0, 1]);
+
+        assert_ok!(Operation::revoke_reserve_member(Origin::root(), 2));
+        System::assert_last_event(Event::Operation(crate::Event::MemberRevoked(2, 2)));
+        assert_eq!(Balances::reserved_balance(&1), 0);
+        assert_eq!(Balances::reserved_balance(&0), 0);
+        assert!(Operation::member_reserves(2).is_empty());
+        assert!(Operation::release_schedule(5).is_empty());
+        assert!(Operation::release_schedule(6).is_empty());
+        assert!(Operation::lock_member_whitelist().is_empty());
+
+        assert_noop!(
+            Operation::force_reserve_by_member(Some(2).into(), 1, 50, 0, 5),
+            Error::<Test>::NotLockMember
+        );
+    });
+}
+
+#[test]
+fn release_reserves_of_removed_member() {
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+        assert_ok!(Operation::add_reserve_member(Origin::root(), 2, Some(500)));
+        assert_ok!(Operation::force_reserve_by_member(
+            Some(2).into(),
+            1,
+            50,
+            0,
+            5
+        ));
+        assert_ok!(Operation::force_reserve_by_member(
+            Some(2).into(),
+            0,
+            60,
+            0,
+            6
+        ));
+        // reserves are held under the pallet's reserve identifier
+        assert_eq!(
+            Balances::reserved_balance_named(&OPERATION_RESERVE_ID, &1),
+            50
+        );
+
+        // quotas of the members left out are removed
+        assert_ok!(Operation::set_reserve_members(Origin::root(), vec![3]));
+        assert_eq!(Operation::member_quota(2), None);
+        assert_eq!(Operation::member_usage(2), (0, 0));
+
+        // root releases a reserve by id
+        assert_noop!(
+            Operation::force_unreserve(Some(2).into(), 0),
+            sp_runtime::traits::BadOrigin
+        );
+        assert_ok!(Operation::force_unreserve(Origin::root(), 0));
+        System::assert_last_event(Event::Operation(crate::Event::ReserveReleased(0, 2, 1, 50)));
+        assert_eq!(Balances::reserved_balance(&1), 0);
+        assert!(Operation::release_schedule(5).is_empty());
+        assert_noop!(
+            Operation::force_unreserve(Origin::root(), 0),
+            Error::<Test>::ReserveNotExist
+        );
+
+        // the other reserves of the removed member are revoked at once
+        assert_ok!(Operation::revoke_reserve_member(Origin::root(), 2));
+        System::assert_last_event(Event::Operation(crate::Event::MemberRevoked(2, 1)));
+        assert_eq!(Balances::reserved_balance(&0), 0);
+        assert_noop!(
+            Operation::revoke_reserve_member(Origin::root(), 2),
+            Error::<Test>::NotLockMember
+        );
+    });
+}
+
+#[test]
+fn freeze_account() {
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+        assert_ok!(Operation::add_reserve_member(Origin::root(), 2, None));
+        assert_noop!(
+            Operation::freeze_account(Some(3).into(), 1, 7, 10),
+            Error::<Test>::NotLockMember
+        );
+        assert_noop!(
+            Operation::freeze_account(Some(2).into(), 1, 7, 52),
+            Error::<Test>::InvalidFreezeExpiry
+        );
+        assert_ok!(Operation::freeze_account(Some(2).into(), 1, 7, 10));
+        System::assert_last_event(Event::Operation(crate::Event::AccountFrozen(
+            1,
+            Some(2),
+            7,
+            Some(10),
+        )));
+        assert!(Operation::is_frozen(&1));
+        assert_noop!(
+            Operation::freeze_account(Some(2).into(), 1, 7, 10),
+            Error::<Test>::AlreadyFrozen
+        );
+
+        // the freeze expires
+        System::set_block_number(10);
+        assert!(!Operation::is_frozen(&1));
+
+        // root overrides the freeze of a member, only root can lift it
+        assert_ok!(Operation::freeze_account(Some(2).into(), 1, 7, 20));
+        assert_ok!(Operation::force_freeze_account(Origin::root(), 1, 8, None));
+        assert_eq!(Operation::blacklist(1).unwrap().member, None);
+        assert_noop!(
+            Operation::unfreeze_account(Some(2).into(), 1),
+            Error::<Test>::NotFreezeOwner
+        );
+        System::set_block_number(1_000);
+        assert!(Operation::is_frozen(&1));
+        assert_ok!(Operation::unfreeze_account(Origin::root(), 1));
+        System::assert_last_event(Event::Operation(crate::Event::AccountUnfrozen(1, None)));
+        assert!(!Operation::is_frozen(&1));
+        assert_noop!(
+            Operation::unfreeze_account(Origin::root(), 1),
+            Error::<Test>::NotFrozen
+        );
+    });
+}
+
+#[test]
+fn check_frozen_rejects_transactions() {
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+        let call = Call::System(frame_system::Call::remark { remark: vec![] });
+        let info = DispatchInfo::default();
+        assert_ok!(CheckFrozen::<Test>::new().validate(&1, &call, &info, 0));
+
+        assert_ok!(Operation::add_reserve_member(Origin::root(), 2, None));
+        assert_ok!(Operation::freeze_account(Some(2).into(), 1, 7, 10));
+        assert_eq!(
+            CheckFrozen::<Test>::new().validate(&1, &call, &info, 0),
+            Err(InvalidTransaction::Custom(FROZEN_ACCOUNT).into())
+        );
+        assert_eq!(
+            CheckFrozen::<Test>::new().pre_dispatch(&1, &call, &info, 0),
+            Err(InvalidTransaction::Custom(FROZEN_ACCOUNT).into())
+        );
+        assert_ok!(CheckFrozen::<Test>::new().validate(&0, &call, &info, 0));
+
+        assert_ok!(Operation::unfreeze_account(Some(2).into(), 1));
+        assert_ok!(CheckFrozen::<Test>::new().pre_dispatch(&1, &call, &info, 0));
+    });
+}
+
+#[test]
+fn frozen_account_cannot_move_funds_through_proxy() {
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+        assert_ok!(Proxy::add_proxy(Origin::signed(1), 0, (), 0));
+        assert_ok!(Operation::add_reserve_member(Origin::root(), 2, None));
+        assert_ok!(Operation::freeze_account(Some(2).into(), 1, 7, 10));
+        assert_eq!(Balances::locks(&1)[0].id, FREEZE_LOCK_ID);
+
+        let transfer = Box::new(Call::Balances(pallet_balances::Call::transfer {
+            dest: 0,
+            value: 10,
+        }));
+        // the proxy call itself succeeds, the transfer made as 1 does not
+        assert_ok!(Proxy::proxy(Origin::signed(0), 1, None, transfer.clone()));
+        assert_eq!(Balances::free_balance(&1), 96);
+        assert_noop!(
+            Balances::transfer(Origin::signed(1), 0, 10),
+            pallet_balances::Error::<Test>::LiquidityRestrictions
+        );
+
+        // the lock is lifted when the freeze expires
+        System::set_block_number(10);
+        Operation::on_initialize(10);
+        assert!(!Operation::is_frozen(&1));
+        assert!(Balances::locks(&1).is_empty());
+        System::assert_last_event(Event::Operation(crate::Event::FreezeExpired(1)));
+        assert_ok!(Proxy::proxy(Origin::signed(0), 1, None, transfer.clone()));
+        assert_eq!(Balances::free_balance(&1), 86);
+
+        // and when it's unfrozen, including after root overrides the expiry
+        assert_ok!(Operation::freeze_account(Some(2).into(), 1, 7, 20));
+        assert_ok!(Operation::force_freeze_account(Origin::root(), 1, 8, None));
+        Operation::on_initialize(20);
+        assert!(Operation::is_frozen(&1));
+        assert_ok!(Operation::unfreeze_account(Origin::root(), 1));
+        assert!(Balances::locks(&1).is_empty());
+        assert_ok!(Proxy::proxy(Origin::signed(0), 1, None, transfer));
+        assert_eq!(Balances::free_balance(&1), 76);
+    });
+}
+
+#[test]
+fn freeze_expiries_per_block() {
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+        assert_ok!(Operation::add_reserve_member(Origin::root(), 2, None));
+        assert_ok!(Operation::freeze_account(Some(2).into(), 0, 7, 10));
+        assert_ok!(Operation::freeze_account(Some(2).into(), 1, 7, 10));
+        assert_eq!(Operation::freeze_expiry_count(10), 2);
+        assert_noop!(
+            Operation::freeze_account(Some(2).into(), 2, 7, 10),
+            Error::<Test>::FreezeScheduleFull
+        );
+        assert_noop!(
+            Operation::force_freeze_account(Origin::root(), 2, 8, Some(10)),
+            Error::<Test>::FreezeScheduleFull
+        );
+        // an overridden freeze leaves its expiry block
+        assert_ok!(Operation::force_freeze_account(
+            Origin::root(),
+            1,
+            8,
+            Some(11)
+        ));
+        assert_eq!(Operation::freeze_expiry_count(10), 1);
+        assert_ok!(Operation::freeze_account(Some(2).into(), 2, 7, 10));
+
+        // freezes beyond the maximum are carried to the next block
+        MaxFreezeExpiriesPerBlock::set(1);
+        System::set_block_number(10);
+        Operation::on_initialize(10);
+        assert_eq!(FreezeExpiry::<Test>::iter_prefix(10).count(), 1);
+        assert_eq!(Operation::freeze_expiry_cursor(), Some(10));
+        System::set_block_number(11);
+        Operation::on_initialize(11);
+        assert_eq!(FreezeExpiry::<Test>::iter_prefix(10).count(), 0);
+        assert_eq!(Operation::freeze_expiry_count(10), 0);
+        assert_eq!(Operation::freeze_expiry_cursor(), Some(11));
+        assert!(Balances::locks(&0).is_empty());
+        assert!(Balances::locks(&2).is_empty());
+        assert!(Operation::blacklist(1).is_some());
+        System::set_block_number(12);
+        Operation::on_initialize(12);
+        assert!(Operation::blacklist(1).is_none());
+        assert_eq!(Operation::freeze_expiry_cursor(), Some(13));
+    });
+}
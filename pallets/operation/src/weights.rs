@@ -49,15 +49,23 @@ pub trait WeightInfo {
     fn force_reserve_by_member() -> Weight;
     fn force_remove_lock() -> Weight;
     fn set_reserve_members() -> Weight;
+    fn unreserve_by_member() -> Weight;
+    fn add_reserve_member() -> Weight;
+    fn remove_reserve_member() -> Weight;
+    fn revoke_reserve_member(r: u32) -> Weight;
+    fn release_reserves(n: u32) -> Weight;
+    fn freeze_account() -> Weight;
+    fn unfreeze_account() -> Weight;
+    fn force_unreserve() -> Weight;
 }
 
 /// Weights for pallet_operation using the Substrate node and recommended hardware.
 pub struct SubstrateWeight<T>(PhantomData<T>);
 impl<T: frame_system::Config> WeightInfo for SubstrateWeight<T> {
     fn force_reserve_by_member() -> Weight {
-        (28_133_000 as Weight)
-            .saturating_add(T::DbWeight::get().reads(2 as Weight))
-            .saturating_add(T::DbWeight::get().writes(1 as Weight))
+        (41_380_000 as Weight)
+            .saturating_add(T::DbWeight::get().reads(7 as Weight))
+            .saturating_add(T::DbWeight::get().writes(6 as Weight))
     }
     fn force_remove_lock() -> Weight {
         (28_374_000 as Weight)
@@ -67,14 +75,62 @@ impl<T: frame_system::Config> WeightInfo for SubstrateWeight<T> {
     fn set_reserve_members() -> Weight {
         (2_244_000 as Weight).saturating_add(T::DbWeight::get().writes(1 as Weight))
     }
+    fn unreserve_by_member() -> Weight {
+        (35_206_000 as Weight)
+            .saturating_add(T::DbWeight::get().reads(4 as Weight))
+            .saturating_add(T::DbWeight::get().writes(4 as Weight))
+    }
+    fn add_reserve_member() -> Weight {
+        (6_872_000 as Weight)
+            .saturating_add(T::DbWeight::get().reads(1 as Weight))
+            .saturating_add(T::DbWeight::get().writes(2 as Weight))
+    }
+    fn remove_reserve_member() -> Weight {
+        (7_415_000 as Weight)
+            .saturating_add(T::DbWeight::get().reads(1 as Weight))
+            .saturating_add(T::DbWeight::get().writes(3 as Weight))
+    }
+    fn revoke_reserve_member(r: u32) -> Weight {
+        (9_820_000 as Weight)
+            // Standard Error: 12_000
+            .saturating_add((31_540_000 as Weight).saturating_mul(r as Weight))
+            .saturating_add(T::DbWeight::get().reads(2 as Weight))
+            .saturating_add(T::DbWeight::get().reads((4 as Weight).saturating_mul(r as Weight)))
+            .saturating_add(T::DbWeight::get().writes(4 as Weight))
+            .saturating_add(T::DbWeight::get().writes((3 as Weight).saturating_mul(r as Weight)))
+    }
+    fn release_reserves(n: u32) -> Weight {
+        (3_012_000 as Weight)
+            // Standard Error: 10_000
+            .saturating_add((29_470_000 as Weight).saturating_mul(n as Weight))
+            .saturating_add(T::DbWeight::get().reads(1 as Weight))
+            .saturating_add(T::DbWeight::get().reads((3 as Weight).saturating_mul(n as Weight)))
+            .saturating_add(T::DbWeight::get().writes(1 as Weight))
+            .saturating_add(T::DbWeight::get().writes((3 as Weight).saturating_mul(n as Weight)))
+    }
+    fn freeze_account() -> Weight {
+        (14_962_000 as Weight)
+            .saturating_add(T::DbWeight::get().reads(4 as Weight))
+            .saturating_add(T::DbWeight::get().writes(5 as Weight))
+    }
+    fn unfreeze_account() -> Weight {
+        (12_308_000 as Weight)
+            .saturating_add(T::DbWeight::get().reads(3 as Weight))
+            .saturating_add(T::DbWeight::get().writes(5 as Weight))
+    }
+    fn force_unreserve() -> Weight {
+        (35_206_000 as Weight)
+            .saturating_add(T::DbWeight::get().reads(4 as Weight))
+            .saturating_add(T::DbWeight::get().writes(4 as Weight))
+    }
 }
 
 // For backwards compatibility and tests
 impl WeightInfo for () {
     fn force_reserve_by_member() -> Weight {
-        (28_133_000 as Weight)
-            .saturating_add(RocksDbWeight::get().reads(2 as Weight))
-            .saturating_add(RocksDbWeight::get().writes(1 as Weight))
+        (41_380_000 as Weight)
+            .saturating_add(RocksDbWeight::get().reads(7 as Weight))
+            .saturating_add(RocksDbWeight::get().writes(6 as Weight))
     }
     fn force_remove_lock() -> Weight {
         (28_374_000 as Weight)
@@ -84,4 +140,52 @@ impl WeightInfo for () {
     fn set_reserve_members() -> Weight {
         (2_244_000 as Weight).saturating_add(RocksDbWeight::get().writes(1 as Weight))
     }
+    fn unreserve_by_member() -> Weight {
+        (35_206_000 as Weight)
+            .saturating_add(RocksDbWeight::get().reads(4 as Weight))
+            .saturating_add(RocksDbWeight::get().writes(4 as Weight))
+    }
+    fn add_reserve_member() -> Weight {
+        (6_872_000 as Weight)
+            .saturating_add(RocksDbWeight::get().reads(1 as Weight))
+            .saturating_add(RocksDbWeight::get().writes(2 as Weight))
+    }
+    fn remove_reserve_member() -> Weight {
+        (7_415_000 as Weight)
+            .saturating_add(RocksDbWeight::get().reads(1 as Weight))
+            .saturating_add(RocksDbWeight::get().writes(3 as Weight))
+    }
+    fn revoke_reserve_member(r: u32) -> Weight {
+        (9_820_000 as Weight)
+            // Standard Error: 12_000
+            .saturating_add((31_540_000 as Weight).saturating_mul(r as Weight))
+            .saturating_add(RocksDbWeight::get().reads(2 as Weight))
+            .saturating_add(RocksDbWeight::get().reads((4 as Weight).saturating_mul(r as Weight)))
+            .saturating_add(RocksDbWeight::get().writes(4 as Weight))
+            .saturating_add(RocksDbWeight::get().writes((3 as Weight).saturating_mul(r as Weight)))
+    }
+    fn release_reserves(n: u32) -> Weight {
+        (3_012_000 as Weight)
+            // Standard Error: 10_000
+            .saturating_add((29_470_000 as Weight).saturating_mul(n as Weight))
+            .saturating_add(RocksDbWeight::get().reads(1 as Weight))
+            .saturating_add(RocksDbWeight::get().reads((3 as Weight).saturating_mul(n as Weight)))
+            .saturating_add(RocksDbWeight::get().writes(1 as Weight))
+            .saturating_add(RocksDbWeight::get().writes((3 as Weight).saturating_mul(n as Weight)))
+    }
+    fn freeze_account() -> Weight {
+        (14_962_000 as Weight)
+            .saturating_add(RocksDbWeight::get().reads(4 as Weight))
+            .saturating_add(RocksDbWeight::get().writes(5 as Weight))
+    }
+    fn unfreeze_account() -> Weight {
+        (12_308_000 as Weight)
+            .saturating_add(RocksDbWeight::get().reads(3 as Weight))
+            .saturating_add(RocksDbWeight::get().writes(5 as Weight))
+    }
+    fn force_unreserve() -> Weight {
+        (35_206_000 as Weight)
+            .saturating_add(RocksDbWeight::get().reads(4 as Weight))
+            .saturating_add(RocksDbWeight::get().writes(4 as Weight))
+    }
 }
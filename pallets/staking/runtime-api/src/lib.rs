@@ -0,0 +1,53 @@
+// Copyright (C) 2021 Deeper Network Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Runtime API definition for the staking pallet.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use codec::Codec;
+use sp_std::vec::Vec;
+
+pub use pallet_staking::{
+    DelegatorRewardRecord, ElectionCandidate, ElectionStrategyKind, EraEmission, EraIndex,
+};
+
+sp_api::decl_runtime_apis! {
+    /// The API to query the staking records of delegators, the emission of the mining reward and
+    /// the validator election.
+    pub trait StakingApi<AccountId, Balance> where
+        AccountId: Codec,
+        Balance: Codec,
+    {
+        /// The reward payouts kept in the reward history of `delegator`, oldest first.
+        fn delegator_reward_history(
+            delegator: AccountId,
+        ) -> Vec<DelegatorRewardRecord<AccountId, Balance>>;
+
+        /// The emission of `count` eras from `from` on under the current emission schedule,
+        /// empty if no schedule is set.
+        fn projected_emission(from: EraIndex, count: u32) -> Vec<EraEmission<Balance>>;
+
+        /// The trusted validators standing for election and what backs each of them.
+        fn election_snapshot() -> Vec<ElectionCandidate<AccountId, Balance>>;
+
+        /// The validators each of `strategies` would elect for `count` seats out of the current
+        /// election snapshot.
+        fn simulate_election(
+            count: u32,
+            strategies: Vec<ElectionStrategyKind>,
+        ) -> Vec<(ElectionStrategyKind, Vec<AccountId>)>;
+    }
+}
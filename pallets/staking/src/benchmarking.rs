@@ -29,7 +29,6 @@ use sp_runtime::PerThing;
 const SEED: u32 = 0;
 const MAX_SPANS: u32 = 100;
 const MAX_SLASHES: u32 = 1000;
-const MAX_DELEGATORS: u32 = 1_000_000;
 const MAX_DELEGATES: u32 = 1;
 const MAX_VALIDATORS: u32 = 1000;
 
@@ -139,11 +138,13 @@ benchmarks! {
         assert!(Validators::<T>::contains_key(stash));
     }
 
-    // Worst case scenario, MAX_DELEGATORS
+    // Worst case scenario, the delegator moves from `n` validators to `n` validators
     delegate {
-        let n in 1 .. MAX_DELEGATORS;
-        let delegator = create_delegator::<T>(n + 1, 100)?;
-        let validators = create_validators_is_accountid::<T>(MAX_DELEGATES, 100)?;
+        let n in 1 .. T::MaxDelegates::get() as u32;
+        let delegator = create_delegator::<T>(USER_SEED, 100)?;
+        let validators = create_validators_is_accountid::<T>(n, 100)?;
+        // delegating again removes the delegator from the validators it delegated to
+        Staking::<T>::delegate(RawOrigin::Signed(delegator.clone()).into(), validators.clone())?;
         whitelist_account!(delegator);
     }: _(RawOrigin::Signed(delegator.clone()), validators)
     verify {
@@ -0,0 +1,311 @@
+// Copyright (C) 2021 Deeper Network Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Validator election strategies for Delegated Proof of Credit system (DPoCr).
+//!
+//! An [`ElectionStrategy`] selects the validators of the next era out of the trusted candidates.
+//! The runtime plugs one in through `Config::ElectionStrategy`, [`GovernedElection`] lets
+//! governance choose among the strategies of this module with `set_election_strategy`.
+//!
+//! [`simulate`] runs several strategies on the same candidates, e.g. offline on a snapshot taken
+//! with [`Pallet::election_snapshot`], to compare their outcomes. Both are exposed through the
+//! staking runtime API.
+
+use crate::{BalanceOf, Config, EraIndex, Pallet};
+use codec::{Decode, Encode};
+use frame_support::traits::Get;
+use scale_info::TypeInfo;
+use sp_runtime::{
+    traits::{AtLeast32BitUnsigned, UniqueSaturatedInto},
+    Perbill, RuntimeDebug,
+};
+use sp_std::{collections::btree_map::BTreeMap, marker::PhantomData, vec, vec::Vec};
+
+/// A validator standing for election together with what backs it.
+#[derive(Clone, PartialEq, Eq, Encode, Decode, RuntimeDebug, TypeInfo)]
+pub struct ElectionCandidate<AccountId, Balance> {
+    /// The validator's stash.
+    pub who: AccountId,
+    /// The era after the one the validator was last elected in, 0 if it never was.
+    pub elected_era: EraIndex,
+    /// The validator's own active bonded stake.
+    pub stake: Balance,
+    /// The delegators of the validator and the credit each delegated to it.
+    pub delegations: Vec<(AccountId, u64)>,
+}
+
+impl<AccountId, Balance> ElectionCandidate<AccountId, Balance> {
+    /// The credit delegated to the validator.
+    pub fn delegated_credit(&self) -> u64 {
+        self.delegations
+            .iter()
+            .fold(0u64, |total, (_, credit)| total.saturating_add(*credit))
+    }
+}
+
+/// Selects the validators of the next era.
+pub trait ElectionStrategy<AccountId, Balance> {
+    /// Select at most `count` validators out of `candidates`.
+    fn elect(
+        candidates: Vec<ElectionCandidate<AccountId, Balance>>,
+        count: usize,
+    ) -> Vec<AccountId>;
+}
+
+/// The election strategies governance can choose from.
+#[derive(Clone, Copy, PartialEq, Eq, Encode, Decode, RuntimeDebug, TypeInfo)]
+pub enum ElectionStrategyKind {
+    /// Rotate the validators, ranking by the number of delegators among the ones elected
+    /// least recently.
+    Headcount,
+    /// Rank by the delegated credit.
+    CreditSum,
+    /// Rank by the share of the total stake and the share of the total delegated credit, the
+    /// latter weighted by the given ratio.
+    StakeCredit(Perbill),
+    /// Sequential Phragmén with the credit of the delegators as approval weight.
+    SequentialPhragmen,
+}
+
+/// The election chains had before strategies were pluggable.
+impl Default for ElectionStrategyKind {
+    fn default() -> Self {
+        ElectionStrategyKind::Headcount
+    }
+}
+
+/// Rank the candidates by `score`, among equal scores validators elected least recently come
+/// first.
+fn elect_by_score<AccountId, Balance, S: Ord>(
+    candidates: Vec<ElectionCandidate<AccountId, Balance>>,
+    count: usize,
+    score: impl Fn(&ElectionCandidate<AccountId, Balance>) -> S,
+) -> Vec<AccountId> {
+    let mut scored: Vec<(S, EraIndex, AccountId)> = candidates
+        .into_iter()
+        .map(|c| (score(&c), c.elected_era, c.who))
+        .collect();
+    scored.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.cmp(&b.1)));
+    scored
+        .into_iter()
+        .take(count)
+        .map(|(_, _, who)| who)
+        .collect()
+}
+
+/// Validators elected least recently come first so that the validator set rotates, among them
+/// the ones with more delegators.
+pub struct Headcount;
+
+impl<AccountId, Balance> ElectionStrategy<AccountId, Balance> for Headcount {
+    fn elect(
+        candidates: Vec<ElectionCandidate<AccountId, Balance>>,
+        count: usize,
+    ) -> Vec<AccountId> {
+        let mut ranked: Vec<(EraIndex, usize, AccountId)> = candidates
+            .into_iter()
+            .map(|c| (c.elected_era, c.delegations.len(), c.who))
+            .collect();
+        ranked.sort_by(|a, b| a.0.cmp(&b.0).then_with(|| b.1.cmp(&a.1)));
+        ranked
+            .into_iter()
+            .take(count)
+            .map(|(_, _, who)| who)
+            .collect()
+    }
+}
+
+/// Rank by the delegated credit.
+pub struct CreditSum;
+
+impl<AccountId, Balance> ElectionStrategy<AccountId, Balance> for CreditSum {
+    fn elect(
+        candidates: Vec<ElectionCandidate<AccountId, Balance>>,
+        count: usize,
+    ) -> Vec<AccountId> {
+        elect_by_score(candidates, count, |c| c.delegated_credit())
+    }
+}
+
+/// Rank by the share of the total stake and the share of the total delegated credit, the latter
+/// weighted by `W`.
+pub struct StakeCredit<W>(PhantomData<W>);
+
+impl<AccountId, Balance, W> ElectionStrategy<AccountId, Balance> for StakeCredit<W>
+where
+    Balance: AtLeast32BitUnsigned + Copy,
+    W: Get<Perbill>,
+{
+    fn elect(
+        candidates: Vec<ElectionCandidate<AccountId, Balance>>,
+        count: usize,
+    ) -> Vec<AccountId> {
+        stake_credit(candidates, count, W::get())
+    }
+}
+
+fn stake_credit<AccountId, Balance: AtLeast32BitUnsigned + Copy>(
+    candidates: Vec<ElectionCandidate<AccountId, Balance>>,
+    count: usize,
+    credit_weight: Perbill,
+) -> Vec<AccountId> {
+    let stake_of =
+        |c: &ElectionCandidate<AccountId, Balance>| -> u128 { c.stake.unique_saturated_into() };
+    let total_stake = candidates
+        .iter()
+        .fold(0u128, |total, c| total.saturating_add(stake_of(c)));
+    let total_credit = candidates.iter().fold(0u128, |total, c| {
+        total.saturating_add(c.delegated_credit() as u128)
+    });
+    let share = |part: u128, total: u128| -> u64 {
+        if total == 0 {
+            0
+        } else {
+            Perbill::from_rational(part, total).deconstruct() as u64
+        }
+    };
+    elect_by_score(candidates, count, |c| {
+        let stake = share(stake_of(c), total_stake);
+        let credit = share(c.delegated_credit() as u128, total_credit);
+        credit_weight.left_from_one().mul_floor(stake) + credit_weight.mul_floor(credit)
+    })
+}
+
+/// Sequential Phragmén with the credit of the delegators as approval weight.
+///
+/// Each round elects the candidate which minimizes the load of its delegators, which spreads the
+/// seats over the groups of delegators in proportion to their credit. Candidates without any
+/// delegator fill the remaining seats, least recently elected first.
+pub struct SequentialPhragmen;
+
+impl<AccountId: Ord + Clone, Balance> ElectionStrategy<AccountId, Balance> for SequentialPhragmen {
+    fn elect(
+        candidates: Vec<ElectionCandidate<AccountId, Balance>>,
+        count: usize,
+    ) -> Vec<AccountId> {
+        const SCALE: u128 = 1_000_000_000_000_000_000;
+
+        // the budget of a delegator is all the credit it delegated
+        let mut budgets: BTreeMap<AccountId, u128> = BTreeMap::new();
+        for candidate in &candidates {
+            for (delegator, credit) in &candidate.delegations {
+                let budget = budgets.entry(delegator.clone()).or_default();
+                *budget = budget.saturating_add(*credit as u128);
+            }
+        }
+        let budget_of = |delegator: &AccountId| budgets.get(delegator).copied().unwrap_or(0);
+
+        let mut loads: BTreeMap<AccountId, u128> = BTreeMap::new();
+        let mut elected = vec![false; candidates.len()];
+        let mut winners: Vec<usize> = Vec::new();
+        while winners.len() < count {
+            let mut best: Option<(u128, usize)> = None;
+            for (i, candidate) in candidates.iter().enumerate() {
+                if elected[i] {
+                    continue;
+                }
+                let approval = candidate
+                    .delegations
+                    .iter()
+                    .fold(0u128, |total, (d, _)| total.saturating_add(budget_of(d)));
+                if approval == 0 {
+                    continue;
+                }
+                let load = candidate.delegations.iter().fold(0u128, |total, (d, _)| {
+                    let load = loads.get(d).copied().unwrap_or(0);
+                    total.saturating_add(budget_of(d).saturating_mul(load))
+                });
+                let score = SCALE.saturating_add(load) / approval;
+                let better = match best {
+                    None => true,
+                    Some((best_score, j)) => {
+                        score < best_score
+                            || (score == best_score
+                                && candidate.elected_era < candidates[j].elected_era)
+                    }
+                };
+                if better {
+                    best = Some((score, i));
+                }
+            }
+            match best {
+                Some((score, i)) => {
+                    elected[i] = true;
+                    winners.push(i);
+                    for (delegator, _) in &candidates[i].delegations {
+                        loads.insert(delegator.clone(), score);
+                    }
+                }
+                None => break,
+            }
+        }
+
+        let mut rest: Vec<usize> = (0..candidates.len()).filter(|i| !elected[*i]).collect();
+        rest.sort_by_key(|i| candidates[*i].elected_era);
+        winners.extend(rest.into_iter().take(count.saturating_sub(winners.len())));
+        winners
+            .into_iter()
+            .map(|i| candidates[i].who.clone())
+            .collect()
+    }
+}
+
+/// Elect with the strategy of the given kind.
+pub fn elect_with<AccountId, Balance>(
+    kind: ElectionStrategyKind,
+    candidates: Vec<ElectionCandidate<AccountId, Balance>>,
+    count: usize,
+) -> Vec<AccountId>
+where
+    AccountId: Ord + Clone,
+    Balance: AtLeast32BitUnsigned + Copy,
+{
+    match kind {
+        ElectionStrategyKind::Headcount => Headcount::elect(candidates, count),
+        ElectionStrategyKind::CreditSum => CreditSum::elect(candidates, count),
+        ElectionStrategyKind::StakeCredit(credit_weight) => {
+            stake_credit(candidates, count, credit_weight)
+        }
+        ElectionStrategyKind::SequentialPhragmen => SequentialPhragmen::elect(candidates, count),
+    }
+}
+
+/// Elect with the strategy chosen by governance through `set_election_strategy`.
+pub struct GovernedElection<T>(PhantomData<T>);
+
+impl<T: Config> ElectionStrategy<T::AccountId, BalanceOf<T>> for GovernedElection<T> {
+    fn elect(
+        candidates: Vec<ElectionCandidate<T::AccountId, BalanceOf<T>>>,
+        count: usize,
+    ) -> Vec<T::AccountId> {
+        elect_with(Pallet::<T>::election_strategy(), candidates, count)
+    }
+}
+
+/// Run each of `strategies` on the same `candidates` and return the validators each elects.
+pub fn simulate<AccountId, Balance>(
+    candidates: &[ElectionCandidate<AccountId, Balance>],
+    count: usize,
+    strategies: &[ElectionStrategyKind],
+) -> Vec<(ElectionStrategyKind, Vec<AccountId>)>
+where
+    AccountId: Ord + Clone,
+    Balance: AtLeast32BitUnsigned + Copy,
+{
+    strategies
+        .iter()
+        .map(|kind| (*kind, elect_with(*kind, candidates.to_vec(), count)))
+        .collect()
+}
@@ -0,0 +1,86 @@
+// Copyright (C) 2021 Deeper Network Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Emission schedule of the mining reward.
+//!
+//! Once governance sets an [`EmissionSchedule`] with `set_emission_schedule`, the mining reward
+//! of each era follows the schedule instead of `EraValidatorReward`: the era budget decays from
+//! one period to the next and is split between the validators of the era and the delegators.
+//! The schedule still draws on the remaining mining reward and stops once it runs out.
+
+use crate::EraIndex;
+use codec::{Decode, Encode};
+use scale_info::TypeInfo;
+use sp_runtime::{
+    traits::{AtLeast32BitUnsigned, Saturating, Zero},
+    Perbill, RuntimeDebug,
+};
+use sp_std::vec::Vec;
+
+/// A decaying emission of the mining reward.
+#[derive(Clone, PartialEq, Eq, Encode, Decode, RuntimeDebug, TypeInfo)]
+pub struct EmissionSchedule<Balance> {
+    /// The era the schedule starts in, eras before it emit nothing.
+    pub start_era: EraIndex,
+    /// The budget of each era of the first period.
+    pub initial_era_budget: Balance,
+    /// The number of eras in a period, the budget stays the same within a period.
+    pub period: EraIndex,
+    /// The part of the era budget kept from one period to the next, 50% halves it. Must be below
+    /// 100%.
+    pub decay: Perbill,
+    /// The part of the era budget paid to the validators, the rest goes to the delegators.
+    pub validator_share: Perbill,
+}
+
+/// The mining reward emitted in an era.
+#[derive(Clone, PartialEq, Eq, Encode, Decode, RuntimeDebug, TypeInfo)]
+pub struct EraEmission<Balance> {
+    pub era: EraIndex,
+    /// The part paid to the validators of the era.
+    pub validators: Balance,
+    /// The part added to the delegator reward pool.
+    pub delegators: Balance,
+}
+
+impl<Balance: AtLeast32BitUnsigned + Copy> EmissionSchedule<Balance> {
+    /// The budget of `era`.
+    pub fn era_budget(&self, era: EraIndex) -> Balance {
+        if era < self.start_era || self.period == 0 {
+            return Zero::zero();
+        }
+        let periods = (era - self.start_era) / self.period;
+        self.decay.saturating_pow(periods as usize) * self.initial_era_budget
+    }
+
+    /// The budget of `era` split between the validators and the delegators.
+    pub fn emission(&self, era: EraIndex) -> EraEmission<Balance> {
+        let budget = self.era_budget(era);
+        let validators = self.validator_share * budget;
+        EraEmission {
+            era,
+            validators,
+            delegators: budget.saturating_sub(validators),
+        }
+    }
+
+    /// The emission of `count` eras from `from` on.
+    pub fn project(&self, from: EraIndex, count: u32) -> Vec<EraEmission<Balance>> {
+        (0..count)
+            .filter_map(|i| from.checked_add(i))
+            .map(|era| self.emission(era))
+            .collect()
+    }
+}
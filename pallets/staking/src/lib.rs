@@ -27,6 +27,8 @@ mod mock;
 
 #[cfg(any(feature = "runtime-benchmarks", test))]
 pub mod benchmarking;
+pub mod election;
+pub mod emission;
 pub mod slashing;
 #[cfg(any(feature = "runtime-benchmarks", test))]
 pub mod testing_utils;
@@ -35,6 +37,8 @@ mod tests;
 pub mod weights;
 
 use codec::{Decode, Encode, HasCompact};
+pub use election::{ElectionCandidate, ElectionStrategy, ElectionStrategyKind, GovernedElection};
+pub use emission::{EmissionSchedule, EraEmission};
 use frame_support::{
     dispatch::{DispatchResult, DispatchResultWithPostInfo},
     ensure,
@@ -43,9 +47,10 @@ use frame_support::{
         Currency, EnsureOrigin, Get, Imbalance, IsSubType, LockIdentifier, LockableCurrency,
         OnUnbalanced, UnixTime, WithdrawReasons,
     },
+    transactional,
     weights::{
         constants::{WEIGHT_PER_MICROS, WEIGHT_PER_NANOS},
-        Weight,
+        DispatchClass, Weight,
     },
 };
 use frame_system::{ensure_root, ensure_signed, offchain::SendTransactionTypes, pallet_prelude::*};
@@ -69,8 +74,8 @@ use sp_staking::{
     SessionIndex,
 };
 use sp_std::{
-    cmp, cmp::Ordering, collections::btree_map::BTreeMap, collections::btree_set::BTreeSet,
-    convert::From, convert::TryInto, prelude::*,
+    cmp, collections::btree_map::BTreeMap, collections::btree_set::BTreeSet, convert::From,
+    convert::TryInto, prelude::*,
 };
 pub use weights::WeightInfo;
 
@@ -179,10 +184,28 @@ pub struct RewardData<Balance: HasCompact> {
     pub poc_reward: Balance,
 }
 
+/// A reward payout to a delegator, kept in the delegator's reward history.
+#[derive(Encode, Decode, RuntimeDebug, TypeInfo, Clone, PartialEq, Eq)]
+pub struct DelegatorRewardRecord<AccountId, Balance> {
+    /// The first era paid for.
+    pub from_era: EraIndex,
+    /// The last era paid for.
+    pub to_era: EraIndex,
+    pub referee_reward: Balance,
+    pub poc_reward: Balance,
+    /// The amount paid to the delegator, at most the remaining mining reward.
+    pub paid: Balance,
+    /// The amount paid to the validators as commission on top of `paid`.
+    pub commission: Balance,
+    /// The validators the delegator delegated to when paid.
+    pub validators: Vec<AccountId>,
+}
+
 /// Preference of what happens regarding validation.
 #[derive(PartialEq, Eq, Clone, Encode, Decode, RuntimeDebug, TypeInfo)]
 pub struct ValidatorPrefs {
-    /// not used. It may be removed in future.
+    /// The validator's cut of the rewards of the delegators delegating to it, at most
+    /// `MaxCommission`. An increase takes effect `CommissionChangeDelay` eras after it is set.
     #[codec(compact)]
     pub commission: Perbill,
     /// Whether or not this validator is accepting more delegations. If `true`, then no delegator
@@ -349,7 +372,16 @@ pub struct Exposure<AccountId, Balance: HasCompact> {
     #[codec(compact)]
     pub own: Balance,
     /// The delegators that are exposed.
-    pub others: Vec<AccountId>,
+    pub others: Vec<IndividualExposure<AccountId>>,
+}
+
+/// The credit a delegator contributes to a single validator.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Encode, Decode, RuntimeDebug, TypeInfo)]
+pub struct IndividualExposure<AccountId> {
+    /// The delegator.
+    pub who: AccountId,
+    /// The part of the delegator's credit delegated to the validator.
+    pub weight: u64,
 }
 
 impl<AccountId, Balance: Default + HasCompact> Default for Exposure<AccountId, Balance> {
@@ -559,11 +591,13 @@ enum Releases {
     V3_0_0,
     V4_0_0,
     V5_0_0,
+    V6_0_0,
+    V7_0_0,
 }
 
 impl Default for Releases {
     fn default() -> Self {
-        Releases::V5_0_0
+        Releases::V7_0_0
     }
 }
 
@@ -592,6 +626,27 @@ pub mod pallet {
         /// max delegates can be selected by one delegator
         type MaxDelegates: Get<usize>;
 
+        /// Selects the validators of each era, see [`election`].
+        type ElectionStrategy: ElectionStrategy<Self::AccountId, BalanceOf<Self>>;
+
+        /// max reward payouts kept in the reward history of a delegator
+        type MaxDelegatorRewardHistory: Get<u32>;
+
+        /// max delegators paid automatically per block. The payouts of an era go on from the
+        /// delegator the previous era stopped at, so delegators not reached in an era are paid
+        /// first in the next one, or claim their rewards with `claim_delegator_reward`
+        type MaxDelegatorPayoutsPerBlock: Get<u32>;
+
+        /// max eras paid by one `claim_delegator_reward`
+        #[pallet::constant]
+        type MaxClaimEras: Get<EraIndex>;
+
+        /// max commission a validator can take from the rewards of its delegators
+        type MaxCommission: Get<Perbill>;
+
+        /// number of eras before a commission increase takes effect
+        type CommissionChangeDelay: Get<EraIndex>;
+
         /// Time used for computing era duration.
         ///
         /// It is guaranteed to start being called from the first `on_finalize`. Thus value at genesis
@@ -859,6 +914,26 @@ pub mod pallet {
     #[pallet::getter(fn remainder_mining_reward)]
     pub type RemainderMiningReward<T> = StorageValue<_, u128>;
 
+    /// The emission schedule set by governance, see [`emission`].
+    #[pallet::storage]
+    #[pallet::getter(fn emission_schedule)]
+    pub type CurrentEmissionSchedule<T: Config> =
+        StorageValue<_, EmissionSchedule<BalanceOf<T>>, OptionQuery>;
+
+    /// The emission for delegators not paid yet, already taken out of `RemainderMiningReward`.
+    /// While an emission schedule is set, delegators are paid out of it instead of
+    /// `RemainderMiningReward`.
+    #[pallet::storage]
+    #[pallet::getter(fn delegator_reward_pool)]
+    pub type DelegatorRewardPool<T: Config> = StorageValue<_, BalanceOf<T>, ValueQuery>;
+
+    /// delegator -> what it has been paid so far for the eras from its earliest unrewarded era
+    /// on, when the mining reward left ran out before they were paid in full
+    #[pallet::storage]
+    #[pallet::getter(fn partial_delegator_payout)]
+    pub type PartialDelegatorPayout<T: Config> =
+        StorageMap<_, Blake2_128Concat, T::AccountId, BalanceOf<T>, ValueQuery>;
+
     /// validator -> ValidatorData
     #[pallet::storage]
     #[pallet::getter(fn candidate_validators)]
@@ -871,6 +946,31 @@ pub mod pallet {
     pub(crate) type Delegators<T: Config> =
         StorageMap<_, Blake2_128Concat, T::AccountId, DelegatorData<T::AccountId>, ValueQuery>;
 
+    /// delegator -> the parts of its credit delegated to each validator, split evenly if `None`
+    #[pallet::storage]
+    #[pallet::getter(fn delegation_weights)]
+    pub(crate) type DelegationWeights<T: Config> =
+        StorageMap<_, Blake2_128Concat, T::AccountId, Vec<(T::AccountId, u32)>, OptionQuery>;
+
+    /// Commission increases of validators: the era the increase takes effect in and the new
+    /// commission. `Validators` keeps the previous commission until then.
+    #[pallet::storage]
+    #[pallet::getter(fn pending_commission)]
+    pub type PendingCommission<T: Config> =
+        StorageMap<_, Twox64Concat, T::AccountId, (EraIndex, Perbill), OptionQuery>;
+
+    /// The era the last commission increase of a validator took effect in and the lowest
+    /// commission before it, unpaid eras before that era are still charged that commission.
+    #[pallet::storage]
+    #[pallet::getter(fn previous_commission)]
+    pub type PreviousCommission<T: Config> =
+        StorageMap<_, Twox64Concat, T::AccountId, (EraIndex, Perbill), OptionQuery>;
+
+    /// The election strategy chosen by governance, used by `GovernedElection`
+    #[pallet::storage]
+    #[pallet::getter(fn election_strategy)]
+    pub type CurrentElectionStrategy<T> = StorageValue<_, ElectionStrategyKind, ValueQuery>;
+
     /// active delegator count
     #[pallet::storage]
     #[pallet::getter(fn active_delegator_count)]
@@ -885,7 +985,8 @@ pub mod pallet {
     #[pallet::getter(fn delegators_key_prefix)]
     pub(crate) type DelegatorsKeyPrefix<T> = StorageValue<_, Vec<u8>, ValueQuery>;
 
-    /// delegators last key
+    /// delegators last key, the automatic payouts go on after it and wrap around once the end
+    /// of `Delegators` is reached
     #[pallet::storage]
     #[pallet::getter(fn delegators_last_key)]
     pub(crate) type DelegatorsLastKey<T> = StorageValue<_, Vec<u8>, ValueQuery>;
@@ -905,6 +1006,23 @@ pub mod pallet {
     #[pallet::getter(fn reward)]
     pub type Reward<T: Config> =
         StorageMap<_, Blake2_128Concat, T::AccountId, RewardData<BalanceOf<T>>>;
+
+    /// (delegator, index) -> reward payout, see [`Pallet::delegator_reward_history`]
+    #[pallet::storage]
+    pub type DelegatorRewardRecords<T: Config> = StorageDoubleMap<
+        _,
+        Blake2_128Concat,
+        T::AccountId,
+        Twox64Concat,
+        u32,
+        DelegatorRewardRecord<T::AccountId, BalanceOf<T>>,
+        OptionQuery,
+    >;
+
+    /// delegator -> (index of the oldest kept payout, index of the next payout)
+    #[pallet::storage]
+    pub type DelegatorRewardRange<T: Config> =
+        StorageMap<_, Blake2_128Concat, T::AccountId, (u32, u32), ValueQuery>;
     /// True if network has been upgraded to this version.
     /// Storage version of the pallet.
     ///
@@ -958,7 +1076,7 @@ pub mod pallet {
             ForceEra::<T>::put(self.force_era);
             EraValidatorReward::<T>::put(self.era_validator_reward);
             SlashRewardFraction::<T>::put(self.slash_reward_fraction);
-            StorageVersion::<T>::put(Releases::V5_0_0);
+            StorageVersion::<T>::put(Releases::V7_0_0);
             for &(ref stash, ref controller, balance, ref status) in &self.stakers {
                 assert!(
                     T::Currency::free_balance(&stash) >= balance,
@@ -1283,6 +1401,29 @@ pub mod pallet {
             let controller = ensure_signed(origin)?;
             let ledger = Self::ledger(&controller).ok_or(Error::<T>::NotController)?;
             let stash = &ledger.stash;
+            ensure!(
+                prefs.commission <= T::MaxCommission::get(),
+                Error::<T>::CommissionTooHigh
+            );
+
+            let mut prefs = prefs;
+            let current_era = T::CreditInterface::get_current_era();
+            let commission = Self::commission_of(stash, current_era);
+            if prefs.commission > commission && <CandidateValidators<T>>::contains_key(stash) {
+                // delegators get notice of a commission increase
+                if Self::pending_commission(stash).map(|(_, c)| c) != Some(prefs.commission) {
+                    let effective_era = current_era.saturating_add(T::CommissionChangeDelay::get());
+                    <PendingCommission<T>>::insert(stash, (effective_era, prefs.commission));
+                    Self::deposit_event(Event::<T>::CommissionIncreaseScheduled(
+                        stash.clone(),
+                        prefs.commission,
+                        effective_era,
+                    ));
+                }
+                prefs.commission = commission;
+            } else {
+                <PendingCommission<T>>::remove(stash);
+            }
             <Validators<T>>::insert(stash, prefs);
             Ok(())
         }
@@ -1723,8 +1864,15 @@ pub mod pallet {
             Ok(())
         }
 
-        /// delegate credit to a set of validators
-        #[pallet::weight(T::WeightInfo::delegate(1))]
+        /// delegate credit to a set of validators, split evenly among them
+        ///
+        /// The eras the delegator has not been paid for yet are paid first, with the delegation
+        /// they were earned with.
+        #[pallet::weight(
+            T::WeightInfo::delegate(validators.len() as u32)
+                .saturating_add(Pallet::<T>::delegator_payout_weight())
+        )]
+        #[transactional]
         pub fn delegate(origin: OriginFor<T>, validators: Vec<T::AccountId>) -> DispatchResult {
             ensure!(
                 Self::era_election_status().is_closed(),
@@ -1732,97 +1880,16 @@ pub mod pallet {
             );
             let delegator = ensure_signed(origin)?;
 
-            ensure!(
-                !<Validators<T>>::contains_key(&delegator),
-                Error::<T>::CallNotAllowed
-            );
-
-            let enough_credit = T::CreditInterface::pass_threshold(&delegator);
-            ensure!(enough_credit, Error::<T>::CreditTooLow);
-
-            ensure!(!validators.is_empty(), Error::<T>::NoValidators);
-            // remove duplicates
-            let validator_set: BTreeSet<T::AccountId> = validators.iter().cloned().collect();
-            // check validators size
-            ensure!(
-                validator_set.len() <= T::MaxDelegates::get() as usize,
-                Error::<T>::TooManyValidators
-            );
-            for validator in &validator_set {
-                ensure!(
-                    <Validators<T>>::contains_key(&validator),
-                    Error::<T>::NotValidator
-                );
-            }
-
-            let current_era = T::CreditInterface::get_current_era();
-            if <Delegators<T>>::contains_key(&delegator) {
-                let old_delegator_data = Self::delegators(&delegator);
-                if !old_delegator_data.delegating {
-                    // the delegator was not delegating
-                    // the delegator delegates again
-                    ActiveDelegatorCount::<T>::mutate(|count| *count = count.saturating_add(1));
-                }
-                let earliest_unrewarded_era = match old_delegator_data.unrewarded_since {
-                    Some(unrewarded_era) => unrewarded_era,
-                    None => current_era,
-                };
-                let delegator_data = DelegatorData {
-                    delegator: delegator.clone(),
-                    delegated_validators: validators.clone(),
-                    unrewarded_since: Some(earliest_unrewarded_era),
-                    delegating: true,
-                };
-                <Delegators<T>>::insert(&delegator, delegator_data);
-
-                for validator in &old_delegator_data.delegated_validators {
-                    <CandidateValidators<T>>::mutate(validator, |v| {
-                        v.delegators.remove(&delegator);
-                    });
-                    let candidate = Self::candidate_validators(validator);
-                    if candidate.delegators.is_empty() {
-                        <CandidateValidators<T>>::remove(validator);
-                    }
-                }
-            } else {
-                let delegator_data = DelegatorData {
-                    delegator: delegator.clone(),
-                    delegated_validators: validators.clone(),
-                    unrewarded_since: Some(current_era),
-                    delegating: true,
-                };
-                <Delegators<T>>::insert(&delegator, delegator_data);
-                ActiveDelegatorCount::<T>::mutate(|count| *count = count.saturating_add(1));
-                DelegatorCount::<T>::mutate(|count| *count = count.saturating_add(1));
-                //  delegator must has enough credit score,so this init must success
-                T::CreditInterface::init_delegator_history(&delegator, current_era);
-            };
-
-            for validator in &validator_set {
-                if <CandidateValidators<T>>::contains_key(validator) {
-                    <CandidateValidators<T>>::mutate(validator, |v| {
-                        v.delegators.insert(delegator.clone());
-                    });
-                } else {
-                    let mut delegators = BTreeSet::new();
-                    delegators.insert(delegator.clone());
-                    let elected_era = EraIndex::default();
-                    <CandidateValidators<T>>::insert(
-                        validator,
-                        ValidatorData {
-                            delegators,
-                            elected_era,
-                        },
-                    );
-                }
-            }
-
-            Self::deposit_event(Event::<T>::Delegated(delegator, validators));
+            Self::do_delegate(delegator.clone(), validators)?;
+            <DelegationWeights<T>>::remove(&delegator);
             Ok(())
         }
 
-        /// undelegate credit from the validators
-        #[pallet::weight(T::WeightInfo::undelegate())]
+        /// undelegate credit from the validators, the eras the delegator has not been paid for
+        /// yet are paid first
+        #[pallet::weight(
+            T::WeightInfo::undelegate().saturating_add(Pallet::<T>::delegator_payout_weight())
+        )]
         pub fn undelegate(origin: OriginFor<T>) -> DispatchResult {
             let delegator = ensure_signed(origin)?;
             ensure!(
@@ -1843,9 +1910,7 @@ pub mod pallet {
             poc_reward: BalanceOf<T>,
         ) -> DispatchResult {
             ensure_root(origin)?;
-            let remainder_mining_reward = T::NumberToCurrency::convert(
-                Self::remainder_mining_reward().unwrap_or(T::TotalMiningReward::get()),
-            );
+            let remainder_mining_reward = Self::delegator_reward_available();
 
             // update RewardData
             if Reward::<T>::contains_key(&delegator) {
@@ -1874,11 +1939,20 @@ pub mod pallet {
             }
             let reward = cmp::min(remainder_mining_reward, referee_reward + poc_reward);
             let imbalance = T::Currency::deposit_creating(&delegator, reward);
-            RemainderMiningReward::<T>::put(
-                TryInto::<u128>::try_into(remainder_mining_reward.saturating_sub(reward))
-                    .ok()
-                    .unwrap(),
+            let current_era = T::CreditInterface::get_current_era();
+            Self::record_delegator_reward(
+                &delegator,
+                DelegatorRewardRecord {
+                    from_era: current_era,
+                    to_era: current_era,
+                    referee_reward,
+                    poc_reward,
+                    paid: reward,
+                    commission: Zero::zero(),
+                    validators: Self::delegators(&delegator).delegated_validators,
+                },
             );
+            Self::set_delegator_reward_available(remainder_mining_reward.saturating_sub(reward));
             Self::deposit_event(Event::CompensationDelegatorReward(
                 delegator.clone(),
                 imbalance.peek(),
@@ -1886,17 +1960,151 @@ pub mod pallet {
 
             Ok(())
         }
+
+        /// delegate credit to a set of validators, split in proportion to the given parts
+        #[pallet::weight(
+            T::WeightInfo::delegate(validators.len() as u32)
+                .saturating_add(Pallet::<T>::delegator_payout_weight())
+        )]
+        #[transactional]
+        pub fn delegate_with_weights(
+            origin: OriginFor<T>,
+            validators: Vec<(T::AccountId, u32)>,
+        ) -> DispatchResult {
+            ensure!(
+                Self::era_election_status().is_closed(),
+                Error::<T>::CallNotAllowed
+            );
+            let delegator = ensure_signed(origin)?;
+            ensure!(
+                validators.iter().all(|(_, part)| *part > 0),
+                Error::<T>::ZeroDelegationWeight
+            );
+
+            // merge duplicates
+            let mut parts: BTreeMap<T::AccountId, u32> = BTreeMap::new();
+            for (validator, part) in validators {
+                let merged = parts.entry(validator).or_default();
+                *merged = merged.saturating_add(part);
+            }
+            let parts: Vec<(T::AccountId, u32)> = parts.into_iter().collect();
+            let targets = parts.iter().map(|(v, _)| v.clone()).collect();
+
+            Self::do_delegate(delegator.clone(), targets)?;
+            <DelegationWeights<T>>::insert(&delegator, parts);
+            Ok(())
+        }
+
+        /// set the strategy `GovernedElection` elects validators with from the next era on
+        #[pallet::weight(10_000 + T::DbWeight::get().writes(1))]
+        pub fn set_election_strategy(
+            origin: OriginFor<T>,
+            strategy: ElectionStrategyKind,
+        ) -> DispatchResult {
+            ensure_root(origin)?;
+            CurrentElectionStrategy::<T>::put(strategy);
+            Self::deposit_event(Event::<T>::ElectionStrategySet(strategy));
+            Ok(())
+        }
+
+        /// pay the rewards of a delegator up to era `to`, any account can claim on behalf of
+        /// the delegator. At most `MaxClaimEras` eras are paid at once, counted from the earliest
+        /// unrewarded era of the delegator, a longer backlog is claimed in several calls.
+        ///
+        /// `from` is the first era the caller expects to be paid. Eras before the earliest
+        /// unrewarded era were paid already and are skipped, but a `from` after it is rejected
+        /// rather than leaving the eras in between unpaid for good. Only the delegator may claim
+        /// a part of its unpaid eras, other callers claim up to the last era over, or the last
+        /// era `MaxClaimEras` allows.
+        #[pallet::weight(Pallet::<T>::delegator_payout_weight())]
+        pub fn claim_delegator_reward(
+            origin: OriginFor<T>,
+            delegator: T::AccountId,
+            from: EraIndex,
+            to: EraIndex,
+        ) -> DispatchResultWithPostInfo {
+            let who = ensure_signed(origin)?;
+            let current_era = T::CreditInterface::get_current_era();
+            ensure!(from <= to && to < current_era, Error::<T>::InvalidEraRange);
+            ensure!(
+                <Delegators<T>>::contains_key(&delegator),
+                Error::<T>::NotDelegator
+            );
+            let delegator_data = Self::delegators(&delegator);
+            let earliest_unrewarded_era = delegator_data.unrewarded_since.unwrap_or(current_era);
+            ensure!(earliest_unrewarded_era <= to, Error::<T>::NoUnclaimedReward);
+            ensure!(
+                from <= earliest_unrewarded_era,
+                Error::<T>::UnclaimedErasSkipped
+            );
+            ensure!(
+                to - earliest_unrewarded_era < T::MaxClaimEras::get(),
+                Error::<T>::TooManyClaimEras
+            );
+            if who != delegator {
+                let last_era = cmp::min(
+                    current_era - 1,
+                    earliest_unrewarded_era + T::MaxClaimEras::get() - 1,
+                );
+                ensure!(to == last_era, Error::<T>::PartialClaimNotAllowed);
+            }
+            let remainder_mining_reward = Self::delegator_reward_available();
+            ensure!(
+                !remainder_mining_reward.is_zero(),
+                Error::<T>::NoUnclaimedReward
+            );
+
+            let (payout, _, payout_weight) =
+                Self::pay_delegator(&delegator_data, to, current_era, remainder_mining_reward);
+            Self::set_delegator_reward_available(remainder_mining_reward.saturating_sub(payout));
+            Ok(Some(
+                payout_weight
+                    .saturating_add(10_000)
+                    .saturating_add(T::DbWeight::get().reads_writes(4, 1)),
+            )
+            .into())
+        }
+
+        /// set the emission schedule of the mining reward, `None` goes back to paying
+        /// `EraValidatorReward` out of the remaining mining reward. The delegator reward pool
+        /// carries over to a new schedule and goes back to the remaining mining reward otherwise.
+        #[pallet::weight(10_000 + T::DbWeight::get().reads_writes(2, 3))]
+        pub fn set_emission_schedule(
+            origin: OriginFor<T>,
+            schedule: Option<EmissionSchedule<BalanceOf<T>>>,
+        ) -> DispatchResult {
+            ensure_root(origin)?;
+            if let Some(schedule) = &schedule {
+                ensure!(
+                    schedule.period > 0 && schedule.decay < Perbill::one(),
+                    Error::<T>::InvalidEmissionSchedule
+                );
+            } else {
+                Self::return_delegator_reward_pool();
+            }
+            CurrentEmissionSchedule::<T>::set(schedule.clone());
+            Self::deposit_event(Event::<T>::EmissionScheduleSet(schedule));
+            Ok(())
+        }
     }
 
     #[pallet::hooks]
     impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {
         fn on_runtime_upgrade() -> frame_support::weights::Weight {
+            let mut weight: Weight = 0;
             if StorageVersion::<T>::get() == Releases::V4_0_0 {
                 StorageVersion::<T>::put(Releases::V5_0_0);
-                migrations::migrate_to_blockable::<T>()
-            } else {
-                0
+                weight = weight.saturating_add(migrations::migrate_to_blockable::<T>());
+            }
+            if StorageVersion::<T>::get() == Releases::V5_0_0 {
+                StorageVersion::<T>::put(Releases::V6_0_0);
+                weight = weight.saturating_add(migrations::migrate_to_weighted_exposure::<T>());
             }
+            if StorageVersion::<T>::get() == Releases::V6_0_0 {
+                StorageVersion::<T>::put(Releases::V7_0_0);
+                weight = weight.saturating_add(migrations::clamp_commissions::<T>());
+            }
+            weight
         }
 
         fn on_initialize(now: T::BlockNumber) -> Weight {
@@ -1915,11 +2123,23 @@ pub mod pallet {
                     if Self::delegator_count() % (blocks_per_era - 2) > 0 {
                         delegator_payouts_per_block += 1;
                     }
+                    // keep the block weight bounded, delegators not reached this era are paid
+                    // first in the next era or claim their rewards
+                    delegator_payouts_per_block = cmp::min(
+                        delegator_payouts_per_block,
+                        T::MaxDelegatorPayoutsPerBlock::get(),
+                    );
                     DelegatorPayoutsPerBlock::<T>::put(delegator_payouts_per_block);
                     let prefix = Self::get_delegators_prefix_hash();
                     DelegatorsKeyPrefix::<T>::put(prefix.clone());
-                    DelegatorsLastKey::<T>::put(prefix);
-                    weight = weight.saturating_add(T::DbWeight::get().reads_writes(3, 1));
+                    // the payouts go on from where the last era stopped
+                    if !Self::delegators_last_key().starts_with(&prefix) {
+                        DelegatorsLastKey::<T>::put(prefix);
+                    }
+                    weight = weight.saturating_add(T::DbWeight::get().reads_writes(4, 2));
+                    weight = weight.saturating_add(Self::promote_commissions(
+                        T::CreditInterface::get_current_era(),
+                    ));
                 } else {
                     weight = weight.saturating_add(Self::pay_delegators());
                 }
@@ -1979,6 +2199,18 @@ pub mod pallet {
         CompensationDelegatorReward(T::AccountId, BalanceOf<T>),
         /// The validator  has been rewarded by this amount. \[account_id, amount\]
         ValidatorReward(T::AccountId, BalanceOf<T>),
+        /// The election strategy has been set
+        ElectionStrategySet(ElectionStrategyKind),
+        /// A validator raises its commission from the given era on. \[stash, commission, era\]
+        CommissionIncreaseScheduled(T::AccountId, Perbill, EraIndex),
+        /// A validator has been paid commission on the reward of a delegator.
+        /// \[validator, delegator, amount\]
+        DelegatorRewardCommission(T::AccountId, T::AccountId, BalanceOf<T>),
+        /// The emission schedule has been set
+        EmissionScheduleSet(Option<EmissionSchedule<BalanceOf<T>>>),
+        /// The delegator reward pool left once every delegator was paid went back to the
+        /// remaining mining reward. \[amount\]
+        DelegatorRewardPoolReturned(BalanceOf<T>),
     }
 
     /// Error for the staking module.
@@ -2032,6 +2264,25 @@ pub mod pallet {
         TooManyValidators,
         /// No candidate validator has been selected
         NoValidators,
+        /// A delegation weight of zero
+        ZeroDelegationWeight,
+        /// The era range is empty or not over yet
+        InvalidEraRange,
+        /// The delegator has no reward to claim in the era range
+        NoUnclaimedReward,
+        /// The era range starts after an era the delegator has not been rewarded for
+        UnclaimedErasSkipped,
+        /// The commission is above `MaxCommission`
+        CommissionTooHigh,
+        /// An emission schedule with a period of zero eras or a decay of 100%
+        InvalidEmissionSchedule,
+        /// The era range pays more than `MaxClaimEras` eras
+        TooManyClaimEras,
+        /// Only the delegator may claim a part of its unpaid eras
+        PartialClaimNotAllowed,
+        /// The delegator has unpaid eras which can't be paid at once, they are claimed before
+        /// the delegation changes
+        DelegatorRewardUnsettled,
     }
 }
 
@@ -2056,6 +2307,47 @@ pub mod migrations {
         ErasValidatorPrefs::<T>::translate::<OldValidatorPrefs, _>(|_, _, p| Some(p.upgraded()));
         T::BlockWeights::get().max_block
     }
+
+    #[derive(Decode)]
+    struct OldExposure<AccountId, Balance: HasCompact> {
+        #[codec(compact)]
+        total: Balance,
+        #[codec(compact)]
+        own: Balance,
+        others: Vec<AccountId>,
+    }
+    impl<AccountId, Balance: HasCompact> OldExposure<AccountId, Balance> {
+        fn upgraded(self) -> Exposure<AccountId, Balance> {
+            Exposure {
+                total: self.total,
+                own: self.own,
+                // the weights of past eras are unknown
+                others: self
+                    .others
+                    .into_iter()
+                    .map(|who| IndividualExposure { who, weight: 0 })
+                    .collect(),
+            }
+        }
+    }
+    pub fn migrate_to_weighted_exposure<T: Config>() -> frame_support::weights::Weight {
+        ErasStakers::<T>::translate::<OldExposure<T::AccountId, BalanceOf<T>>, _>(|_, _, e| {
+            Some(e.upgraded())
+        });
+        T::BlockWeights::get().max_block
+    }
+
+    /// Commissions were not bounded before `MaxCommission`, clamp the commissions set before.
+    pub fn clamp_commissions<T: Config>() -> frame_support::weights::Weight {
+        let max_commission = T::MaxCommission::get();
+        let mut count: Weight = 0;
+        Validators::<T>::translate::<ValidatorPrefs, _>(|_, mut prefs| {
+            count += 1;
+            prefs.commission = prefs.commission.min(max_commission);
+            Some(prefs)
+        });
+        T::DbWeight::get().reads_writes(count, count)
+    }
 }
 
 impl<T: Config> pallet::Pallet<T> {
@@ -2087,6 +2379,8 @@ impl<T: Config> pallet::Pallet<T> {
     /// Chill a stash account.
     fn chill_stash(stash: &T::AccountId) {
         <Validators<T>>::remove(stash);
+        <PendingCommission<T>>::remove(stash);
+        <PreviousCommission<T>>::remove(stash);
     }
 
     /// Plan a new session potentially trigger a new era.
@@ -2206,13 +2500,38 @@ impl<T: Config> pallet::Pallet<T> {
 
     /// pay validator rewards based on their reward points
     fn pay_validators(era: EraIndex) {
+        let emission_schedule = Self::emission_schedule();
         let remainder_mining_reward = T::NumberToCurrency::convert(
             Self::remainder_mining_reward().unwrap_or(T::TotalMiningReward::get()),
         );
-        if remainder_mining_reward == Zero::zero() {
+        let (era_payout, delegator_emission) = match &emission_schedule {
+            Some(schedule) => {
+                // the schedule never emits more than the remaining mining reward
+                let emission = schedule.emission(era);
+                let validators = cmp::min(emission.validators, remainder_mining_reward);
+                let delegators = cmp::min(
+                    emission.delegators,
+                    remainder_mining_reward.saturating_sub(validators),
+                );
+                DelegatorRewardPool::<T>::mutate(|pool| *pool = pool.saturating_add(delegators));
+                (validators, delegators)
+            }
+            None => (
+                cmp::min(Self::era_validator_reward(), remainder_mining_reward),
+                Zero::zero(),
+            ),
+        };
+        let remainder_mining_reward = remainder_mining_reward.saturating_sub(delegator_emission);
+        if era_payout == Zero::zero() {
+            if !delegator_emission.is_zero() {
+                RemainderMiningReward::<T>::put(
+                    TryInto::<u128>::try_into(remainder_mining_reward)
+                        .ok()
+                        .unwrap(),
+                );
+            }
             return;
         }
-        let era_payout = cmp::min(Self::era_validator_reward(), remainder_mining_reward);
         let mut total_payout = Zero::zero();
         let era_reward_points = <ErasRewardPoints<T>>::get(&era);
         let total_reward_points = era_reward_points.total;
@@ -2245,6 +2564,45 @@ impl<T: Config> pallet::Pallet<T> {
         );
     }
 
+    /// The mining reward left to pay delegators: the delegator reward pool while an emission
+    /// schedule is set, the remaining mining reward otherwise.
+    fn delegator_reward_available() -> BalanceOf<T> {
+        if Self::emission_schedule().is_some() {
+            Self::delegator_reward_pool()
+        } else {
+            T::NumberToCurrency::convert(
+                Self::remainder_mining_reward().unwrap_or(T::TotalMiningReward::get()),
+            )
+        }
+    }
+
+    /// Store what is left to pay delegators after paying them out of
+    /// `delegator_reward_available`.
+    fn set_delegator_reward_available(available: BalanceOf<T>) {
+        if Self::emission_schedule().is_some() {
+            DelegatorRewardPool::<T>::put(available);
+        } else {
+            RemainderMiningReward::<T>::put(TryInto::<u128>::try_into(available).ok().unwrap());
+        }
+    }
+
+    /// Move the delegator reward pool back to the remaining mining reward, returns the amount
+    /// moved.
+    fn return_delegator_reward_pool() -> BalanceOf<T> {
+        let pool = DelegatorRewardPool::<T>::take();
+        if !pool.is_zero() {
+            let remainder_mining_reward = T::NumberToCurrency::convert(
+                Self::remainder_mining_reward().unwrap_or(T::TotalMiningReward::get()),
+            );
+            RemainderMiningReward::<T>::put(
+                TryInto::<u128>::try_into(remainder_mining_reward.saturating_add(pool))
+                    .ok()
+                    .unwrap(),
+            );
+        }
+        pool
+    }
+
     /// Validators can set reward destination or payee, so we need to handle that.
     fn make_validator_payout(
         stash: &T::AccountId,
@@ -2272,10 +2630,8 @@ impl<T: Config> pallet::Pallet<T> {
 
     /// Pay delegators based on their credit
     fn pay_delegators() -> Weight {
-        let mut remainder_mining_reward = T::NumberToCurrency::convert(
-            Self::remainder_mining_reward().unwrap_or(T::TotalMiningReward::get()),
-        );
-        let mut weight = T::DbWeight::get().reads_writes(1, 0);
+        let mut remainder_mining_reward = Self::delegator_reward_available();
+        let mut weight = T::DbWeight::get().reads_writes(2, 0);
         if remainder_mining_reward == Zero::zero() {
             return weight;
         }
@@ -2283,6 +2639,7 @@ impl<T: Config> pallet::Pallet<T> {
         let mut last_key = Self::delegators_last_key(); // 1 read
         let mut next_key = Self::next_delegators_key(&last_key); // 1 read
         let mut counter = 0;
+        let mut pass_finished = false;
         let delegator_payouts_per_block = Self::delegator_payouts_per_block(); // 1 read
         let current_era = T::CreditInterface::get_current_era(); // 1 read
 
@@ -2290,53 +2647,104 @@ impl<T: Config> pallet::Pallet<T> {
         while next_key.starts_with(&prefix) && counter < delegator_payouts_per_block {
             let optional_delegator_data = Self::get_delegator_data(&next_key); // 1 read
             weight = weight.saturating_add(T::DbWeight::get().reads_writes(1, 0));
-            if optional_delegator_data.is_none() {
-                break;
-            }
-            let delegator_data = optional_delegator_data.unwrap();
-            let (payout, payout_weight) =
-                Self::pay_delegator(&delegator_data, current_era, remainder_mining_reward);
-            weight = weight.saturating_add(payout_weight);
-            remainder_mining_reward = remainder_mining_reward.saturating_sub(payout);
-            if remainder_mining_reward == Zero::zero() {
-                break;
+            // the cursor is kept across eras, so an undecodable entry is skipped rather than
+            // stopping the payouts for good
+            if let Some(delegator_data) = optional_delegator_data {
+                // a delegator who could not be paid in full empties what is available, so the
+                // cursor stays before them and they are paid first once there is more
+                let (payout, _, payout_weight) = Self::pay_delegator(
+                    &delegator_data,
+                    current_era.saturating_sub(1),
+                    current_era,
+                    remainder_mining_reward,
+                );
+                weight = weight.saturating_add(payout_weight);
+                remainder_mining_reward = remainder_mining_reward.saturating_sub(payout);
+                if remainder_mining_reward == Zero::zero() {
+                    break;
+                }
             }
             last_key = next_key.clone();
             next_key = Self::next_delegators_key(&last_key); // 1 read
             weight = weight.saturating_add(T::DbWeight::get().reads_writes(1, 0));
             counter += 1;
         }
-        if counter == delegator_payouts_per_block {
-            // might not be over yet
-            DelegatorsLastKey::<T>::put(last_key); // persist the last key for next block
-            weight = weight.saturating_add(T::DbWeight::get().reads_writes(0, 1));
+        if next_key.starts_with(&prefix) {
+            // persist the last key for the next block, or the next era
+            DelegatorsLastKey::<T>::put(last_key);
+        } else {
+            // every delegator was visited, start over from the first one in the next era
+            DelegatorsLastKey::<T>::put(prefix);
+            DelegatorPayoutsPerBlock::<T>::put(0);
+            pass_finished = true;
+        }
+        weight = weight.saturating_add(T::DbWeight::get().reads_writes(0, 2));
+        Self::set_delegator_reward_available(remainder_mining_reward);
+        weight = weight.saturating_add(T::DbWeight::get().reads_writes(1, 1));
+        if pass_finished && Self::emission_schedule().is_some() {
+            // every delegator has been paid in full, what is left of the pool was not earned
+            let returned = Self::return_delegator_reward_pool();
+            if !returned.is_zero() {
+                Self::deposit_event(Event::<T>::DelegatorRewardPoolReturned(returned));
+            }
+            weight = weight.saturating_add(T::DbWeight::get().reads_writes(3, 2));
         }
-        RemainderMiningReward::<T>::put(
-            TryInto::<u128>::try_into(remainder_mining_reward)
-                .ok()
-                .unwrap(),
-        );
-        weight.saturating_add(T::DbWeight::get().reads_writes(0, 1))
+        weight
     }
 
+    /// Pay a delegator for the eras from the earliest unrewarded one up to `to_era`, as far as
+    /// `remainder_mining_reward` goes. The eras stay unrewarded until they are paid in full,
+    /// what was paid of them so far is kept in `PartialDelegatorPayout`. Returns the payout and
+    /// whether the eras were paid in full.
     fn pay_delegator(
         delegator_data: &DelegatorData<T::AccountId>,
+        to_era: EraIndex,
         current_era: EraIndex,
         remainder_mining_reward: BalanceOf<T>,
-    ) -> (BalanceOf<T>, Weight) {
+    ) -> (BalanceOf<T>, bool, Weight) {
         let earliest_unrewarded_era = delegator_data.unrewarded_since.unwrap_or(current_era);
-        if earliest_unrewarded_era == current_era {
-            return (BalanceOf::<T>::zero(), Weight::zero());
+        if earliest_unrewarded_era > to_era || to_era >= current_era {
+            return (BalanceOf::<T>::zero(), true, Weight::zero());
         }
 
         let delegator = &delegator_data.delegator;
         let mut payout = BalanceOf::<T>::zero();
+        let mut paid_in_full = true;
         let mut weight = T::DbWeight::get().reads_writes(1, 0); // for im_ever_online
 
         let (rewards, get_reward_weight) =
-            T::CreditInterface::get_reward(delegator, earliest_unrewarded_era, current_era - 1);
+            T::CreditInterface::get_reward(delegator, earliest_unrewarded_era, to_era);
         weight = weight.saturating_add(get_reward_weight);
         if let Some((referee_reward, poc_reward)) = rewards {
+            let paid_before = Self::partial_delegator_payout(delegator); // 1 read
+            let owed = (referee_reward + poc_reward).saturating_sub(paid_before);
+            let reward = cmp::min(remainder_mining_reward, owed);
+            paid_in_full = reward == owed;
+            weight = weight.saturating_add(T::DbWeight::get().reads_writes(1, 1));
+            if !paid_in_full {
+                PartialDelegatorPayout::<T>::insert(delegator, paid_before + reward); // 1 write
+                if !reward.is_zero() {
+                    let (commission, commission_weight) = Self::pay_commissions(
+                        delegator_data,
+                        reward,
+                        earliest_unrewarded_era,
+                        to_era,
+                    );
+                    weight = weight.saturating_add(commission_weight);
+                    Self::deposit_delegator_reward(
+                        delegator_data,
+                        earliest_unrewarded_era,
+                        to_era,
+                        (referee_reward, poc_reward),
+                        reward,
+                        commission,
+                    ); // 1 read, up to 4 writes
+                    weight = weight.saturating_add(T::DbWeight::get().reads_writes(1, 4));
+                }
+                return (reward, false, weight);
+            }
+            // 1 write
+            PartialDelegatorPayout::<T>::remove(delegator);
             // update RewardData
             if Reward::<T>::contains_key(delegator) {
                 // 1 read
@@ -2365,26 +2773,153 @@ impl<T: Config> pallet::Pallet<T> {
                 Reward::<T>::insert(delegator, reward_data); // 1 write
                 weight = weight.saturating_add(T::DbWeight::get().reads_writes(0, 1));
             }
-            let reward = cmp::min(remainder_mining_reward, referee_reward + poc_reward);
-            let imbalance = T::Currency::deposit_creating(delegator, reward); // 1 write
-            weight = weight.saturating_add(T::DbWeight::get().reads_writes(0, 1));
-            Self::deposit_event(Event::<T>::DelegatorReward(
-                (*delegator).clone(),
-                imbalance.peek(),
-            ));
+            let (commission, commission_weight) =
+                Self::pay_commissions(delegator_data, reward, earliest_unrewarded_era, to_era);
+            weight = weight.saturating_add(commission_weight);
+            Self::deposit_delegator_reward(
+                delegator_data,
+                earliest_unrewarded_era,
+                to_era,
+                (referee_reward, poc_reward),
+                reward,
+                commission,
+            ); // 1 read, up to 4 writes
+            weight = weight.saturating_add(T::DbWeight::get().reads_writes(1, 4));
             payout = reward;
         }
-        if delegator_data.delegating {
+        if delegator_data.delegating || to_era + 1 < current_era {
             Delegators::<T>::mutate(delegator, |data| {
-                data.unrewarded_since = Some(current_era);
+                data.unrewarded_since = Some(to_era + 1);
             });
             weight = weight.saturating_add(T::DbWeight::get().reads_writes(0, 1));
         } else {
             Delegators::<T>::remove(delegator);
+            // kept by `_undelegate` until the last eras are paid
+            DelegationWeights::<T>::remove(delegator);
             DelegatorCount::<T>::mutate(|count| *count = count.saturating_sub(1));
+            weight = weight.saturating_add(T::DbWeight::get().reads_writes(1, 3));
         }
 
-        (payout, weight)
+        (payout, paid_in_full, weight)
+    }
+
+    /// Deposit `reward` less `commission` to a delegator, record it and emit the event.
+    fn deposit_delegator_reward(
+        delegator_data: &DelegatorData<T::AccountId>,
+        from_era: EraIndex,
+        to_era: EraIndex,
+        (referee_reward, poc_reward): (BalanceOf<T>, BalanceOf<T>),
+        reward: BalanceOf<T>,
+        commission: BalanceOf<T>,
+    ) {
+        let delegator = &delegator_data.delegator;
+        let imbalance = T::Currency::deposit_creating(delegator, reward.saturating_sub(commission));
+        Self::record_delegator_reward(
+            delegator,
+            DelegatorRewardRecord {
+                from_era,
+                to_era,
+                referee_reward,
+                poc_reward,
+                paid: imbalance.peek(),
+                commission,
+                validators: delegator_data.delegated_validators.clone(),
+            },
+        );
+        Self::deposit_event(Event::<T>::DelegatorReward(
+            (*delegator).clone(),
+            imbalance.peek(),
+        ));
+    }
+
+    /// Pay the eras a delegator has not been paid for yet with the delegation they were earned
+    /// with, before the delegation changes. At most `MaxClaimEras` eras are paid, returns
+    /// whether all of them were, in full.
+    fn settle_delegator_reward(delegator: &T::AccountId) -> bool {
+        if !<Delegators<T>>::contains_key(delegator) {
+            return true;
+        }
+        let delegator_data = Self::delegators(delegator);
+        let current_era = T::CreditInterface::get_current_era();
+        let earliest_unrewarded_era = match delegator_data.unrewarded_since {
+            Some(era) if era < current_era => era,
+            _ => return true,
+        };
+        let to_era = cmp::min(
+            current_era - 1,
+            earliest_unrewarded_era.saturating_add(T::MaxClaimEras::get().saturating_sub(1)),
+        );
+        let available = Self::delegator_reward_available();
+        let (payout, paid_in_full, _) =
+            Self::pay_delegator(&delegator_data, to_era, current_era, available);
+        Self::set_delegator_reward_available(available.saturating_sub(payout));
+        paid_in_full && to_era + 1 == current_era
+    }
+
+    /// The weight of paying a delegator up to `MaxClaimEras` eras.
+    pub(crate) fn delegator_payout_weight() -> Weight {
+        10_000
+            + T::DbWeight::get().reads_writes(13, 9)
+            + T::DbWeight::get().reads_writes(14, 1) * T::MaxDelegates::get() as Weight
+            + T::DbWeight::get().reads(2) * T::MaxClaimEras::get() as Weight
+    }
+
+    /// Pay the validators of a delegator their commission on `reward` earned over the eras
+    /// `from_era` to `to_era`, the part of each validator follows the delegation of the
+    /// delegator. Returns the commission paid.
+    fn pay_commissions(
+        delegator_data: &DelegatorData<T::AccountId>,
+        reward: BalanceOf<T>,
+        from_era: EraIndex,
+        to_era: EraIndex,
+    ) -> (BalanceOf<T>, Weight) {
+        let mut paid = BalanceOf::<T>::zero();
+        let mut weight = T::DbWeight::get().reads_writes(1, 0);
+        for (validator, share) in Self::delegation_shares(delegator_data) {
+            let (commission, commission_weight) =
+                Self::commission_over(&validator, share * reward, from_era, to_era);
+            weight = weight.saturating_add(commission_weight);
+            if commission.is_zero() {
+                continue;
+            }
+            if let Some(imbalance) = Self::make_validator_payout(&validator, commission) {
+                paid = paid.saturating_add(imbalance.peek());
+                Self::deposit_event(Event::<T>::DelegatorRewardCommission(
+                    validator,
+                    delegator_data.delegator.clone(),
+                    imbalance.peek(),
+                ));
+            }
+            weight = weight.saturating_add(T::DbWeight::get().reads_writes(3, 1));
+        }
+        (paid, weight)
+    }
+
+    /// The share of each validator in the delegation of a delegator.
+    fn delegation_shares(
+        delegator_data: &DelegatorData<T::AccountId>,
+    ) -> Vec<(T::AccountId, Perbill)> {
+        match Self::delegation_weights(&delegator_data.delegator) {
+            Some(parts) => {
+                let total = parts.iter().map(|(_, p)| *p as u64).sum::<u64>();
+                parts
+                    .into_iter()
+                    .map(|(v, p)| (v, Perbill::from_rational(p as u64, total)))
+                    .collect()
+            }
+            None => {
+                let validators: BTreeSet<_> = delegator_data
+                    .delegated_validators
+                    .iter()
+                    .cloned()
+                    .collect();
+                let total = validators.len() as u64;
+                validators
+                    .into_iter()
+                    .map(|v| (v, Perbill::from_rational(1, total)))
+                    .collect()
+            }
+        }
     }
 
     /// Compute payout for era.
@@ -2425,13 +2960,12 @@ impl<T: Config> pallet::Pallet<T> {
         IsCurrentSessionFinal::<T>::put(false);
     }
 
-    fn trusted_validator(account: &T::AccountId) -> bool {
-        let whitelist = Self::validator_whitelist();
-        whitelist.contains(account) || whitelist.is_empty()
-    }
-
     /// elect new validators at the beginning of the era.
     ///
+    /// The validators are selected by `Config::ElectionStrategy` out of the
+    /// [`Pallet::election_snapshot`]. The reads and writes of the election are registered in the
+    /// weight of the block planning the era.
+    ///
     /// updates the following storage items:
     /// - [`EraElectionStatus`]: with `None`.
     /// - [`ErasStakers`]: with the new staker set.
@@ -2440,25 +2974,12 @@ impl<T: Config> pallet::Pallet<T> {
     ///
     /// If the election has been successful, It passes the new set upwards.
     fn elect(current_era: EraIndex) -> Option<Vec<T::AccountId>> {
-        let mut validators: Vec<(T::AccountId, u32, EraIndex)> = Validators::<T>::iter()
-            .filter(|(validator, _)| Self::trusted_validator(&validator))
-            .map(|(validator, _)| {
-                // if let Some(candidate_validator) = Self::candidate_validators(&validator) {
-                //     (
-                //         validator.clone(),
-                //         candidate_validator.delegators.len() as u32,
-                //         candidate_validator.elected_era,
-                //     )
-                // }
-                let candidate_validator = Self::candidate_validators(&validator);
-                (
-                    validator.clone(),
-                    candidate_validator.delegators.len() as u32,
-                    candidate_validator.elected_era,
-                )
-            })
-            .collect();
-        if validators.len() < Self::minimum_validator_count().max(1) as usize {
+        let (candidates, snapshot_weight) = Self::snapshot_candidates();
+        <frame_system::Pallet<T>>::register_extra_weight_unchecked(
+            snapshot_weight,
+            DispatchClass::Mandatory,
+        );
+        if candidates.len() < Self::minimum_validator_count().max(1) as usize {
             // If we don't have enough candidate_validators, nothing to do.
             log!(
                 warn,
@@ -2467,11 +2988,10 @@ impl<T: Config> pallet::Pallet<T> {
             );
             None
         } else {
-            validators.sort_by(|a, b| Self::compare(&(a.1, a.2), &(b.1, b.2)));
-            let truncated = validators.len() > Self::validator_count() as usize;
-            validators.truncate(Self::validator_count() as usize);
-            let elected_validators: Vec<T::AccountId> =
-                validators.iter().map(|(v, _, _)| (*v).clone()).collect();
+            let count = Self::validator_count() as usize;
+            let truncated = candidates.len() > count;
+            let mut elected_validators = T::ElectionStrategy::elect(candidates.clone(), count);
+            elected_validators.truncate(count);
             for elected_validator in &elected_validators {
                 <CandidateValidators<T>>::mutate(&elected_validator, |validator_data| {
                     validator_data.elected_era = current_era + 1; // makes sure it's not 0
@@ -2494,9 +3014,19 @@ impl<T: Config> pallet::Pallet<T> {
                     .unwrap_or_default();
                 // expose delegators only if not all validators elected.
                 let others = if truncated {
-                    let candidate = Self::candidate_validators(v);
-
-                    candidate.delegators.into_iter().collect()
+                    candidates
+                        .iter()
+                        .find(|c| &c.who == v)
+                        .map(|c| {
+                            c.delegations
+                                .iter()
+                                .map(|(who, weight)| IndividualExposure {
+                                    who: who.clone(),
+                                    weight: *weight,
+                                })
+                                .collect()
+                        })
+                        .unwrap_or_default()
                 } else {
                     Vec::new()
                 };
@@ -2509,16 +3039,61 @@ impl<T: Config> pallet::Pallet<T> {
                 total_stake = total_stake.saturating_add(stake);
             }
             ErasTotalStake::<T>::insert(&current_era, total_stake);
+            // per elected validator: `CandidateValidators`, `Bonded` and `Ledger` read,
+            // `CandidateValidators` and `ErasStakers` written
+            let elected = elected_validators.len() as Weight;
+            <frame_system::Pallet<T>>::register_extra_weight_unchecked(
+                T::DbWeight::get().reads_writes(3 * elected, 2 * elected + 1),
+                DispatchClass::Mandatory,
+            );
             Some(elected_validators)
         }
     }
 
-    fn compare(a: &(u32, EraIndex), b: &(u32, EraIndex)) -> Ordering {
-        match a.1.cmp(&b.1) {
-            Ordering::Less => Ordering::Less,
-            Ordering::Greater => Ordering::Greater,
-            Ordering::Equal => b.0.cmp(&a.0),
-        }
+    /// The trusted validators standing for election and what backs each of them.
+    pub fn election_snapshot() -> Vec<ElectionCandidate<T::AccountId, BalanceOf<T>>> {
+        Self::snapshot_candidates().0
+    }
+
+    /// The election snapshot and the weight of the reads taking it. The credit of each delegator
+    /// is read once however many validators it delegates to.
+    fn snapshot_candidates() -> (Vec<ElectionCandidate<T::AccountId, BalanceOf<T>>>, Weight) {
+        let whitelist = Self::validator_whitelist();
+        let mut delegated: BTreeMap<T::AccountId, Vec<(T::AccountId, u64)>> = BTreeMap::new();
+        let mut validators: Weight = 0;
+        let candidates = Validators::<T>::iter()
+            .filter(|(validator, _)| {
+                validators += 1;
+                whitelist.is_empty() || whitelist.contains(validator)
+            })
+            .map(|(validator, _)| {
+                let candidate_validator = Self::candidate_validators(&validator);
+                let delegations = candidate_validator
+                    .delegators
+                    .into_iter()
+                    .map(|delegator| {
+                        let credits = delegated
+                            .entry(delegator.clone())
+                            .or_insert_with(|| Self::delegated_credits(&delegator));
+                        let credit = credits
+                            .iter()
+                            .find(|(v, _)| v == &validator)
+                            .map_or(0, |(_, c)| *c);
+                        (delegator, credit)
+                    })
+                    .collect();
+                ElectionCandidate {
+                    stake: Self::slashable_balance_of(&validator),
+                    who: validator,
+                    elected_era: candidate_validator.elected_era,
+                    delegations,
+                }
+            })
+            .collect::<Vec<_>>();
+        // whitelist, `Validators` of each validator, `CandidateValidators`, `Bonded` and `Ledger`
+        // of each candidate, credit, `DelegationWeights` and `Delegators` of each delegator
+        let reads = 1 + validators + 3 * candidates.len() as Weight + 3 * delegated.len() as Weight;
+        (candidates, T::DbWeight::get().reads(reads))
     }
 
     /// Remove all associated data of a stash account from the staking system.
@@ -2538,6 +3113,8 @@ impl<T: Config> pallet::Pallet<T> {
 
         <Payee<T>>::remove(stash);
         <Validators<T>>::remove(stash);
+        <PendingCommission<T>>::remove(stash);
+        <PreviousCommission<T>>::remove(stash);
 
         frame_system::Pallet::<T>::dec_consumers(stash);
 
@@ -2623,7 +3200,235 @@ impl<T: Config> pallet::Pallet<T> {
         SlashRewardFraction::<T>::put(fraction);
     }
 
+    fn do_delegate(delegator: T::AccountId, validators: Vec<T::AccountId>) -> DispatchResult {
+        ensure!(
+            !<Validators<T>>::contains_key(&delegator),
+            Error::<T>::CallNotAllowed
+        );
+
+        let enough_credit = T::CreditInterface::pass_threshold(&delegator);
+        ensure!(enough_credit, Error::<T>::CreditTooLow);
+
+        ensure!(!validators.is_empty(), Error::<T>::NoValidators);
+        // remove duplicates
+        let validator_set: BTreeSet<T::AccountId> = validators.iter().cloned().collect();
+        // check validators size
+        ensure!(
+            validator_set.len() <= T::MaxDelegates::get() as usize,
+            Error::<T>::TooManyValidators
+        );
+        for validator in &validator_set {
+            ensure!(
+                <Validators<T>>::contains_key(&validator),
+                Error::<T>::NotValidator
+            );
+        }
+        ensure!(
+            Self::settle_delegator_reward(&delegator),
+            Error::<T>::DelegatorRewardUnsettled
+        );
+
+        let current_era = T::CreditInterface::get_current_era();
+        if <Delegators<T>>::contains_key(&delegator) {
+            let old_delegator_data = Self::delegators(&delegator);
+            if !old_delegator_data.delegating {
+                // the delegator was not delegating
+                // the delegator delegates again
+                ActiveDelegatorCount::<T>::mutate(|count| *count = count.saturating_add(1));
+            }
+            let earliest_unrewarded_era = match old_delegator_data.unrewarded_since {
+                Some(unrewarded_era) => unrewarded_era,
+                None => current_era,
+            };
+            let delegator_data = DelegatorData {
+                delegator: delegator.clone(),
+                delegated_validators: validators.clone(),
+                unrewarded_since: Some(earliest_unrewarded_era),
+                delegating: true,
+            };
+            <Delegators<T>>::insert(&delegator, delegator_data);
+
+            for validator in &old_delegator_data.delegated_validators {
+                <CandidateValidators<T>>::mutate(validator, |v| {
+                    v.delegators.remove(&delegator);
+                });
+                let candidate = Self::candidate_validators(validator);
+                if candidate.delegators.is_empty() {
+                    <CandidateValidators<T>>::remove(validator);
+                }
+            }
+        } else {
+            let delegator_data = DelegatorData {
+                delegator: delegator.clone(),
+                delegated_validators: validators.clone(),
+                unrewarded_since: Some(current_era),
+                delegating: true,
+            };
+            <Delegators<T>>::insert(&delegator, delegator_data);
+            ActiveDelegatorCount::<T>::mutate(|count| *count = count.saturating_add(1));
+            DelegatorCount::<T>::mutate(|count| *count = count.saturating_add(1));
+            //  delegator must has enough credit score,so this init must success
+            T::CreditInterface::init_delegator_history(&delegator, current_era);
+        };
+
+        for validator in &validator_set {
+            if <CandidateValidators<T>>::contains_key(validator) {
+                <CandidateValidators<T>>::mutate(validator, |v| {
+                    v.delegators.insert(delegator.clone());
+                });
+            } else {
+                let mut delegators = BTreeSet::new();
+                delegators.insert(delegator.clone());
+                let elected_era = EraIndex::default();
+                <CandidateValidators<T>>::insert(
+                    validator,
+                    ValidatorData {
+                        delegators,
+                        elected_era,
+                    },
+                );
+            }
+        }
+
+        Self::deposit_event(Event::<T>::Delegated(delegator, validators));
+        Ok(())
+    }
+
+    /// Append `record` to the reward history of `delegator`, dropping the oldest payout beyond
+    /// `MaxDelegatorRewardHistory`.
+    fn record_delegator_reward(
+        delegator: &T::AccountId,
+        record: DelegatorRewardRecord<T::AccountId, BalanceOf<T>>,
+    ) {
+        let (mut first, next) = DelegatorRewardRange::<T>::get(delegator);
+        DelegatorRewardRecords::<T>::insert(delegator, next, record);
+        let next = next.wrapping_add(1);
+        if next.wrapping_sub(first) > T::MaxDelegatorRewardHistory::get() {
+            DelegatorRewardRecords::<T>::remove(delegator, first);
+            first = first.wrapping_add(1);
+        }
+        DelegatorRewardRange::<T>::insert(delegator, (first, next));
+    }
+
+    /// The reward payouts of `delegator` kept in its reward history, oldest first.
+    pub fn delegator_reward_history(
+        delegator: &T::AccountId,
+    ) -> Vec<DelegatorRewardRecord<T::AccountId, BalanceOf<T>>> {
+        let (first, next) = DelegatorRewardRange::<T>::get(delegator);
+        (0..next.wrapping_sub(first))
+            .filter_map(|i| DelegatorRewardRecords::<T>::get(delegator, first.wrapping_add(i)))
+            .collect()
+    }
+
+    /// The emission of `count` eras from `from` on under the current emission schedule, empty
+    /// if no schedule is set.
+    pub fn projected_emission(from: EraIndex, count: u32) -> Vec<EraEmission<BalanceOf<T>>> {
+        Self::emission_schedule()
+            .map(|schedule| schedule.project(from, count))
+            .unwrap_or_default()
+    }
+
+    /// The commission of a validator in the given era, a scheduled increase counts from the era
+    /// it takes effect in.
+    pub fn commission_of(validator: &T::AccountId, era: EraIndex) -> Perbill {
+        let current = Self::validators(validator).commission;
+        let commission = match Self::pending_commission(validator) {
+            Some((effective_era, commission)) if effective_era <= era => commission,
+            _ => match Self::previous_commission(validator) {
+                Some((since, previous)) if era < since => previous.min(current),
+                _ => current,
+            },
+        };
+        commission.min(T::MaxCommission::get())
+    }
+
+    /// The commission of a validator on `amount` earned evenly over the eras `from_era` to
+    /// `to_era`, each era charged at the commission of that era.
+    fn commission_over(
+        validator: &T::AccountId,
+        amount: BalanceOf<T>,
+        from_era: EraIndex,
+        to_era: EraIndex,
+    ) -> (BalanceOf<T>, Weight) {
+        let eras = to_era.saturating_sub(from_era).saturating_add(1);
+        // the commission only changes at the era of the pending or the last increase
+        let mut changes: Vec<EraIndex> = Self::pending_commission(validator)
+            .into_iter()
+            .chain(Self::previous_commission(validator))
+            .map(|(era, _)| era)
+            .filter(|era| *era > from_era && *era <= to_era)
+            .collect();
+        changes.sort_unstable();
+        changes.dedup();
+        changes.push(to_era.saturating_add(1));
+        let mut weight = T::DbWeight::get().reads_writes(2, 0);
+        let mut commission = BalanceOf::<T>::zero();
+        let mut start = from_era;
+        for end in changes {
+            let part = Perbill::from_rational(end - start, eras) * amount;
+            commission = commission.saturating_add(Self::commission_of(validator, start) * part);
+            weight = weight.saturating_add(T::DbWeight::get().reads_writes(3, 0));
+            start = end;
+        }
+        (commission, weight)
+    }
+
+    /// Move the commission increases that took effect by `era` into `Validators`.
+    fn promote_commissions(era: EraIndex) -> Weight {
+        let mut weight: Weight = 0;
+        let due: Vec<(T::AccountId, EraIndex, Perbill)> = PendingCommission::<T>::iter()
+            .inspect(|_| weight = weight.saturating_add(T::DbWeight::get().reads(1)))
+            .filter(|(_, (effective_era, _))| *effective_era <= era)
+            .map(|(validator, (effective_era, commission))| (validator, effective_era, commission))
+            .collect();
+        for (validator, effective_era, commission) in due {
+            PendingCommission::<T>::remove(&validator);
+            if Validators::<T>::contains_key(&validator) {
+                let current = Self::validators(&validator).commission;
+                let previous = Self::previous_commission(&validator)
+                    .map_or(current, |(_, previous)| previous.min(current));
+                Validators::<T>::mutate(&validator, |prefs| prefs.commission = commission);
+                PreviousCommission::<T>::insert(&validator, (effective_era, previous));
+            }
+            weight = weight.saturating_add(T::DbWeight::get().reads_writes(4, 3));
+        }
+        weight
+    }
+
+    /// The part of the credit of `delegator` delegated to `validator`.
+    pub fn delegated_credit(delegator: &T::AccountId, validator: &T::AccountId) -> u64 {
+        Self::delegated_credits(delegator)
+            .into_iter()
+            .find(|(v, _)| v == validator)
+            .map_or(0, |(_, credit)| credit)
+    }
+
+    /// The part of the credit of `delegator` delegated to each of its validators.
+    fn delegated_credits(delegator: &T::AccountId) -> Vec<(T::AccountId, u64)> {
+        let credit = T::CreditInterface::get_credit_score(delegator).unwrap_or(0);
+        let parts: Vec<(T::AccountId, u64)> = match Self::delegation_weights(delegator) {
+            Some(parts) => parts.into_iter().map(|(v, p)| (v, p as u64)).collect(),
+            None => Self::delegators(delegator)
+                .delegated_validators
+                .into_iter()
+                .collect::<BTreeSet<_>>()
+                .into_iter()
+                .map(|v| (v, 1))
+                .collect(),
+        };
+        let total = parts.iter().map(|(_, p)| *p).sum::<u64>();
+        if total == 0 {
+            return Vec::new();
+        }
+        parts
+            .into_iter()
+            .map(|(v, part)| (v, (credit as u128 * part as u128 / total as u128) as u64))
+            .collect()
+    }
+
     fn _undelegate(delegator: &T::AccountId) {
+        // a longer backlog keeps the delegation, see `pay_delegator`
+        let settled = Self::settle_delegator_reward(delegator);
         let delegator_data = Self::delegators(delegator);
 
         if delegator_data.delegating {
@@ -2637,10 +3442,13 @@ impl<T: Config> pallet::Pallet<T> {
                     _ => (),
                 }
             }
+            if settled {
+                <DelegationWeights<T>>::remove(delegator);
+            }
 
             match delegator_data.unrewarded_since {
                 Some(earliest_unrewarded_era) => {
-                    if earliest_unrewarded_era == CurrentEra::<T>::get().unwrap_or(0) {
+                    if earliest_unrewarded_era == T::CreditInterface::get_current_era() {
                         <Delegators<T>>::remove(delegator);
                         DelegatorCount::<T>::mutate(|count| *count = count.saturating_sub(1));
                     } else {
@@ -24,13 +24,14 @@ use frame_support::{
     assert_ok, parameter_types,
     traits::{Currency, FindAuthor, GenesisBuild, Get, Hooks, OneSessionHandler},
     weights::constants::RocksDbWeight,
+    PalletId,
 };
 use node_primitives::Moment;
 use pallet_credit::{CreditData, CreditLevel, CreditSetting};
 use sp_core::H256;
 use sp_io;
 use sp_runtime::{
-    testing::{Header, TestXt, UintAuthorityId},
+    testing::{Header, TestSignature, TestXt, UintAuthorityId},
     traits::{IdentityLookup, Zero},
 };
 use sp_staking::offence::{OffenceDetails, OnOffenceHandler};
@@ -107,6 +108,7 @@ frame_support::construct_runtime!(
         Session: pallet_session::{Pallet, Call, Storage, Event, Config<T>},
         DeeperNode: pallet_deeper_node::{Pallet, Call, Storage, Event<T>, Config<T>},
         Micropayment: pallet_micropayment::{Pallet, Call, Storage, Event<T>},
+        Assets: pallet_assets::{Pallet, Call, Storage, Event<T>},
         Historical: pallet_session::historical::{Pallet, Storage},
     }
 );
@@ -174,6 +176,32 @@ impl pallet_balances::Config for Test {
     type WeightInfo = ();
 }
 
+parameter_types! {
+    pub const AssetDeposit: Balance = 1;
+    pub const AssetAccountDeposit: Balance = 1;
+    pub const ApprovalDeposit: Balance = 1;
+    pub const StringLimit: u32 = 50;
+    pub const MetadataDepositBase: Balance = 1;
+    pub const MetadataDepositPerByte: Balance = 1;
+}
+
+impl pallet_assets::Config for Test {
+    type Event = Event;
+    type Balance = u64;
+    type AssetId = u32;
+    type Currency = Balances;
+    type ForceOrigin = frame_system::EnsureRoot<AccountId>;
+    type AssetDeposit = AssetDeposit;
+    type AssetAccountDeposit = AssetAccountDeposit;
+    type StringLimit = StringLimit;
+    type MetadataDepositBase = MetadataDepositBase;
+    type MetadataDepositPerByte = MetadataDepositPerByte;
+    type ApprovalDeposit = ApprovalDeposit;
+    type Freezer = ();
+    type Extra = ();
+    type WeightInfo = ();
+}
+
 pub struct TestAccountCreator;
 
 impl pallet_micropayment::AccountCreator<u64> for TestAccountCreator {
@@ -186,6 +214,12 @@ parameter_types! {
     pub const SecsPerBlock: u32 = 5u32;
     pub const DataPerDPR: u64 = 1024 * 1024 * 1024 * 1024;
     pub const MicropaymentBurn: Percent = Percent::from_percent(10);
+    pub const MicropaymentChallengePeriod: u64 = 10;
+    pub const MinCreditChannelAge: u64 = 10;
+    pub const MaxCreditPaymentPerPair: Balance = 1_000;
+    pub const MicropaymentPalletId: PalletId = PalletId(*b"dp/mcpay");
+    pub const LegacyPayloadWindow: u64 = 20;
+    pub const SettlementRetryDelay: u64 = 10;
 }
 impl pallet_micropayment::Config for Test {
     type Event = Event;
@@ -198,6 +232,17 @@ impl pallet_micropayment::Config for Test {
     type NodeInterface = DeeperNode;
     type MicropaymentBurn = MicropaymentBurn;
     type Slash = ();
+    type ValidatorReward = ();
+    type ChallengePeriod = MicropaymentChallengePeriod;
+    type Signature = TestSignature;
+    type Signer = UintAuthorityId;
+    type AssetId = u32;
+    type Assets = Assets;
+    type MinCreditChannelAge = MinCreditChannelAge;
+    type MaxCreditPaymentPerPair = MaxCreditPaymentPerPair;
+    type PalletId = MicropaymentPalletId;
+    type LegacyPayloadWindow = LegacyPayloadWindow;
+    type SettlementRetryDelay = SettlementRetryDelay;
 }
 
 parameter_types! {
@@ -319,6 +364,11 @@ pub const TOTAL_MINING_REWARD: u128 = 6_000_000_000_000_000_000_000_000;
 parameter_types! {
     pub const MiningReward: u128 = TOTAL_MINING_REWARD;
     pub const MaxDelegates: usize = 10;
+    pub const MaxDelegatorRewardHistory: u32 = 3;
+    pub const MaxDelegatorPayoutsPerBlock: u32 = 10;
+    pub const MaxClaimEras: EraIndex = 3;
+    pub const MaxCommission: Perbill = Perbill::from_percent(50);
+    pub const CommissionChangeDelay: EraIndex = 1;
 }
 
 impl Config for Test {
@@ -337,6 +387,12 @@ impl Config for Test {
     type CreditInterface = Credit;
     type NodeInterface = DeeperNode;
     type MaxDelegates = MaxDelegates;
+    type ElectionStrategy = GovernedElection<Test>;
+    type MaxDelegatorRewardHistory = MaxDelegatorRewardHistory;
+    type MaxDelegatorPayoutsPerBlock = MaxDelegatorPayoutsPerBlock;
+    type MaxClaimEras = MaxClaimEras;
+    type MaxCommission = MaxCommission;
+    type CommissionChangeDelay = CommissionChangeDelay;
     type NumberToCurrency = NumberCurrencyConverter;
     type TotalMiningReward = MiningReward;
     type ExistentialDeposit = ExistentialDeposit;
@@ -277,7 +277,7 @@ pub(crate) fn compute_slash<T: Config>(
     Some(UnappliedSlash {
         validator: stash.clone(),
         own: val_slashed,
-        others: exposure.others.clone(),
+        others: exposure.others.iter().map(|o| o.who.clone()).collect(),
         reporters: Vec::new(),
         payout: reward_payout,
     })
@@ -21,6 +21,7 @@ use super::*;
 use frame_support::{
     assert_noop, assert_ok,
     traits::{Currency, Hooks, ReservableCurrency},
+    weights::constants::RocksDbWeight,
 };
 use frame_system::RawOrigin;
 use mock::*;
@@ -44,6 +45,14 @@ macro_rules! assert_eq_uvec {
     };
 }
 
+/// 1001 delegates its credit evenly to 11 and 21 in default setup
+fn delegated_by_1001() -> IndividualExposure<AccountId> {
+    IndividualExposure {
+        who: 1001,
+        weight: (INITIAL_CREDIT + CREDIT_ATTENUATION_STEP) / 2,
+    }
+}
+
 #[test]
 fn kill_stash_works() {
     ExtBuilder::default().build_and_execute(|| {
@@ -126,7 +135,7 @@ fn basic_setup_works() {
             Exposure {
                 total: 1000,
                 own: 1000,
-                others: vec![1001]
+                others: vec![delegated_by_1001()]
             },
         );
         assert_eq!(
@@ -134,7 +143,7 @@ fn basic_setup_works() {
             Exposure {
                 total: 1000,
                 own: 1000,
-                others: vec![1001]
+                others: vec![delegated_by_1001()]
             },
         );
 
@@ -193,6 +202,45 @@ fn difference_compensation() {
     });
 }
 
+#[test]
+fn delegator_reward_history_is_bounded() {
+    ExtBuilder::default().build_and_execute(|| {
+        // 1001 delegates 11 and 21 in default setup
+        for poc_reward in 1..=4 {
+            assert_ok!(Staking::difference_compensation(
+                Origin::root(),
+                1001,
+                10,
+                poc_reward
+            ));
+        }
+
+        // MaxDelegatorRewardHistory is 3, the oldest payout is dropped
+        let history = Staking::delegator_reward_history(&1001);
+        assert_eq!(
+            history
+                .iter()
+                .map(|record| record.poc_reward)
+                .collect::<Vec<_>>(),
+            vec![2, 3, 4]
+        );
+        assert_eq!(
+            history[2],
+            DelegatorRewardRecord {
+                from_era: 0,
+                to_era: 0,
+                referee_reward: 10,
+                poc_reward: 4,
+                paid: 14,
+                commission: 0,
+                validators: vec![11, 21],
+            }
+        );
+        assert_eq!(DelegatorRewardRange::<Test>::get(&1001), (1, 4));
+        assert!(DelegatorRewardRecords::<Test>::get(&1001, 0).is_none());
+    });
+}
+
 #[test]
 fn change_controller_works() {
     ExtBuilder::default().build_and_execute(|| {
@@ -284,6 +332,12 @@ fn rewards_should_work() {
                 Balances::total_balance(&1002),
                 init_balance_1001 + 21369858941948251800
             );
+            let history = Staking::delegator_reward_history(&1002);
+            assert_eq!(history.len(), 1);
+            assert_eq!((history[0].from_era, history[0].to_era), (0, 0));
+            assert_eq!(history[0].paid, 21369858941948251800);
+            assert_eq!(history[0].validators, vec![11, 21]);
+            assert!(Staking::delegator_reward_history(&1003).is_empty());
             remainder = remainder - 21369858941948251800;
             assert_eq!(Balances::total_balance(&1001), init_balance_1002); // 1001 is not paid yet
             assert_eq!(Balances::total_balance(&1003), init_balance_1003); // 1003 is not paid yet
@@ -325,6 +379,206 @@ fn rewards_should_work() {
         });
 }
 
+#[test]
+fn claim_delegator_reward_works() {
+    ExtBuilder::default()
+        .session_per_era(6)
+        .num_delegators(3)
+        .build_and_execute(|| {
+            assert_ok!(Staking::delegate(Origin::signed(1002), vec![11, 21]));
+            assert_ok!(Staking::delegate(Origin::signed(1003), vec![11, 21]));
+            let init_balance_1003 = Balances::total_balance(&1003);
+
+            // era 0 is not over yet
+            assert_noop!(
+                Staking::claim_delegator_reward(Origin::signed(1), 1003, 0, 0),
+                Error::<Test>::InvalidEraRange
+            );
+
+            start_session(6);
+            run_to_block(BLOCKS_PER_ERA + 1); // 1002 is paid automatically
+            let remainder = Staking::remainder_mining_reward().unwrap();
+
+            assert_noop!(
+                Staking::claim_delegator_reward(Origin::signed(1), 1003, 1, 0),
+                Error::<Test>::InvalidEraRange
+            );
+            assert_noop!(
+                Staking::claim_delegator_reward(Origin::signed(1), 1, 0, 0),
+                Error::<Test>::NotDelegator
+            );
+            assert_noop!(
+                Staking::claim_delegator_reward(Origin::signed(1), 1002, 0, 0),
+                Error::<Test>::NoUnclaimedReward
+            );
+
+            // anyone can claim on behalf of 1003
+            assert_ok!(Staking::claim_delegator_reward(
+                Origin::signed(1),
+                1003,
+                0,
+                0
+            ));
+            assert_eq!(
+                Balances::total_balance(&1003),
+                init_balance_1003 + 21369858941948251800
+            );
+            assert_eq!(
+                Staking::remainder_mining_reward().unwrap(),
+                remainder - 21369858941948251800
+            );
+            assert_eq!(Staking::delegators(&1003).unrewarded_since, Some(1));
+            let history = Staking::delegator_reward_history(&1003);
+            assert_eq!((history[0].from_era, history[0].to_era), (0, 0));
+            assert_noop!(
+                Staking::claim_delegator_reward(Origin::signed(1003), 1003, 0, 0),
+                Error::<Test>::NoUnclaimedReward
+            );
+
+            // automatic payouts skip 1003 now
+            run_to_block(BLOCKS_PER_ERA + 3);
+            assert_eq!(
+                Balances::total_balance(&1003),
+                init_balance_1003 + 21369858941948251800
+            );
+
+            // a backlog longer than MaxClaimEras is claimed in several calls
+            run_to_block(4 * BLOCKS_PER_ERA + 3);
+            assert!(Credit::get_current_era() > 3);
+            Delegators::<Test>::mutate(&1003, |data| data.unrewarded_since = Some(0));
+            assert_noop!(
+                Staking::claim_delegator_reward(Origin::signed(1), 1003, 0, 3),
+                Error::<Test>::TooManyClaimEras
+            );
+            // others claim as many eras as allowed
+            assert_noop!(
+                Staking::claim_delegator_reward(Origin::signed(1), 1003, 0, 1),
+                Error::<Test>::PartialClaimNotAllowed
+            );
+            assert_ok!(Staking::claim_delegator_reward(
+                Origin::signed(1),
+                1003,
+                0,
+                2
+            ));
+            assert_eq!(Staking::delegators(&1003).unrewarded_since, Some(3));
+
+            // the delegator may claim a part of them
+            Delegators::<Test>::mutate(&1003, |data| data.unrewarded_since = Some(0));
+            assert_ok!(Staking::claim_delegator_reward(
+                Origin::signed(1003),
+                1003,
+                0,
+                0
+            ));
+            assert_eq!(Staking::delegators(&1003).unrewarded_since, Some(1));
+        });
+}
+
+#[test]
+fn delegator_reward_commission() {
+    ExtBuilder::default()
+        .session_per_era(6)
+        .build_and_execute(|| {
+            let prefs = |percent| ValidatorPrefs {
+                commission: Perbill::from_percent(percent),
+                ..Default::default()
+            };
+            // MaxCommission is 50%
+            assert_noop!(
+                Staking::validate(Origin::signed(10), prefs(60)),
+                Error::<Test>::CommissionTooHigh
+            );
+
+            // 1001 delegates 11, the increase takes effect after CommissionChangeDelay
+            assert_ok!(Staking::validate(Origin::signed(10), prefs(10)));
+            assert_eq!(Staking::validators(&11).commission, Perbill::zero());
+            assert_eq!(
+                Staking::pending_commission(&11),
+                Some((1, Perbill::from_percent(10)))
+            );
+            assert_eq!(Staking::commission_of(&11, 0), Perbill::zero());
+            assert_eq!(Staking::commission_of(&11, 1), Perbill::from_percent(10));
+
+            // a decrease takes effect at once and cancels the increase
+            assert_ok!(Staking::validate(Origin::signed(10), prefs(0)));
+            assert_eq!(Staking::pending_commission(&11), None);
+
+            // validators without delegators set their commission at once
+            assert_ok!(Staking::validate(Origin::signed(30), prefs(20)));
+            assert_eq!(
+                Staking::validators(&31).commission,
+                Perbill::from_percent(20)
+            );
+            assert_eq!(Staking::pending_commission(&31), None);
+
+            assert_ok!(Staking::validate(Origin::signed(10), prefs(10)));
+            run_to_block(BLOCKS_PER_ERA + 1);
+            // the increase moves into Validators once it takes effect
+            assert_eq!(Staking::pending_commission(&11), None);
+            assert_eq!(
+                Staking::validators(&11).commission,
+                Perbill::from_percent(10)
+            );
+            assert_eq!(
+                Staking::previous_commission(&11),
+                Some((1, Perbill::zero()))
+            );
+            // era 0 is paid without commission
+            let history = Staking::delegator_reward_history(&1001);
+            assert_eq!(history[0].commission, 0);
+            assert_eq!(Staking::commission_of(&11, 0), Perbill::zero());
+
+            run_to_block(2 * BLOCKS_PER_ERA + 1);
+            // 11 takes 10% of the half of the reward of 1001 delegated to it
+            let history = Staking::delegator_reward_history(&1001);
+            let record = &history[1];
+            assert_eq!((record.from_era, record.to_era), (1, 1));
+            let reward = record.paid + record.commission;
+            assert!(reward > 0);
+            assert_eq!(
+                record.commission,
+                Perbill::from_percent(10) * (Perbill::from_percent(50) * reward)
+            );
+
+            // eras 0 and 1 paid at once, only era 1 is charged the increase
+            Delegators::<Test>::mutate(&1001, |data| data.unrewarded_since = Some(0));
+            assert_ok!(Staking::claim_delegator_reward(
+                Origin::signed(1001),
+                1001,
+                0,
+                1
+            ));
+            let history = Staking::delegator_reward_history(&1001);
+            let record = history.last().unwrap();
+            assert_eq!((record.from_era, record.to_era), (0, 1));
+            let reward = record.paid + record.commission;
+            assert!(reward > 0);
+            assert_eq!(
+                record.commission,
+                Perbill::from_percent(10)
+                    * (Perbill::from_rational(1u32, 2) * (Perbill::from_percent(50) * reward))
+            );
+        });
+}
+
+#[test]
+fn commission_clamped_to_max_commission() {
+    ExtBuilder::default().build_and_execute(|| {
+        // commissions set before MaxCommission existed
+        Validators::<Test>::mutate(&11, |prefs| prefs.commission = Perbill::from_percent(80));
+        assert_eq!(Staking::commission_of(&11, 0), Perbill::from_percent(50));
+
+        StorageVersion::<Test>::put(Releases::V6_0_0);
+        Staking::on_runtime_upgrade();
+        assert_eq!(StorageVersion::<Test>::get(), Releases::V7_0_0);
+        assert_eq!(
+            Staking::validators(&11).commission,
+            Perbill::from_percent(50)
+        );
+    });
+}
+
 #[test]
 fn many_delegators_rewards_should_work() {
     ExtBuilder::default()
@@ -376,6 +630,152 @@ fn many_delegators_rewards_should_work() {
         });
 }
 
+#[test]
+fn delegation_change_pays_unpaid_eras_first() {
+    ExtBuilder::default()
+        .session_per_era(6)
+        .build_and_execute(|| {
+            // 31 has no delegators, its commission takes effect at once
+            assert_ok!(Staking::validate(
+                Origin::signed(30),
+                ValidatorPrefs {
+                    commission: Perbill::from_percent(20),
+                    ..Default::default()
+                }
+            ));
+            // 1001 delegates 11 and 21 in default setup
+            assert_ok!(Staking::delegate(Origin::signed(1001), vec![31]));
+
+            run_to_block(BLOCKS_PER_ERA);
+            let balance_31 = Balances::total_balance(&31);
+            assert_ok!(Staking::delegate(Origin::signed(1001), vec![11, 21]));
+            // era 0 is paid with the delegation it was earned with
+            let history = Staking::delegator_reward_history(&1001);
+            let record = history.last().unwrap();
+            assert_eq!((record.from_era, record.to_era), (0, 0));
+            assert_eq!(record.validators, vec![31]);
+            assert!(record.paid > 0);
+            assert_eq!(
+                record.commission,
+                Perbill::from_percent(20) * (record.paid + record.commission)
+            );
+            assert_eq!(Balances::total_balance(&31), balance_31 + record.commission);
+            assert_eq!(Staking::delegators(&1001).unrewarded_since, Some(1));
+
+            // a backlog longer than MaxClaimEras is claimed first
+            run_to_block(4 * BLOCKS_PER_ERA + 3);
+            Delegators::<Test>::mutate(&1001, |data| data.unrewarded_since = Some(0));
+            assert_noop!(
+                Staking::delegate(Origin::signed(1001), vec![31]),
+                Error::<Test>::DelegatorRewardUnsettled
+            );
+        });
+}
+
+#[test]
+fn delegator_payouts_go_on_where_the_last_era_stopped() {
+    ExtBuilder::default()
+        .session_per_era(6)
+        .num_delegators(3)
+        .build_and_execute(|| {
+            assert_ok!(Staking::delegate(Origin::signed(1002), vec![11, 21]));
+            assert_ok!(Staking::delegate(Origin::signed(1003), vec![11, 21]));
+            // the order of the automatic payouts
+            let delegators: Vec<AccountId> = Delegators::<Test>::iter_keys().collect();
+            let init_balances: Vec<Balance> = delegators
+                .iter()
+                .map(|d| Balances::total_balance(d))
+                .collect();
+
+            run_to_block(BLOCKS_PER_ERA + 1);
+            assert_eq!(Staking::delegator_payouts_per_block(), 1);
+            assert_eq!(
+                Balances::total_balance(&delegators[0]),
+                init_balances[0] + 21369858941948251800
+            );
+            // the others are not reached in era 1
+            DelegatorPayoutsPerBlock::<Test>::put(0);
+
+            run_to_block(2 * BLOCKS_PER_ERA + 2);
+            // the second and third delegators are paid first, for eras 0 and 1
+            for i in 1..3 {
+                assert_eq!(
+                    Balances::total_balance(&delegators[i]),
+                    init_balances[i] + 21369858941948251800 * 2
+                );
+            }
+            assert_eq!(
+                Balances::total_balance(&delegators[0]),
+                init_balances[0] + 21369858941948251800
+            );
+
+            // every delegator was visited, the next era starts over from the first one
+            assert_eq!(
+                Staking::delegators_last_key(),
+                Staking::delegators_key_prefix()
+            );
+            assert_eq!(Staking::delegator_payouts_per_block(), 0);
+            run_to_block(3 * BLOCKS_PER_ERA + 1);
+            assert_eq!(
+                Balances::total_balance(&delegators[0]),
+                init_balances[0] + 21369858941948251800 * 3
+            );
+        });
+}
+
+#[test]
+fn delegators_short_of_reward_are_paid_in_full_later() {
+    ExtBuilder::default()
+        .session_per_era(6)
+        .num_delegators(2)
+        .build_and_execute(|| {
+            assert_ok!(Staking::delegate(Origin::signed(1002), vec![11, 21]));
+            let delegators: Vec<AccountId> = Delegators::<Test>::iter_keys().collect();
+            let init_balances: Vec<Balance> = delegators
+                .iter()
+                .map(|d| Balances::total_balance(d))
+                .collect();
+            let reward = 21369858941948251800;
+
+            run_to_block(BLOCKS_PER_ERA);
+            // only half of the reward of era 0 of the first delegator is left
+            RemainderMiningReward::<Test>::put(reward / 2);
+            run_to_block(BLOCKS_PER_ERA + 2);
+            assert_eq!(
+                Balances::total_balance(&delegators[0]),
+                init_balances[0] + reward / 2
+            );
+            assert_eq!(
+                Staking::partial_delegator_payout(&delegators[0]),
+                reward / 2
+            );
+            // era 0 stays unrewarded until it is paid in full
+            assert_eq!(
+                Staking::delegators(&delegators[0]).unrewarded_since,
+                Some(0)
+            );
+            assert_eq!(Balances::total_balance(&delegators[1]), init_balances[1]);
+            assert_eq!(Staking::remainder_mining_reward(), Some(0));
+
+            run_to_block(2 * BLOCKS_PER_ERA);
+            RemainderMiningReward::<Test>::put(reward * 10);
+            run_to_block(2 * BLOCKS_PER_ERA + 2);
+            // the first delegator is paid the rest of era 0 first
+            for i in 0..2 {
+                assert_eq!(
+                    Balances::total_balance(&delegators[i]),
+                    init_balances[i] + reward * 2
+                );
+                assert_eq!(
+                    Staking::delegators(&delegators[i]).unrewarded_since,
+                    Some(2)
+                );
+            }
+            assert_eq!(Staking::partial_delegator_payout(&delegators[0]), 0);
+            assert_eq!(Staking::remainder_mining_reward(), Some(reward * 6));
+        });
+}
+
 #[test]
 fn no_rewards_if_undelegating_in_the_same_era() {
     ExtBuilder::default()
@@ -641,7 +1041,7 @@ fn delegators_also_get_slashed() {
         let slash_percent = Perbill::from_percent(5);
         let initial_exposure = Staking::eras_stakers(active_era(), 11);
         // 1001 is a delegator for 11
-        assert_eq!(initial_exposure.others.first().unwrap(), &1001);
+        assert_eq!(initial_exposure.others.first().unwrap().who, 1001);
         let initial_credit = Credit::get_credit_score(&1001).unwrap_or(0);
         assert_eq!(initial_credit, INITIAL_CREDIT + CREDIT_ATTENUATION_STEP);
 
@@ -1036,7 +1436,7 @@ fn bond_extra_and_withdraw_unbonded_works() {
             Exposure {
                 total: 1000,
                 own: 1000,
-                others: vec![1001]
+                others: vec![delegated_by_1001()]
             }
         );
 
@@ -1082,7 +1482,7 @@ fn bond_extra_and_withdraw_unbonded_works() {
             Exposure {
                 total: 1000 + 100,
                 own: 1000 + 100,
-                others: vec![1001]
+                others: vec![delegated_by_1001()]
             }
         );
 
@@ -2873,6 +3273,231 @@ fn delegate() {
         });
 }
 
+#[test]
+fn delegate_with_weights() {
+    ExtBuilder::default()
+        .validator_pool(true) // 11, 21, 31, 41
+        .num_delegators(4) // 1001, 1002, 1003, 1004
+        .build_and_execute(|| {
+            assert_noop!(
+                Staking::delegate_with_weights(Origin::signed(1002), vec![(31, 1), (41, 0)]),
+                Error::<Test>::ZeroDelegationWeight
+            );
+            assert_noop!(
+                Staking::delegate_with_weights(Origin::signed(1002), vec![(31, 1), (5, 1)]),
+                Error::<Test>::NotValidator
+            );
+
+            // duplicates are merged
+            assert_ok!(Staking::delegate_with_weights(
+                Origin::signed(1002),
+                vec![(31, 2), (41, 1), (31, 1)]
+            ));
+            assert_eq!(
+                Staking::delegation_weights(1002),
+                Some(vec![(31, 3), (41, 1)])
+            );
+            assert_eq!(Staking::delegators(1002).delegated_validators, vec![31, 41]);
+            assert!(Staking::candidate_validators(31).delegators.contains(&1002));
+            let credit = INITIAL_CREDIT + CREDIT_ATTENUATION_STEP;
+            assert_eq!(Staking::delegated_credit(&1002, &31), credit * 3 / 4);
+            assert_eq!(Staking::delegated_credit(&1002, &41), credit / 4);
+            assert_eq!(Staking::delegated_credit(&1002, &11), 0);
+            // an even split without weights
+            assert_eq!(Staking::delegated_credit(&1001, &11), credit / 2);
+
+            // delegating again evenly drops the weights
+            assert_ok!(Staking::delegate(Origin::signed(1002), vec![31, 41]));
+            assert_eq!(Staking::delegation_weights(1002), None);
+            assert_eq!(Staking::delegated_credit(&1002, &31), credit / 2);
+
+            // the snapshot reads the credit of a delegator once for all of its validators
+            let (candidates, weight) = Staking::snapshot_candidates();
+            let delegations: usize = candidates.iter().map(|c| c.delegations.len()).sum();
+            let delegators: BTreeSet<_> = candidates
+                .iter()
+                .flat_map(|c| c.delegations.iter().map(|(d, _)| *d))
+                .collect();
+            assert!(delegators.len() < delegations);
+            let validators = Validators::<Test>::iter().count() as Weight;
+            assert_eq!(
+                weight,
+                RocksDbWeight::get().reads(
+                    1 + validators
+                        + 3 * candidates.len() as Weight
+                        + 3 * delegators.len() as Weight
+                )
+            );
+
+            assert_ok!(Staking::delegate_with_weights(
+                Origin::signed(1002),
+                vec![(31, 1)]
+            ));
+            assert_ok!(Staking::undelegate(Origin::signed(1002)));
+            assert_eq!(Staking::delegation_weights(1002), None);
+        });
+}
+
+#[test]
+fn election_weighted_by_delegated_credit() {
+    ExtBuilder::default()
+        .validator_pool(true) // 11, 21, 31, 41
+        .num_delegators(4) // 1001, 1002, 1003, 1004
+        .build_and_execute(|| {
+            let credit = INITIAL_CREDIT + CREDIT_ATTENUATION_STEP;
+            assert_ok!(Staking::set_election_strategy(
+                RawOrigin::Root.into(),
+                ElectionStrategyKind::CreditSum
+            ));
+            assert_ok!(Staking::set_validator_count(RawOrigin::Root.into(), 1));
+            // 31 has two delegators, but 41 has more delegated credit
+            assert_ok!(Staking::delegate_with_weights(
+                Origin::signed(1002),
+                vec![(31, 3), (41, 1)]
+            ));
+            assert_ok!(Staking::delegate_with_weights(
+                Origin::signed(1003),
+                vec![(31, 1), (41, 9)]
+            ));
+            assert_ok!(Staking::delegate(Origin::signed(1004), vec![41]));
+
+            mock::start_active_era(1);
+            assert_eq_uvec!(validator_controllers(), vec![40]);
+            assert_eq!(
+                Staking::eras_stakers(Staking::active_era().unwrap().index, 41).others,
+                vec![
+                    IndividualExposure {
+                        who: 1002,
+                        weight: credit / 4
+                    },
+                    IndividualExposure {
+                        who: 1003,
+                        weight: credit * 9 / 10
+                    },
+                    IndividualExposure {
+                        who: 1004,
+                        weight: credit
+                    },
+                ]
+            );
+        });
+}
+
+#[test]
+fn set_election_strategy() {
+    ExtBuilder::default()
+        .validator_pool(true) // 11, 21, 31, 41
+        .num_delegators(4) // 1001, 1002, 1003, 1004
+        .build_and_execute(|| {
+            assert_eq!(
+                Staking::election_strategy(),
+                ElectionStrategyKind::Headcount
+            );
+            assert_ok!(Staking::set_validator_count(RawOrigin::Root.into(), 1));
+            // 31 has more delegators, 41 more credit
+            assert_ok!(Staking::delegate_with_weights(
+                Origin::signed(1002),
+                vec![(31, 1), (21, 99)]
+            ));
+            assert_ok!(Staking::delegate_with_weights(
+                Origin::signed(1003),
+                vec![(31, 1), (21, 99)]
+            ));
+            assert_ok!(Staking::delegate(Origin::signed(1004), vec![41]));
+
+            let snapshot = Staking::election_snapshot();
+            assert_eq!(
+                election::simulate(
+                    &snapshot,
+                    1,
+                    &[
+                        ElectionStrategyKind::Headcount,
+                        ElectionStrategyKind::CreditSum
+                    ]
+                ),
+                vec![
+                    (ElectionStrategyKind::Headcount, vec![31]),
+                    (ElectionStrategyKind::CreditSum, vec![41]),
+                ]
+            );
+
+            assert_noop!(
+                Staking::set_election_strategy(Origin::signed(1), ElectionStrategyKind::Headcount),
+                BadOrigin
+            );
+            assert_ok!(Staking::set_election_strategy(
+                RawOrigin::Root.into(),
+                ElectionStrategyKind::Headcount
+            ));
+            assert_eq!(
+                Staking::election_strategy(),
+                ElectionStrategyKind::Headcount
+            );
+            mock::start_active_era(1);
+            assert_eq_uvec!(validator_controllers(), vec![30]);
+        });
+}
+
+#[test]
+fn election_strategies_compared() {
+    let candidate =
+        |who: AccountId, stake: Balance, delegations: Vec<(AccountId, u64)>| ElectionCandidate {
+            who,
+            elected_era: 0,
+            stake,
+            delegations,
+        };
+    let candidates = vec![
+        candidate(1, 1000, vec![(100, 10), (101, 10), (102, 10)]),
+        candidate(2, 10, vec![(103, 80)]),
+        candidate(3, 5000, vec![]),
+        candidate(4, 10, vec![(103, 20), (104, 50)]),
+    ];
+    assert_eq!(
+        election::simulate(
+            &candidates,
+            2,
+            &[
+                ElectionStrategyKind::Headcount,
+                ElectionStrategyKind::CreditSum,
+                ElectionStrategyKind::StakeCredit(Perbill::zero()),
+                ElectionStrategyKind::StakeCredit(Perbill::from_percent(50)),
+                ElectionStrategyKind::SequentialPhragmen,
+            ]
+        ),
+        vec![
+            (ElectionStrategyKind::Headcount, vec![1, 4]),
+            (ElectionStrategyKind::CreditSum, vec![2, 4]),
+            (
+                ElectionStrategyKind::StakeCredit(Perbill::zero()),
+                vec![3, 1]
+            ),
+            (
+                ElectionStrategyKind::StakeCredit(Perbill::from_percent(50)),
+                vec![3, 2]
+            ),
+            // 103 backs 2 and 4, so electing 4 makes 2 cheaper than 1
+            (ElectionStrategyKind::SequentialPhragmen, vec![4, 2]),
+        ]
+    );
+
+    // the least recently elected candidates come first by headcount
+    let mut rotated = candidates.clone();
+    rotated[3].elected_era = 1;
+    assert_eq!(election::Headcount::elect(rotated.clone(), 2), vec![1, 2]);
+    // but only break ties of a score
+    assert_eq!(election::CreditSum::elect(rotated.clone(), 2), vec![2, 4]);
+    let mut tied = candidates.clone();
+    tied[1].elected_era = 1;
+    tied[3].delegations = vec![(103, 80)];
+    assert_eq!(election::CreditSum::elect(tied, 2), vec![4, 2]);
+    // candidates without delegators fill the remaining seats
+    assert_eq!(
+        election::SequentialPhragmen::elect(rotated, 4),
+        vec![4, 2, 1, 3]
+    );
+}
+
 #[test]
 fn undelegate() {
     ExtBuilder::default()
@@ -2908,13 +3533,12 @@ fn undelegate() {
             assert_eq!(Staking::delegator_count(), 1);
             assert_eq!(Staking::active_delegator_count(), 1);
             run_to_block(BLOCKS_PER_ERA);
+            let balance = Balances::total_balance(&1001);
             assert_ok!(Staking::undelegate(Origin::signed(1001)));
-            // reward not paid yet, hence not deleted yet
-            assert!(Delegators::<Test>::contains_key(&1001));
-            let delegator_data = Staking::delegators(&1001);
-            assert_eq!(delegator_data.unrewarded_since.unwrap(), 0);
-            assert!(!delegator_data.delegating);
-            assert_eq!(Staking::delegator_count(), 1);
+            // era 0 is paid at once, hence deleted
+            assert!(Balances::total_balance(&1001) > balance);
+            assert!(!Delegators::<Test>::contains_key(&1001));
+            assert_eq!(Staking::delegator_count(), 0);
             assert_eq!(Staking::active_delegator_count(), 0);
         });
 }
@@ -2968,3 +3592,209 @@ fn elected_validators_should_rotate() {
             assert_eq_uvec!(Session::validators(), vec![11, 21]);
         });
 }
+
+#[test]
+fn emission_schedule_decays() {
+    let schedule = EmissionSchedule::<Balance> {
+        start_era: 2,
+        initial_era_budget: 1_000,
+        period: 3,
+        decay: Perbill::from_percent(50),
+        validator_share: Perbill::from_percent(40),
+    };
+    assert_eq!(schedule.era_budget(1), 0);
+    assert_eq!(schedule.era_budget(2), 1_000);
+    assert_eq!(schedule.era_budget(4), 1_000);
+    assert_eq!(schedule.era_budget(5), 500);
+    assert_eq!(schedule.era_budget(8), 250);
+    assert_eq!(
+        schedule.emission(5),
+        EraEmission {
+            era: 5,
+            validators: 200,
+            delegators: 300,
+        }
+    );
+    assert_eq!(
+        schedule
+            .project(3, 3)
+            .iter()
+            .map(|emission| (emission.era, emission.validators + emission.delegators))
+            .collect::<Vec<_>>(),
+        vec![(3, 1_000), (4, 1_000), (5, 500)]
+    );
+}
+
+#[test]
+fn emission_schedule_pays_era_budget() {
+    ExtBuilder::default()
+        .session_per_era(6)
+        .build_and_execute(|| {
+            let schedule = EmissionSchedule {
+                start_era: 0,
+                initial_era_budget: 4_000,
+                period: 1,
+                decay: Perbill::from_percent(50),
+                validator_share: Perbill::from_percent(50),
+            };
+            assert_noop!(
+                Staking::set_emission_schedule(
+                    Origin::root(),
+                    Some(EmissionSchedule {
+                        period: 0,
+                        ..schedule.clone()
+                    })
+                ),
+                Error::<Test>::InvalidEmissionSchedule
+            );
+            // a schedule that never decays would emit without end
+            assert_noop!(
+                Staking::set_emission_schedule(
+                    Origin::root(),
+                    Some(EmissionSchedule {
+                        decay: Perbill::one(),
+                        ..schedule.clone()
+                    })
+                ),
+                Error::<Test>::InvalidEmissionSchedule
+            );
+            assert_ok!(Staking::set_emission_schedule(
+                Origin::root(),
+                Some(schedule)
+            ));
+            assert_eq!(
+                Staking::projected_emission(1, 1),
+                vec![EraEmission {
+                    era: 1,
+                    validators: 1_000,
+                    delegators: 1_000,
+                }]
+            );
+
+            Payee::<Test>::insert(11, RewardDestination::Controller);
+            Payee::<Test>::insert(21, RewardDestination::Controller);
+            let init_balance_10 = Balances::total_balance(&10);
+            let init_balance_20 = Balances::total_balance(&20);
+            let init_balance_1001 = Balances::total_balance(&1001);
+            let remainder = Staking::remainder_mining_reward().unwrap_or(TOTAL_MINING_REWARD);
+            <Pallet<Test>>::reward_by_ids(vec![(11, 50)]);
+            <Pallet<Test>>::reward_by_ids(vec![(21, 50)]);
+
+            start_session(6);
+            assert_eq!(Staking::active_era().unwrap().index, 1);
+            // validators share half of the 4000 budget of era 0
+            assert_eq!(Balances::total_balance(&10), init_balance_10 + 1_000);
+            assert_eq!(Balances::total_balance(&20), init_balance_20 + 1_000);
+            assert_eq!(Staking::delegator_reward_pool(), 2_000);
+            // the whole budget is taken out of the remaining mining reward
+            assert_eq!(Staking::remainder_mining_reward(), Some(remainder - 4_000));
+
+            // 1001 is paid out of the delegator reward pool
+            run_to_block(BLOCKS_PER_ERA + 1);
+            assert_eq!(Balances::total_balance(&1001), init_balance_1001 + 2_000);
+            assert_eq!(Staking::delegator_reward_pool(), 0);
+            assert_eq!(Staking::remainder_mining_reward(), Some(remainder - 4_000));
+            // the pool fell short, the rest of era 0 is paid later
+            assert_eq!(Staking::partial_delegator_payout(&1001), 2_000);
+            assert_eq!(Staking::delegators(&1001).unrewarded_since, Some(0));
+        });
+}
+
+#[test]
+fn emission_schedule_switch_keeps_unpaid_pool() {
+    ExtBuilder::default()
+        .session_per_era(6)
+        .build_and_execute(|| {
+            let schedule = EmissionSchedule {
+                start_era: 0,
+                initial_era_budget: 4_000,
+                period: 1,
+                decay: Perbill::from_percent(50),
+                validator_share: Perbill::from_percent(50),
+            };
+            assert_ok!(Staking::set_emission_schedule(
+                Origin::root(),
+                Some(schedule.clone())
+            ));
+            let remainder = Staking::remainder_mining_reward().unwrap_or(TOTAL_MINING_REWARD);
+            <Pallet<Test>>::reward_by_ids(vec![(11, 100)]);
+            start_session(6);
+            assert_eq!(Staking::delegator_reward_pool(), 2_000);
+
+            // the unpaid pool carries over to a new schedule
+            assert_ok!(Staking::set_emission_schedule(
+                Origin::root(),
+                Some(EmissionSchedule {
+                    initial_era_budget: 2_000,
+                    ..schedule
+                })
+            ));
+            assert_eq!(Staking::delegator_reward_pool(), 2_000);
+            assert_eq!(Staking::remainder_mining_reward(), Some(remainder - 4_000));
+
+            // and goes back to the remaining mining reward without a schedule
+            assert_ok!(Staking::set_emission_schedule(Origin::root(), None));
+            assert_eq!(Staking::delegator_reward_pool(), 0);
+            assert_eq!(Staking::remainder_mining_reward(), Some(remainder - 2_000));
+        });
+}
+
+#[test]
+fn emission_schedule_capped_by_remaining_mining_reward() {
+    ExtBuilder::default()
+        .session_per_era(6)
+        .build_and_execute(|| {
+            RemainderMiningReward::<Test>::put(3_000);
+            assert_ok!(Staking::set_emission_schedule(
+                Origin::root(),
+                Some(EmissionSchedule {
+                    start_era: 0,
+                    initial_era_budget: 4_000,
+                    period: 1,
+                    decay: Perbill::from_percent(50),
+                    validator_share: Perbill::from_percent(50),
+                })
+            ));
+            Payee::<Test>::insert(11, RewardDestination::Controller);
+            let init_balance_10 = Balances::total_balance(&10);
+            <Pallet<Test>>::reward_by_ids(vec![(11, 100)]);
+            start_session(6);
+            // validators get their 2000, delegators only the 1000 left
+            assert_eq!(Balances::total_balance(&10), init_balance_10 + 2_000);
+            assert_eq!(Staking::delegator_reward_pool(), 1_000);
+            assert_eq!(Staking::remainder_mining_reward(), Some(0));
+        });
+}
+
+#[test]
+fn emission_schedule_returns_unearned_pool() {
+    ExtBuilder::default()
+        .session_per_era(6)
+        .build_and_execute(|| {
+            let reward = 21369858941948251800;
+            assert_ok!(Staking::set_emission_schedule(
+                Origin::root(),
+                Some(EmissionSchedule {
+                    start_era: 0,
+                    initial_era_budget: reward * 4,
+                    period: 1,
+                    decay: Perbill::from_percent(50),
+                    validator_share: Perbill::from_percent(50),
+                })
+            ));
+            let init_balance_1001 = Balances::total_balance(&1001);
+            let remainder = Staking::remainder_mining_reward().unwrap_or(TOTAL_MINING_REWARD);
+            <Pallet<Test>>::reward_by_ids(vec![(11, 100)]);
+            start_session(6);
+            assert_eq!(Staking::delegator_reward_pool(), reward * 2);
+
+            // 1001 is the only delegator, what it did not earn goes back once it is paid
+            run_to_block(BLOCKS_PER_ERA + 1);
+            assert_eq!(Balances::total_balance(&1001), init_balance_1001 + reward);
+            assert_eq!(Staking::delegator_reward_pool(), 0);
+            assert_eq!(
+                Staking::remainder_mining_reward(),
+                Some(remainder - reward * 3)
+            );
+        });
+}
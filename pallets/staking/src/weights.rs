@@ -111,10 +111,13 @@ impl<T: frame_system::Config> WeightInfo for SubstrateWeight<T> {
             .saturating_add(T::DbWeight::get().reads(2 as Weight))
             .saturating_add(T::DbWeight::get().writes(1 as Weight))
     }
-    fn delegate(_n: u32) -> Weight {
-        (46_255_000 as Weight)
-            .saturating_add(T::DbWeight::get().reads(8 as Weight))
-            .saturating_add(T::DbWeight::get().writes(5 as Weight))
+    fn delegate(n: u32) -> Weight {
+        (34_812_000 as Weight) // Standard Error: 21_000
+            .saturating_add((5_716_000 as Weight).saturating_mul(n as Weight))
+            .saturating_add(T::DbWeight::get().reads(6 as Weight))
+            .saturating_add(T::DbWeight::get().reads((3 as Weight).saturating_mul(n as Weight)))
+            .saturating_add(T::DbWeight::get().writes(4 as Weight))
+            .saturating_add(T::DbWeight::get().writes((2 as Weight).saturating_mul(n as Weight)))
     }
     fn undelegate() -> Weight {
         (37_300_000 as Weight)
@@ -256,10 +259,13 @@ impl WeightInfo for () {
             .saturating_add(RocksDbWeight::get().reads(2 as Weight))
             .saturating_add(RocksDbWeight::get().writes(1 as Weight))
     }
-    fn delegate(_n: u32) -> Weight {
-        (46_255_000 as Weight)
-            .saturating_add(RocksDbWeight::get().reads(8 as Weight))
-            .saturating_add(RocksDbWeight::get().writes(5 as Weight))
+    fn delegate(n: u32) -> Weight {
+        (34_812_000 as Weight) // Standard Error: 21_000
+            .saturating_add((5_716_000 as Weight).saturating_mul(n as Weight))
+            .saturating_add(RocksDbWeight::get().reads(6 as Weight))
+            .saturating_add(RocksDbWeight::get().reads((3 as Weight).saturating_mul(n as Weight)))
+            .saturating_add(RocksDbWeight::get().writes(4 as Weight))
+            .saturating_add(RocksDbWeight::get().writes((2 as Weight).saturating_mul(n as Weight)))
     }
     fn undelegate() -> Weight {
         (37_300_000 as Weight)
@@ -121,6 +121,26 @@ fn create_credit_tips<T: Config>(t: u32, hash: T::Hash, value: u64) -> Result<()
     Ok(())
 }
 
+// Create `t` new adjustments for the credit adjustment with `hash`.
+// This function automatically makes the adjustment able to close.
+fn create_credit_adjustments<T: Config>(
+    t: u32,
+    hash: T::Hash,
+    value: i64,
+) -> Result<(), &'static str> {
+    for i in 0..t {
+        let caller = account("member", i, SEED);
+        ensure!(T::Tippers::contains(&caller), "caller is not a tipper");
+        TipsMod::<T>::credit_adjust(RawOrigin::Signed(caller).into(), hash, value)?;
+    }
+    CreditAdjustments::<T>::mutate(hash, |maybe_adjustment| {
+        if let Some(open) = maybe_adjustment {
+            open.closes = Some(T::BlockNumber::zero());
+        }
+    });
+    Ok(())
+}
+
 fn setup_pot_account<T: Config>() {
     let pot_account = TipsMod::<T>::account_id();
     let value = T::Currency::minimum_balance().saturating_mul(1_000_000_000u32.into());
@@ -237,6 +257,9 @@ benchmarks! {
 
         create_tips::<T>(t, hash.clone(), value)?;
 
+        // Worst case: the payout is checked against a budget
+        TipBudgets::<T>::insert(TipsMod::<T>::funding_source(), value.saturating_mul(2u32.into()));
+
         let caller = account("caller", t, SEED);
         // Whitelist caller account from further DB operations.
         let caller_key = frame_system::Account::<T>::hashed_key_for(&caller);
@@ -262,6 +285,8 @@ benchmarks! {
         ensure!(Tips::<T>::contains_key(hash), "tip does not exist");
 
         create_credit_tips::<T>(t, hash.clone(), value)?;
+        // weighting reads the credit score of every tipper
+        CreditTipAggregation::<T>::put(CreditAggregation::Weighted);
 
         let caller = account("caller", t, SEED);
         // Whitelist caller account from further DB operations.
@@ -290,5 +315,72 @@ benchmarks! {
         ensure!(Tips::<T>::contains_key(hash), "tip does not exist");
     }: _(RawOrigin::Root, hash)
 
+    credit_adjust_new {
+        let t in 1 .. MAX_TIPPERS;
+
+        let (caller, _, beneficiary, _) = setup_credit_tip::<T>(0, t)?;
+        // Whitelist caller account from further DB operations.
+        let caller_key = frame_system::Account::<T>::hashed_key_for(&caller);
+        frame_benchmarking::benchmarking::add_to_whitelist(caller_key.into());
+    }: _(RawOrigin::Signed(caller), H256::zero(), beneficiary, -10)
+
+    credit_adjust {
+        let t in 1 .. MAX_TIPPERS;
+        let (member, _, beneficiary, _) = setup_credit_tip::<T>(0, t)?;
+        TipsMod::<T>::credit_adjust_new(
+            RawOrigin::Signed(member).into(),
+            H256::zero(),
+            beneficiary.clone(),
+            -10
+        )?;
+        let hash = T::Hashing::hash_of(&(&H256::zero(), &beneficiary));
+        ensure!(CreditAdjustments::<T>::contains_key(hash), "adjustment does not exist");
+        create_credit_adjustments::<T>(t - 1, hash.clone(), -10)?;
+        let caller = account("member", t - 1, SEED);
+        // Whitelist caller account from further DB operations.
+        let caller_key = frame_system::Account::<T>::hashed_key_for(&caller);
+        frame_benchmarking::benchmarking::add_to_whitelist(caller_key.into());
+    }: _(RawOrigin::Signed(caller), hash, -10)
+
+    close_credit_adjust {
+        let t in 1 .. MAX_TIPPERS;
+
+        // Set up a new credit adjustment
+        let (member, _, beneficiary, _) = setup_credit_tip::<T>(0, t)?;
+        TipsMod::<T>::credit_adjust_new(
+            RawOrigin::Signed(member).into(),
+            H256::zero(),
+            beneficiary.clone(),
+            -10
+        )?;
+        let hash = T::Hashing::hash_of(&(&H256::zero(), &beneficiary));
+        ensure!(CreditAdjustments::<T>::contains_key(hash), "adjustment does not exist");
+
+        create_credit_adjustments::<T>(t, hash.clone(), -10)?;
+
+        let caller = account("caller", t, SEED);
+        // Whitelist caller account from further DB operations.
+        let caller_key = frame_system::Account::<T>::hashed_key_for(&caller);
+        frame_benchmarking::benchmarking::add_to_whitelist(caller_key.into());
+    }: _(RawOrigin::Signed(caller), hash)
+
+    retract_credit_adjust {
+        let (member, _, beneficiary, _) = setup_credit_tip::<T>(0, MAX_TIPPERS)?;
+        TipsMod::<T>::credit_adjust_new(
+            RawOrigin::Signed(member.clone()).into(),
+            H256::zero(),
+            beneficiary.clone(),
+            -10
+        )?;
+        let hash = T::Hashing::hash_of(&(&H256::zero(), &beneficiary));
+        ensure!(CreditAdjustments::<T>::contains_key(hash), "adjustment does not exist");
+        // Whitelist caller account from further DB operations.
+        let caller_key = frame_system::Account::<T>::hashed_key_for(&member);
+        frame_benchmarking::benchmarking::add_to_whitelist(caller_key.into());
+    }: _(RawOrigin::Signed(member), hash)
+    verify {
+        ensure!(!CreditAdjustments::<T>::contains_key(hash), "adjustment not retracted");
+    }
+
     impl_benchmark_test_suite!(TipsMod, crate::tests::new_test_ext(), crate::tests::Test);
 }
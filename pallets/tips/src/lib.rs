@@ -28,6 +28,15 @@
 //! close of the countdown period, the median of all declared tips is paid to the reported
 //! beneficiary, along with any finders fee, in case of a public (and bonded) original report.
 //!
+//! Tips which finished their countdown are closed automatically in `on_idle`. Tips which don't
+//! reach the threshold within `TipExpiry` blocks are pruned there too, and the finder's deposit is
+//! returned or slashed according to `ExpiredTipDeposit`. Credit adjustments are closed and pruned
+//! the same way.
+//!
+//! Tips are paid from the `TipFundingSource`: the treasury, a designated account or an asset held
+//! by a designated account. Each source may be given a budget per `TipBudgetPeriod`, a tip which
+//! would exceed it stays open until the next period.
+//!
 //!
 //! ### Terminology
 //!
@@ -51,6 +60,18 @@
 //! - `tip_new` - Report an item worthy of a tip and declare a specific amount to tip.
 //! - `tip` - Declare or redeclare an amount to tip for a particular reason.
 //! - `close_tip` - Close and pay out a tip.
+//! - `set_tip_funding_source` - Choose where tips are paid from.
+//! - `set_tip_budget` - Limit what a funding source pays out per budget period.
+//!
+//! Credit tipping:
+//! - `credit_tip_new` - Report an item worthy of credit and declare the credit to add.
+//! - `credit_tip` - Declare or redeclare the credit to add for a particular reason.
+//! - `close_credit_tip` - Close a credit tip and add the aggregated credit.
+//! - `set_credit_tip_aggregation` - Choose how the declared credits are aggregated.
+//! - `credit_adjust_new` - Open a signed credit adjustment for a reason hash, positive or negative.
+//! - `credit_adjust` - Declare or redeclare the adjustment for a particular reason hash.
+//! - `close_credit_adjust` - Close a credit adjustment and apply the median adjustment.
+//! - `retract_credit_adjust` - Retract a credit adjustment opened by the sender.
 
 #![cfg_attr(not(feature = "std"), no_std)]
 
@@ -59,19 +80,27 @@ mod tests;
 pub mod weights;
 
 use sp_runtime::{
-    traits::{BadOrigin, Hash, TrailingZeroInput, Zero},
-    Percent, RuntimeDebug,
+    traits::{
+        BadOrigin, CheckedDiv, Hash, One, Saturating, TrailingZeroInput, UniqueSaturatedFrom,
+        UniqueSaturatedInto, Zero,
+    },
+    DispatchError, Percent, RuntimeDebug,
 };
 use sp_std::prelude::*;
 
 use codec::{Decode, Encode};
 use frame_support::{
+    dispatch::DispatchResult,
+    ensure,
+    storage::{with_transaction, TransactionOutcome},
     traits::{
-        ContainsLengthBound, Currency, EnsureOrigin, ExistenceRequirement::KeepAlive, Get,
-        OnUnbalanced, ReservableCurrency, SortedMembers,
+        fungibles, ContainsLengthBound, Currency, EnsureOrigin, ExistenceRequirement::KeepAlive,
+        Get, OnUnbalanced, ReservableCurrency, SortedMembers,
     },
+    weights::Weight,
     Parameter,
 };
+use sp_core::H256;
 
 pub use pallet::*;
 use pallet_credit::CreditInterface;
@@ -79,6 +108,11 @@ pub use weights::WeightInfo;
 
 pub type BalanceOf<T> = pallet_treasury::BalanceOf<T>;
 pub type NegativeImbalanceOf<T> = pallet_treasury::NegativeImbalanceOf<T>;
+pub type AssetIdOf<T> =
+    <<T as Config>::Assets as fungibles::Inspect<<T as frame_system::Config>::AccountId>>::AssetId;
+pub type AssetBalanceOf<T> =
+    <<T as Config>::Assets as fungibles::Inspect<<T as frame_system::Config>::AccountId>>::Balance;
+pub type FundingSourceOf<T> = FundingSource<<T as frame_system::Config>::AccountId, AssetIdOf<T>>;
 
 /// An open tipping "motion". Retains all details of a tip including information on the finder
 /// and the members who have voted.
@@ -109,6 +143,68 @@ pub struct OpenTip<
     credits: Vec<(AccountId, u64)>,
 }
 
+/// An open credit adjustment. Tippers declare signed adjustments of the credit of `who`, a
+/// negative adjustment penalises `who`.
+#[derive(Clone, Eq, PartialEq, Encode, Decode, RuntimeDebug, scale_info::TypeInfo)]
+pub struct OpenCreditAdjustment<AccountId: Parameter, BlockNumber: Parameter> {
+    /// The hash of the reason for the adjustment, e.g. of the evidence of an abuse.
+    reason: H256,
+    /// The account whose credit is adjusted.
+    who: AccountId,
+    /// The account who began this adjustment.
+    finder: AccountId,
+    /// The block number at which this adjustment will close if `Some`. If `None`, then no
+    /// closing is scheduled.
+    closes: Option<BlockNumber>,
+    /// The members who have declared an adjustment. Sorted by AccountId.
+    adjustments: Vec<(AccountId, i64)>,
+}
+
+/// What happens to the finder's deposit of a tip which expires.
+#[derive(Clone, Copy, Eq, PartialEq, Encode, Decode, RuntimeDebug, scale_info::TypeInfo)]
+pub enum DepositPolicy {
+    /// The deposit is returned to the finder.
+    Return,
+    /// The deposit is slashed.
+    Slash,
+}
+
+/// Where the payout of a tip comes from.
+#[derive(Clone, Eq, PartialEq, Encode, Decode, RuntimeDebug, scale_info::TypeInfo)]
+pub enum FundingSource<AccountId, AssetId> {
+    /// The treasury pot.
+    Treasury,
+    /// A designated account, e.g. the `TipPaymentAddress`.
+    Account(AccountId),
+    /// An asset held by a designated account. Tips are paid one smallest unit of the asset per
+    /// smallest unit of the currency.
+    Asset(AssetId, AccountId),
+}
+
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, RuntimeDebug, scale_info::TypeInfo)]
+pub enum Releases {
+    V1_0_0,
+}
+
+/// How the credits declared by tippers are aggregated when a credit tip closes.
+#[derive(Clone, Copy, Eq, PartialEq, Encode, Decode, RuntimeDebug, scale_info::TypeInfo)]
+pub enum CreditAggregation {
+    /// The median of the declared credits.
+    Median,
+    /// The mean of the declared credits after dropping the given percent of the lowest and
+    /// of the highest ones.
+    TrimmedMean(Percent),
+    /// The median of the declared credits, each weighted by the credit score of its tipper.
+    /// Falls back to `Median` if no tipper has a credit score.
+    Weighted,
+}
+
+impl Default for CreditAggregation {
+    fn default() -> Self {
+        CreditAggregation::Median
+    }
+}
+
 #[frame_support::pallet]
 pub mod pallet {
     use super::*;
@@ -133,6 +229,10 @@ pub mod pallet {
         #[pallet::constant]
         type MaximumCreditReward: Get<u64>;
 
+        /// Maximum credit penalty of a negative credit adjustment
+        #[pallet::constant]
+        type MaximumCreditPenalty: Get<u64>;
+
         /// The amount held on deposit per byte within the tip report reason or bounty description.
         #[pallet::constant]
         type DataDepositPerByte: Get<BalanceOf<Self>>;
@@ -141,6 +241,14 @@ pub mod pallet {
         #[pallet::constant]
         type TipCountdown: Get<Self::BlockNumber>;
 
+        /// The period after which a tip that has not achieved threshold tippers is pruned.
+        #[pallet::constant]
+        type TipExpiry: Get<Self::BlockNumber>;
+
+        /// What happens to the finder's deposit of an expired tip.
+        #[pallet::constant]
+        type ExpiredTipDeposit: Get<DepositPolicy>;
+
         /// The percent of the final tip which goes to the original reporter of the tip.
         #[pallet::constant]
         type TipFindersFee: Get<Percent>;
@@ -149,6 +257,13 @@ pub mod pallet {
         #[pallet::constant]
         type TipReportDepositBase: Get<BalanceOf<Self>>;
 
+        /// The period over which the spending of a tip funding source is limited by its budget.
+        #[pallet::constant]
+        type TipBudgetPeriod: Get<Self::BlockNumber>;
+
+        /// The assets tips may be paid in.
+        type Assets: fungibles::Transfer<Self::AccountId>;
+
         /// Origin from which tippers must come.
         ///
         /// `ContainsLengthBound::max_len` must be cost free (i.e. no storage read or heavy
@@ -186,6 +301,86 @@ pub mod pallet {
     #[pallet::getter(fn tip_payment_address)]
     pub type TipPaymentAddress<T: Config> = StorageValue<_, T::AccountId, OptionQuery>;
 
+    /// Where tips are paid from, the `TipPaymentAddress` if `None`.
+    #[pallet::storage]
+    #[pallet::getter(fn tip_funding_source)]
+    pub type TipFundingSource<T: Config> = StorageValue<_, FundingSourceOf<T>, OptionQuery>;
+
+    /// The maximum paid out by a funding source per `TipBudgetPeriod`, unlimited if `None`.
+    #[pallet::storage]
+    #[pallet::getter(fn tip_budgets)]
+    pub type TipBudgets<T: Config> =
+        StorageMap<_, Blake2_128Concat, FundingSourceOf<T>, BalanceOf<T>, OptionQuery>;
+
+    /// The budget period of the last payout of a funding source and what it paid in that period.
+    #[pallet::storage]
+    pub type TipSpending<T: Config> = StorageMap<
+        _,
+        Blake2_128Concat,
+        FundingSourceOf<T>,
+        (T::BlockNumber, BalanceOf<T>),
+        OptionQuery,
+    >;
+
+    /// The block at which a tip expires if it has not achieved threshold tippers.
+    #[pallet::storage]
+    #[pallet::getter(fn tip_expiries)]
+    pub type TipExpiries<T: Config> =
+        StorageMap<_, Twox64Concat, T::Hash, T::BlockNumber, OptionQuery>;
+
+    /// The tips due at a block, to close or to expire, visited by `on_idle` once the block is
+    /// reached. Entries of tips which were closed since are skipped.
+    #[pallet::storage]
+    pub type TipSchedule<T: Config> =
+        StorageDoubleMap<_, Twox64Concat, T::BlockNumber, Twox64Concat, T::Hash, (), OptionQuery>;
+
+    /// The next block of `TipSchedule` visited by `on_idle`, the first visit starts at the
+    /// current block.
+    #[pallet::storage]
+    pub type TipScheduleCursor<T: Config> = StorageValue<_, T::BlockNumber, OptionQuery>;
+
+    /// The raw key of the last tip scheduled by the migration to `TipSchedule`, empty if it
+    /// hasn't started yet and `None` once it is done.
+    #[pallet::storage]
+    pub type TipMigrationCursor<T: Config> = StorageValue<_, Vec<u8>, OptionQuery>;
+
+    /// The block at which a credit adjustment expires if it has not achieved threshold tippers.
+    #[pallet::storage]
+    #[pallet::getter(fn credit_adjustment_expiries)]
+    pub type CreditAdjustmentExpiries<T: Config> =
+        StorageMap<_, Twox64Concat, T::Hash, T::BlockNumber, OptionQuery>;
+
+    /// The credit adjustments due at a block, to close or to expire, visited by `on_idle` once
+    /// the block is reached.
+    #[pallet::storage]
+    pub type CreditAdjustmentSchedule<T: Config> =
+        StorageDoubleMap<_, Twox64Concat, T::BlockNumber, Twox64Concat, T::Hash, (), OptionQuery>;
+
+    /// The next block of `CreditAdjustmentSchedule` visited by `on_idle`, the first visit starts
+    /// at the current block.
+    #[pallet::storage]
+    pub type CreditAdjustmentScheduleCursor<T: Config> =
+        StorageValue<_, T::BlockNumber, OptionQuery>;
+
+    #[pallet::storage]
+    pub type StorageVersion<T: Config> = StorageValue<_, Releases, OptionQuery>;
+
+    /// Credit adjustments that are not yet completed. Keyed by the hash of `(reason, who)`.
+    #[pallet::storage]
+    #[pallet::getter(fn credit_adjustments)]
+    pub type CreditAdjustments<T: Config> = StorageMap<
+        _,
+        Twox64Concat,
+        T::Hash,
+        OpenCreditAdjustment<T::AccountId, T::BlockNumber>,
+        OptionQuery,
+    >;
+
+    /// The rule aggregating the credits declared for a credit tip
+    #[pallet::storage]
+    #[pallet::getter(fn credit_tip_aggregation)]
+    pub type CreditTipAggregation<T: Config> = StorageValue<_, CreditAggregation, ValueQuery>;
+
     #[pallet::event]
     #[pallet::generate_deposit(pub(super) fn deposit_event)]
     pub enum Event<T: Config> {
@@ -197,14 +392,33 @@ pub mod pallet {
         TipClosing(T::Hash),
         /// A tip suggestion has been closed. \[tip_hash, who, payout\]
         TipClosed(T::Hash, T::AccountId, BalanceOf<T>),
-        /// A credit tip suggestion has been closed. \[tip_hash, who, add_credit\]
-        CreditTipClosed(T::Hash, T::AccountId, u64),
+        /// A credit tip suggestion has been closed. \[tip_hash, who, add_credit, aggregation\]
+        CreditTipClosed(T::Hash, T::AccountId, u64, CreditAggregation),
+        /// A credit tip closed without any active tipper, no credit is added. \[tip_hash, who\]
+        CreditTipClosedWithoutTippers(T::Hash, T::AccountId),
         /// A tip suggestion has been retracted. \[tip_hash\]
         TipRetracted(T::Hash),
         /// A tip suggestion has been slashed. \[tip_hash, finder, deposit\]
         TipSlashed(T::Hash, T::AccountId, BalanceOf<T>),
         /// Set up a tip payment address
         SetPaymentAddress(T::AccountId),
+        /// Set the credit tip aggregation rule
+        SetCreditTipAggregation(CreditAggregation),
+        /// A tip expired without achieving threshold tippers. \[tip_hash, finder, deposit,
+        /// deposit_policy\]
+        TipExpired(T::Hash, T::AccountId, BalanceOf<T>, DepositPolicy),
+        /// A new credit adjustment has been opened. \[adjustment_hash\]
+        NewCreditAdjustment(T::Hash),
+        /// A credit adjustment has been closed. \[adjustment_hash, who, adjustment\]
+        CreditAdjustmentClosed(T::Hash, T::AccountId, i64),
+        /// A credit adjustment has been retracted. \[adjustment_hash\]
+        CreditAdjustmentRetracted(T::Hash),
+        /// A credit adjustment expired without achieving threshold tippers. \[adjustment_hash\]
+        CreditAdjustmentExpired(T::Hash),
+        /// Set the source tips are paid from
+        SetTipFundingSource(FundingSourceOf<T>),
+        /// Set the budget of a tip funding source. \[source, budget\]
+        SetTipBudget(FundingSourceOf<T>, Option<BalanceOf<T>>),
     }
 
     /// Old name generated by `decl_event`.
@@ -225,6 +439,35 @@ pub mod pallet {
         StillOpen,
         /// The tip cannot be claimed/closed because it's still in the countdown period.
         Premature,
+        /// The tip cannot be paid out because its funding source exhausted the budget of the
+        /// current period.
+        TipBudgetExceeded,
+    }
+
+    #[pallet::hooks]
+    impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {
+        fn on_idle(now: T::BlockNumber, remaining_weight: Weight) -> Weight {
+            let mut weight = Self::migrate_tips(now, remaining_weight);
+            weight = weight.saturating_add(Self::process_tips(
+                now,
+                remaining_weight.saturating_sub(weight),
+            ));
+            weight.saturating_add(Self::process_credit_adjustments(
+                now,
+                remaining_weight.saturating_sub(weight),
+            ))
+        }
+
+        fn on_runtime_upgrade() -> Weight {
+            if StorageVersion::<T>::get().is_none() {
+                // the open tips are scheduled by `on_idle`, so the migration is spread over
+                // many blocks
+                TipMigrationCursor::<T>::put(Vec::<u8>::new());
+                StorageVersion::<T>::put(Releases::V1_0_0);
+                return T::DbWeight::get().reads_writes(1, 2);
+            }
+            0
+        }
     }
 
     #[pallet::call]
@@ -285,6 +528,7 @@ pub mod pallet {
                 credits: vec![],
             };
             Tips::<T>::insert(&hash, tip);
+            Self::set_tip_expiry(&hash);
             Self::deposit_event(Event::NewTip(hash));
             Ok(())
         }
@@ -316,6 +560,7 @@ pub mod pallet {
 
             Reasons::<T>::remove(&tip.reason);
             Tips::<T>::remove(&hash);
+            Self::remove_tip_expiry(&hash);
             if !tip.deposit.is_zero() {
                 let err_amount = T::Currency::unreserve(&who, tip.deposit);
                 debug_assert!(err_amount.is_zero());
@@ -376,6 +621,7 @@ pub mod pallet {
                 credits: vec![],
             };
             Tips::<T>::insert(&hash, tip);
+            Self::set_tip_expiry(&hash);
             Ok(())
         }
 
@@ -414,6 +660,7 @@ pub mod pallet {
 
             let mut tip = Tips::<T>::get(hash).ok_or(Error::<T>::UnknownTip)?;
             if Self::insert_tip_and_check_closing(&mut tip, tipper, tip_value) {
+                Self::schedule_tip(&hash, tip.closes);
                 Self::deposit_event(Event::TipClosing(hash.clone()));
             }
             Tips::<T>::insert(&hash, tip);
@@ -447,9 +694,11 @@ pub mod pallet {
                 Error::<T>::Premature
             );
             // closed.
-            Reasons::<T>::remove(&tip.reason);
+            let reason = tip.reason;
+            Self::payout_tip(hash, tip)?;
+            Reasons::<T>::remove(&reason);
             Tips::<T>::remove(hash);
-            Self::payout_tip(hash, tip);
+            Self::remove_tip_expiry(&hash);
             Ok(())
         }
 
@@ -470,6 +719,7 @@ pub mod pallet {
             T::RejectOrigin::ensure_origin(origin)?;
 
             let tip = Tips::<T>::take(hash).ok_or(Error::<T>::UnknownTip)?;
+            Self::remove_tip_expiry(&hash);
 
             if !tip.deposit.is_zero() {
                 let imbalance = T::Currency::slash_reserved(&tip.finder, tip.deposit).0;
@@ -510,6 +760,7 @@ pub mod pallet {
                 credits,
             };
             Tips::<T>::insert(&hash, tip);
+            Self::set_tip_expiry(&hash);
             Self::deposit_event(Event::NewCreditTip(hash.clone()));
             Ok(())
         }
@@ -521,6 +772,7 @@ pub mod pallet {
 
             let mut tip = Tips::<T>::get(hash).ok_or(Error::<T>::UnknownTip)?;
             if Self::insert_credit_tip_and_check_closing(&mut tip, tipper, add_credit) {
+                Self::schedule_tip(&hash, tip.closes);
                 Self::deposit_event(Event::TipClosing(hash.clone()));
             }
             Tips::<T>::insert(&hash, tip);
@@ -540,6 +792,7 @@ pub mod pallet {
             // closed.
             Reasons::<T>::remove(&tip.reason);
             Tips::<T>::remove(hash);
+            Self::remove_tip_expiry(&hash);
             Self::payout_credit_tip(hash, tip);
             Ok(())
         }
@@ -552,6 +805,150 @@ pub mod pallet {
             Self::deposit_event(Event::SetPaymentAddress(owner));
             Ok(())
         }
+
+        #[pallet::weight(10_000 + T::DbWeight::get().writes(1))]
+        pub fn set_credit_tip_aggregation(
+            origin: OriginFor<T>,
+            aggregation: CreditAggregation,
+        ) -> DispatchResult {
+            ensure_root(origin)?;
+
+            CreditTipAggregation::<T>::put(aggregation);
+            Self::deposit_event(Event::SetCreditTipAggregation(aggregation));
+            Ok(())
+        }
+
+        /// Open a credit adjustment of `who` for `reason` and declare `adjustment`.
+        ///
+        /// The dispatch origin for this call must be _Signed_ and the signing account must be a
+        /// member of the `Tippers` set.
+        #[pallet::weight(<T as Config>::WeightInfo::credit_adjust_new(T::Tippers::max_len() as u32))]
+        pub fn credit_adjust_new(
+            origin: OriginFor<T>,
+            reason: H256,
+            who: T::AccountId,
+            adjustment: i64,
+        ) -> DispatchResult {
+            let tipper = ensure_signed(origin)?;
+            ensure!(T::Tippers::contains(&tipper), BadOrigin);
+            let hash = T::Hashing::hash_of(&(&reason, &who));
+            ensure!(
+                !CreditAdjustments::<T>::contains_key(&hash),
+                Error::<T>::AlreadyKnown
+            );
+
+            let mut open = OpenCreditAdjustment {
+                reason,
+                who,
+                finder: tipper.clone(),
+                closes: None,
+                adjustments: vec![],
+            };
+            let closing =
+                Self::insert_credit_adjustment_and_check_closing(&mut open, tipper, adjustment);
+            if closing {
+                Self::schedule_credit_adjustment(&hash, open.closes);
+            }
+            CreditAdjustments::<T>::insert(&hash, open);
+            Self::set_credit_adjustment_expiry(&hash);
+            Self::deposit_event(Event::NewCreditAdjustment(hash));
+            if closing {
+                Self::deposit_event(Event::TipClosing(hash));
+            }
+            Ok(())
+        }
+
+        /// Declare or redeclare `adjustment` for an open credit adjustment, the countdown
+        /// starts once half of the tippers have declared.
+        ///
+        /// The dispatch origin for this call must be _Signed_ and the signing account must be a
+        /// member of the `Tippers` set.
+        #[pallet::weight(<T as Config>::WeightInfo::credit_adjust(T::Tippers::max_len() as u32))]
+        pub fn credit_adjust(
+            origin: OriginFor<T>,
+            hash: T::Hash,
+            adjustment: i64,
+        ) -> DispatchResult {
+            let tipper = ensure_signed(origin)?;
+            ensure!(T::Tippers::contains(&tipper), BadOrigin);
+
+            let mut open = CreditAdjustments::<T>::get(hash).ok_or(Error::<T>::UnknownTip)?;
+            if Self::insert_credit_adjustment_and_check_closing(&mut open, tipper, adjustment) {
+                Self::schedule_credit_adjustment(&hash, open.closes);
+                Self::deposit_event(Event::TipClosing(hash));
+            }
+            CreditAdjustments::<T>::insert(&hash, open);
+            Ok(())
+        }
+
+        /// Close a credit adjustment after its countdown and apply the median adjustment,
+        /// clamped to `MaximumCreditReward` and `MaximumCreditPenalty`.
+        ///
+        /// The dispatch origin for this call must be _Signed_.
+        #[pallet::weight(<T as Config>::WeightInfo::close_credit_adjust(T::Tippers::max_len() as u32))]
+        pub fn close_credit_adjust(origin: OriginFor<T>, hash: T::Hash) -> DispatchResult {
+            ensure_signed(origin)?;
+
+            let open = CreditAdjustments::<T>::get(hash).ok_or(Error::<T>::UnknownTip)?;
+            let n = open.closes.as_ref().ok_or(Error::<T>::StillOpen)?;
+            ensure!(
+                frame_system::Pallet::<T>::block_number() >= *n,
+                Error::<T>::Premature
+            );
+            CreditAdjustments::<T>::remove(hash);
+            Self::remove_credit_adjustment_expiry(&hash);
+            Self::apply_credit_adjustment(hash, open);
+            Ok(())
+        }
+
+        /// Set where tips are paid from.
+        ///
+        /// The dispatch origin for this call must be _Root_.
+        #[pallet::weight(10_000 + T::DbWeight::get().writes(1))]
+        pub fn set_tip_funding_source(
+            origin: OriginFor<T>,
+            source: FundingSourceOf<T>,
+        ) -> DispatchResult {
+            ensure_root(origin)?;
+
+            TipFundingSource::<T>::put(source.clone());
+            Self::deposit_event(Event::SetTipFundingSource(source));
+            Ok(())
+        }
+
+        /// Set the maximum `source` pays out per `TipBudgetPeriod`, `None` removes the limit.
+        ///
+        /// The dispatch origin for this call must be _Root_.
+        #[pallet::weight(10_000 + T::DbWeight::get().writes(1))]
+        pub fn set_tip_budget(
+            origin: OriginFor<T>,
+            source: FundingSourceOf<T>,
+            budget: Option<BalanceOf<T>>,
+        ) -> DispatchResult {
+            ensure_root(origin)?;
+
+            TipBudgets::<T>::set(&source, budget);
+            Self::deposit_event(Event::SetTipBudget(source, budget));
+            Ok(())
+        }
+
+        /// Retract a credit adjustment, nothing is applied.
+        ///
+        /// The dispatch origin for this call must be _Signed_ and the adjustment identified by
+        /// `hash` must have been opened by the signing account through `credit_adjust_new`.
+        ///
+        /// Emits `CreditAdjustmentRetracted` if successful.
+        #[pallet::weight(<T as Config>::WeightInfo::retract_credit_adjust())]
+        pub fn retract_credit_adjust(origin: OriginFor<T>, hash: T::Hash) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            let open = CreditAdjustments::<T>::get(&hash).ok_or(Error::<T>::UnknownTip)?;
+            ensure!(open.finder == who, Error::<T>::NotFinder);
+
+            CreditAdjustments::<T>::remove(&hash);
+            Self::remove_credit_adjustment_expiry(&hash);
+            Self::deposit_event(Event::CreditAdjustmentRetracted(hash));
+            Ok(())
+        }
     }
 }
 
@@ -573,6 +970,321 @@ impl<T: Config> Pallet<T> {
         }
     }
 
+    /// The source tips are currently paid from.
+    pub fn funding_source() -> FundingSourceOf<T> {
+        Self::tip_funding_source().unwrap_or_else(|| FundingSource::Account(Self::account_id()))
+    }
+
+    /// What `source` has paid out in the current budget period.
+    pub fn tip_spent(source: &FundingSourceOf<T>) -> BalanceOf<T> {
+        match TipSpending::<T>::get(source) {
+            Some((period, spent)) if period == Self::tip_budget_period() => spent,
+            _ => Zero::zero(),
+        }
+    }
+
+    fn tip_budget_period() -> T::BlockNumber {
+        frame_system::Pallet::<T>::block_number()
+            .checked_div(&T::TipBudgetPeriod::get())
+            .unwrap_or_else(Zero::zero)
+    }
+
+    /// What `source` will have paid in the current budget period once it pays `amount`, fails
+    /// if it exceeds the budget of `source`.
+    fn tip_spent_after(
+        source: &FundingSourceOf<T>,
+        amount: BalanceOf<T>,
+    ) -> Result<BalanceOf<T>, DispatchError> {
+        let spent = Self::tip_spent(source).saturating_add(amount);
+        if let Some(budget) = Self::tip_budgets(source) {
+            ensure!(spent <= budget, Error::<T>::TipBudgetExceeded);
+        }
+        Ok(spent)
+    }
+
+    fn transfer_tip(
+        source: &FundingSourceOf<T>,
+        to: &T::AccountId,
+        amount: BalanceOf<T>,
+    ) -> DispatchResult {
+        match source {
+            FundingSource::Treasury => T::Currency::transfer(
+                &pallet_treasury::Pallet::<T>::account_id(),
+                to,
+                amount,
+                KeepAlive,
+            ),
+            FundingSource::Account(from) => T::Currency::transfer(from, to, amount, KeepAlive),
+            FundingSource::Asset(id, from) => {
+                let amount: u128 = amount.unique_saturated_into();
+                <T::Assets as fungibles::Transfer<T::AccountId>>::transfer(
+                    *id,
+                    from,
+                    to,
+                    AssetBalanceOf::<T>::unique_saturated_from(amount),
+                    true,
+                )
+                .map(|_| ())
+            }
+        }
+    }
+
+    fn set_tip_expiry(hash: &T::Hash) {
+        let expiry = frame_system::Pallet::<T>::block_number() + T::TipExpiry::get();
+        TipExpiries::<T>::insert(hash, expiry);
+        TipSchedule::<T>::insert(expiry, hash, ());
+    }
+
+    fn remove_tip_expiry(hash: &T::Hash) {
+        if let Some(expiry) = TipExpiries::<T>::take(hash) {
+            TipSchedule::<T>::remove(expiry, hash);
+        }
+    }
+
+    fn schedule_tip(hash: &T::Hash, due: Option<T::BlockNumber>) {
+        if let Some(due) = due {
+            TipSchedule::<T>::insert(due, hash, ());
+        }
+    }
+
+    /// Schedule the tips opened before `TipSchedule` was introduced, as many as
+    /// `remaining_weight` allows from where the last call stopped.
+    fn migrate_tips(now: T::BlockNumber, remaining_weight: Weight) -> Weight {
+        let key = match TipMigrationCursor::<T>::get() {
+            Some(key) => key,
+            None => return 0,
+        };
+        let db_weight = T::DbWeight::get();
+        let per_tip = db_weight.reads_writes(2, 3);
+        let base = db_weight.reads_writes(1, 1);
+        if remaining_weight < base.saturating_add(per_tip) {
+            return 0;
+        }
+        let max = ((remaining_weight - base) / per_tip.max(1)) as usize;
+
+        let iter = if key.is_empty() {
+            Tips::<T>::iter()
+        } else {
+            Tips::<T>::iter_from(key)
+        };
+        let tips: Vec<_> = iter.take(max).collect();
+        match tips.last() {
+            Some((hash, _)) if tips.len() == max => {
+                TipMigrationCursor::<T>::put(Tips::<T>::hashed_key_for(hash))
+            }
+            _ => TipMigrationCursor::<T>::kill(),
+        }
+
+        let mut weight = base;
+        for (hash, tip) in tips {
+            // blocks already visited are scheduled at the current block
+            match Self::tip_expiries(hash) {
+                Some(expiry) => TipSchedule::<T>::insert(expiry.max(now), hash, ()),
+                // opened before expiry was introduced, expires one period from now
+                None => Self::set_tip_expiry(&hash),
+            }
+            Self::schedule_tip(&hash, tip.closes.map(|closes| closes.max(now)));
+            weight = weight.saturating_add(per_tip);
+        }
+        weight
+    }
+
+    /// Close the tips which finished their countdown and prune the expired tips, visiting the
+    /// blocks of `TipSchedule` up to `now` as far as `remaining_weight` allows.
+    fn process_tips(now: T::BlockNumber, remaining_weight: Weight) -> Weight {
+        let db_weight = T::DbWeight::get();
+        let per_tip = Self::idle_weight_per_item();
+        // read and write the cursor
+        let mut used_weight = db_weight.reads_writes(1, 1);
+        if remaining_weight < used_weight.saturating_add(per_tip) {
+            return 0;
+        }
+        let mut cursor = TipScheduleCursor::<T>::get().unwrap_or(now);
+        while cursor <= now {
+            if used_weight.saturating_add(db_weight.reads(1)) > remaining_weight {
+                break;
+            }
+            used_weight = used_weight.saturating_add(db_weight.reads(1));
+            let max_visited = ((remaining_weight - used_weight) / per_tip.max(1)) as usize;
+            // collect the keys first, entries are removed while visiting
+            let due: Vec<T::Hash> = TipSchedule::<T>::iter_key_prefix(cursor)
+                .take(max_visited.saturating_add(1))
+                .collect();
+            let all_visited = due.len() <= max_visited;
+            for hash in due.into_iter().take(max_visited) {
+                used_weight = used_weight.saturating_add(per_tip);
+                TipSchedule::<T>::remove(cursor, hash);
+                Self::process_tip(hash, now);
+            }
+            if !all_visited {
+                break;
+            }
+            cursor += One::one();
+        }
+        TipScheduleCursor::<T>::put(cursor);
+        used_weight
+    }
+
+    fn process_tip(hash: T::Hash, now: T::BlockNumber) {
+        let tip = match Tips::<T>::get(hash) {
+            Some(tip) => tip,
+            None => return,
+        };
+        match tip.closes {
+            Some(closes) if now >= closes => {
+                let reason = tip.reason;
+                let closed = if tip.credits.is_empty() {
+                    match Self::payout_tip(hash, tip) {
+                        Ok(()) => true,
+                        // over budget tips stay open until the next budget period, the others
+                        // are retried in the next block
+                        Err(e) => {
+                            let retry = if e == DispatchError::from(Error::<T>::TipBudgetExceeded) {
+                                Self::next_tip_budget_period_start()
+                            } else {
+                                now
+                            };
+                            TipSchedule::<T>::insert(retry.max(now + One::one()), hash, ());
+                            false
+                        }
+                    }
+                } else {
+                    Self::payout_credit_tip(hash, tip);
+                    true
+                };
+                if closed {
+                    Reasons::<T>::remove(&reason);
+                    Tips::<T>::remove(hash);
+                    Self::remove_tip_expiry(&hash);
+                }
+            }
+            // closed at the block it is scheduled at
+            Some(_) => {}
+            None => {
+                if Self::tip_expiries(hash).map_or(false, |expiry| now >= expiry) {
+                    Self::expire_tip(hash, tip);
+                }
+            }
+        }
+    }
+
+    /// The first block of the next budget period.
+    fn next_tip_budget_period_start() -> T::BlockNumber {
+        Self::tip_budget_period()
+            .saturating_add(One::one())
+            .saturating_mul(T::TipBudgetPeriod::get())
+    }
+
+    /// The weight `on_idle` reserves for visiting one tip or credit adjustment.
+    fn idle_weight_per_item() -> Weight {
+        let tippers = T::Tippers::max_len() as u32;
+        T::WeightInfo::close_tip(tippers)
+            .max(T::WeightInfo::close_credit_tip(tippers))
+            .max(T::WeightInfo::close_credit_adjust(tippers))
+            .saturating_add(T::DbWeight::get().reads_writes(1, 1))
+    }
+
+    fn set_credit_adjustment_expiry(hash: &T::Hash) {
+        let expiry = frame_system::Pallet::<T>::block_number() + T::TipExpiry::get();
+        CreditAdjustmentExpiries::<T>::insert(hash, expiry);
+        CreditAdjustmentSchedule::<T>::insert(expiry, hash, ());
+    }
+
+    fn remove_credit_adjustment_expiry(hash: &T::Hash) {
+        if let Some(expiry) = CreditAdjustmentExpiries::<T>::take(hash) {
+            CreditAdjustmentSchedule::<T>::remove(expiry, hash);
+        }
+    }
+
+    fn schedule_credit_adjustment(hash: &T::Hash, due: Option<T::BlockNumber>) {
+        if let Some(due) = due {
+            CreditAdjustmentSchedule::<T>::insert(due, hash, ());
+        }
+    }
+
+    /// Apply the credit adjustments which finished their countdown and prune the expired ones,
+    /// visiting the blocks of `CreditAdjustmentSchedule` up to `now` as far as
+    /// `remaining_weight` allows.
+    fn process_credit_adjustments(now: T::BlockNumber, remaining_weight: Weight) -> Weight {
+        let db_weight = T::DbWeight::get();
+        let per_item = Self::idle_weight_per_item();
+        // read and write the cursor
+        let mut used_weight = db_weight.reads_writes(1, 1);
+        if remaining_weight < used_weight.saturating_add(per_item) {
+            return 0;
+        }
+        let mut cursor = CreditAdjustmentScheduleCursor::<T>::get().unwrap_or(now);
+        while cursor <= now {
+            if used_weight.saturating_add(db_weight.reads(1)) > remaining_weight {
+                break;
+            }
+            used_weight = used_weight.saturating_add(db_weight.reads(1));
+            let max_visited = ((remaining_weight - used_weight) / per_item.max(1)) as usize;
+            let due: Vec<T::Hash> = CreditAdjustmentSchedule::<T>::iter_key_prefix(cursor)
+                .take(max_visited.saturating_add(1))
+                .collect();
+            let all_visited = due.len() <= max_visited;
+            for hash in due.into_iter().take(max_visited) {
+                used_weight = used_weight.saturating_add(per_item);
+                CreditAdjustmentSchedule::<T>::remove(cursor, hash);
+                Self::process_credit_adjustment(hash, now);
+            }
+            if !all_visited {
+                break;
+            }
+            cursor += One::one();
+        }
+        CreditAdjustmentScheduleCursor::<T>::put(cursor);
+        used_weight
+    }
+
+    fn process_credit_adjustment(hash: T::Hash, now: T::BlockNumber) {
+        let open = match CreditAdjustments::<T>::get(hash) {
+            Some(open) => open,
+            None => return,
+        };
+        match open.closes {
+            Some(closes) if now >= closes => {
+                CreditAdjustments::<T>::remove(hash);
+                Self::remove_credit_adjustment_expiry(&hash);
+                Self::apply_credit_adjustment(hash, open);
+            }
+            None if Self::credit_adjustment_expiries(hash)
+                .map_or(false, |expiry| now >= expiry) =>
+            {
+                CreditAdjustments::<T>::remove(hash);
+                Self::remove_credit_adjustment_expiry(&hash);
+                Self::deposit_event(Event::CreditAdjustmentExpired(hash));
+            }
+            _ => {}
+        }
+    }
+
+    /// Prune a tip which has not achieved threshold tippers, the finder's deposit is handled
+    /// according to `ExpiredTipDeposit`.
+    fn expire_tip(
+        hash: T::Hash,
+        tip: OpenTip<T::AccountId, BalanceOf<T>, T::BlockNumber, T::Hash>,
+    ) {
+        Reasons::<T>::remove(&tip.reason);
+        Tips::<T>::remove(hash);
+        Self::remove_tip_expiry(&hash);
+        let policy = T::ExpiredTipDeposit::get();
+        if !tip.deposit.is_zero() {
+            match policy {
+                DepositPolicy::Return => {
+                    let err_amount = T::Currency::unreserve(&tip.finder, tip.deposit);
+                    debug_assert!(err_amount.is_zero());
+                }
+                DepositPolicy::Slash => {
+                    let imbalance = T::Currency::slash_reserved(&tip.finder, tip.deposit).0;
+                    T::OnSlash::on_unbalanced(imbalance);
+                }
+            }
+        }
+        Self::deposit_event(Event::TipExpired(hash, tip.finder, tip.deposit, policy));
+    }
+
     /// Given a mutable reference to an `OpenTip`, insert the tip into it and check whether it
     /// closes, if so, then deposit the relevant event and set closing accordingly.
     ///
@@ -617,40 +1329,51 @@ impl<T: Config> Pallet<T> {
         });
     }
 
-    /// Execute the payout of a tip.
+    /// Execute the payout of a tip from the funding source. Nothing is changed if the payout
+    /// exceeds the budget of the source or the source can't pay it, the tip then stays open.
     ///
     /// Up to three balance operations.
     /// Plus `O(T)` (`T` is Tippers length).
     fn payout_tip(
         hash: T::Hash,
         tip: OpenTip<T::AccountId, BalanceOf<T>, T::BlockNumber, T::Hash>,
-    ) {
+    ) -> DispatchResult {
         let mut tips = tip.tips;
         Self::retain_active_tips(&mut tips);
         tips.sort_by_key(|i| i.1);
 
-        let treasury = Self::account_id();
+        let source = Self::funding_source();
+
+        // nothing is paid if no tipper is left
+        let total = tips.get(tips.len() / 2).map_or_else(Zero::zero, |t| t.1);
+        let spent = Self::tip_spent_after(&source, total)?;
+
+        let finders_fee = if tip.finders_fee && tip.finder != tip.who {
+            T::TipFindersFee::get() * total
+        } else {
+            Zero::zero()
+        };
+        let payout = total - finders_fee;
+        // both transfers or none
+        with_transaction(|| {
+            let res = if finders_fee.is_zero() {
+                Ok(())
+            } else {
+                Self::transfer_tip(&source, &tip.finder, finders_fee)
+            };
+            match res.and_then(|_| Self::transfer_tip(&source, &tip.who, payout)) {
+                Ok(()) => TransactionOutcome::Commit(Ok(())),
+                Err(e) => TransactionOutcome::Rollback(Err(e)),
+            }
+        })?;
 
-        let mut payout = tips[tips.len() / 2].1;
+        TipSpending::<T>::insert(&source, (Self::tip_budget_period(), spent));
         if !tip.deposit.is_zero() {
             let err_amount = T::Currency::unreserve(&tip.finder, tip.deposit);
             debug_assert!(err_amount.is_zero());
         }
-
-        if tip.finders_fee && tip.finder != tip.who {
-            // pay out the finder's fee.
-            let finders_fee = T::TipFindersFee::get() * payout;
-            payout -= finders_fee;
-            // this should go through given we checked it's at most the free balance, but still
-            // we only make a best-effort.
-            let res = T::Currency::transfer(&treasury, &tip.finder, finders_fee, KeepAlive);
-            debug_assert!(res.is_ok());
-        }
-
-        // same as above: best-effort only.
-        let res = T::Currency::transfer(&treasury, &tip.who, payout, KeepAlive);
-        debug_assert!(res.is_ok());
         Self::deposit_event(Event::TipClosed(hash, tip.who, payout));
+        Ok(())
     }
 
     fn insert_credit_tip_and_check_closing(
@@ -672,7 +1395,28 @@ impl<T: Config> Pallet<T> {
         }
     }
 
-    fn retain_active_credit_tips(tips: &mut Vec<(T::AccountId, u64)>) {
+    /// Insert the adjustment of `tipper` into an open credit adjustment and check whether it
+    /// closes, in which case its countdown is started like the one of a tip.
+    fn insert_credit_adjustment_and_check_closing(
+        open: &mut OpenCreditAdjustment<T::AccountId, T::BlockNumber>,
+        tipper: T::AccountId,
+        adjustment: i64,
+    ) -> bool {
+        match open.adjustments.binary_search_by_key(&&tipper, |x| &x.0) {
+            Ok(pos) => open.adjustments[pos] = (tipper, adjustment),
+            Err(pos) => open.adjustments.insert(pos, (tipper, adjustment)),
+        }
+        Self::retain_active_credit_tips(&mut open.adjustments);
+        let threshold = (T::Tippers::count() + 1) / 2;
+        if open.adjustments.len() >= threshold && open.closes.is_none() {
+            open.closes = Some(frame_system::Pallet::<T>::block_number() + T::TipCountdown::get());
+            true
+        } else {
+            false
+        }
+    }
+
+    fn retain_active_credit_tips<V>(tips: &mut Vec<(T::AccountId, V)>) {
         let members = T::Tippers::sorted_members();
         let mut members_iter = members.iter();
         let mut member = members_iter.next();
@@ -699,17 +1443,100 @@ impl<T: Config> Pallet<T> {
     ) {
         let mut tips = tip.credits;
         Self::retain_active_credit_tips(&mut tips);
-        tips.sort_by_key(|i| i.1);
-
-        let pay_credit = tips[tips.len() / 2].1.min(T::MaximumCreditReward::get());
 
         if !tip.deposit.is_zero() {
             let err_amount = T::Currency::unreserve(&tip.finder, tip.deposit);
             debug_assert!(err_amount.is_zero());
         }
 
-        T::CreditInterface::update_credit_by_tip(tip.who.clone(), pay_credit);
-        Self::deposit_event(Event::CreditTipClosed(hash, tip.who, pay_credit));
+        let aggregation = Self::credit_tip_aggregation();
+        match Self::aggregate_credits(tips, aggregation) {
+            Some(credit) => {
+                let pay_credit = credit.min(T::MaximumCreditReward::get());
+                T::CreditInterface::update_credit_by_tip(tip.who.clone(), pay_credit);
+                Self::deposit_event(Event::CreditTipClosed(
+                    hash,
+                    tip.who,
+                    pay_credit,
+                    aggregation,
+                ));
+            }
+            None => Self::deposit_event(Event::CreditTipClosedWithoutTippers(hash, tip.who)),
+        }
+    }
+
+    /// Apply the median of the adjustments declared by active tippers.
+    fn apply_credit_adjustment(
+        hash: T::Hash,
+        open: OpenCreditAdjustment<T::AccountId, T::BlockNumber>,
+    ) {
+        let mut adjustments = open.adjustments;
+        Self::retain_active_credit_tips(&mut adjustments);
+        if adjustments.is_empty() {
+            Self::deposit_event(Event::CreditTipClosedWithoutTippers(hash, open.who));
+            return;
+        }
+        adjustments.sort_by_key(|i| i.1);
+        let median = adjustments[adjustments.len() / 2].1;
+
+        let adjustment = if median > 0 {
+            let add_credit = (median as u64).min(T::MaximumCreditReward::get());
+            T::CreditInterface::update_credit_by_tip(open.who.clone(), add_credit);
+            add_credit as i64
+        } else if median < 0 {
+            let sub_credit = median
+                .unsigned_abs()
+                .min(T::MaximumCreditPenalty::get())
+                .min(i64::MAX as u64);
+            T::CreditInterface::update_credit_by_penalty(open.who.clone(), sub_credit, open.reason);
+            -(sub_credit as i64)
+        } else {
+            0
+        };
+        Self::deposit_event(Event::CreditAdjustmentClosed(hash, open.who, adjustment));
+    }
+
+    /// Aggregate the credits declared by tippers, `None` if there is none.
+    fn aggregate_credits(
+        mut tips: Vec<(T::AccountId, u64)>,
+        aggregation: CreditAggregation,
+    ) -> Option<u64> {
+        if tips.is_empty() {
+            return None;
+        }
+        tips.sort_by_key(|i| i.1);
+        let median = tips[tips.len() / 2].1;
+        match aggregation {
+            CreditAggregation::Median => Some(median),
+            CreditAggregation::TrimmedMean(trim) => {
+                let len = tips.len();
+                // keep at least one credit
+                let cut = (trim * len as u32).min((len as u32 - 1) / 2) as usize;
+                let kept = &tips[cut..len - cut];
+                let sum = kept
+                    .iter()
+                    .fold(0u128, |acc, (_, credit)| acc + *credit as u128);
+                Some((sum / kept.len() as u128) as u64)
+            }
+            CreditAggregation::Weighted => {
+                let weights: Vec<u64> = tips
+                    .iter()
+                    .map(|(tipper, _)| T::CreditInterface::get_credit_score(tipper).unwrap_or(0))
+                    .collect();
+                let total = weights.iter().fold(0u128, |acc, w| acc + *w as u128);
+                if total == 0 {
+                    return Some(median);
+                }
+                let mut acc = 0u128;
+                for ((_, credit), weight) in tips.iter().zip(weights) {
+                    acc += weight as u128;
+                    if acc * 2 > total {
+                        return Some(*credit);
+                    }
+                }
+                Some(median)
+            }
+        }
     }
 
     pub fn migrate_retract_tip_for_tip_new(module: &[u8], item: &[u8]) {
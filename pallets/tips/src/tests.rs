@@ -29,7 +29,7 @@ use sp_runtime::{
 };
 use sp_storage::Storage;
 
-use frame_support::traits::ConstU32;
+use frame_support::traits::{ConstU32, ConstU64, Hooks};
 use frame_support::{
     assert_noop, assert_ok, pallet_prelude::GenesisBuild, parameter_types,
     storage::StoragePrefixedMap, traits::SortedMembers, weights::Weight, PalletId,
@@ -56,6 +56,7 @@ frame_support::construct_runtime!(
         Credit: pallet_credit::{Pallet, Call, Storage, Event<T>, Config<T>},
         DeeperNode: pallet_deeper_node::{Pallet, Call, Storage, Event<T>, Config<T>},
         Timestamp: pallet_timestamp::{Pallet, Call, Storage, Inherent},
+        Assets: pallet_assets::{Pallet, Call, Storage, Event<T>},
     }
 );
 
@@ -204,20 +205,52 @@ impl pallet_deeper_node::Config for Test {
     type MaxIpLength = MaxIpLength;
     type WeightInfo = ();
 }
+parameter_types! {
+    pub const AssetDeposit: u64 = 1;
+    pub const ApprovalDeposit: u64 = 1;
+    pub const StringLimit: u32 = 50;
+    pub const MetadataDepositBase: u64 = 1;
+    pub const MetadataDepositPerByte: u64 = 1;
+}
+impl pallet_assets::Config for Test {
+    type Event = Event;
+    type Balance = u64;
+    type AssetId = u32;
+    type Currency = Balances;
+    type ForceOrigin = frame_system::EnsureRoot<u128>;
+    type AssetDeposit = AssetDeposit;
+    type AssetAccountDeposit = ConstU64<1>;
+    type StringLimit = StringLimit;
+    type MetadataDepositBase = MetadataDepositBase;
+    type MetadataDepositPerByte = MetadataDepositPerByte;
+    type ApprovalDeposit = ApprovalDeposit;
+    type Freezer = ();
+    type Extra = ();
+    type WeightInfo = ();
+}
 parameter_types! {
     pub const TipCountdown: u64 = 1;
+    pub const TipExpiry: u64 = 10;
+    pub static ExpiredTipDeposit: DepositPolicy = DepositPolicy::Return;
     pub const TipFindersFee: Percent = Percent::from_percent(20);
     pub const TipReportDepositBase: u64 = 1;
     pub const MaximumCreditReward: u64 = 15;
+    pub const MaximumCreditPenalty: u64 = 50;
+    pub const TipBudgetPeriod: u64 = 10;
 }
 impl Config for Test {
     type MaximumReasonLength = MaximumReasonLength;
     type MaximumCreditReward = MaximumCreditReward;
+    type MaximumCreditPenalty = MaximumCreditPenalty;
     type CreditInterface = Credit;
     type Tippers = TenToFourteen;
     type TipCountdown = TipCountdown;
+    type TipExpiry = TipExpiry;
+    type ExpiredTipDeposit = ExpiredTipDeposit;
     type TipFindersFee = TipFindersFee;
     type TipReportDepositBase = TipReportDepositBase;
+    type TipBudgetPeriod = TipBudgetPeriod;
+    type Assets = Assets;
     type DataDepositPerByte = DataDepositPerByte;
     type Event = Event;
     type WeightInfo = ();
@@ -417,6 +450,227 @@ fn credit_tip_new_and_check_get_level() {
     });
 }
 
+fn set_credit(who: u128, credit: u64) {
+    assert_ok!(Credit::add_or_update_credit_data(
+        RawOrigin::Root.into(),
+        who,
+        CreditData {
+            campaign_id: 0,
+            credit,
+            initial_credit_level: CreditLevel::One,
+            rank_in_initial_credit_level: 0,
+            number_of_referees: 0,
+            current_credit_level: Credit::get_credit_level(credit),
+            reward_eras: 0,
+        }
+    ));
+}
+
+#[test]
+fn credit_tip_aggregation_rules() {
+    new_test_ext().execute_with(|| {
+        let tips = vec![(10, 1), (11, 2), (12, 3), (13, 4), (14, 100)];
+        for rule in [
+            CreditAggregation::Median,
+            CreditAggregation::TrimmedMean(Percent::from_percent(20)),
+            CreditAggregation::Weighted,
+        ] {
+            assert_eq!(Tips::aggregate_credits(vec![], rule), None);
+        }
+        assert_eq!(
+            Tips::aggregate_credits(tips.clone(), CreditAggregation::Median),
+            Some(3)
+        );
+        assert_eq!(
+            Tips::aggregate_credits(
+                tips.clone(),
+                CreditAggregation::TrimmedMean(Percent::zero())
+            ),
+            Some(22)
+        );
+        assert_eq!(
+            Tips::aggregate_credits(
+                tips.clone(),
+                CreditAggregation::TrimmedMean(Percent::from_percent(20))
+            ),
+            Some(3)
+        );
+        // at most (n - 1) / 2 credits are dropped from each end
+        assert_eq!(
+            Tips::aggregate_credits(
+                tips.clone(),
+                CreditAggregation::TrimmedMean(Percent::from_percent(100))
+            ),
+            Some(3)
+        );
+        // no tipper has credit, same as median
+        assert_eq!(
+            Tips::aggregate_credits(tips.clone(), CreditAggregation::Weighted),
+            Some(3)
+        );
+        for tipper in 10..14 {
+            set_credit(tipper, 100);
+        }
+        set_credit(14, 400);
+        assert_eq!(
+            Tips::aggregate_credits(tips, CreditAggregation::Weighted),
+            Some(100)
+        );
+    });
+}
+
+#[test]
+fn close_credit_tip_with_aggregation() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(DeeperNode::im_online(Origin::signed(3)));
+        set_credit(3, 100);
+        assert_noop!(
+            Tips::set_credit_tip_aggregation(Origin::signed(10), CreditAggregation::Weighted),
+            BadOrigin
+        );
+        let rule = CreditAggregation::TrimmedMean(Percent::from_percent(34));
+        assert_ok!(Tips::set_credit_tip_aggregation(Origin::root(), rule));
+        assert_eq!(Tips::credit_tip_aggregation(), rule);
+
+        assert_ok!(Tips::credit_tip_new(
+            Origin::signed(10),
+            b"awesome.dot".to_vec(),
+            3,
+            1
+        ));
+        let h = tip_hash();
+        assert_ok!(Tips::credit_tip(Origin::signed(11), h.clone(), 8));
+        assert_ok!(Tips::credit_tip(Origin::signed(12), h.clone(), 100));
+        System::set_block_number(2);
+        assert_ok!(Tips::close_credit_tip(Origin::signed(100), h.into()));
+        assert_eq!(last_event(), TipEvent::CreditTipClosed(h, 3, 8, rule));
+        assert_eq!(Credit::user_credit(3).unwrap().credit, 108);
+    });
+}
+
+#[test]
+fn close_credit_tip_without_tippers() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(DeeperNode::im_online(Origin::signed(3)));
+        set_credit(3, 100);
+        assert_ok!(Tips::credit_tip_new(
+            Origin::signed(10),
+            b"awesome.dot".to_vec(),
+            3,
+            10
+        ));
+        let h = tip_hash();
+        assert_ok!(Tips::credit_tip(Origin::signed(11), h.clone(), 10));
+        assert_ok!(Tips::credit_tip(Origin::signed(12), h.clone(), 10));
+        System::set_block_number(2);
+
+        // all tippers left the committee before the tip is closed
+        TEN_TO_FOURTEEN.with(|v| v.borrow_mut().clear());
+        assert_ok!(Tips::close_credit_tip(Origin::signed(100), h.into()));
+        assert_eq!(last_event(), TipEvent::CreditTipClosedWithoutTippers(h, 3));
+        assert_eq!(Credit::user_credit(3).unwrap().credit, 100);
+        assert!(Tips::tips(h).is_none());
+    });
+}
+
+fn adjustment_hash(reason: H256) -> H256 {
+    BlakeTwo256::hash_of(&(reason, 3u128))
+}
+
+#[test]
+fn credit_adjust_penalises() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(DeeperNode::im_online(Origin::signed(3)));
+        set_credit(3, 300);
+        let reason = H256::repeat_byte(1);
+        let h = adjustment_hash(reason);
+
+        assert_noop!(
+            Tips::credit_adjust_new(Origin::signed(9), reason, 3, -10),
+            BadOrigin
+        );
+        assert_ok!(Tips::credit_adjust_new(Origin::signed(10), reason, 3, -10));
+        assert_eq!(last_event(), TipEvent::NewCreditAdjustment(h));
+        assert_noop!(
+            Tips::credit_adjust_new(Origin::signed(11), reason, 3, -20),
+            Error::<Test>::AlreadyKnown
+        );
+        assert_noop!(
+            Tips::close_credit_adjust(Origin::signed(100), h),
+            Error::<Test>::StillOpen
+        );
+        assert_ok!(Tips::credit_adjust(Origin::signed(11), h, -200));
+        assert_ok!(Tips::credit_adjust(Origin::signed(12), h, 5));
+        assert_eq!(last_event(), TipEvent::TipClosing(h));
+        // redeclare
+        assert_ok!(Tips::credit_adjust(Origin::signed(12), h, -100));
+        assert_noop!(
+            Tips::close_credit_adjust(Origin::signed(100), h),
+            Error::<Test>::Premature
+        );
+
+        System::set_block_number(2);
+        assert_ok!(Tips::close_credit_adjust(Origin::signed(100), h));
+        // median -100, clamped to MaximumCreditPenalty
+        assert_eq!(last_event(), TipEvent::CreditAdjustmentClosed(h, 3, -50));
+        assert_eq!(Credit::user_credit(3).unwrap().credit, 250);
+        assert_eq!(
+            Credit::credit_penalty_history(3),
+            vec![pallet_credit::CreditPenalty {
+                era: 0,
+                credit: 50,
+                reason
+            }]
+        );
+        assert!(Tips::credit_adjustments(h).is_none());
+        assert_noop!(
+            Tips::close_credit_adjust(Origin::signed(100), h),
+            Error::<Test>::UnknownTip
+        );
+    });
+}
+
+#[test]
+fn credit_adjust_rewards() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(DeeperNode::im_online(Origin::signed(3)));
+        set_credit(3, 100);
+        let reason = H256::repeat_byte(2);
+        let h = adjustment_hash(reason);
+        assert_ok!(Tips::credit_adjust_new(Origin::signed(10), reason, 3, 10));
+        assert_ok!(Tips::credit_adjust(Origin::signed(11), h, 8));
+        assert_ok!(Tips::credit_adjust(Origin::signed(12), h, -30));
+        System::set_block_number(2);
+        assert_ok!(Tips::close_credit_adjust(Origin::signed(100), h));
+        assert_eq!(last_event(), TipEvent::CreditAdjustmentClosed(h, 3, 8));
+        assert_eq!(Credit::user_credit(3).unwrap().credit, 108);
+        assert!(Credit::credit_penalty_history(3).is_empty());
+    });
+}
+
+#[test]
+fn credit_adjust_retracted_by_finder() {
+    new_test_ext().execute_with(|| {
+        let reason = H256::repeat_byte(3);
+        let h = adjustment_hash(reason);
+        assert_noop!(
+            Tips::retract_credit_adjust(Origin::signed(10), h),
+            Error::<Test>::UnknownTip
+        );
+        assert_ok!(Tips::credit_adjust_new(Origin::signed(10), reason, 3, -10));
+        assert_ok!(Tips::credit_adjust(Origin::signed(11), h, -10));
+        assert_noop!(
+            Tips::retract_credit_adjust(Origin::signed(11), h),
+            Error::<Test>::NotFinder
+        );
+        assert_ok!(Tips::retract_credit_adjust(Origin::signed(10), h));
+        assert_eq!(last_event(), TipEvent::CreditAdjustmentRetracted(h));
+        assert!(Tips::credit_adjustments(h).is_none());
+        // the reason may be adjusted again
+        assert_ok!(Tips::credit_adjust_new(Origin::signed(11), reason, 3, -10));
+    });
+}
+
 #[test]
 fn report_awesome_and_tip_works() {
     new_test_ext().execute_with(|| {
@@ -774,3 +1028,379 @@ fn genesis_funding_works() {
         );
     });
 }
+
+fn idle_weight_per_tip() -> Weight {
+    <() as WeightInfo>::close_tip(5)
+        .max(<() as WeightInfo>::close_credit_tip(5))
+        .max(<() as WeightInfo>::close_credit_adjust(5))
+}
+
+#[test]
+fn tip_expires_and_deposit_returned() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(Tips::report_awesome(
+            Origin::signed(0),
+            b"awesome.dot".to_vec(),
+            3
+        ));
+        let h = tip_hash();
+        assert_eq!(Tips::tip_expiries(h), Some(10));
+        assert_eq!(Balances::reserved_balance(0), 12);
+
+        Tips::on_idle(9, Weight::max_value());
+        assert!(Tips::tips(h).is_some());
+
+        Tips::on_idle(10, Weight::max_value());
+        assert!(Tips::tips(h).is_none());
+        assert!(Tips::tip_expiries(h).is_none());
+        assert!(Tips::reasons(BlakeTwo256::hash(b"awesome.dot")).is_none());
+        assert_eq!(
+            last_event(),
+            TipEvent::TipExpired(h, 0, 12, DepositPolicy::Return)
+        );
+        assert_eq!(Balances::reserved_balance(0), 0);
+        assert_eq!(Balances::free_balance(0), 100);
+    });
+}
+
+#[test]
+fn tip_expires_and_deposit_slashed() {
+    new_test_ext().execute_with(|| {
+        ExpiredTipDeposit::set(&DepositPolicy::Slash);
+        assert_ok!(Tips::report_awesome(
+            Origin::signed(0),
+            b"awesome.dot".to_vec(),
+            3
+        ));
+        let h = tip_hash();
+        // a tip reaching threshold tippers does not expire
+        assert_ok!(Tips::tip_new(
+            Origin::signed(10),
+            b"awesome.dot.ksm".to_vec(),
+            3,
+            10
+        ));
+        let e = tip_hash_error();
+        assert_ok!(Tips::tip(Origin::signed(11), e, 10));
+        assert_ok!(Tips::tip(Origin::signed(12), e, 10));
+        // leave the closing tip open
+        pallet_tips::Tips::<Test>::mutate(e, |tip| tip.as_mut().unwrap().closes = Some(100));
+
+        Tips::on_idle(10, Weight::max_value());
+        assert!(Tips::tips(h).is_none());
+        assert_eq!(
+            last_event(),
+            TipEvent::TipExpired(h, 0, 12, DepositPolicy::Slash)
+        );
+        assert_eq!(Balances::reserved_balance(0), 0);
+        assert_eq!(Balances::free_balance(0), 88);
+        assert!(Tips::tips(e).is_some());
+    });
+}
+
+#[test]
+fn on_idle_closes_tips() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(Balances::set_balance(RawOrigin::Root.into(), 5, 100, 0));
+        TipPaymentAddress::<Test>::put(5);
+        assert_ok!(DeeperNode::im_online(Origin::signed(4)));
+        set_credit(4, 100);
+
+        assert_ok!(Tips::tip_new(
+            Origin::signed(10),
+            b"awesome.dot".to_vec(),
+            3,
+            10
+        ));
+        let h = tip_hash();
+        assert_ok!(Tips::tip(Origin::signed(11), h, 10));
+        assert_ok!(Tips::tip(Origin::signed(12), h, 10));
+
+        assert_ok!(Tips::credit_tip_new(
+            Origin::signed(10),
+            b"awesome.ksm".to_vec(),
+            4,
+            10
+        ));
+        let c = BlakeTwo256::hash_of(&(BlakeTwo256::hash(b"awesome.ksm"), 4u128));
+        assert_ok!(Tips::credit_tip(Origin::signed(11), c, 10));
+        assert_ok!(Tips::credit_tip(Origin::signed(12), c, 10));
+
+        // scheduled to expire and to close
+        assert!(TipSchedule::<Test>::contains_key(10, h));
+        assert!(TipSchedule::<Test>::contains_key(1, h));
+
+        // still in countdown
+        Tips::on_idle(0, Weight::max_value());
+        assert!(Tips::tips(h).is_some());
+        assert!(Tips::tips(c).is_some());
+
+        Tips::on_idle(1, Weight::max_value());
+        assert!(Tips::tips(h).is_none());
+        assert!(Tips::tips(c).is_none());
+        assert_eq!(TipSchedule::<Test>::iter().count(), 0);
+        assert_eq!(Balances::free_balance(3), 10);
+        assert_eq!(Credit::user_credit(4).unwrap().credit, 110);
+    });
+}
+
+#[test]
+fn on_idle_bounded_by_weight() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(Tips::report_awesome(
+            Origin::signed(0),
+            b"awesome.dot".to_vec(),
+            3
+        ));
+        assert_ok!(Tips::report_awesome(
+            Origin::signed(1),
+            b"awesome.dot.ksm".to_vec(),
+            3
+        ));
+
+        // not enough weight for a tip
+        assert_eq!(Tips::on_idle(10, idle_weight_per_tip() - 1), 0);
+        assert_eq!(pallet_tips::Tips::<Test>::iter().count(), 2);
+
+        // one tip each time
+        assert!(Tips::on_idle(10, idle_weight_per_tip()) > 0);
+        assert_eq!(pallet_tips::Tips::<Test>::iter().count(), 1);
+        assert_eq!(TipScheduleCursor::<Test>::get(), Some(10));
+        Tips::on_idle(10, idle_weight_per_tip());
+        assert_eq!(pallet_tips::Tips::<Test>::iter().count(), 0);
+        assert_eq!(Balances::reserved_balance(0), 0);
+        assert_eq!(Balances::reserved_balance(1), 0);
+    });
+}
+
+#[test]
+fn tip_expiry_migration() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(Tips::tip_new(
+            Origin::signed(10),
+            b"awesome.dot".to_vec(),
+            3,
+            10
+        ));
+        let h = tip_hash();
+        TipExpiries::<Test>::remove(h);
+        TipSchedule::<Test>::remove(10, h);
+        System::set_block_number(5);
+
+        // the upgrade itself doesn't visit the tips
+        Tips::on_runtime_upgrade();
+        assert_eq!(StorageVersion::<Test>::get(), Some(Releases::V1_0_0));
+        assert!(Tips::tip_expiries(h).is_none());
+
+        Tips::on_idle(5, Weight::max_value());
+        assert_eq!(Tips::tip_expiries(h), Some(15));
+        assert!(TipSchedule::<Test>::contains_key(15, h));
+        assert!(TipMigrationCursor::<Test>::get().is_none());
+        Tips::on_idle(14, Weight::max_value());
+        assert!(Tips::tips(h).is_some());
+        Tips::on_idle(15, Weight::max_value());
+        assert!(Tips::tips(h).is_none());
+    });
+}
+
+#[test]
+fn on_idle_expires_and_closes_credit_adjustments() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(DeeperNode::im_online(Origin::signed(3)));
+        set_credit(3, 300);
+        let expiring = H256::repeat_byte(4);
+        let e = adjustment_hash(expiring);
+        assert_ok!(Tips::credit_adjust_new(
+            Origin::signed(10),
+            expiring,
+            3,
+            -10
+        ));
+        assert_eq!(Tips::credit_adjustment_expiries(e), Some(10));
+
+        let closing = H256::repeat_byte(5);
+        let c = adjustment_hash(closing);
+        assert_ok!(Tips::credit_adjust_new(Origin::signed(10), closing, 3, -20));
+        assert_ok!(Tips::credit_adjust(Origin::signed(11), c, -20));
+        assert_ok!(Tips::credit_adjust(Origin::signed(12), c, -20));
+
+        // still in countdown
+        Tips::on_idle(0, Weight::max_value());
+        assert!(Tips::credit_adjustments(c).is_some());
+
+        Tips::on_idle(1, Weight::max_value());
+        assert!(Tips::credit_adjustments(c).is_none());
+        assert!(Tips::credit_adjustment_expiries(c).is_none());
+        assert_eq!(Credit::user_credit(3).unwrap().credit, 280);
+        assert!(Tips::credit_adjustments(e).is_some());
+
+        Tips::on_idle(10, Weight::max_value());
+        assert!(Tips::credit_adjustments(e).is_none());
+        assert!(Tips::credit_adjustment_expiries(e).is_none());
+        assert_eq!(last_event(), TipEvent::CreditAdjustmentExpired(e));
+        assert_eq!(Credit::user_credit(3).unwrap().credit, 280);
+    });
+}
+
+#[test]
+fn on_idle_credit_adjustments_bounded_by_weight() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(Tips::credit_adjust_new(
+            Origin::signed(10),
+            H256::repeat_byte(6),
+            3,
+            -10
+        ));
+        assert_ok!(Tips::credit_adjust_new(
+            Origin::signed(10),
+            H256::repeat_byte(7),
+            3,
+            -10
+        ));
+
+        // one adjustment each time
+        Tips::on_idle(10, idle_weight_per_tip());
+        assert_eq!(CreditAdjustments::<Test>::iter().count(), 1);
+        assert_eq!(CreditAdjustmentScheduleCursor::<Test>::get(), Some(10));
+        Tips::on_idle(10, idle_weight_per_tip());
+        assert_eq!(CreditAdjustments::<Test>::iter().count(), 0);
+    });
+}
+
+fn ready_tip(reason: &[u8]) -> H256 {
+    assert_ok!(Tips::tip_new(Origin::signed(10), reason.to_vec(), 3, 10));
+    let h = BlakeTwo256::hash_of(&(BlakeTwo256::hash(reason), 3u128));
+    assert_ok!(Tips::tip(Origin::signed(11), h, 10));
+    assert_ok!(Tips::tip(Origin::signed(12), h, 10));
+    h
+}
+
+#[test]
+fn tip_budget_limits_payouts() {
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+        let source = Tips::funding_source();
+        assert_eq!(source, FundingSource::Account(0));
+        assert_noop!(
+            Tips::set_tip_budget(Origin::signed(0), source.clone(), Some(15)),
+            BadOrigin
+        );
+        assert_ok!(Tips::set_tip_budget(
+            RawOrigin::Root.into(),
+            source.clone(),
+            Some(15)
+        ));
+        assert_eq!(
+            last_event(),
+            TipEvent::SetTipBudget(source.clone(), Some(15))
+        );
+
+        let h = ready_tip(b"awesome.dot");
+        let k = ready_tip(b"awesome.ksm");
+        System::set_block_number(2);
+        assert_ok!(Tips::close_tip(Origin::signed(0), h));
+        assert_eq!(Tips::tip_spent(&source), 10);
+
+        // the budget of this period is exhausted
+        assert_noop!(
+            Tips::close_tip(Origin::signed(0), k),
+            Error::<Test>::TipBudgetExceeded
+        );
+        Tips::on_idle(2, Weight::max_value());
+        assert!(Tips::tips(k).is_some());
+        assert!(TipSchedule::<Test>::contains_key(10, k));
+        assert_eq!(Balances::free_balance(3), 10);
+
+        // paid in the next period
+        System::set_block_number(10);
+        assert_eq!(Tips::tip_spent(&source), 0);
+        Tips::on_idle(10, Weight::max_value());
+        assert!(Tips::tips(k).is_none());
+        assert_eq!(Balances::free_balance(3), 20);
+        assert_eq!(Tips::tip_spent(&source), 10);
+    });
+}
+
+#[test]
+fn tip_stays_open_if_source_cannot_pay() {
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+        assert_ok!(Balances::set_balance(RawOrigin::Root.into(), 5, 10, 0));
+        let source = FundingSource::Account(5);
+        assert_ok!(Tips::set_tip_funding_source(
+            RawOrigin::Root.into(),
+            source.clone()
+        ));
+        assert_ok!(Tips::report_awesome(
+            Origin::signed(0),
+            b"awesome.dot".to_vec(),
+            3
+        ));
+        let h = tip_hash();
+        assert_ok!(Tips::tip(Origin::signed(10), h, 10));
+        assert_ok!(Tips::tip(Origin::signed(11), h, 10));
+        assert_ok!(Tips::tip(Origin::signed(12), h, 10));
+        System::set_block_number(2);
+
+        // the finder's fee could be paid but not the rest of the tip
+        assert!(Tips::close_tip(Origin::signed(0), h).is_err());
+        assert!(Tips::tips(h).is_some());
+        assert_eq!(Tips::tip_spent(&source), 0);
+        assert_eq!(Balances::free_balance(5), 10);
+        assert_eq!(Balances::free_balance(0), 88);
+        assert_eq!(Balances::reserved_balance(0), 12);
+        Tips::on_idle(2, Weight::max_value());
+        assert!(Tips::tips(h).is_some());
+
+        assert_ok!(Balances::set_balance(RawOrigin::Root.into(), 5, 100, 0));
+        Tips::on_idle(3, Weight::max_value());
+        assert!(Tips::tips(h).is_none());
+        assert_eq!(Balances::free_balance(3), 8);
+        assert_eq!(Balances::free_balance(0), 102);
+        assert_eq!(Tips::tip_spent(&source), 10);
+    });
+}
+
+#[test]
+fn tip_funding_sources() {
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+        assert_noop!(
+            Tips::set_tip_funding_source(Origin::signed(0), FundingSource::Treasury),
+            BadOrigin
+        );
+
+        // treasury
+        Balances::make_free_balance_be(&Treasury::account_id(), 101);
+        assert_ok!(Tips::set_tip_funding_source(
+            RawOrigin::Root.into(),
+            FundingSource::Treasury
+        ));
+        assert_eq!(
+            last_event(),
+            TipEvent::SetTipFundingSource(FundingSource::Treasury)
+        );
+        let h = ready_tip(b"awesome.dot");
+        System::set_block_number(2);
+        assert_ok!(Tips::close_tip(Origin::signed(0), h));
+        assert_eq!(Balances::free_balance(3), 10);
+        assert_eq!(Treasury::pot(), 90);
+        assert_eq!(Tips::tip_spent(&FundingSource::Treasury), 10);
+
+        // asset held by account 5
+        assert_ok!(Assets::force_create(RawOrigin::Root.into(), 1, 5, true, 1));
+        assert_ok!(Assets::mint(Origin::signed(5), 1, 5, 100));
+        let source = FundingSource::Asset(1, 5);
+        assert_ok!(Tips::set_tip_funding_source(
+            RawOrigin::Root.into(),
+            source.clone()
+        ));
+        let k = ready_tip(b"awesome.ksm");
+        System::set_block_number(3);
+        assert_ok!(Tips::close_tip(Origin::signed(0), k));
+        assert_eq!(Assets::balance(1, 3), 10);
+        assert_eq!(Assets::balance(1, 5), 90);
+        assert_eq!(Balances::free_balance(3), 10);
+        assert_eq!(Tips::tip_spent(&source), 10);
+    });
+}
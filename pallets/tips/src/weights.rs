@@ -55,6 +55,10 @@ pub trait WeightInfo {
     fn close_tip(t: u32) -> Weight;
     fn close_credit_tip(t: u32) -> Weight;
     fn slash_tip(t: u32) -> Weight;
+    fn credit_adjust_new(t: u32) -> Weight;
+    fn credit_adjust(t: u32) -> Weight;
+    fn close_credit_adjust(t: u32) -> Weight;
+    fn retract_credit_adjust() -> Weight;
 }
 
 /// Weights for pallet_tips using the Substrate node and recommended hardware.
@@ -100,13 +104,14 @@ impl<T: frame_system::Config> WeightInfo for SubstrateWeight<T> {
     fn close_tip(t: u32) -> Weight {
         (51_394_000 as Weight) // Standard Error: 1_000
             .saturating_add((360_000 as Weight).saturating_mul(t as Weight))
-            .saturating_add(T::DbWeight::get().reads(5 as Weight))
-            .saturating_add(T::DbWeight::get().writes(4 as Weight))
+            .saturating_add(T::DbWeight::get().reads(8 as Weight))
+            .saturating_add(T::DbWeight::get().writes(5 as Weight))
     }
     fn close_credit_tip(t: u32) -> Weight {
         (27_990_000 as Weight) // Standard Error: 0
             .saturating_add((366_000 as Weight).saturating_mul(t as Weight))
-            .saturating_add(T::DbWeight::get().reads(3 as Weight))
+            .saturating_add(T::DbWeight::get().reads(4 as Weight))
+            .saturating_add(T::DbWeight::get().reads((1 as Weight).saturating_mul(t as Weight)))
             .saturating_add(T::DbWeight::get().writes(2 as Weight))
     }
     fn slash_tip(t: u32) -> Weight {
@@ -115,6 +120,29 @@ impl<T: frame_system::Config> WeightInfo for SubstrateWeight<T> {
             .saturating_add(T::DbWeight::get().reads(1 as Weight))
             .saturating_add(T::DbWeight::get().writes(2 as Weight))
     }
+    fn credit_adjust_new(t: u32) -> Weight {
+        (18_240_000 as Weight) // Standard Error: 0
+            .saturating_add((141_000 as Weight).saturating_mul(t as Weight))
+            .saturating_add(T::DbWeight::get().reads(2 as Weight))
+            .saturating_add(T::DbWeight::get().writes(2 as Weight))
+    }
+    fn credit_adjust(t: u32) -> Weight {
+        (13_318_000 as Weight) // Standard Error: 0
+            .saturating_add((571_000 as Weight).saturating_mul(t as Weight))
+            .saturating_add(T::DbWeight::get().reads(2 as Weight))
+            .saturating_add(T::DbWeight::get().writes(1 as Weight))
+    }
+    fn close_credit_adjust(t: u32) -> Weight {
+        (31_604_000 as Weight) // Standard Error: 0
+            .saturating_add((368_000 as Weight).saturating_mul(t as Weight))
+            .saturating_add(T::DbWeight::get().reads(6 as Weight))
+            .saturating_add(T::DbWeight::get().writes(5 as Weight))
+    }
+    fn retract_credit_adjust() -> Weight {
+        (17_042_000 as Weight)
+            .saturating_add(T::DbWeight::get().reads(1 as Weight))
+            .saturating_add(T::DbWeight::get().writes(2 as Weight))
+    }
 }
 
 // For backwards compatibility and tests
@@ -159,13 +187,14 @@ impl WeightInfo for () {
     fn close_tip(t: u32) -> Weight {
         (51_394_000 as Weight) // Standard Error: 1_000
             .saturating_add((360_000 as Weight).saturating_mul(t as Weight))
-            .saturating_add(RocksDbWeight::get().reads(5 as Weight))
-            .saturating_add(RocksDbWeight::get().writes(4 as Weight))
+            .saturating_add(RocksDbWeight::get().reads(8 as Weight))
+            .saturating_add(RocksDbWeight::get().writes(5 as Weight))
     }
     fn close_credit_tip(t: u32) -> Weight {
         (27_990_000 as Weight) // Standard Error: 0
             .saturating_add((366_000 as Weight).saturating_mul(t as Weight))
-            .saturating_add(RocksDbWeight::get().reads(3 as Weight))
+            .saturating_add(RocksDbWeight::get().reads(4 as Weight))
+            .saturating_add(RocksDbWeight::get().reads((1 as Weight).saturating_mul(t as Weight)))
             .saturating_add(RocksDbWeight::get().writes(2 as Weight))
     }
     fn slash_tip(t: u32) -> Weight {
@@ -174,4 +203,27 @@ impl WeightInfo for () {
             .saturating_add(RocksDbWeight::get().reads(1 as Weight))
             .saturating_add(RocksDbWeight::get().writes(2 as Weight))
     }
+    fn credit_adjust_new(t: u32) -> Weight {
+        (18_240_000 as Weight) // Standard Error: 0
+            .saturating_add((141_000 as Weight).saturating_mul(t as Weight))
+            .saturating_add(RocksDbWeight::get().reads(2 as Weight))
+            .saturating_add(RocksDbWeight::get().writes(2 as Weight))
+    }
+    fn credit_adjust(t: u32) -> Weight {
+        (13_318_000 as Weight) // Standard Error: 0
+            .saturating_add((571_000 as Weight).saturating_mul(t as Weight))
+            .saturating_add(RocksDbWeight::get().reads(2 as Weight))
+            .saturating_add(RocksDbWeight::get().writes(1 as Weight))
+    }
+    fn close_credit_adjust(t: u32) -> Weight {
+        (31_604_000 as Weight) // Standard Error: 0
+            .saturating_add((368_000 as Weight).saturating_mul(t as Weight))
+            .saturating_add(RocksDbWeight::get().reads(6 as Weight))
+            .saturating_add(RocksDbWeight::get().writes(5 as Weight))
+    }
+    fn retract_credit_adjust() -> Weight {
+        (17_042_000 as Weight)
+            .saturating_add(RocksDbWeight::get().reads(1 as Weight))
+            .saturating_add(RocksDbWeight::get().writes(2 as Weight))
+    }
 }
@@ -61,7 +61,8 @@ use sp_runtime::traits::{
     PostDispatchInfoOf, SaturatedConversion, StaticLookup,
 };
 use sp_runtime::transaction_validity::{
-    TransactionPriority, TransactionSource, TransactionValidity, TransactionValidityError,
+    InvalidTransaction, TransactionPriority, TransactionSource, TransactionValidity,
+    TransactionValidityError,
 };
 use sp_runtime::RuntimeAppPublic;
 use sp_runtime::{
@@ -79,7 +80,9 @@ pub use pallet_micropayment;
 use fp_rpc::{TransactionStatus, TxPoolResponse};
 use pallet_ethereum::{Call::transact, Transaction as EthereumTransaction};
 use pallet_evm::FeeCalculator;
-use pallet_evm::{Account as EVMAccount, EVMCurrencyAdapter, PairedAddressMapping, Runner};
+use pallet_evm::{
+    Account as EVMAccount, AddressMapping, EVMCurrencyAdapter, PairedAddressMapping, Runner,
+};
 
 mod precompiles;
 use precompiles::FrontierPrecompiles;
@@ -156,10 +159,10 @@ pub const VERSION: RuntimeVersion = RuntimeVersion {
     // and set impl_version to 0. If only runtime
     // implementation changes and behavior does not, then leave spec_version as
     // is and increment impl_version.
-    spec_version: 12,
+    spec_version: 13,
     impl_version: 0,
     apis: RUNTIME_API_VERSIONS,
-    transaction_version: 6,
+    transaction_version: 7,
     state_version: 1,
 };
 
@@ -457,6 +460,7 @@ parameter_types! {
     // For weight estimation, we assume that the most locks on an individual account will be 50.
     // This number may need to be adjusted in the future if this assumption no longer holds true.
     pub const MaxLocks: u32 = 50;
+    pub const MaxReserves: u32 = 50;
 }
 
 impl pallet_balances::Config for Runtime {
@@ -466,16 +470,33 @@ impl pallet_balances::Config for Runtime {
     type Event = Event;
     type ExistentialDeposit = ExistentialDeposit;
     type AccountStore = frame_system::Pallet<Runtime>;
-    type MaxReserves = ();
+    type MaxReserves = MaxReserves;
     type ReserveIdentifier = [u8; 8];
     type WeightInfo = pallet_balances::weights::SubstrateWeight<Runtime>;
 }
 
+parameter_types! {
+    pub const DefaultMemberQuota: Balance = 1_000_000 * DPR;
+    pub const QuotaPeriod: BlockNumber = 1 * DAYS;
+    pub const MaxReserveDuration: BlockNumber = 180 * DAYS;
+    pub const MaxReservesPerMember: u32 = 100;
+    pub const MaxReleasesPerBlock: u32 = 16;
+    pub const MaxFreezeDuration: BlockNumber = 90 * DAYS;
+    pub const MaxFreezeExpiriesPerBlock: u32 = 16;
+}
+
 impl pallet_operation::Config for Runtime {
     type MaxMember = MaxLocks;
     type Event = Event;
     type Currency = Balances;
     type WeightInfo = pallet_operation::weights::SubstrateWeight<Runtime>;
+    type DefaultMemberQuota = DefaultMemberQuota;
+    type QuotaPeriod = QuotaPeriod;
+    type MaxReserveDuration = MaxReserveDuration;
+    type MaxReservesPerMember = MaxReservesPerMember;
+    type MaxReleasesPerBlock = MaxReleasesPerBlock;
+    type MaxFreezeDuration = MaxFreezeDuration;
+    type MaxFreezeExpiriesPerBlock = MaxFreezeExpiriesPerBlock;
 }
 
 parameter_types! {
@@ -552,7 +573,12 @@ parameter_types! {
     pub const BondingDuration: pallet_staking::EraIndex = 24 * 28;
     pub const SlashDeferDuration: pallet_staking::EraIndex = 24 * 7; // 1/4 the bonding duration.
     pub const MiningReward: u128 = TOTAL_MINING_REWARD;
-    pub const MaxDelegates: usize = 1;
+    pub const MaxDelegates: usize = 16;
+    pub const MaxDelegatorRewardHistory: u32 = 366;
+    pub const MaxDelegatorPayoutsPerBlock: u32 = 100;
+    pub const MaxClaimEras: pallet_staking::EraIndex = 30;
+    pub const MaxCommission: Perbill = Perbill::from_percent(50);
+    pub const CommissionChangeDelay: pallet_staking::EraIndex = 7;
 }
 
 pub struct NumberCurrencyConverter;
@@ -568,6 +594,12 @@ impl pallet_staking::Config for Runtime {
     type CreditInterface = Credit;
     type NodeInterface = DeeperNode;
     type MaxDelegates = MaxDelegates;
+    type ElectionStrategy = pallet_staking::GovernedElection<Runtime>;
+    type MaxDelegatorRewardHistory = MaxDelegatorRewardHistory;
+    type MaxDelegatorPayoutsPerBlock = MaxDelegatorPayoutsPerBlock;
+    type MaxClaimEras = MaxClaimEras;
+    type MaxCommission = MaxCommission;
+    type CommissionChangeDelay = CommissionChangeDelay;
     type UnixTime = Timestamp;
     type NumberToCurrency = NumberCurrencyConverter;
     type Event = Event;
@@ -751,6 +783,9 @@ parameter_types! {
     pub const SpendPeriod: BlockNumber = 24 * DAYS;
     pub const Burn: Permill = Permill::from_percent(1);
     pub const TipCountdown: BlockNumber = 1 * DAYS;
+    pub const TipExpiry: BlockNumber = 30 * DAYS;
+    pub const ExpiredTipDeposit: pallet_tips::DepositPolicy = pallet_tips::DepositPolicy::Return;
+    pub const TipBudgetPeriod: BlockNumber = 30 * DAYS;
     pub const TipFindersFee: Percent = Percent::from_percent(20);
     pub const TipReportDepositBase: Balance = 1 * DPR;
     pub const DataDepositPerByte: Balance = 1 * CENTS;
@@ -818,6 +853,7 @@ impl pallet_child_bounties::Config for Runtime {
 
 parameter_types! {
     pub const MaximumCreditReward: u64 = 15;
+    pub const MaximumCreditPenalty: u64 = 100;
 }
 
 impl pallet_tips::Config for Runtime {
@@ -825,10 +861,15 @@ impl pallet_tips::Config for Runtime {
     type DataDepositPerByte = DataDepositPerByte;
     type MaximumReasonLength = MaximumReasonLength;
     type MaximumCreditReward = MaximumCreditReward;
+    type MaximumCreditPenalty = MaximumCreditPenalty;
     type Tippers = Elections;
     type TipCountdown = TipCountdown;
+    type TipExpiry = TipExpiry;
+    type ExpiredTipDeposit = ExpiredTipDeposit;
     type TipFindersFee = TipFindersFee;
     type TipReportDepositBase = TipReportDepositBase;
+    type TipBudgetPeriod = TipBudgetPeriod;
+    type Assets = Assets;
     type CreditInterface = Credit;
     type WeightInfo = pallet_tips::weights::SubstrateWeight<Runtime>;
 }
@@ -916,6 +957,7 @@ where
             frame_system::CheckNonce::<Runtime>::from(nonce),
             frame_system::CheckWeight::<Runtime>::new(),
             pallet_transaction_payment::ChargeTransactionPayment::<Runtime>::from(tip),
+            pallet_operation::CheckFrozen::<Runtime>::new(),
         );
         let raw_payload = SignedPayload::new(call, extra)
             .map_err(|e| {
@@ -1136,6 +1178,12 @@ parameter_types! {
     pub const SecsPerBlock: u32 = MILLISECS_PER_BLOCK as u32 / 1000;
     pub const DataPerDPR: u64 = 1024 * 1024 * 1024 * 1024;
     pub const MicropaymentBurn: Percent = Percent::from_percent(10);
+    pub const MicropaymentChallengePeriod: BlockNumber = 1 * DAYS;
+    pub const MinCreditChannelAge: BlockNumber = 1 * HOURS;
+    pub const MaxCreditPaymentPerPair: Balance = 1_000 * DPR;
+    pub const MicropaymentPalletId: PalletId = PalletId(*b"dp/mcpay");
+    pub const LegacyPayloadWindow: BlockNumber = 30 * DAYS;
+    pub const SettlementRetryDelay: BlockNumber = 1 * HOURS;
 }
 
 pub fn create_sr25519_pubkey(seed: Vec<u8>) -> MultiSigner {
@@ -1164,6 +1212,17 @@ impl pallet_micropayment::Config for Runtime {
     type NodeInterface = DeeperNode;
     type MicropaymentBurn = MicropaymentBurn;
     type Slash = Treasury;
+    type ValidatorReward = Author;
+    type ChallengePeriod = MicropaymentChallengePeriod;
+    type Signature = Signature;
+    type Signer = AccountPublic;
+    type AssetId = u32;
+    type Assets = Assets;
+    type MinCreditChannelAge = MinCreditChannelAge;
+    type MaxCreditPaymentPerPair = MaxCreditPaymentPerPair;
+    type PalletId = MicropaymentPalletId;
+    type LegacyPayloadWindow = LegacyPayloadWindow;
+    type SettlementRetryDelay = SettlementRetryDelay;
 }
 
 parameter_types! {
@@ -1207,12 +1266,24 @@ impl pallet_credit::Config for Runtime {
     type BurnedTo = Treasury;
 }
 
+parameter_types! {
+    pub const FullCreditTrafficPerDay: u64 = 1024 * 1024 * 1024;
+    pub const MaxAtmosKeys: u32 = 8;
+    pub const AtmosKeyOverlap: BlockNumber = 1 * DAYS;
+    pub const TrafficBatchLifetime: BlockNumber = 7 * DAYS;
+}
+
 impl pallet_credit_accumulation::Config for Runtime {
     type Event = Event;
     type Currency = Balances;
     type CreditInterface = Credit;
     type AccountCreator = DefaultAccountCreator;
     type WeightInfo = pallet_credit_accumulation::weights::SubstrateWeight<Runtime>;
+    type FullCreditTrafficPerDay = FullCreditTrafficPerDay;
+    type MaxAtmosKeys = MaxAtmosKeys;
+    type AtmosKeyOverlap = AtmosKeyOverlap;
+    type LegacyPayloadWindow = LegacyPayloadWindow;
+    type TrafficBatchLifetime = TrafficBatchLifetime;
 }
 
 pub struct FindAuthorTruncated<F>(PhantomData<F>);
@@ -1352,7 +1423,7 @@ construct_runtime!(
         Lottery: pallet_lottery::{Pallet, Call, Storage, Event<T>} = 47,
         ChildBounties: pallet_child_bounties::{Pallet, Call, Storage, Event<T>} = 48,
 
-        Micropayment: pallet_micropayment::{Pallet, Call, Storage, Event<T>} = 60,
+        Micropayment: pallet_micropayment::{Pallet, Call, Storage, Config, Event<T>} = 60,
         DeeperNode: pallet_deeper_node::{Pallet, Call, Storage, Event<T>, Config<T> } = 61,
         CreditAccumulation: pallet_credit_accumulation::{Pallet, Call, Storage, Event<T>} = 62,
 
@@ -1413,6 +1484,7 @@ pub type SignedExtra = (
     frame_system::CheckNonce<Runtime>,
     frame_system::CheckWeight<Runtime>,
     pallet_transaction_payment::ChargeTransactionPayment<Runtime>,
+    pallet_operation::CheckFrozen<Runtime>,
 );
 /// The payload being signed in transactions.
 pub type SignedPayload = generic::SignedPayload<Call, SignedExtra>;
@@ -1436,6 +1508,15 @@ pub type Executive = frame_executive::Executive<
     (),
 >;
 
+/// Reject ethereum transactions sent from an address whose paired account is frozen
+fn check_evm_frozen(source: &H160) -> Result<(), TransactionValidityError> {
+    let who = <Runtime as pallet_evm::Config>::AddressMapping::into_account_id(*source);
+    if Operation::is_frozen(&who) {
+        return Err(InvalidTransaction::Custom(pallet_operation::FROZEN_ACCOUNT).into());
+    }
+    Ok(())
+}
+
 impl fp_self_contained::SelfContainedCall for Call {
     type SignedInfo = H160;
 
@@ -1455,7 +1536,12 @@ impl fp_self_contained::SelfContainedCall for Call {
 
     fn validate_self_contained(&self, info: &Self::SignedInfo) -> Option<TransactionValidity> {
         match self {
-            Call::Ethereum(call) => call.validate_self_contained(info),
+            Call::Ethereum(call) => {
+                if let Err(e) = check_evm_frozen(info) {
+                    return Some(Err(e));
+                }
+                call.validate_self_contained(info)
+            }
             _ => None,
         }
     }
@@ -1465,7 +1551,12 @@ impl fp_self_contained::SelfContainedCall for Call {
         info: &Self::SignedInfo,
     ) -> Option<Result<(), TransactionValidityError>> {
         match self {
-            Call::Ethereum(call) => call.pre_dispatch_self_contained(info),
+            Call::Ethereum(call) => {
+                if let Err(e) = check_evm_frozen(info) {
+                    return Some(Err(e));
+                }
+                call.pre_dispatch_self_contained(info)
+            }
             _ => None,
         }
     }
@@ -1863,6 +1954,37 @@ impl_runtime_apis! {
         }
     }
 
+    impl pallet_staking_runtime_api::StakingApi<Block, AccountId, Balance> for Runtime {
+        fn delegator_reward_history(
+            delegator: AccountId,
+        ) -> Vec<pallet_staking_runtime_api::DelegatorRewardRecord<AccountId, Balance>> {
+            Staking::delegator_reward_history(&delegator)
+        }
+
+        fn projected_emission(
+            from: pallet_staking_runtime_api::EraIndex,
+            count: u32,
+        ) -> Vec<pallet_staking_runtime_api::EraEmission<Balance>> {
+            Staking::projected_emission(from, count)
+        }
+
+        fn election_snapshot(
+        ) -> Vec<pallet_staking_runtime_api::ElectionCandidate<AccountId, Balance>> {
+            Staking::election_snapshot()
+        }
+
+        fn simulate_election(
+            count: u32,
+            strategies: Vec<pallet_staking_runtime_api::ElectionStrategyKind>,
+        ) -> Vec<(pallet_staking_runtime_api::ElectionStrategyKind, Vec<AccountId>)> {
+            pallet_staking::election::simulate(
+                &Staking::election_snapshot(),
+                count as usize,
+                &strategies,
+            )
+        }
+    }
+
     impl pallet_mmr::primitives::MmrApi<
         Block,
         mmr::Hash,
@@ -111,6 +111,7 @@ pub fn config_endowed(code: Option<&[u8]>, extra_endowed: Vec<AccountId>) -> Gen
         },
         vesting: Default::default(),
         deeper_node: Default::default(),
+        micropayment: Default::default(),
         credit: Default::default(),
         evm: Default::default(),
         ethereum: Default::default(),
@@ -80,6 +80,7 @@ pub fn signed_extra(nonce: Index, extra_fee: Balance) -> SignedExtra {
         frame_system::CheckNonce::from(nonce),
         frame_system::CheckWeight::new(),
         pallet_transaction_payment::ChargeTransactionPayment::from(extra_fee),
+        pallet_operation::CheckFrozen::new(),
     )
 }
 